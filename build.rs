@@ -0,0 +1,22 @@
+fn main() {
+    #[cfg(feature = "ffi")]
+    {
+        let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        println!("cargo:rerun-if-changed=src/ffi.rs");
+
+        let config = cbindgen::Config {
+            language: cbindgen::Language::C,
+            include_guard: Some(String::from("BUFFEREDLINEAR_TOOLS_H")),
+            documentation: true,
+            usize_is_size_t: true,
+            ..Default::default()
+        };
+
+        cbindgen::Builder::new()
+            .with_config(config)
+            .with_src(format!("{crate_dir}/src/ffi.rs"))
+            .generate()
+            .expect("Failed to generate the C header")
+            .write_to_file(format!("{crate_dir}/include/bufferedlinear_tools.h"));
+    }
+}
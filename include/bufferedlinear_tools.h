@@ -0,0 +1,71 @@
+#ifndef BUFFEREDLINEAR_TOOLS_H
+#define BUFFEREDLINEAR_TOOLS_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * A parsed region file.
+ */
+typedef struct BltRegion BltRegion;
+
+/**
+ * The message of the last error on this thread, or null. Valid until the next failing call.
+ */
+const char *blt_last_error(void);
+
+/**
+ * Reads and parses a region file in any supported format.
+ *
+ * # Safety
+ * `path` must be a valid nul terminated UTF-8 string.
+ */
+struct BltRegion *blt_region_open(const char *path);
+
+/**
+ * Number of chunks in the region, or -1 if `region` is null.
+ *
+ * # Safety
+ * `region` must be null or returned by `blt_region_open` and not freed.
+ */
+int blt_region_chunk_count(const struct BltRegion *region);
+
+/**
+ * The uncompressed NBT of the chunk in the slot of the given chunk coordinates, its length is
+ * written to `out_len`. Returns null without setting an error if the slot is empty.
+ *
+ * # Safety
+ * `region` must be returned by `blt_region_open` and not freed, `out_len` must be writable.
+ */
+uint8_t *blt_region_get_chunk(const struct BltRegion *region, int x, int z, size_t *out_len);
+
+/**
+ * Releases a region returned by `blt_region_open`.
+ *
+ * # Safety
+ * `region` must be null or returned by `blt_region_open` and not freed before.
+ */
+void blt_region_free(struct BltRegion *region);
+
+/**
+ * Releases a buffer returned by `blt_region_get_chunk`.
+ *
+ * # Safety
+ * `buffer` must be null or returned by `blt_region_get_chunk` with this `len`, and not freed before.
+ */
+void blt_buffer_free(uint8_t *buffer,
+                     size_t len);
+
+/**
+ * Converts a region file into the format named by the output file's extension. Returns 0 on
+ * success and -1 on failure.
+ *
+ * # Safety
+ * `input` and `output` must be valid nul terminated UTF-8 strings.
+ */
+int blt_convert_file(const char *input, const char *output, uint8_t compression_level);
+
+#endif  /* BUFFEREDLINEAR_TOOLS_H */
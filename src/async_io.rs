@@ -0,0 +1,116 @@
+//! Async conversion, built with the `async` feature. Files are read and written with `tokio::fs`
+//! and parsing and compression run on tokio's blocking pool, so no thread is spawned per file.
+
+use crate::region_file::{region_coords_from_path, Region, RegionFormat};
+use std::error::Error;
+use std::io;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::task::{spawn_blocking, JoinSet};
+
+pub type AsyncResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+
+/// Reads a region file of any supported format.
+pub async fn read_region_file(path: &Path) -> AsyncResult<(RegionFormat, Region)> {
+    let bytes = fs::read(path).await?;
+    let path = path.to_path_buf();
+
+    spawn_blocking(move || {
+        let format = RegionFormat::detect(&path, &bytes).ok_or("Unknown region file format")?;
+        Ok((format, Region::from_bytes(format, &bytes)?))
+    })
+    .await?
+}
+
+/// Converts a region file into the format named by the output file's extension.
+pub async fn convert_region_file(input: &Path, output: &Path, compression_level: u8) -> AsyncResult<()> {
+    let format = output
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .and_then(RegionFormat::from_extension)
+        .ok_or("Output extension must be mca, linear or blinear")?;
+
+    let bytes = fs::read(input).await?;
+    let input = input.to_path_buf();
+
+    let converted = spawn_blocking(move || -> AsyncResult<Vec<u8>> {
+        let input_format = RegionFormat::detect(&input, &bytes).ok_or("Unknown region file format")?;
+        let region = Region::from_bytes(input_format, &bytes)?;
+        Ok(region.to_bytes(format, region_coords_from_path(&input), region.timestamp(), compression_level)?)
+    })
+    .await??;
+
+    fs::write(output, converted).await?;
+
+    Ok(())
+}
+
+/// Converts every region file of a folder into `format`, with at most `max_in_flight` files
+/// read, converted or written at once. Returns the result of each input file.
+pub async fn convert_folder(
+    input_folder: &Path,
+    output_folder: &Path,
+    format: RegionFormat,
+    compression_level: u8,
+    max_in_flight: usize,
+) -> io::Result<Vec<(PathBuf, AsyncResult<()>)>> {
+    fs::create_dir_all(output_folder).await?;
+
+    let mut entries = fs::read_dir(input_folder).await?;
+    let mut tasks = JoinSet::new();
+    let mut results = Vec::new();
+
+    while let Some(entry) = entries.next_entry().await? {
+        let input = entry.path();
+        let is_region = input.extension().and_then(|extension| extension.to_str()).and_then(RegionFormat::from_extension).is_some();
+        let Some((region_x, region_z)) = region_coords_from_path(&input).filter(|_| is_region) else {
+            continue;
+        };
+
+        if tasks.len() >= max_in_flight.max(1) {
+            results.push(tasks.join_next().await.unwrap()?);
+        }
+
+        let output = output_folder.join(format!("r.{}.{}.{}", region_x, region_z, format.extension()));
+        tasks.spawn(async move {
+            let result = convert_region_file(&input, &output, compression_level).await;
+            (input, result)
+        });
+    }
+
+    while let Some(result) = tasks.join_next().await {
+        results.push(result?);
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::nbt::tag::Tag;
+
+    #[tokio::test]
+    async fn test_convert_folder() {
+        let folder = std::env::temp_dir().join("bufferedlinear_tools_async_test");
+        let (input, output) = (folder.join("in"), folder.join("out"));
+        std::fs::create_dir_all(&input).unwrap();
+
+        for region_x in 0..3 {
+            let chunk = Chunk::new_from_block_pos(1, 2, 0, Tag::Compound { name: None, value: Vec::new() });
+            std::fs::write(input.join(format!("r.{region_x}.0.blinear")), Region::new(vec![chunk], 0).to_bytes_blinear(0, 3)).unwrap();
+        }
+        std::fs::write(input.join("notes.txt"), "not a region").unwrap();
+
+        let results = convert_folder(&input, &output, RegionFormat::Mca, 6, 2).await.unwrap();
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|(_, result)| result.is_ok()));
+
+        let (format, region) = read_region_file(&output.join("r.2.0.mca")).await.unwrap();
+        assert_eq!(format, RegionFormat::Mca);
+        assert_eq!(region.chunks().len(), 1);
+
+        std::fs::remove_dir_all(&folder).unwrap();
+    }
+}
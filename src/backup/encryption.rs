@@ -0,0 +1,95 @@
+use age::secrecy::SecretString;
+use age::{scrypt, x25519, DecryptError, Decryptor, EncryptError, Identity};
+use std::env;
+use std::fs;
+use std::io::Read;
+use std::iter;
+use std::path::Path;
+
+/// Suffix of the files of a backup written encrypted.
+pub const ENCRYPTED_SUFFIX: &str = ".age";
+/// Environment variable holding the passphrase of `--encrypt passphrase` and of restoring such backups.
+pub const PASSPHRASE_ENV: &str = "BLT_BACKUP_PASSPHRASE";
+
+/// How backup files are encrypted, in the age format.
+#[derive(Clone)]
+pub enum Encryption {
+    Recipient(x25519::Recipient),
+    Passphrase(SecretString),
+}
+
+impl Encryption {
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, EncryptError> {
+        match self {
+            Encryption::Recipient(recipient) => age::encrypt(recipient, plaintext),
+            Encryption::Passphrase(passphrase) => age::encrypt(&scrypt::Recipient::new(passphrase.clone()), plaintext),
+        }
+    }
+}
+
+fn passphrase_from_env() -> Option<SecretString> {
+    env::var(PASSPHRASE_ENV).ok().filter(|passphrase| !passphrase.is_empty()).map(SecretString::from)
+}
+
+/// Parses `age:<recipient>`, or `passphrase` which reads the passphrase from `BLT_BACKUP_PASSPHRASE`.
+pub fn parse_encryption(s: &str) -> Result<Encryption, String> {
+    if s == "passphrase" {
+        return passphrase_from_env().map(Encryption::Passphrase).ok_or_else(|| format!("{PASSPHRASE_ENV} must be set to encrypt with a passphrase"));
+    }
+
+    let recipient = s.strip_prefix("age:").ok_or("Encryption must be age:<recipient> or passphrase")?;
+    recipient.parse().map(Encryption::Recipient).map_err(|err| format!("Invalid age recipient : {err}"))
+}
+
+/// Keys for reading encrypted backup files: the x25519 identities of an age identity file, and
+/// the passphrase from `BLT_BACKUP_PASSPHRASE` when it is set.
+pub struct Decryption {
+    identities: Vec<x25519::Identity>,
+    passphrase: Option<SecretString>,
+}
+
+impl Decryption {
+    pub fn new(identity_file: Option<&Path>) -> Result<Self, String> {
+        let mut identities = Vec::new();
+        if let Some(path) = identity_file {
+            let contents = fs::read_to_string(path).map_err(|err| format!("Failed to read identity file {} : {}", path.display(), err))?;
+            for line in contents.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+                identities.push(line.parse().map_err(|err| format!("Invalid identity in {} : {}", path.display(), err))?);
+            }
+        }
+
+        Ok(Decryption { identities, passphrase: passphrase_from_env() })
+    }
+
+    pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>, DecryptError> {
+        let decryptor = Decryptor::new_buffered(ciphertext)?;
+
+        let mut plaintext = Vec::new();
+        let mut reader = if decryptor.is_scrypt() {
+            let passphrase = self.passphrase.clone().ok_or(DecryptError::NoMatchingKeys)?;
+            decryptor.decrypt(iter::once(&scrypt::Identity::new(passphrase) as &dyn Identity))?
+        } else {
+            decryptor.decrypt(self.identities.iter().map(|identity| identity as &dyn Identity))?
+        };
+        reader.read_to_end(&mut plaintext)?;
+
+        Ok(plaintext)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_to_recipient() {
+        let identity = x25519::Identity::generate();
+        let encryption = parse_encryption(&format!("age:{}", identity.to_public())).unwrap();
+        let ciphertext = encryption.encrypt(b"region").unwrap();
+
+        let decryption = Decryption { identities: vec![identity], passphrase: None };
+        assert_eq!(decryption.decrypt(&ciphertext).unwrap(), b"region");
+        assert!(Decryption { identities: vec![x25519::Identity::generate()], passphrase: None }.decrypt(&ciphertext).is_err());
+        assert!(parse_encryption("age:nonsense").is_err());
+    }
+}
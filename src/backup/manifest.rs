@@ -0,0 +1,120 @@
+use crate::backup::{BackupError, ENCRYPTED_SUFFIX};
+use crate::region_file::RegionFormat;
+use chrono::{DateTime, FixedOffset};
+use std::fs;
+use std::path::Path;
+use toml::{Table, Value};
+
+/// File in every backup folder listing the files it holds. Written last, so a backup without one
+/// is incomplete.
+pub const MANIFEST_FILE: &str = "manifest.toml";
+
+/// One world file of a backup.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ManifestEntry {
+    /// Path inside the backup, `/` separated, ending in `.age` when encrypted
+    pub path: String,
+    /// Path of the world file it was made from, differs from `path` for converted region files
+    pub source: String,
+    /// Hash of the world file's bytes
+    pub hash: u64,
+    /// Backup holding the file when it was unchanged since an earlier backup, `None` when this
+    /// backup holds it
+    pub stored_in: Option<String>,
+}
+
+impl ManifestEntry {
+    pub fn is_encrypted(&self) -> bool {
+        self.path.ends_with(ENCRYPTED_SUFFIX)
+    }
+
+    /// `path` without the suffix of encrypted files.
+    pub fn plain_path(&self) -> &str {
+        self.path.strip_suffix(ENCRYPTED_SUFFIX).unwrap_or(&self.path)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Manifest {
+    pub created: DateTime<FixedOffset>,
+    /// Format region files were converted to, `None` when they were copied as they were
+    pub format: Option<RegionFormat>,
+    /// Backup a differential backup was compared against, `None` for full backups
+    pub parent: Option<String>,
+    /// Whether the files this backup holds are encrypted
+    pub encrypted: bool,
+    pub files: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    pub fn to_toml(&self) -> String {
+        let mut table = Table::new();
+        table.insert(String::from("created"), Value::String(self.created.to_rfc3339()));
+        if let Some(format) = self.format {
+            table.insert(String::from("format"), Value::String(String::from(format.extension())));
+        }
+        if self.encrypted {
+            table.insert(String::from("encrypted"), Value::Boolean(true));
+        }
+        if let Some(parent) = &self.parent {
+            table.insert(String::from("parent"), Value::String(parent.clone()));
+        }
+
+        let mut files = Table::new();
+        for entry in &self.files {
+            let mut file = Table::new();
+            file.insert(String::from("source"), Value::String(entry.source.clone()));
+            file.insert(String::from("hash"), Value::String(format!("{:016x}", entry.hash)));
+            if let Some(stored_in) = &entry.stored_in {
+                file.insert(String::from("stored_in"), Value::String(stored_in.clone()));
+            }
+            files.insert(entry.path.clone(), Value::Table(file));
+        }
+        table.insert(String::from("files"), Value::Table(files));
+
+        table.to_string()
+    }
+
+    pub fn from_toml(manifest: &str) -> Result<Self, BackupError> {
+        let invalid = |field: &str| BackupError::InvalidManifest(format!("missing or invalid {field}"));
+        let table: Table = manifest.parse().map_err(|err: toml::de::Error| BackupError::InvalidManifest(err.message().to_string()))?;
+
+        let created = table.get("created").and_then(Value::as_str).and_then(|created| DateTime::parse_from_rfc3339(created).ok()).ok_or_else(|| invalid("created"))?;
+        let format = match table.get("format") {
+            Some(format) => Some(format.as_str().and_then(RegionFormat::from_extension).ok_or_else(|| invalid("format"))?),
+            None => None,
+        };
+        let encrypted = match table.get("encrypted") {
+            Some(encrypted) => encrypted.as_bool().ok_or_else(|| invalid("encrypted"))?,
+            None => false,
+        };
+        let parent = match table.get("parent") {
+            Some(parent) => Some(parent.as_str().ok_or_else(|| invalid("parent"))?.to_string()),
+            None => None,
+        };
+
+        let mut files = Vec::new();
+        for (path, file) in table.get("files").and_then(Value::as_table).ok_or_else(|| invalid("files"))? {
+            let source = file.get("source").and_then(Value::as_str).ok_or_else(|| invalid(&format!("source of {path}")))?;
+            let hash = file.get("hash").and_then(Value::as_str).and_then(|hash| u64::from_str_radix(hash, 16).ok()).ok_or_else(|| invalid(&format!("hash of {path}")))?;
+            let stored_in = match file.get("stored_in") {
+                Some(stored_in) => Some(stored_in.as_str().ok_or_else(|| invalid(&format!("stored_in of {path}")))?.to_string()),
+                None => None,
+            };
+            files.push(ManifestEntry { path: path.clone(), source: source.to_string(), hash, stored_in });
+        }
+
+        Ok(Manifest { created, format, parent, encrypted, files })
+    }
+
+    /// Names of the other backups holding files of this one.
+    pub fn references(&self) -> impl Iterator<Item = &str> {
+        self.files.iter().filter_map(|entry| entry.stored_in.as_deref())
+    }
+
+    pub fn read(backup_folder: &Path) -> Result<Self, BackupError> {
+        let path = backup_folder.join(MANIFEST_FILE);
+        let manifest = fs::read_to_string(&path).map_err(|source| BackupError::Io { path, source })?;
+        Self::from_toml(&manifest)
+    }
+}
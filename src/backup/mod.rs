@@ -0,0 +1,379 @@
+mod encryption;
+mod manifest;
+mod retention;
+
+pub use crate::backup::encryption::{Decryption, ENCRYPTED_SUFFIX};
+pub use crate::backup::manifest::{Manifest, ManifestEntry, MANIFEST_FILE};
+use crate::backup::encryption::{parse_encryption, Encryption};
+use crate::backup::retention::{backups_to_prune, RetentionPolicy};
+use crate::incremental::hash;
+use crate::throttle;
+use crate::region_file::{blinear_dictionary_for, region_coords_from_path, ParseLimits, Region, RegionFormat};
+use crate::{validate_compression_level, OutputFormat};
+use chrono::{DateTime, Local};
+use clap::Args;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Names of backup folders, `backup-<local time>`.
+const BACKUP_PREFIX: &str = "backup-";
+/// Suffix of a backup folder still being written.
+const PARTIAL_SUFFIX: &str = ".partial";
+/// Folders whose region files are converted to `--format`.
+const REGION_FOLDERS: [&str; 3] = ["region", "poi", "entities"];
+/// Lock file of a running server, not part of a world's data.
+const SESSION_LOCK: &str = "session.lock";
+
+#[derive(Error, Debug)]
+pub enum BackupError {
+    #[error("{path} : {source}")]
+    Io { path: PathBuf, source: io::Error },
+    #[error("Invalid backup manifest, {0}")]
+    InvalidManifest(String),
+    #[error("Backup {0} already exists")]
+    Exists(PathBuf),
+    #[error("{0} files could not be backed up")]
+    FailedFiles(usize),
+    #[error("Restore target {0} is not empty")]
+    NotEmpty(PathBuf),
+    #[error("{0} files could not be restored")]
+    FailedRestore(usize),
+}
+
+#[derive(Args)]
+pub struct BackupArgs {
+    /// World folder to back up
+    pub world_path: PathBuf,
+
+    /// Folder holding the backups, each written into its own `backup-<time>` folder
+    pub destination: PathBuf,
+
+    /// Convert the region, poi and entities files to this format, they are copied unchanged otherwise
+    #[arg(long, value_enum)]
+    pub format: Option<OutputFormat>,
+
+    /// Compression level when converting region files
+    #[arg(short, long, default_value = "6", value_parser = validate_compression_level)]
+    pub compression_level: u32,
+
+    /// Encrypt every file but the manifest with age, to `age:<recipient>`, or with the passphrase
+    /// in `BLT_BACKUP_PASSPHRASE` for `passphrase`
+    #[arg(long, value_name = "SPEC", value_parser = parse_encryption)]
+    pub encrypt: Option<Encryption>,
+
+    /// Only write the files whose content changed since the newest backup in the destination,
+    /// the manifest refers to the backups holding the others
+    #[arg(long)]
+    pub differential: bool,
+
+    /// After a successful backup, keep only the newest N backups in the destination, along with
+    /// those kept by `--keep-daily` and `--keep-weekly`
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub keep: Option<u32>,
+
+    /// After a successful backup, keep the newest backup of each of the last N days that have one
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub keep_daily: Option<u32>,
+
+    /// After a successful backup, keep the newest backup of each of the last N weeks that have one
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub keep_weekly: Option<u32>,
+}
+
+impl BackupArgs {
+    fn retention(&self) -> RetentionPolicy {
+        let periods = |count: Option<u32>| count.unwrap_or(0) as usize;
+        RetentionPolicy { last: periods(self.keep), daily: periods(self.keep_daily), weekly: periods(self.keep_weekly) }
+    }
+}
+
+fn io_error(path: &Path) -> impl FnOnce(io::Error) -> BackupError + '_ {
+    move |source| BackupError::Io { path: path.to_path_buf(), source }
+}
+
+/// Every file below `folder`, as `/` separated paths relative to the world folder.
+fn world_files(world_folder: &Path, folder: &Path, files: &mut Vec<String>) -> Result<(), BackupError> {
+    for entry in fs::read_dir(folder).map_err(io_error(folder))? {
+        let path = entry.map_err(io_error(folder))?.path();
+        if path.is_dir() {
+            world_files(world_folder, &path, files)?;
+        } else if !path.ends_with(SESSION_LOCK) {
+            let relative = path.strip_prefix(world_folder).expect("Scanned path is inside the world");
+            files.push(relative.components().map(|component| component.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/"));
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether the world file is in a region, poi or entities folder of any dimension.
+pub fn in_region_folder(source: &str) -> bool {
+    source.rsplit('/').nth(1).is_some_and(|folder| REGION_FOLDERS.contains(&folder))
+}
+
+/// The backup a differential backup is compared against, with its files by source path.
+struct Parent {
+    name: String,
+    files: HashMap<String, ManifestEntry>,
+}
+
+/// Parses region file bytes, blinear ones with the dictionary next to `path` if they need one.
+pub fn parse_region_bytes(path: &Path, bytes: &[u8], format: RegionFormat) -> Result<Region, Box<dyn Error>> {
+    Ok(match format {
+        RegionFormat::Blinear => Region::from_bytes_blinear_with_dictionary(bytes, &ParseLimits::DEFAULT, blinear_dictionary_for(path, bytes)?.as_deref())?,
+        _ => Region::from_bytes(format, bytes)?,
+    })
+}
+
+/// Region file bytes in another format.
+pub fn convert_region_bytes(path: &Path, bytes: &[u8], input_format: RegionFormat, format: RegionFormat, compression_level: u8) -> Result<Vec<u8>, Box<dyn Error>> {
+    let region = parse_region_bytes(path, bytes, input_format)?;
+    Ok(region.to_bytes(format, region_coords_from_path(path), region.timestamp(), compression_level)?)
+}
+
+/// Writes one world file into the backup folder, converting region files when a format is given.
+/// Files unchanged since the parent are not written, their entry points to the backup holding them.
+fn backup_file(world_folder: &Path, source: &str, backup_folder: &Path, parent: Option<&Parent>, args: &BackupArgs) -> Result<ManifestEntry, Box<dyn Error>> {
+    let input = world_folder.join(source);
+    let bytes = throttle::read(&input)?;
+    let mut entry = ManifestEntry { path: source.to_string(), source: source.to_string(), hash: hash(&bytes), stored_in: None };
+
+    if let Some(parent) = parent
+        && let Some(previous) = parent.files.get(source)
+        && previous.hash == entry.hash
+    {
+        let stored_in = previous.stored_in.clone().unwrap_or_else(|| parent.name.clone());
+        return Ok(ManifestEntry { stored_in: Some(stored_in), ..previous.clone() });
+    }
+
+    let format = args.format.map(RegionFormat::from);
+    let converted = match (format, RegionFormat::detect(&input, &bytes)) {
+        (Some(format), Some(input_format)) if in_region_folder(source) => {
+            entry.path = Path::new(source).with_extension(format.extension()).to_string_lossy().replace('\\', "/");
+            Some(convert_region_bytes(&input, &bytes, input_format, format, args.compression_level as u8)?)
+        }
+        _ => None,
+    };
+
+    let mut contents = converted.unwrap_or(bytes);
+    if let Some(encryption) = &args.encrypt {
+        contents = encryption.encrypt(&contents)?;
+        entry.path.push_str(ENCRYPTED_SUFFIX);
+    }
+
+    let output = backup_folder.join(&entry.path);
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    throttle::write(&output, contents)?;
+
+    Ok(entry)
+}
+
+/// Every complete backup in the destination, with the time it was made.
+pub fn list_backups(destination: &Path) -> Result<Vec<(String, DateTime<Local>)>, BackupError> {
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(destination).map_err(io_error(destination))? {
+        let path = entry.map_err(io_error(destination))?.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if !name.starts_with(BACKUP_PREFIX) || name.ends_with(PARTIAL_SUFFIX) || !path.join(MANIFEST_FILE).is_file() {
+            continue;
+        }
+
+        let manifest = Manifest::read(&path)?;
+        backups.push((name.to_string(), manifest.created.with_timezone(&Local)));
+    }
+
+    Ok(backups)
+}
+
+/// The newest backup in the destination, if its region files are in the same format as this backup's.
+fn find_parent(args: &BackupArgs) -> Result<Option<Parent>, BackupError> {
+    if !args.destination.is_dir() {
+        return Ok(None);
+    }
+    let Some((name, _)) = list_backups(&args.destination)?.into_iter().max_by_key(|(_, created)| *created) else {
+        return Ok(None);
+    };
+
+    let manifest = Manifest::read(&args.destination.join(&name))?;
+    if manifest.format != args.format.map(RegionFormat::from) {
+        println!("Newest backup {} has region files in another format, writing a full backup", name);
+        return Ok(None);
+    }
+    if manifest.encrypted != args.encrypt.is_some() {
+        println!("Newest backup {} is {}, writing a full backup", name, if manifest.encrypted { "encrypted" } else { "not encrypted" });
+        return Ok(None);
+    }
+
+    let files = manifest.files.into_iter().map(|entry| (entry.source.clone(), entry)).collect();
+    Ok(Some(Parent { name, files }))
+}
+
+/// Removes the backups in the destination the retention policy does not keep and no kept
+/// differential backup refers to, returns their names.
+fn prune_backups(destination: &Path, policy: &RetentionPolicy) -> Result<Vec<String>, BackupError> {
+    let backups: Vec<_> = list_backups(destination)?.into_iter().map(|(name, created)| (name, created.naive_local())).collect();
+    let mut pruned = backups_to_prune(&backups, policy);
+
+    let mut referenced = HashSet::new();
+    for (name, _) in backups.iter().filter(|(name, _)| !pruned.contains(name)) {
+        referenced.extend(Manifest::read(&destination.join(name))?.references().map(String::from));
+    }
+    pruned.retain(|name| !referenced.contains(name));
+
+    for name in &pruned {
+        let folder = destination.join(name);
+        fs::remove_dir_all(&folder).map_err(io_error(&folder))?;
+    }
+
+    Ok(pruned)
+}
+
+/// Writes a backup of the world into a new folder of the destination, then prunes old backups
+/// by the retention flags. Returns the new backup's folder.
+pub fn run_backup(args: &BackupArgs) -> Result<PathBuf, Box<dyn Error>> {
+    write_backup(args, Local::now())
+}
+
+fn write_backup(args: &BackupArgs, created: DateTime<Local>) -> Result<PathBuf, Box<dyn Error>> {
+    let name = format!("{}{}", BACKUP_PREFIX, created.format("%Y%m%d-%H%M%S"));
+    let backup_folder = args.destination.join(&name);
+    if backup_folder.exists() {
+        return Err(BackupError::Exists(backup_folder).into());
+    }
+
+    let mut sources = Vec::new();
+    world_files(&args.world_path, &args.world_path, &mut sources)?;
+    sources.sort();
+
+    let parent = if args.differential { find_parent(args)? } else { None };
+
+    let staging = args.destination.join(format!("{name}{PARTIAL_SUFFIX}"));
+    fs::create_dir_all(&staging).map_err(io_error(&staging))?;
+
+    let results: Vec<Result<ManifestEntry, Box<dyn Error + Send + Sync>>> = sources
+        .par_iter()
+        .map(|source| backup_file(&args.world_path, source, &staging, parent.as_ref(), args).map_err(|err| err.to_string().into()))
+        .collect();
+
+    let mut files = Vec::new();
+    let mut failed = 0;
+    for (source, result) in sources.iter().zip(results) {
+        match result {
+            Ok(entry) => files.push(entry),
+            Err(err) => {
+                eprintln!("Failed to back up file {} !, error : {}", args.world_path.join(source).display(), err);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(BackupError::FailedFiles(failed).into());
+    }
+
+    let converted = files.iter().filter(|entry| entry.stored_in.is_none() && entry.plain_path() != entry.source).count();
+    let unchanged = files.iter().filter(|entry| entry.stored_in.is_some()).count();
+    let manifest = Manifest { created: created.fixed_offset(), format: args.format.map(RegionFormat::from), parent: parent.map(|parent| parent.name), encrypted: args.encrypt.is_some(), files };
+    let manifest_path = staging.join(MANIFEST_FILE);
+    throttle::write(&manifest_path, manifest.to_toml()).map_err(io_error(&manifest_path))?;
+    fs::rename(&staging, &backup_folder).map_err(io_error(&backup_folder))?;
+    throttle::sync_parent(&backup_folder).map_err(io_error(&backup_folder))?;
+
+    match &manifest.parent {
+        Some(parent) => println!("{}: {} files, {} unchanged since {}, {} region files converted", backup_folder.display(), manifest.files.len(), unchanged, parent, converted),
+        None => println!("{}: {} files, {} region files converted", backup_folder.display(), manifest.files.len(), converted),
+    }
+
+    let policy = args.retention();
+    if !policy.is_empty() {
+        for pruned in prune_backups(&args.destination, &policy)? {
+            println!("Removed old backup {}", args.destination.join(pruned).display());
+        }
+    }
+
+    Ok(backup_folder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::nbt::tag::Tag;
+
+    #[test]
+    fn test_backup_and_prune() {
+        let folder = std::env::temp_dir().join("bufferedlinear_tools_backup_test");
+        let _ = fs::remove_dir_all(&folder);
+        let world = folder.join("world");
+        fs::create_dir_all(world.join("region")).unwrap();
+
+        let chunks = (0..3).map(|x| Chunk::new_from_block_pos(x, 0, 1, Tag::Compound { name: None, value: Vec::new() })).collect();
+        fs::write(world.join("region/r.0.0.mca"), Region::new(chunks, 1).to_bytes(RegionFormat::Mca, None, 1, 6).unwrap()).unwrap();
+        fs::write(world.join("level.dat"), b"level").unwrap();
+        fs::write(world.join(SESSION_LOCK), b"lock").unwrap();
+
+        // an older backup, kept by neither rule
+        let destination = folder.join("backups");
+        let old = destination.join("backup-20200101-000000");
+        fs::create_dir_all(&old).unwrap();
+        let old_manifest = Manifest { created: DateTime::parse_from_rfc3339("2020-01-01T00:00:00+00:00").unwrap(), format: None, parent: None, encrypted: false, files: Vec::new() };
+        fs::write(old.join(MANIFEST_FILE), old_manifest.to_toml()).unwrap();
+
+        let args = BackupArgs { world_path: world, destination: destination.clone(), format: Some(OutputFormat::Linear), compression_level: 6, encrypt: None, differential: false, keep: Some(1), keep_daily: None, keep_weekly: None };
+        let backup = run_backup(&args).unwrap();
+
+        let manifest = Manifest::read(&backup).unwrap();
+        assert_eq!(Manifest::from_toml(&manifest.to_toml()).unwrap(), manifest);
+        let paths: Vec<&str> = manifest.files.iter().map(|entry| entry.path.as_str()).collect();
+        assert_eq!(paths, ["level.dat", "region/r.0.0.linear"]);
+        assert_eq!(fs::read(backup.join("level.dat")).unwrap(), b"level");
+        assert_eq!(Region::from_bytes(RegionFormat::Linear, &fs::read(backup.join("region/r.0.0.linear")).unwrap()).unwrap().chunks().len(), 3);
+        assert!(!old.exists());
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn test_differential_backup() {
+        let folder = std::env::temp_dir().join("bufferedlinear_tools_backup_differential_test");
+        let _ = fs::remove_dir_all(&folder);
+        let world = folder.join("world");
+        fs::create_dir_all(world.join("region")).unwrap();
+        fs::write(world.join("region/r.0.0.mca"), b"region").unwrap();
+        fs::write(world.join("level.dat"), b"level").unwrap();
+
+        let destination = folder.join("backups");
+        let args = BackupArgs { world_path: world.clone(), destination: destination.clone(), format: None, compression_level: 6, encrypt: None, differential: true, keep: Some(1), keep_daily: None, keep_weekly: None };
+        let at = |time: &str| DateTime::parse_from_rfc3339(time).unwrap().with_timezone(&Local);
+
+        let full = write_backup(&args, at("2026-10-14T00:00:00+00:00")).unwrap();
+        assert_eq!(Manifest::read(&full).unwrap().parent, None);
+
+        fs::write(world.join("level.dat"), b"changed").unwrap();
+        let first = write_backup(&args, at("2026-10-15T00:00:00+00:00")).unwrap();
+        let second = write_backup(&args, at("2026-10-16T00:00:00+00:00")).unwrap();
+        let name = |backup: &Path| backup.file_name().unwrap().to_str().unwrap().to_string();
+
+        assert!(!first.join("region/r.0.0.mca").exists());
+        assert_eq!(fs::read(first.join("level.dat")).unwrap(), b"changed");
+
+        let manifest = Manifest::read(&second).unwrap();
+        assert_eq!(manifest.parent, Some(name(&first)));
+        let stored_in: Vec<Option<String>> = manifest.files.iter().map(|entry| entry.stored_in.clone()).collect();
+        assert_eq!(stored_in, [Some(name(&first)), Some(name(&full))]);
+
+        // --keep 1 keeps the backups the newest one refers to
+        assert!(full.exists() && first.exists());
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
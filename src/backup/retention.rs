@@ -0,0 +1,74 @@
+use chrono::{Datelike, NaiveDateTime};
+use std::collections::HashSet;
+
+/// How many backups to keep: the newest `last`, and the newest backup of each of the most recent
+/// `daily` days and `weekly` ISO weeks that have one. A backup kept by any rule is kept.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    pub last: usize,
+    pub daily: usize,
+    pub weekly: usize,
+}
+
+impl RetentionPolicy {
+    pub fn is_empty(&self) -> bool {
+        self.last == 0 && self.daily == 0 && self.weekly == 0
+    }
+}
+
+/// Keeps the first backup of each of the first `periods` periods, `sorted` newest first.
+fn keep_newest_per<'a, K: PartialEq>(sorted: &[&'a (String, NaiveDateTime)], periods: usize, period: impl Fn(&NaiveDateTime) -> K, keep: &mut HashSet<&'a str>) {
+    let mut current = None;
+    let mut kept = 0;
+
+    for (name, created) in sorted {
+        if kept == periods {
+            break;
+        }
+
+        let key = period(created);
+        if current.as_ref() != Some(&key) {
+            keep.insert(name.as_str());
+            current = Some(key);
+            kept += 1;
+        }
+    }
+}
+
+/// Names of the backups the policy drops, newest first, given each backup's name and creation time.
+pub fn backups_to_prune(backups: &[(String, NaiveDateTime)], policy: &RetentionPolicy) -> Vec<String> {
+    let mut sorted: Vec<&(String, NaiveDateTime)> = backups.iter().collect();
+    sorted.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| b.0.cmp(&a.0)));
+
+    let mut keep: HashSet<&str> = sorted.iter().take(policy.last).map(|(name, _)| name.as_str()).collect();
+    keep_newest_per(&sorted, policy.daily, |created| (created.year(), created.ordinal()), &mut keep);
+    keep_newest_per(&sorted, policy.weekly, |created| created.iso_week(), &mut keep);
+
+    sorted.into_iter().filter(|(name, _)| !keep.contains(name.as_str())).map(|(name, _)| name.clone()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backups_to_prune() {
+        // two backups a day from Monday 2026-10-05 to Friday 2026-10-16
+        let backups: Vec<(String, NaiveDateTime)> = (5..=16)
+            .flat_map(|day| [6, 18].map(move |hour| NaiveDateTime::parse_from_str(&format!("2026-10-{day:02} {hour:02}:00:00"), "%Y-%m-%d %H:%M:%S").unwrap()))
+            .map(|created| (created.format("%d-%H").to_string(), created))
+            .collect();
+        let kept = |policy: RetentionPolicy| {
+            let pruned = backups_to_prune(&backups, &policy);
+            let mut kept: Vec<String> = backups.iter().map(|(name, _)| name.clone()).filter(|name| !pruned.contains(name)).collect();
+            kept.sort();
+            kept
+        };
+
+        assert_eq!(kept(RetentionPolicy { last: 3, ..Default::default() }), ["15-18", "16-06", "16-18"]);
+        assert_eq!(kept(RetentionPolicy { daily: 2, ..Default::default() }), ["15-18", "16-18"]);
+        assert_eq!(kept(RetentionPolicy { weekly: 3, ..Default::default() }), ["11-18", "16-18"]);
+        assert_eq!(kept(RetentionPolicy { last: 1, daily: 2, weekly: 2 }), ["11-18", "15-18", "16-18"]);
+        assert!(backups_to_prune(&backups, &RetentionPolicy { last: 100, ..Default::default() }).is_empty());
+    }
+}
@@ -0,0 +1,334 @@
+//! Reads every live key of a LevelDB store, in the dialect Bedrock uses: table blocks compressed
+//! with zlib or raw deflate instead of snappy. The whole store is loaded into memory, the newest
+//! sequence number of each key decides between tables and logs.
+
+use crate::bedrock::BedrockError;
+use flate2::read::{DeflateDecoder, ZlibDecoder};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+const TABLE_MAGIC: u64 = 0xdb4775248b80fb57;
+const FOOTER_SIZE: usize = 48;
+const LOG_BLOCK_SIZE: usize = 32768;
+const LOG_HEADER_SIZE: usize = 7;
+
+/// Value type of an internal key, deletions carry no value.
+const TYPE_VALUE: u8 = 1;
+
+/// Per key: the sequence number of its newest write and the value, `None` once deleted.
+type Entries = BTreeMap<Vec<u8>, (u64, Option<Vec<u8>>)>;
+
+/// A key of a block with its value, which stays borrowed from the block.
+type BlockEntry<'a> = (Vec<u8>, &'a [u8]);
+
+fn corrupt(what: &'static str) -> BedrockError {
+    BedrockError::CorruptDatabase(what)
+}
+
+fn read_varint(bytes: &[u8], pos: &mut usize) -> Result<u64, BedrockError> {
+    let mut value = 0u64;
+
+    for shift in (0..64).step_by(7) {
+        let byte = *bytes.get(*pos).ok_or(corrupt("varint"))?;
+        *pos += 1;
+
+        value |= ((byte & 0x7F) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    Err(corrupt("varint"))
+}
+
+fn read_slice<'a>(bytes: &'a [u8], pos: &mut usize, len: usize) -> Result<&'a [u8], BedrockError> {
+    let slice = pos.checked_add(len).and_then(|end| bytes.get(*pos..end)).ok_or(corrupt("length"))?;
+    *pos += len;
+    Ok(slice)
+}
+
+fn read_prefixed<'a>(bytes: &'a [u8], pos: &mut usize) -> Result<&'a [u8], BedrockError> {
+    let len = read_varint(bytes, pos)? as usize;
+    read_slice(bytes, pos, len)
+}
+
+fn insert(entries: &mut Entries, key: &[u8], sequence: u64, value: Option<&[u8]>) {
+    match entries.get(key) {
+        Some((newest, _)) if *newest > sequence => {}
+        _ => {
+            entries.insert(key.to_vec(), (sequence, value.map(<[u8]>::to_vec)));
+        }
+    }
+}
+
+/// Decompresses the block a handle (offset, size) points to, using the type byte after it.
+fn read_block(table: &[u8], handle: &[u8]) -> Result<Vec<u8>, BedrockError> {
+    let mut pos = 0;
+    let offset = read_varint(handle, &mut pos)? as usize;
+    let size = read_varint(handle, &mut pos)? as usize;
+
+    let mut block_pos = offset;
+    let data = read_slice(table, &mut block_pos, size)?;
+    let compression = *table.get(block_pos).ok_or(corrupt("block trailer"))?;
+
+    let mut decompressed = Vec::new();
+    match compression {
+        0 => decompressed.extend_from_slice(data),
+        2 => {
+            ZlibDecoder::new(data).read_to_end(&mut decompressed)?;
+        }
+        4 => {
+            DeflateDecoder::new(data).read_to_end(&mut decompressed)?;
+        }
+        _ => return Err(BedrockError::UnsupportedCompression(compression)),
+    }
+
+    Ok(decompressed)
+}
+
+/// The keys and values of a block, undoing the prefix compression of keys.
+fn block_entries(block: &[u8]) -> Result<Vec<BlockEntry<'_>>, BedrockError> {
+    let restarts_start = block.len().checked_sub(4).ok_or(corrupt("block"))?;
+    let restarts = u32::from_le_bytes(block[restarts_start..].try_into().unwrap()) as usize;
+    let data_end = restarts.checked_mul(4).and_then(|size| restarts_start.checked_sub(size)).ok_or(corrupt("block restarts"))?;
+
+    let mut entries = Vec::new();
+    let mut key: Vec<u8> = Vec::new();
+    let mut pos = 0;
+
+    while pos < data_end {
+        let shared = read_varint(block, &mut pos)? as usize;
+        let non_shared = read_varint(block, &mut pos)? as usize;
+        let value_len = read_varint(block, &mut pos)? as usize;
+
+        if shared > key.len() {
+            return Err(corrupt("shared key prefix"));
+        }
+        key.truncate(shared);
+        key.extend_from_slice(read_slice(block, &mut pos, non_shared)?);
+
+        entries.push((key.clone(), read_slice(block, &mut pos, value_len)?));
+    }
+
+    Ok(entries)
+}
+
+fn read_table(table: &[u8], entries: &mut Entries) -> Result<(), BedrockError> {
+    let footer = table.len().checked_sub(FOOTER_SIZE).map(|start| &table[start..]).ok_or(corrupt("table footer"))?;
+    if u64::from_le_bytes(footer[40..48].try_into().unwrap()) != TABLE_MAGIC {
+        return Err(corrupt("table magic"));
+    }
+
+    // the footer starts with the metaindex handle, which only leads to filters
+    let mut pos = 0;
+    read_varint(footer, &mut pos)?;
+    read_varint(footer, &mut pos)?;
+    let index = read_block(table, &footer[pos..])?;
+
+    for (_, handle) in block_entries(&index)? {
+        for (internal_key, value) in block_entries(&read_block(table, handle)?)? {
+            let key_len = internal_key.len().checked_sub(8).ok_or(corrupt("internal key"))?;
+            let tag = u64::from_le_bytes(internal_key[key_len..].try_into().unwrap());
+
+            let value = (tag as u8 == TYPE_VALUE).then_some(value);
+            insert(entries, &internal_key[..key_len], tag >> 8, value);
+        }
+    }
+
+    Ok(())
+}
+
+/// Applies one write batch: a sequence number and count, then the puts and deletes.
+fn read_write_batch(batch: &[u8], entries: &mut Entries) -> Result<(), BedrockError> {
+    let mut pos = 0;
+    let sequence = u64::from_le_bytes(read_slice(batch, &mut pos, 8)?.try_into().unwrap());
+    let count = u32::from_le_bytes(read_slice(batch, &mut pos, 4)?.try_into().unwrap());
+
+    for index in 0..count as u64 {
+        let value_type = *read_slice(batch, &mut pos, 1)?.first().unwrap();
+        let key = read_prefixed(batch, &mut pos)?;
+        let value = match value_type {
+            TYPE_VALUE => Some(read_prefixed(batch, &mut pos)?),
+            _ => None,
+        };
+
+        insert(entries, key, sequence + index, value);
+    }
+
+    Ok(())
+}
+
+/// Reads a log file of 32 KiB blocks, whose records may be split over several blocks. A record
+/// cut off by a crash ends the log.
+fn read_log(log: &[u8], entries: &mut Entries) -> Result<(), BedrockError> {
+    let mut record = Vec::new();
+    let mut pos = 0;
+
+    while pos + LOG_HEADER_SIZE <= log.len() {
+        let block_left = LOG_BLOCK_SIZE - pos % LOG_BLOCK_SIZE;
+        if block_left < LOG_HEADER_SIZE {
+            pos += block_left;
+            continue;
+        }
+
+        let len = u16::from_le_bytes(log[pos + 4..pos + 6].try_into().unwrap()) as usize;
+        let record_type = log[pos + 6];
+        let Some(data) = log.get(pos + LOG_HEADER_SIZE..pos + LOG_HEADER_SIZE + len).filter(|_| LOG_HEADER_SIZE + len <= block_left) else {
+            break;
+        };
+        pos += LOG_HEADER_SIZE + len;
+
+        match record_type {
+            // full record
+            1 => read_write_batch(data, entries)?,
+            // first, middle and last fragment
+            2 => record = data.to_vec(),
+            3 => record.extend_from_slice(data),
+            4 => {
+                record.extend_from_slice(data);
+                read_write_batch(&record, entries)?;
+            }
+            // zero padding at the end of a block
+            _ => pos += block_left - LOG_HEADER_SIZE - len,
+        }
+    }
+
+    Ok(())
+}
+
+/// Every live key of the store in `db_folder` with its value, in key order.
+pub fn read_database(db_folder: &Path) -> Result<BTreeMap<Vec<u8>, Vec<u8>>, BedrockError> {
+    let mut entries = Entries::new();
+
+    for entry in fs::read_dir(db_folder)? {
+        let path = entry?.path();
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("ldb" | "sst") => read_table(&fs::read(&path)?, &mut entries)?,
+            Some("log") => read_log(&fs::read(&path)?, &mut entries)?,
+            _ => {}
+        }
+    }
+
+    Ok(entries
+        .into_iter()
+        .filter_map(|(key, (_, value))| Some((key, value?)))
+        .collect())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use flate2::write::ZlibEncoder;
+    use std::io::Write;
+
+    fn varint(mut value: usize, out: &mut Vec<u8>) {
+        while value >= 0x80 {
+            out.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        out.push(value as u8);
+    }
+
+    fn block(entries: &[(Vec<u8>, Vec<u8>)]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for (key, value) in entries {
+            varint(0, &mut out);
+            varint(key.len(), &mut out);
+            varint(value.len(), &mut out);
+            out.extend_from_slice(key);
+            out.extend_from_slice(value);
+        }
+        out.extend_from_slice(&0u32.to_le_bytes());
+        out.extend_from_slice(&1u32.to_le_bytes());
+        out
+    }
+
+    fn push_block(table: &mut Vec<u8>, block: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(block).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut handle = Vec::new();
+        varint(table.len(), &mut handle);
+        varint(compressed.len(), &mut handle);
+        table.extend_from_slice(&compressed);
+        table.extend_from_slice(&[2, 0, 0, 0, 0]);
+        handle
+    }
+
+    /// A table with a single zlib compressed data block holding `(key, sequence, value)` puts.
+    pub(crate) fn table(puts: &[(Vec<u8>, u64, Vec<u8>)]) -> Vec<u8> {
+        let data: Vec<(Vec<u8>, Vec<u8>)> = puts
+            .iter()
+            .map(|(key, sequence, value)| {
+                let mut internal_key = key.clone();
+                internal_key.extend_from_slice(&(sequence << 8 | TYPE_VALUE as u64).to_le_bytes());
+                (internal_key, value.clone())
+            })
+            .collect();
+
+        let mut table = Vec::new();
+        let data_handle = push_block(&mut table, &block(&data));
+        let meta_handle = push_block(&mut table, &block(&[]));
+        let index_handle = push_block(&mut table, &block(&[(data.last().unwrap().0.clone(), data_handle)]));
+
+        let mut footer = meta_handle;
+        footer.extend_from_slice(&index_handle);
+        footer.resize(40, 0);
+        footer.extend_from_slice(&TABLE_MAGIC.to_le_bytes());
+        table.extend_from_slice(&footer);
+        table
+    }
+
+    /// Value type, key and value of one write in a batch.
+    type BatchWrite<'a> = (u8, &'a [u8], &'a [u8]);
+
+    fn log(batches: &[(u64, Vec<BatchWrite>)]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for (sequence, writes) in batches {
+            let mut batch = sequence.to_le_bytes().to_vec();
+            batch.extend_from_slice(&(writes.len() as u32).to_le_bytes());
+            for (value_type, key, value) in writes {
+                batch.push(*value_type);
+                varint(key.len(), &mut batch);
+                batch.extend_from_slice(key);
+                if *value_type == TYPE_VALUE {
+                    varint(value.len(), &mut batch);
+                    batch.extend_from_slice(value);
+                }
+            }
+
+            out.extend_from_slice(&[0; 4]);
+            out.extend_from_slice(&(batch.len() as u16).to_le_bytes());
+            out.push(1);
+            out.extend_from_slice(&batch);
+        }
+        out
+    }
+
+    #[test]
+    fn test_read_database() {
+        let folder = std::env::temp_dir().join("bufferedlinear_tools_leveldb_test");
+        fs::create_dir_all(&folder).unwrap();
+
+        let puts = [(b"a".to_vec(), 1, b"old".to_vec()), (b"b".to_vec(), 2, b"kept".to_vec()), (b"c".to_vec(), 3, b"deleted".to_vec())];
+        fs::write(folder.join("000005.ldb"), table(&puts)).unwrap();
+        fs::write(folder.join("000007.log"), log(&[(10, vec![(TYPE_VALUE, b"a", b"new"), (0, b"c", b"")]), (12, vec![(TYPE_VALUE, b"d", b"logged")])])).unwrap();
+        fs::write(folder.join("CURRENT"), "MANIFEST-000001\n").unwrap();
+
+        let database = read_database(&folder).unwrap();
+        fs::remove_dir_all(&folder).unwrap();
+
+        let expected: BTreeMap<Vec<u8>, Vec<u8>> = [(b"a".to_vec(), b"new".to_vec()), (b"b".to_vec(), b"kept".to_vec()), (b"d".to_vec(), b"logged".to_vec())].into_iter().collect();
+        assert_eq!(database, expected);
+    }
+
+    #[test]
+    fn test_corrupt_table() {
+        let mut entries = Entries::new();
+        assert!(read_table(&[0; 10], &mut entries).is_err());
+        assert!(read_table(&[0; 64], &mut entries).is_err());
+    }
+}
@@ -0,0 +1,257 @@
+mod leveldb;
+mod nbt_le;
+mod sub_chunk;
+
+use crate::bedrock::leveldb::read_database;
+use crate::bedrock::sub_chunk::decode_sub_chunk;
+use crate::chunk::Chunk;
+use crate::nbt::parse::NbtError;
+use crate::nbt::tag::Tag;
+use crate::region_file::{Region, RegionFormat};
+use crate::{validate_compression_level, OutputFormat};
+use chrono::Local;
+use clap::{Args, ValueEnum};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// Record tag of a sub chunk in a chunk key, followed by the section index.
+const SUB_CHUNK_PREFIX: u8 = 0x2f;
+/// DataVersion of the written chunks, 1.21
+const JAVA_DATA_VERSION: i32 = 3953;
+
+#[derive(Error, Debug)]
+pub enum BedrockError {
+    #[error("Corrupt LevelDB {0}!")]
+    CorruptDatabase(&'static str),
+    #[error("Unsupported LevelDB block compression {0}!")]
+    UnsupportedCompression(u8),
+    #[error("Corrupt sub chunk!")]
+    CorruptSubChunk,
+    #[error("Unsupported sub chunk version {0}!")]
+    SubChunkVersion(u8),
+    #[error(transparent)]
+    Nbt(#[from] NbtError),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum Dimension {
+    Overworld,
+    Nether,
+    End,
+}
+
+impl Dimension {
+    /// The dimension id Bedrock keys carry, the overworld has none.
+    fn id(self) -> Option<i32> {
+        match self {
+            Dimension::Overworld => None,
+            Dimension::Nether => Some(1),
+            Dimension::End => Some(2),
+        }
+    }
+
+    /// Folder of the dimension's region files in a Java world.
+    fn region_folder(self) -> &'static str {
+        match self {
+            Dimension::Overworld => "region",
+            Dimension::Nether => "DIM-1/region",
+            Dimension::End => "DIM1/region",
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct BedrockArgs {
+    /// Bedrock world folder, containing `db`
+    pub world_path: PathBuf,
+
+    /// Java world folder receiving the region files
+    pub output_path: PathBuf,
+
+    /// Format of the written region files
+    #[arg(long, value_enum, default_value = "linear")]
+    pub format: OutputFormat,
+
+    #[arg(long, value_enum, default_value = "overworld")]
+    pub dimension: Dimension,
+
+    /// Compression level when writing region files
+    #[arg(short, long, default_value = "6", value_parser = validate_compression_level)]
+    pub compression_level: u32,
+}
+
+/// The parts of a key holding a record of a chunk.
+struct ChunkKey<'a> {
+    x: i32,
+    z: i32,
+    /// Absent in the overworld
+    dimension: Option<i32>,
+    tag: u8,
+    /// What follows the tag, the section index of sub chunks
+    suffix: &'a [u8],
+}
+
+fn parse_chunk_key(key: &[u8]) -> Option<ChunkKey<'_>> {
+    let int = |offset: usize| i32::from_le_bytes(key[offset..offset + 4].try_into().unwrap());
+
+    let (dimension, tag_offset) = match key.len() {
+        9 | 10 => (None, 8),
+        13 | 14 => (Some(int(8)), 12),
+        _ => return None,
+    };
+
+    Some(ChunkKey { x: int(0), z: int(4), dimension, tag: key[tag_offset], suffix: &key[tag_offset + 1..] })
+}
+
+/// The Java chunk NBT for the sections of one Bedrock chunk.
+fn java_chunk(x: i32, z: i32, sections: Vec<Tag>) -> Tag {
+    let min_y = sections.iter().filter_map(|section| section.find_tag("Y")).filter_map(|y| match y {
+        Tag::Byte { value, .. } => Some(*value as i32),
+        _ => None,
+    });
+
+    Tag::Compound {
+        name: None,
+        value: vec![
+            Tag::Int { name: Some(String::from("DataVersion")), value: JAVA_DATA_VERSION },
+            Tag::Int { name: Some(String::from("xPos")), value: x },
+            Tag::Int { name: Some(String::from("yPos")), value: min_y.min().unwrap_or(0) },
+            Tag::Int { name: Some(String::from("zPos")), value: z },
+            Tag::String { name: Some(String::from("Status")), value: String::from("minecraft:full") },
+            Tag::Long { name: Some(String::from("LastUpdate")), value: 0 },
+            Tag::Long { name: Some(String::from("InhabitedTime")), value: 0 },
+            Tag::List { name: Some(String::from("sections")), value: sections, tag_type: 10 },
+        ],
+    }
+}
+
+/// Reads the sub chunks of one dimension into Java chunks, grouped by region. Sub chunks in a
+/// format this reader does not know are reported and left out.
+fn read_chunks(database: &BTreeMap<Vec<u8>, Vec<u8>>, dimension: Dimension) -> BTreeMap<(i32, i32), Vec<Chunk>> {
+    let mut sections: BTreeMap<(i32, i32), Vec<Tag>> = BTreeMap::new();
+
+    for (key, value) in database {
+        let Some(ChunkKey { x, z, dimension: key_dimension, tag: SUB_CHUNK_PREFIX, suffix: &[y] }) = parse_chunk_key(key) else {
+            continue;
+        };
+        if key_dimension != dimension.id() {
+            continue;
+        }
+
+        match decode_sub_chunk(value, y as i8) {
+            Ok(Some(section)) => sections.entry((x, z)).or_default().push(section),
+            Ok(None) => {}
+            Err(err) => eprintln!("Failed to read sub chunk {} of chunk {}, {} !, error : {}", y as i8, x, z, err),
+        }
+    }
+
+    let timestamp = Local::now().timestamp();
+    let mut regions: BTreeMap<(i32, i32), Vec<Chunk>> = BTreeMap::new();
+    for ((x, z), sections) in sections {
+        regions.entry((x >> 5, z >> 5)).or_default().push(Chunk::new_from_block_pos(x, z, timestamp, java_chunk(x, z, sections)));
+    }
+
+    regions
+}
+
+pub fn run_bedrock(args: &BedrockArgs) -> Result<(), Box<dyn Error>> {
+    let database = read_database(&args.world_path.join("db"))?;
+    let regions = read_chunks(&database, args.dimension);
+    if regions.is_empty() {
+        return Err("The world has no chunks in this dimension".into());
+    }
+
+    let format = RegionFormat::from(args.format);
+    let region_folder = args.output_path.join(args.dimension.region_folder());
+    fs::create_dir_all(&region_folder)?;
+
+    let regions: Vec<((i32, i32), Vec<Chunk>)> = regions.into_iter().collect();
+    regions.into_par_iter().for_each(|((region_x, region_z), chunks)| {
+        let file = region_folder.join(format!("r.{}.{}.{}", region_x, region_z, format.extension()));
+        let region = Region::new(chunks, Local::now().timestamp());
+
+        let written = region
+            .to_bytes(format, Some((region_x, region_z)), region.timestamp(), args.compression_level as u8)
+            .map_err(|err| err.to_string())
+            .and_then(|bytes| fs::write(&file, bytes).map_err(|err| err.to_string()));
+        match written {
+            Ok(()) => println!("Wrote {} chunks to {}", region.chunks().len(), file.display()),
+            Err(err) => eprintln!("Failed to write file {} !, error : {}", file.display(), err),
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bedrock::leveldb::tests::table;
+    use crate::bedrock::nbt_le::tests::palette_entry;
+    use crate::chunk_data::blocks::block_sections;
+
+    fn chunk_key(x: i32, z: i32, dimension: Option<i32>, y: i8) -> Vec<u8> {
+        let mut key = x.to_le_bytes().to_vec();
+        key.extend_from_slice(&z.to_le_bytes());
+        if let Some(dimension) = dimension {
+            key.extend_from_slice(&dimension.to_le_bytes());
+        }
+        key.push(SUB_CHUNK_PREFIX);
+        key.push(y as u8);
+        key
+    }
+
+    /// A version 9 sub chunk of air with stone at (1, 2, 3) in the section.
+    fn sub_chunk(y: i8) -> Vec<u8> {
+        let mut words = vec![0u32; 128];
+        let index = (1 << 8) | (3 << 4) | 2;
+        words[index / 32] |= 1 << (index % 32);
+
+        let mut data = vec![9, 1, y as u8, 1 << 1];
+        data.extend(words.iter().flat_map(|word| word.to_le_bytes()));
+        data.extend_from_slice(&2i32.to_le_bytes());
+        data.extend_from_slice(&palette_entry("minecraft:air", &[]));
+        data.extend_from_slice(&palette_entry("minecraft:stone", &[("stone_type_is_smooth", 0)]));
+        data
+    }
+
+    #[test]
+    fn test_read_chunks() {
+        let folder = std::env::temp_dir().join("bufferedlinear_tools_bedrock_test");
+        fs::create_dir_all(&folder).unwrap();
+        fs::write(
+            folder.join("000003.ldb"),
+            table(&[
+                (chunk_key(-1, 40, None, -4), 1, sub_chunk(-4)),
+                (chunk_key(-1, 40, None, 2), 2, sub_chunk(2)),
+                (chunk_key(-1, 40, Some(1), 0), 3, sub_chunk(0)),
+            ]),
+        )
+        .unwrap();
+        let database = read_database(&folder).unwrap();
+        fs::remove_dir_all(&folder).unwrap();
+
+        let regions = read_chunks(&database, Dimension::Overworld);
+        assert_eq!(regions.keys().collect::<Vec<_>>(), [&(-1, 1)]);
+
+        let chunk = &regions[&(-1, 1)][0];
+        assert_eq!((chunk.x(), chunk.z()), (-1, 40));
+        assert_eq!(chunk.nbt_position(), Some((-1, 40)));
+        assert_eq!(chunk.find_field("yPos").and_then(Tag::get_int), Some(&-4));
+
+        let sections = block_sections(chunk);
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0].block_at(1, 2, 3).name, "minecraft:stone");
+        assert!(sections[0].block_at(1, 3, 3).is_air());
+
+        assert_eq!(read_chunks(&database, Dimension::Nether)[&(-1, 1)].len(), 1);
+        assert!(read_chunks(&database, Dimension::End).is_empty());
+    }
+}
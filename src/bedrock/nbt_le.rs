@@ -0,0 +1,153 @@
+//! Bedrock's little endian NBT, as stored in block palettes. Strings are plain UTF-8.
+
+use crate::nbt::parse::NbtError;
+use crate::nbt::tag::Tag;
+
+const MAX_DEPTH: usize = 512;
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], NbtError> {
+        let slice = self.pos.checked_add(len).and_then(|end| self.bytes.get(self.pos..end)).ok_or(NbtError::UnexpectedEof)?;
+        self.pos += len;
+        Ok(slice)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], NbtError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn length(&mut self) -> Result<usize, NbtError> {
+        let len = i32::from_le_bytes(self.array()?);
+        let len = usize::try_from(len).map_err(|_| NbtError::NegativeLength(len))?;
+
+        // every element takes at least a byte
+        if len > self.bytes.len() - self.pos {
+            return Err(NbtError::UnexpectedEof);
+        }
+        Ok(len)
+    }
+
+    fn string(&mut self) -> Result<String, NbtError> {
+        let len = u16::from_le_bytes(self.array()?) as usize;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| NbtError::InvalidString)
+    }
+
+    fn payload(&mut self, tag_type: u8, name: Option<String>, depth: usize) -> Result<Tag, NbtError> {
+        if depth >= MAX_DEPTH {
+            return Err(NbtError::TooDeep(MAX_DEPTH));
+        }
+
+        Ok(match tag_type {
+            1 => Tag::Byte { name, value: self.array::<1>()?[0] as i8 },
+            2 => Tag::Short { name, value: i16::from_le_bytes(self.array()?) },
+            3 => Tag::Int { name, value: i32::from_le_bytes(self.array()?) },
+            4 => Tag::Long { name, value: i64::from_le_bytes(self.array()?) },
+            5 => Tag::Float { name, value: f32::from_le_bytes(self.array()?) },
+            6 => Tag::Double { name, value: f64::from_le_bytes(self.array()?) },
+            7 => {
+                let len = self.length()?;
+                Tag::ByteArray { name, value: self.take(len)?.iter().map(|byte| *byte as i8).collect() }
+            }
+            8 => Tag::String { name, value: self.string()? },
+            9 => {
+                let element_type = self.array::<1>()?[0];
+                let len = self.length()?;
+                let value = (0..len).map(|_| self.payload(element_type, None, depth + 1)).collect::<Result<_, _>>()?;
+                Tag::List { name, value, tag_type: element_type }
+            }
+            10 => {
+                let mut value = Vec::new();
+                while let Some(child) = self.named(depth + 1)? {
+                    value.push(child);
+                }
+                Tag::Compound { name, value }
+            }
+            11 => {
+                let len = self.length()?;
+                Tag::IntArray { name, value: self.take(len.checked_mul(4).ok_or(NbtError::UnexpectedEof)?)?.chunks_exact(4).map(|int| i32::from_le_bytes(int.try_into().unwrap())).collect() }
+            }
+            12 => {
+                let len = self.length()?;
+                Tag::LongArray { name, value: self.take(len.checked_mul(8).ok_or(NbtError::UnexpectedEof)?)?.chunks_exact(8).map(|long| i64::from_le_bytes(long.try_into().unwrap())).collect() }
+            }
+            _ => return Err(NbtError::UnknownTagType(tag_type)),
+        })
+    }
+
+    /// A named tag, `None` at the end of a compound.
+    fn named(&mut self, depth: usize) -> Result<Option<Tag>, NbtError> {
+        let tag_type = self.array::<1>()?[0];
+        if tag_type == 0 {
+            return Ok(None);
+        }
+
+        let name = self.string()?;
+        self.payload(tag_type, Some(name), depth).map(Some)
+    }
+}
+
+/// Parses the root tags stored back to back in `bytes` from `pos` on, `count` of them, moving
+/// `pos` past them.
+pub fn parse_le_tags(bytes: &[u8], pos: &mut usize, count: usize) -> Result<Vec<Tag>, NbtError> {
+    let mut reader = Reader { bytes, pos: *pos };
+
+    let mut tags = Vec::new();
+    for _ in 0..count {
+        tags.push(reader.named(0)?.ok_or(NbtError::UnexpectedEof)?);
+    }
+
+    *pos = reader.pos;
+    Ok(tags)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    fn string(value: &str, out: &mut Vec<u8>) {
+        out.extend_from_slice(&(value.len() as u16).to_le_bytes());
+        out.extend_from_slice(value.as_bytes());
+    }
+
+    /// A Bedrock palette entry with a `name`, a byte state and a version.
+    pub(crate) fn palette_entry(name: &str, states: &[(&str, i8)]) -> Vec<u8> {
+        let mut out = vec![10];
+        string("", &mut out);
+        out.push(8);
+        string("name", &mut out);
+        string(name, &mut out);
+        out.push(10);
+        string("states", &mut out);
+        for (state, value) in states {
+            out.push(1);
+            string(state, &mut out);
+            out.push(*value as u8);
+        }
+        out.push(0);
+        out.push(3);
+        string("version", &mut out);
+        out.extend_from_slice(&18100737i32.to_le_bytes());
+        out.push(0);
+        out
+    }
+
+    #[test]
+    fn test_parse_le_tags() {
+        let mut bytes = palette_entry("minecraft:stone", &[]);
+        bytes.extend_from_slice(&palette_entry("minecraft:lit_furnace", &[("lit", 1)]));
+
+        let mut pos = 0;
+        let tags = parse_le_tags(&bytes, &mut pos, 2).unwrap();
+        assert_eq!(pos, bytes.len());
+        assert_eq!(tags[1].find_tag("name").and_then(Tag::get_string).unwrap(), "minecraft:lit_furnace");
+        assert_eq!(tags[1].find_tag("states").and_then(|states| states.find_tag("lit")), Some(&Tag::Byte { name: Some(String::from("lit")), value: 1 }));
+        assert_eq!(tags[0].find_tag("version").and_then(Tag::get_int), Some(&18100737));
+
+        assert_eq!(parse_le_tags(&bytes[..10], &mut 0, 1), Err(NbtError::UnexpectedEof));
+    }
+}
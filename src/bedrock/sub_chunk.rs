@@ -0,0 +1,115 @@
+//! Bedrock sub chunks (`SubChunkPrefix` records, versions 1, 8 and 9) turned into Java sections.
+//! Only the first block layer is read, the second one holds water in waterlogged blocks.
+
+use crate::bedrock::nbt_le::parse_le_tags;
+use crate::bedrock::BedrockError;
+use crate::chunk_data::packed::{bits_for_palette, pack};
+use crate::nbt::tag::Tag;
+
+const SECTION_BLOCKS: usize = 4096;
+
+fn named<T>(name: &str, make: impl FnOnce(Option<String>) -> T) -> T {
+    make(Some(String::from(name)))
+}
+
+/// A palette entry `{name, states, version}` as a Java `{Name, Properties}` entry. State values
+/// are kept as text, bytes holding 0 or 1 become `false` and `true`.
+fn java_palette_entry(entry: &Tag) -> Tag {
+    let name = entry.find_tag("name").and_then(Tag::get_string).cloned().unwrap_or_else(|| String::from("minecraft:air"));
+
+    let properties: Vec<Tag> = entry
+        .find_tag("states")
+        .and_then(Tag::children)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|state| {
+            let value = match state {
+                Tag::Byte { value: 0, .. } => String::from("false"),
+                Tag::Byte { value: 1, .. } => String::from("true"),
+                _ => state.value_to_string()?,
+            };
+            Some(Tag::String { name: state.get_name(), value })
+        })
+        .collect();
+
+    let mut value = vec![named("Name", |tag_name| Tag::String { name: tag_name, value: name })];
+    if !properties.is_empty() {
+        value.push(named("Properties", |name| Tag::Compound { name, value: properties }));
+    }
+
+    Tag::Compound { name: None, value }
+}
+
+/// Decodes a sub chunk into a Java section, `None` when it holds no block layer. `key_y` is the
+/// section index from the record key, used by versions that do not store their own.
+pub fn decode_sub_chunk(data: &[u8], key_y: i8) -> Result<Option<Tag>, BedrockError> {
+    let (layers, y, mut pos) = match data.first() {
+        Some(1) => (1, key_y, 1),
+        Some(8) => (*data.get(1).ok_or(BedrockError::CorruptSubChunk)?, key_y, 2),
+        Some(9) => (*data.get(1).ok_or(BedrockError::CorruptSubChunk)?, *data.get(2).ok_or(BedrockError::CorruptSubChunk)? as i8, 3),
+        Some(version) => return Err(BedrockError::SubChunkVersion(*version)),
+        None => return Err(BedrockError::CorruptSubChunk),
+    };
+    if layers == 0 {
+        return Ok(None);
+    }
+
+    let flags = *data.get(pos).ok_or(BedrockError::CorruptSubChunk)?;
+    pos += 1;
+    if flags & 1 != 0 {
+        // runtime ids only appear in network packets, never on disk
+        return Err(BedrockError::CorruptSubChunk);
+    }
+
+    let bits = (flags >> 1) as usize;
+    let mut indices = vec![0u64; SECTION_BLOCKS];
+    let palette_len = if bits == 0 {
+        1
+    } else {
+        if bits > 16 {
+            return Err(BedrockError::CorruptSubChunk);
+        }
+
+        let per_word = 32 / bits;
+        let words = SECTION_BLOCKS.div_ceil(per_word);
+        let packed = data.get(pos..pos + words * 4).ok_or(BedrockError::CorruptSubChunk)?;
+        pos += words * 4;
+
+        // Bedrock orders blocks x, z, y from the outside in, Java y, z, x
+        for index in 0..SECTION_BLOCKS {
+            let word = u32::from_le_bytes(packed[index / per_word * 4..][..4].try_into().unwrap());
+            let value = (word >> (index % per_word * bits)) & ((1 << bits) - 1);
+
+            let (x, z, y) = (index >> 8, (index >> 4) & 15, index & 15);
+            indices[y << 8 | z << 4 | x] = value as u64;
+        }
+
+        let len = data.get(pos..pos + 4).ok_or(BedrockError::CorruptSubChunk)?;
+        pos += 4;
+        i32::from_le_bytes(len.try_into().unwrap()).max(0) as usize
+    };
+
+    let palette = parse_le_tags(data, &mut pos, palette_len)?;
+    if indices.iter().any(|index| *index as usize >= palette.len()) {
+        return Err(BedrockError::CorruptSubChunk);
+    }
+
+    let mut block_states = vec![named("palette", |name| Tag::List { name, value: palette.iter().map(java_palette_entry).collect(), tag_type: 10 })];
+    if palette.len() > 1 {
+        let bits = bits_for_palette(palette.len()).max(4);
+        block_states.push(named("data", |name| Tag::LongArray { name, value: pack(&indices, bits) }));
+    }
+
+    // biomes live in a separate record with their own ids, every section starts out as plains
+    let plains = Tag::String { name: None, value: String::from("minecraft:plains") };
+    let biomes = vec![named("palette", |name| Tag::List { name, value: vec![plains], tag_type: 8 })];
+
+    Ok(Some(Tag::Compound {
+        name: None,
+        value: vec![
+            named("Y", |name| Tag::Byte { name, value: y }),
+            named("block_states", |name| Tag::Compound { name, value: block_states }),
+            named("biomes", |name| Tag::Compound { name, value: biomes }),
+        ],
+    }))
+}
@@ -0,0 +1,114 @@
+use crate::region_file::{read_region_file, region_coords_from_path, Region, RegionFormat};
+use clap::Args;
+use std::error::Error;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+#[derive(Args)]
+pub struct BenchCompressArgs {
+    /// Region file in any of the supported formats (mca, linear, blinear)
+    pub region_file: PathBuf,
+
+    /// Lowest compression level to measure
+    #[arg(long, default_value = "1", value_parser = crate::validate_compression_level)]
+    pub min_level: u32,
+
+    /// Highest compression level to measure
+    #[arg(long, default_value = "22", value_parser = crate::validate_compression_level)]
+    pub max_level: u32,
+
+    /// Runs per level, the fastest encode and decode are reported
+    #[arg(long, default_value = "3")]
+    pub iterations: u32,
+}
+
+struct Measurement {
+    size: usize,
+    encode: Duration,
+    decode: Duration,
+}
+
+/// Writes the region in `format` and reads it back `iterations` times, keeping the fastest runs.
+fn measure(region: &Region, region_coords: Option<(i32, i32)>, format: RegionFormat, compression_level: u8, iterations: u32) -> Result<Measurement, Box<dyn Error>> {
+    let mut measurement = Measurement { size: 0, encode: Duration::MAX, decode: Duration::MAX };
+
+    for _ in 0..iterations.max(1) {
+        let started = Instant::now();
+        let bytes = region.to_bytes(format, region_coords, region.timestamp(), compression_level)?;
+        measurement.encode = measurement.encode.min(started.elapsed());
+
+        let started = Instant::now();
+        Region::from_bytes(format, &bytes)?;
+        measurement.decode = measurement.decode.min(started.elapsed());
+
+        measurement.size = bytes.len();
+    }
+
+    Ok(measurement)
+}
+
+/// The codecs measured, as the format writing them and the levels it distinguishes.
+fn codec_levels(min_level: u32, max_level: u32) -> Vec<(&'static str, RegionFormat, Vec<u32>)> {
+    vec![
+        ("zstd", RegionFormat::Blinear, (min_level..=max_level).collect()),
+        ("zlib", RegionFormat::Mca, (min_level..=max_level.min(*RegionFormat::Mca.compression_levels().end() as u32)).collect()),
+    ]
+}
+
+/// Compresses one region at every level of every supported codec and prints size and timings.
+/// Decode times include parsing the chunk NBT, as a real read does.
+pub fn run_bench_compress(args: &BenchCompressArgs) -> Result<(), Box<dyn Error>> {
+    if args.min_level > args.max_level {
+        return Err("--min-level must not be above --max-level".into());
+    }
+
+    let (format, region) = read_region_file(&args.region_file)?;
+    let raw_size: usize = region.chunks().iter().map(|chunk| chunk.to_raw_bytes().len()).sum();
+
+    println!("{} ({:?}, {} chunks, {} bytes of NBT)", args.region_file.display(), format, region.chunks().len(), raw_size);
+    println!("{:<6} {:>5} {:>12} {:>7} {:>11} {:>11}", "codec", "level", "size", "ratio", "encode ms", "decode ms");
+
+    for (codec, format, levels) in codec_levels(args.min_level, args.max_level) {
+        for level in levels {
+            let measurement = measure(&region, region_coords_from_path(&args.region_file), format, level as u8, args.iterations)?;
+            println!(
+                "{:<6} {:>5} {:>12} {:>6.2}% {:>11.2} {:>11.2}",
+                codec,
+                level,
+                measurement.size,
+                measurement.size as f64 * 100.0 / raw_size.max(1) as f64,
+                measurement.encode.as_secs_f64() * 1000.0,
+                measurement.decode.as_secs_f64() * 1000.0,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::nbt::tag::Tag;
+
+    #[test]
+    fn test_codec_levels() {
+        let levels = codec_levels(3, 12);
+        assert_eq!(levels[0].2, (3..=12).collect::<Vec<_>>());
+        assert_eq!(levels[1].2, (3..=9).collect::<Vec<_>>());
+        assert!(codec_levels(10, 12)[1].2.is_empty());
+    }
+
+    #[test]
+    fn test_measure() {
+        let data = Tag::Compound { name: None, value: vec![Tag::LongArray { name: Some(String::from("heights")), value: vec![7; 512] }] };
+        let region = Region::new(vec![Chunk::new_from_block_pos(0, 0, 0, data)], 0);
+
+        for format in [RegionFormat::Blinear, RegionFormat::Mca] {
+            let measurement = measure(&region, None, format, 3, 2).unwrap();
+            assert!(measurement.size > 0);
+            assert!(measurement.encode < Duration::MAX && measurement.decode < Duration::MAX);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use crate::chunk::Chunk;
+use crate::region_file::{read_region_file, Region, RegionFormat};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+/// Keeps the most recently used parsed regions in memory, keyed by path. Regions are loaded on
+/// first use and the least recently used one is dropped once `capacity` are held.
+pub struct RegionCache {
+    capacity: usize,
+    /// Least recently used first
+    entries: Vec<(PathBuf, Region)>,
+}
+
+impl RegionCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: Vec::new(),
+        }
+    }
+
+    /// The parsed region of a file, read and parsed if it is not cached.
+    pub fn get(&mut self, path: &Path) -> Result<&Region, Box<dyn Error>> {
+        match self.entries.iter().position(|(cached, _)| cached == path) {
+            Some(index) => {
+                let entry = self.entries.remove(index);
+                self.entries.push(entry);
+            }
+            None => {
+                let (_, region) = read_region_file(path)?;
+                if self.entries.len() == self.capacity {
+                    self.entries.remove(0);
+                }
+                self.entries.push((path.to_path_buf(), region));
+            }
+        }
+
+        Ok(&self.entries.last().unwrap().1)
+    }
+
+    /// The chunk at the given global chunk coordinates from a folder of region files in any
+    /// format, `None` if its region file or the chunk does not exist.
+    pub fn chunk(&mut self, region_folder: &Path, x: i32, z: i32) -> Result<Option<&Chunk>, Box<dyn Error>> {
+        let (region_x, region_z) = (x >> 5, z >> 5);
+        let path = [RegionFormat::Mca, RegionFormat::Linear, RegionFormat::Blinear]
+            .iter()
+            .map(|format| region_folder.join(format!("r.{}.{}.{}", region_x, region_z, format.extension())))
+            .find(|path| path.exists());
+
+        let Some(path) = path else {
+            return Ok(None);
+        };
+
+        let region = self.get(&path)?;
+        Ok(region.chunks().iter().find(|chunk| chunk.global_position(region_x, region_z) == (x, z)))
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        self.entries.iter().any(|(cached, _)| cached == path)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nbt::tag::Tag;
+    use std::fs;
+
+    #[test]
+    fn test_lru_eviction() {
+        let folder = std::env::temp_dir().join("bufferedlinear_tools_cache_test");
+        fs::create_dir_all(&folder).unwrap();
+
+        let paths: Vec<PathBuf> = (0..3)
+            .map(|region_x| {
+                let chunk = Chunk::new_from_block_pos(region_x, 0, 0, Tag::Compound { name: None, value: Vec::new() });
+                let path = folder.join(format!("r.{region_x}.0.blinear"));
+                fs::write(&path, Region::new(vec![chunk], 0).to_bytes_blinear(0, 3)).unwrap();
+                path
+            })
+            .collect();
+
+        let mut cache = RegionCache::new(2);
+        cache.get(&paths[0]).unwrap();
+        cache.get(&paths[1]).unwrap();
+        cache.get(&paths[0]).unwrap();
+        cache.get(&paths[2]).unwrap();
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.contains(&paths[0]) && cache.contains(&paths[2]));
+        assert!(!cache.contains(&paths[1]));
+
+        assert!(cache.chunk(&folder, 33, 0).unwrap().is_some());
+        assert!(cache.chunk(&folder, 34, 0).unwrap().is_none());
+        assert!(cache.chunk(&folder, 200, 0).unwrap().is_none());
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
@@ -1,52 +1,29 @@
 use crate::nbt::binary_reader::BinaryReader;
-use crate::nbt::parse::parse_tag;
+use crate::nbt::parse::{parse_tag, NbtError, ParseOptions};
 use crate::nbt::tag::Tag;
+use crate::timings::{time, Phase};
 
 pub struct Chunk{
     position: i64,
     timestamp: i64,
+    raw_size: usize,
+    compressed_size: usize,
     pub data: Tag
 }
 
 impl Chunk {
 
-    pub fn from_region_index(
-        chunk_index: usize,
-        region_x: i32,
-        region_z: i32,
-        timestamp: i64,
-        data: &[u8]
-    ) -> Result<Self, &'static str> {
-        let parsed_data = parse_tag(&mut BinaryReader::new(&data));
-
-        let local_x = (chunk_index % 32) as i32;
-        let local_z = (chunk_index / 32) as i32;
-
-        let global_x = 1024 * region_x + local_x;
-        let global_z = 1024 * region_z + local_z;
-
-        Ok(Self::new_from_block_pos(global_x, global_z, timestamp, parsed_data))
-    }
-
-    pub fn from_sector(sector_index: i32, timestamp: i64, data: &[u8]) -> Result<Self, &'static str> {
-        let parsed_data = parse_tag(&mut BinaryReader::new(&data));
+    pub fn from_sector(sector_index: i32, timestamp: i64, data: &[u8], options: &ParseOptions) -> Result<Self, NbtError> {
+        let parsed_data = time(Phase::Parse, || parse_tag(&mut BinaryReader::new(data), options))?;
 
         let x = sector_index & 31;
         let z = (sector_index >> 5) & 31;
 
-        Ok(Self::new_from_block_pos(x, z, timestamp, parsed_data))
+        Ok(Self::new_from_block_pos(x, z, timestamp, parsed_data).with_sizes(data.len(), 0))
     }
 
     pub fn to_raw_bytes(&self) -> Vec<u8> {
-        self.data.to_bytes()
-    }
-
-    pub fn new(position: i64, timestamp: i64, data: Tag) -> Self {
-        Self {
-            position,
-            timestamp,
-            data
-        }
+        time(Phase::Serialize, || self.data.to_bytes())
     }
 
     pub fn new_from_block_pos(x: i32, z: i32, timestamp: i64, data: Tag) -> Self {
@@ -55,23 +32,44 @@ impl Chunk {
         Self {
             position,
             timestamp,
+            raw_size: 0,
+            compressed_size: 0,
             data
         }
     }
 
+    /// Records how many bytes the chunk occupied in the file it was read from, uncompressed and
+    /// compressed. Formats compressing several chunks together report the chunk's share of it.
+    pub fn with_sizes(mut self, raw_size: usize, compressed_size: usize) -> Self {
+        self.raw_size = raw_size;
+        self.compressed_size = compressed_size;
+        self
+    }
+
     pub fn position_to_sector_index(&self) -> i32 {
-        let x = &self.x();
-        let z = &self.z();
+        let x = self.x();
+        let z = self.z();
 
-        ((x & 31) as usize + ((z & 31) as usize) << 5) as i32
+        (x & 31) + ((z & 31) << 5)
+    }
+
+    /// Global chunk coordinates, given the coordinates of the region the chunk was read from.
+    pub fn global_position(&self, region_x: i32, region_z: i32) -> (i32, i32) {
+        (region_x * 32 + (self.x() & 31), region_z * 32 + (self.z() & 31))
+    }
+
+    /// Moves the chunk to another slot. Positions may be region local or global, only the
+    /// position within the region decides the slot a writer puts the chunk in.
+    pub fn set_position(&mut self, x: i32, z: i32) {
+        self.position = ((x as i64) << 32) | (z as i64 & 0xFFFFFFFF);
     }
 
     pub fn x(&self) -> i32 {
-        (self.position as u32) as i32
+        ((self.position as u64 >> 32) as u32) as i32
     }
 
     pub fn z(&self) -> i32 {
-        ((self.position as u64 >> 32) as u32) as i32
+        (self.position as u32) as i32
     }
 
     pub fn get_data(&self) -> &Tag {
@@ -81,4 +79,102 @@ impl Chunk {
     pub fn timestamp(&self) -> i64 {
         self.timestamp
     }
-}
\ No newline at end of file
+
+    pub fn raw_size(&self) -> usize {
+        self.raw_size
+    }
+
+    pub fn compressed_size(&self) -> usize {
+        self.compressed_size
+    }
+
+    /// Looks up a top level chunk field, which pre-1.18 chunks keep under `Level`.
+    pub fn find_field(&self, name: &str) -> Option<&Tag> {
+        self.data
+            .find_tag(name)
+            .or_else(|| self.data.find_tag("Level").and_then(|level| level.find_tag(name)))
+    }
+
+    pub fn data_version(&self) -> Option<i32> {
+        self.find_field("DataVersion").and_then(|tag| tag.get_int()).copied()
+    }
+
+    /// The compound holding the top level chunk fields, `Level` for pre-1.18 chunks.
+    pub fn fields_mut(&mut self) -> &mut Tag {
+        if self.data.find_tag("Level").is_some() {
+            return self.data.find_tag_mut("Level").unwrap();
+        }
+
+        &mut self.data
+    }
+
+    /// The chunk coordinates recorded in the NBT itself: `xPos`/`zPos` for terrain chunks, the
+    /// `Position` int array for entity chunks.
+    pub fn nbt_position(&self) -> Option<(i32, i32)> {
+        if let (Some(x), Some(z)) = (self.find_field("xPos"), self.find_field("zPos")) {
+            return Some((*x.get_int()?, *z.get_int()?));
+        }
+
+        match self.data.find_tag("Position") {
+            Some(Tag::IntArray { value, .. }) if value.len() == 2 => Some((value[0], value[1])),
+            _ => None,
+        }
+    }
+
+    /// Rewrites the NBT position fields that exist in the chunk, returns false if there are none.
+    pub fn set_nbt_position(&mut self, x: i32, z: i32) -> bool {
+        let root_has_position = self.data.find_tag("xPos").is_some() || self.data.find_tag("Position").is_some();
+        let holder = match self.data.find_tag_mut("Level") {
+            Some(level) if !root_has_position => level,
+            _ => &mut self.data,
+        };
+
+        let mut updated = false;
+        if let Some(Tag::Int { value, .. }) = holder.find_tag_mut("xPos") {
+            *value = x;
+            updated = true;
+        }
+        if let Some(Tag::Int { value, .. }) = holder.find_tag_mut("zPos") {
+            *value = z;
+            updated = true;
+        }
+        if let Some(Tag::IntArray { value, .. }) = holder.find_tag_mut("Position")
+            && value.len() == 2
+        {
+            *value = vec![x, z];
+            updated = true;
+        }
+
+        updated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_pos() {
+        let chunk = Chunk::new_from_block_pos(3, -5, 0, Tag::End);
+
+        assert_eq!((chunk.x(), chunk.z()), (3, -5));
+    }
+
+    #[test]
+    fn test_position_to_sector_index() {
+        let chunk = Chunk::new_from_block_pos(3, 5, 0, Tag::End);
+        assert_eq!(chunk.position_to_sector_index(), 163);
+
+        let chunk = Chunk::new_from_block_pos(-1, 33, 0, Tag::End);
+        assert_eq!(chunk.position_to_sector_index(), 31 + (1 << 5));
+    }
+
+    #[test]
+    fn test_from_sector() {
+        let data = Tag::Compound { name: None, value: Vec::new() }.to_bytes();
+        let chunk = Chunk::from_sector(163, 0, &data, &ParseOptions::default()).unwrap();
+
+        assert_eq!((chunk.x(), chunk.z()), (3, 5));
+        assert_eq!(chunk.position_to_sector_index(), 163);
+    }
+}
@@ -0,0 +1,89 @@
+use crate::chunk::Chunk;
+use crate::chunk_data::packed::{bits_for_palette, unpack};
+use crate::nbt::tag::Tag;
+use std::collections::BTreeMap;
+
+// biomes are stored per 4x4x4 cell, 64 cells per section
+const CELLS_PER_SECTION: usize = 64;
+
+fn count_section_biomes(biomes: &Tag, counts: &mut BTreeMap<String, u64>) {
+    let palette: Vec<&String> = biomes
+        .find_tag("palette")
+        .and_then(|palette| palette.children())
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|biome| biome.get_string())
+        .collect();
+
+    let indices = match biomes.find_tag("data") {
+        Some(Tag::LongArray { value, .. }) if palette.len() > 1 => {
+            unpack(value, bits_for_palette(palette.len()), CELLS_PER_SECTION, false)
+        }
+        _ => vec![0; CELLS_PER_SECTION],
+    };
+
+    for index in indices {
+        if let Some(biome) = palette.get(index as usize) {
+            *counts.entry((*biome).clone()).or_default() += 1;
+        }
+    }
+}
+
+/// Adds the number of biome cells of every biome in the chunk to `counts`. Chunks from before
+/// 1.18 only store numeric biome ids, which are counted as `legacy:<id>`.
+pub fn count_biomes(chunk: &Chunk, counts: &mut BTreeMap<String, u64>) {
+    if let Some(Tag::IntArray { value, .. }) = chunk.find_field("Biomes") {
+        for id in value {
+            *counts.entry(format!("legacy:{id}")).or_default() += 1;
+        }
+        return;
+    }
+
+    let sections = chunk
+        .find_field("sections")
+        .and_then(|sections| sections.children())
+        .unwrap_or_default();
+
+    for biomes in sections.iter().filter_map(|section| section.find_tag("biomes")) {
+        count_section_biomes(biomes, counts);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section_biomes(palette: &[&str], data: Option<Vec<i64>>) -> Tag {
+        let mut value = vec![Tag::List {
+            name: Some(String::from("palette")),
+            tag_type: 8,
+            value: palette
+                .iter()
+                .map(|biome| Tag::String { name: None, value: String::from(*biome) })
+                .collect(),
+        }];
+        if let Some(data) = data {
+            value.push(Tag::LongArray { name: Some(String::from("data")), value: data });
+        }
+
+        Tag::Compound { name: Some(String::from("biomes")), value }
+    }
+
+    #[test]
+    fn test_count_single_biome_section() {
+        let mut counts = BTreeMap::new();
+        count_section_biomes(&section_biomes(&["minecraft:plains"], None), &mut counts);
+
+        assert_eq!(counts.get("minecraft:plains"), Some(&64));
+    }
+
+    #[test]
+    fn test_count_paletted_section() {
+        // one bit per cell, the first three cells use the second palette entry
+        let mut counts = BTreeMap::new();
+        count_section_biomes(&section_biomes(&["minecraft:plains", "minecraft:river"], Some(vec![0b111])), &mut counts);
+
+        assert_eq!(counts.get("minecraft:plains"), Some(&61));
+        assert_eq!(counts.get("minecraft:river"), Some(&3));
+    }
+}
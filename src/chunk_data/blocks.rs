@@ -0,0 +1,108 @@
+use crate::chunk::Chunk;
+use crate::chunk_data::packed::{bits_for_palette, unpack, NON_SPANNING_DATA_VERSION};
+use crate::nbt::tag::Tag;
+
+const BLOCKS_PER_SECTION: usize = 4096;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct BlockState {
+    pub name: String,
+    pub properties: Vec<(String, String)>,
+}
+
+impl BlockState {
+    fn from_tag(tag: &Tag) -> Self {
+        let name = tag
+            .find_tag("Name")
+            .and_then(|name| name.get_string())
+            .cloned()
+            .unwrap_or_else(|| String::from("minecraft:air"));
+        let properties = tag
+            .find_tag("Properties")
+            .and_then(|properties| properties.children())
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|property| Some((property.get_name()?, property.get_string()?.clone())))
+            .collect();
+
+        Self { name, properties }
+    }
+
+    pub fn is_air(&self) -> bool {
+        matches!(self.name.as_str(), "minecraft:air" | "minecraft:cave_air" | "minecraft:void_air")
+    }
+}
+
+/// The block states of one 16x16x16 chunk section.
+pub struct BlockSection {
+    pub y: i32,
+    pub palette: Vec<BlockState>,
+    indices: Vec<u16>,
+}
+
+impl BlockSection {
+    /// Palette index of the block at section-local coordinates.
+    pub fn index_at(&self, x: usize, y: usize, z: usize) -> usize {
+        self.indices
+            .get((y * 16 + z) * 16 + x)
+            .map_or(0, |index| *index as usize)
+    }
+
+    pub fn block_at(&self, x: usize, y: usize, z: usize) -> &BlockState {
+        let index = self.index_at(x, y, z);
+        self.palette.get(index).unwrap_or(&self.palette[0])
+    }
+}
+
+fn decode_section(section: &Tag, spanning: bool) -> Option<BlockSection> {
+    let y = match section.find_tag("Y")? {
+        Tag::Byte { value, .. } => *value as i32,
+        Tag::Int { value, .. } => *value,
+        _ => return None,
+    };
+
+    // 1.18+ nests the palette under block_states, 1.13 - 1.17 keep it on the section
+    let (palette_tag, data_tag) = match section.find_tag("block_states") {
+        Some(block_states) => (block_states.find_tag("palette")?, block_states.find_tag("data")),
+        None => (section.find_tag("Palette")?, section.find_tag("BlockStates")),
+    };
+
+    let palette: Vec<BlockState> = palette_tag.children()?.iter().map(BlockState::from_tag).collect();
+    if palette.is_empty() {
+        return None;
+    }
+
+    let indices = match data_tag {
+        Some(Tag::LongArray { value, .. }) if palette.len() > 1 => {
+            let bits = bits_for_palette(palette.len()).max(4);
+            unpack(value, bits, BLOCKS_PER_SECTION, spanning)
+                .into_iter()
+                .map(|index| index as u16)
+                .collect()
+        }
+        _ => Vec::new(),
+    };
+
+    Some(BlockSection { y, palette, indices })
+}
+
+/// Decodes the block sections of a chunk, sorted from the bottom up.
+pub fn block_sections(chunk: &Chunk) -> Vec<BlockSection> {
+    let spanning = chunk
+        .find_field("DataVersion")
+        .and_then(|tag| tag.get_int())
+        .is_some_and(|version| *version < NON_SPANNING_DATA_VERSION);
+
+    let sections = chunk
+        .find_field("sections")
+        .or_else(|| chunk.find_field("Sections"))
+        .and_then(|sections| sections.children())
+        .unwrap_or_default();
+
+    let mut decoded: Vec<BlockSection> = sections
+        .iter()
+        .filter_map(|section| decode_section(section, spanning))
+        .collect();
+    decoded.sort_by_key(|section| section.y);
+    decoded
+}
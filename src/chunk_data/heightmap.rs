@@ -0,0 +1,71 @@
+use crate::chunk::Chunk;
+use crate::chunk_data::packed::unpack;
+use crate::nbt::tag::Tag;
+
+const HEIGHTMAP_BITS: u32 = 9;
+const COLUMNS: usize = 256;
+// 256 nine bit values fill exactly 36 longs when packed back to back
+const SPANNING_HEIGHTMAP_LONGS: usize = 36;
+
+/// Lowest block y of a chunk: `yPos` sections below zero since 1.18, otherwise 0.
+pub fn min_block_y(chunk: &Chunk) -> i32 {
+    chunk
+        .find_field("yPos")
+        .and_then(|tag| tag.get_int())
+        .map_or(0, |section_y| section_y * 16)
+}
+
+/// Decodes a heightmap such as `WORLD_SURFACE` into 256 values, indexed by `z * 16 + x`. Each
+/// value is the number of blocks from the chunk's minimum y up to and including the highest
+/// matching block, so 0 means an empty column.
+pub fn decode_heightmap(chunk: &Chunk, name: &str) -> Option<Vec<u16>> {
+    let Tag::LongArray { value, .. } = chunk.find_field("Heightmaps")?.find_tag(name)? else {
+        return None;
+    };
+
+    let spanning = value.len() == SPANNING_HEIGHTMAP_LONGS;
+    Some(
+        unpack(value, HEIGHTMAP_BITS, COLUMNS, spanning)
+            .into_iter()
+            .map(|height| height as u16)
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk_with_heightmap(longs: Vec<i64>) -> Chunk {
+        let heightmaps = Tag::Compound {
+            name: Some(String::from("Heightmaps")),
+            value: vec![Tag::LongArray {
+                name: Some(String::from("WORLD_SURFACE")),
+                value: longs,
+            }],
+        };
+        let root = Tag::Compound {
+            name: None,
+            value: vec![Tag::Int { name: Some(String::from("yPos")), value: -4 }, heightmaps],
+        };
+
+        Chunk::new_from_block_pos(0, 0, 0, root)
+    }
+
+    #[test]
+    fn test_decode_heightmap() {
+        // seven 9 bit values per long, 37 longs in total
+        let mut longs = vec![0i64; 37];
+        longs[0] = 133 | (70 << 9);
+        longs[36] = 5 << 18;
+
+        let chunk = chunk_with_heightmap(longs);
+        let heights = decode_heightmap(&chunk, "WORLD_SURFACE").unwrap();
+
+        assert_eq!(heights.len(), 256);
+        assert_eq!(heights[..3], [133, 70, 0]);
+        assert_eq!(heights[254], 5);
+        assert_eq!(min_block_y(&chunk), -64);
+        assert!(decode_heightmap(&chunk, "MOTION_BLOCKING").is_none());
+    }
+}
@@ -0,0 +1,4 @@
+pub mod biomes;
+pub mod blocks;
+pub mod heightmap;
+pub mod packed;
@@ -0,0 +1,95 @@
+/// First DataVersion (20w17a) whose packed arrays stop values from spanning two longs.
+pub const NON_SPANNING_DATA_VERSION: i32 = 2527;
+
+/// Number of bits needed to index a palette of `palette_len` entries.
+pub fn bits_for_palette(palette_len: usize) -> u32 {
+    if palette_len <= 1 {
+        0
+    } else {
+        usize::BITS - (palette_len - 1).leading_zeros()
+    }
+}
+
+/// Unpacks `count` values of `bits` bits each from a packed long array. Values past the end of a
+/// truncated array read as zero.
+pub fn unpack(longs: &[i64], bits: u32, count: usize, spanning: bool) -> Vec<u64> {
+    if bits == 0 || bits > 32 {
+        return vec![0; count];
+    }
+
+    let mask = (1u64 << bits) - 1;
+    let long_at = |index: usize| longs.get(index).map_or(0, |long| *long as u64);
+
+    (0..count)
+        .map(|index| {
+            if spanning {
+                let bit_index = index * bits as usize;
+                let (long_index, offset) = (bit_index / 64, (bit_index % 64) as u32);
+                let mut value = long_at(long_index) >> offset;
+
+                if offset + bits > 64 {
+                    value |= long_at(long_index + 1) << (64 - offset);
+                }
+                value & mask
+            } else {
+                let per_long = (64 / bits) as usize;
+                let offset = (index % per_long) as u32 * bits;
+                (long_at(index / per_long) >> offset) & mask
+            }
+        })
+        .collect()
+}
+
+/// Packs values of `bits` bits each into longs the way DataVersions from
+/// [`NON_SPANNING_DATA_VERSION`] on expect, the inverse of a non spanning [`unpack`].
+#[cfg(feature = "bedrock")]
+pub fn pack(values: &[u64], bits: u32) -> Vec<i64> {
+    let per_long = (64 / bits) as usize;
+    let mut longs = vec![0u64; values.len().div_ceil(per_long)];
+
+    for (index, value) in values.iter().enumerate() {
+        longs[index / per_long] |= value << ((index % per_long) as u32 * bits);
+    }
+
+    longs.into_iter().map(|long| long as i64).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bits_for_palette() {
+        assert_eq!(bits_for_palette(1), 0);
+        assert_eq!(bits_for_palette(2), 1);
+        assert_eq!(bits_for_palette(16), 4);
+        assert_eq!(bits_for_palette(17), 5);
+    }
+
+    #[test]
+    fn test_unpack_non_spanning() {
+        // 5 bits per value leaves 4 padding bits at the top of every long
+        let longs = [(1i64 << 55) | (3 << 5) | 2, 7];
+
+        assert_eq!(unpack(&longs, 5, 14, false)[..3], [2, 3, 0]);
+        assert_eq!(unpack(&longs, 5, 14, false)[11..], [1, 7, 0]);
+    }
+
+    #[cfg(feature = "bedrock")]
+    #[test]
+    fn test_pack_round_trip() {
+        let values: Vec<u64> = (0..4096).map(|index| index % 19).collect();
+        let longs = pack(&values, 5);
+
+        assert_eq!(longs.len(), 4096usize.div_ceil(12));
+        assert_eq!(unpack(&longs, 5, 4096, false), values);
+    }
+
+    #[test]
+    fn test_unpack_spanning() {
+        // the 13th 5 bit value starts at bit 60 and continues in the next long
+        let longs = [0b1011i64 << 60, 0b1];
+
+        assert_eq!(unpack(&longs, 5, 13, true)[12], 0b11011);
+    }
+}
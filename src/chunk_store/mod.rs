@@ -0,0 +1,308 @@
+use crate::chunk::Chunk;
+use crate::region_file::{ParseLimits, RawRegion, Region, RegionFormat, LINEAR_DEFAULT_GRID_SIZE};
+use crate::{throttle, validate_compression_level, OutputFormat};
+use clap::Args;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use thiserror::Error;
+
+/// Extension of the region manifests written in place of region files.
+pub const MANIFEST_EXTENSION: &str = "chunks";
+const MANIFEST_MAGIC: &[u8; 4] = b"BLCS";
+const MANIFEST_VERSION: u8 = 1;
+const MANIFEST_HEADER_SIZE: usize = 24;
+const MANIFEST_ENTRY_SIZE: usize = 42;
+/// zstd level chunk payloads are stored with.
+const OBJECT_COMPRESSION_LEVEL: i32 = 6;
+
+#[derive(Args)]
+pub struct MaterializeArgs {
+    /// Folder written by a conversion with `--chunk-store`, its `.chunks` manifests are read
+    pub input: PathBuf,
+
+    /// Folder receiving the region files, in the same layout as the manifests
+    pub output: PathBuf,
+
+    /// The chunk store the conversion wrote to
+    #[arg(long, value_name = "DIR")]
+    pub store: PathBuf,
+
+    /// Format of the written region files, defaults to the conversion's output format
+    #[arg(long, value_enum)]
+    pub format: Option<OutputFormat>,
+
+    /// Compression level of the written region files
+    #[arg(short, long, default_value = "6", value_parser = validate_compression_level)]
+    pub compression_level: u32,
+}
+
+#[derive(Error, Debug)]
+pub enum ChunkStoreError {
+    #[error("Invalid chunk manifest {0}")]
+    InvalidManifest(PathBuf),
+    #[error("Chunk {0} is missing from the store")]
+    MissingChunk(String),
+    #[error("Chunk {0} in the store does not match its hash")]
+    CorruptChunk(String),
+    #[error("{0} manifests could not be materialized")]
+    Failed(usize),
+}
+
+/// One chunk of a region manifest.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ManifestEntry {
+    slot: u16,
+    timestamp: i64,
+    hash: [u8; 32],
+}
+
+/// Replaces a region file, naming the stored payload of every chunk.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct RegionManifest {
+    format: RegionFormat,
+    region_x: i32,
+    region_z: i32,
+    timestamp: i64,
+    entries: Vec<ManifestEntry>,
+}
+
+fn format_id(format: RegionFormat) -> u8 {
+    match format {
+        RegionFormat::Mca => 0,
+        RegionFormat::Linear => 1,
+        RegionFormat::Blinear => 2,
+    }
+}
+
+fn hex(hash: &[u8; 32]) -> String {
+    hash.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+impl RegionManifest {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(MANIFEST_HEADER_SIZE + self.entries.len() * MANIFEST_ENTRY_SIZE);
+        bytes.extend_from_slice(MANIFEST_MAGIC);
+        bytes.push(MANIFEST_VERSION);
+        bytes.push(format_id(self.format));
+        bytes.extend_from_slice(&self.region_x.to_be_bytes());
+        bytes.extend_from_slice(&self.region_z.to_be_bytes());
+        bytes.extend_from_slice(&self.timestamp.to_be_bytes());
+        bytes.extend_from_slice(&(self.entries.len() as u16).to_be_bytes());
+        for entry in &self.entries {
+            bytes.extend_from_slice(&entry.slot.to_be_bytes());
+            bytes.extend_from_slice(&entry.timestamp.to_be_bytes());
+            bytes.extend_from_slice(&entry.hash);
+        }
+
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < MANIFEST_HEADER_SIZE || &bytes[..4] != MANIFEST_MAGIC || bytes[4] != MANIFEST_VERSION {
+            return None;
+        }
+
+        let format = *RegionFormat::ALL.iter().find(|format| format_id(**format) == bytes[5])?;
+        let count = u16::from_be_bytes(bytes[22..24].try_into().unwrap()) as usize;
+        let body = &bytes[MANIFEST_HEADER_SIZE..];
+        if count > 1024 || body.len() != count * MANIFEST_ENTRY_SIZE {
+            return None;
+        }
+
+        let entries: Vec<ManifestEntry> = body
+            .chunks_exact(MANIFEST_ENTRY_SIZE)
+            .map(|entry| ManifestEntry {
+                slot: u16::from_be_bytes(entry[0..2].try_into().unwrap()),
+                timestamp: i64::from_be_bytes(entry[2..10].try_into().unwrap()),
+                hash: entry[10..42].try_into().unwrap(),
+            })
+            .collect();
+        if entries.iter().any(|entry| entry.slot >= 1024) {
+            return None;
+        }
+
+        Some(Self {
+            format,
+            region_x: i32::from_be_bytes(bytes[6..10].try_into().unwrap()),
+            region_z: i32::from_be_bytes(bytes[10..14].try_into().unwrap()),
+            timestamp: i64::from_be_bytes(bytes[14..22].try_into().unwrap()),
+            entries,
+        })
+    }
+}
+
+/// A chunk read back through a manifest.
+pub struct StoredChunk {
+    /// When the manifest was written, in milliseconds since the epoch
+    pub written: i64,
+    pub timestamp: i64,
+    pub data: Vec<u8>,
+}
+
+/// A folder of zstd compressed chunk NBT, each stored once under the SHA-256 of the NBT.
+pub struct ChunkStore {
+    folder: PathBuf,
+}
+
+/// Tells apart the temporary files of chunks stored at the same time.
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+impl ChunkStore {
+    pub fn new(folder: PathBuf) -> Self {
+        Self { folder }
+    }
+
+    pub fn folder(&self) -> &Path {
+        &self.folder
+    }
+
+    fn object_path(&self, hash: &[u8; 32]) -> PathBuf {
+        let name = hex(hash);
+        self.folder.join(&name[..2]).join(name)
+    }
+
+    /// Stores the chunk unless the store already has it, returns its hash.
+    fn put(&self, data: &[u8]) -> Result<[u8; 32], Box<dyn Error>> {
+        let hash: [u8; 32] = Sha256::digest(data).into();
+        let path = self.object_path(&hash);
+        if path.exists() {
+            return Ok(hash);
+        }
+
+        // other threads may store the same chunk, each renames a complete file into place
+        fs::create_dir_all(path.parent().unwrap())?;
+        let temp = path.with_extension(format!("{}.{}.tmp", std::process::id(), TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)));
+        throttle::write(&temp, zstd::encode_all(data, OBJECT_COMPRESSION_LEVEL)?)?;
+        fs::rename(&temp, &path)?;
+        throttle::sync_parent(&path)?;
+
+        Ok(hash)
+    }
+
+    fn get(&self, hash: &[u8; 32]) -> Result<Vec<u8>, Box<dyn Error>> {
+        let path = self.object_path(hash);
+        if !path.exists() {
+            return Err(Box::new(ChunkStoreError::MissingChunk(hex(hash))));
+        }
+
+        let data = zstd::decode_all(&throttle::read(&path)?[..])?;
+        if Sha256::digest(&data)[..] != hash[..] {
+            return Err(Box::new(ChunkStoreError::CorruptChunk(hex(hash))));
+        }
+
+        Ok(data)
+    }
+
+    /// The chunk in `slot` of a manifest, `None` when the manifest has no chunk there.
+    pub fn read_chunk(&self, manifest_file: &Path, slot: usize) -> Result<Option<StoredChunk>, Box<dyn Error>> {
+        let manifest = RegionManifest::from_bytes(&throttle::read(manifest_file)?).ok_or_else(|| ChunkStoreError::InvalidManifest(manifest_file.to_path_buf()))?;
+        match manifest.entries.iter().find(|entry| entry.slot as usize == slot) {
+            Some(entry) => Ok(Some(StoredChunk { written: manifest.timestamp, timestamp: entry.timestamp, data: self.get(&entry.hash)? })),
+            None => Ok(None),
+        }
+    }
+
+    /// Stores the chunks of a region, returns the manifest bytes replacing its region file.
+    pub fn put_region(&self, region: &RawRegion, format: RegionFormat, region_coords: (i32, i32), timestamp: i64) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut entries = Vec::new();
+        for (slot, chunk) in region.slots().iter().enumerate() {
+            if let Some(chunk) = chunk {
+                entries.push(ManifestEntry { slot: slot as u16, timestamp: chunk.timestamp, hash: self.put(&chunk.data)? });
+            }
+        }
+
+        let (region_x, region_z) = region_coords;
+        Ok(RegionManifest { format, region_x, region_z, timestamp, entries }.to_bytes())
+    }
+}
+
+/// Manifests below `folder`, recursively.
+fn manifest_files(folder: &Path, files: &mut Vec<PathBuf>) -> Result<(), Box<dyn Error>> {
+    for entry in fs::read_dir(folder)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            manifest_files(&path, files)?;
+        } else if path.extension().is_some_and(|extension| extension == MANIFEST_EXTENSION) {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Rebuilds the region file of one manifest, returns the path written.
+fn materialize_file(manifest_file: &Path, store: &ChunkStore, args: &MaterializeArgs) -> Result<PathBuf, Box<dyn Error>> {
+    let manifest = RegionManifest::from_bytes(&throttle::read(manifest_file)?).ok_or_else(|| ChunkStoreError::InvalidManifest(manifest_file.to_path_buf()))?;
+    let format = args.format.map_or(manifest.format, RegionFormat::from);
+
+    let mut chunks = Vec::with_capacity(manifest.entries.len());
+    for entry in &manifest.entries {
+        chunks.push(Chunk::from_sector(entry.slot as i32, entry.timestamp, &store.get(&entry.hash)?, &ParseLimits::DEFAULT.nbt)?);
+    }
+    let region = Region::new(chunks, manifest.timestamp);
+
+    let compression_level = args.compression_level as u8;
+    let bytes = match format {
+        RegionFormat::Linear => region.to_bytes_linear_v2(manifest.region_x, manifest.region_z, manifest.timestamp, compression_level, LINEAR_DEFAULT_GRID_SIZE)?,
+        _ => region.to_bytes(format, Some((manifest.region_x, manifest.region_z)), manifest.timestamp, compression_level)?,
+    };
+
+    let relative = manifest_file.strip_prefix(&args.input).expect("Scanned path is inside the input");
+    let output = args.output.join(relative).with_extension(format.extension());
+    fs::create_dir_all(output.parent().unwrap())?;
+    throttle::write(&output, bytes)?;
+
+    Ok(output)
+}
+
+pub fn run_materialize(args: &MaterializeArgs) -> Result<(), Box<dyn Error>> {
+    let mut manifests = Vec::new();
+    manifest_files(&args.input, &mut manifests)?;
+    let store = ChunkStore::new(args.store.clone());
+
+    let failed = manifests
+        .par_iter()
+        .filter(|manifest_file| match materialize_file(manifest_file, &store, args) {
+            Ok(output) => {
+                println!("Materialized {}", output.display());
+                false
+            }
+            Err(err) => {
+                eprintln!("Failed to materialize {} !, error : {}", manifest_file.display(), err);
+                true
+            }
+        })
+        .count();
+
+    if failed > 0 {
+        return Err(Box::new(ChunkStoreError::Failed(failed)));
+    }
+
+    println!("Materialized {} region files", manifests.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_roundtrip() {
+        let manifest = RegionManifest {
+            format: RegionFormat::Linear,
+            region_x: -1,
+            region_z: 4,
+            timestamp: 1_700_000_000_000,
+            entries: vec![ManifestEntry { slot: 33, timestamp: 1_700_000_000, hash: [7; 32] }, ManifestEntry { slot: 1023, timestamp: 0, hash: [255; 32] }],
+        };
+
+        let bytes = manifest.to_bytes();
+        assert_eq!(bytes.len(), MANIFEST_HEADER_SIZE + 2 * MANIFEST_ENTRY_SIZE);
+        assert_eq!(RegionManifest::from_bytes(&bytes), Some(manifest));
+        assert_eq!(RegionManifest::from_bytes(&bytes[..bytes.len() - 1]), None);
+    }
+}
@@ -0,0 +1,148 @@
+use crate::region_file::{read_region_file, region_coords_from_path, Region};
+use crate::{folder_name, scan_region_files, RegionType};
+use clap::Args;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Args)]
+pub struct CleanupArgs {
+    /// Path to your Minecraft world, its poi and entities region files are rewritten in place
+    pub world_path: PathBuf,
+
+    /// Only report what would be removed
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Compression level when rewriting region files
+    #[arg(short, long, default_value = "6", value_parser = crate::validate_compression_level)]
+    pub compression_level: u32,
+}
+
+/// Global positions of every terrain chunk of a world. Fails if any terrain region file can not
+/// be read, since its chunks would otherwise all look missing.
+pub fn terrain_chunks(world_path: &Path) -> Result<HashSet<(i32, i32)>, Box<dyn Error>> {
+    let mut positions = HashSet::new();
+
+    for region_file in scan_region_files(world_path.join(folder_name(RegionType::REGION))) {
+        let Some((region_x, region_z)) = region_coords_from_path(&region_file) else {
+            continue;
+        };
+
+        let (_, region) = read_region_file(&region_file)
+            .map_err(|err| format!("Failed to read terrain file {} !, error : {}", region_file.display(), err))?;
+        positions.extend(region.chunks().iter().map(|chunk| chunk.global_position(region_x, region_z)));
+    }
+
+    Ok(positions)
+}
+
+/// Drops the chunks of a poi or entities region that have no terrain chunk, returns how many.
+pub fn drop_orphans(region: &mut Region, region_x: i32, region_z: i32, terrain: &HashSet<(i32, i32)>) -> usize {
+    let before = region.chunks().len();
+    region.retain_chunks(|chunk| terrain.contains(&chunk.global_position(region_x, region_z)));
+
+    before - region.chunks().len()
+}
+
+fn cleanup_file(
+    region_file: &Path,
+    terrain: &HashSet<(i32, i32)>,
+    args: &CleanupArgs,
+) -> Result<usize, Box<dyn Error>> {
+    let (region_x, region_z) = region_coords_from_path(region_file).ok_or("File name is not r.<x>.<z>.<ext>")?;
+    let (format, mut region) = read_region_file(region_file)?;
+
+    let dropped = drop_orphans(&mut region, region_x, region_z, terrain);
+    if dropped == 0 || args.dry_run {
+        return Ok(dropped);
+    }
+
+    if region.chunks().is_empty() {
+        fs::remove_file(region_file)?;
+        return Ok(dropped);
+    }
+
+    // write next to the original and swap, so an interrupted run never leaves a truncated file
+    let bytes = region.to_bytes(format, Some((region_x, region_z)), region.timestamp(), args.compression_level as u8)?;
+    let temp_file = region_file.with_extension("tmp");
+    fs::write(&temp_file, bytes)?;
+    fs::rename(&temp_file, region_file)?;
+
+    Ok(dropped)
+}
+
+pub fn run_cleanup(args: &CleanupArgs) -> Result<(), Box<dyn Error>> {
+    let terrain = terrain_chunks(&args.world_path)?;
+    let mut total = 0;
+
+    for region_type in [RegionType::POI, RegionType::ENTITIES] {
+        let mut region_files = scan_region_files(args.world_path.join(folder_name(region_type)));
+        region_files.sort();
+
+        for region_file in region_files {
+            match cleanup_file(&region_file, &terrain, args) {
+                Ok(0) => {}
+                Ok(dropped) => {
+                    total += dropped;
+                    println!("{}: {} orphaned chunks", region_file.display(), dropped);
+                }
+                Err(err) => eprintln!("Failed to clean file {} !, error : {}", region_file.display(), err),
+            }
+        }
+    }
+
+    let action = if args.dry_run { "would be removed" } else { "removed" };
+    println!("{total} orphaned poi and entities chunks {action}");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::nbt::tag::Tag;
+
+    #[test]
+    fn test_drop_orphans() {
+        let chunk = |x, z| Chunk::new_from_block_pos(x, z, 0, Tag::Compound { name: None, value: Vec::new() });
+        let mut region = Region::new(vec![chunk(0, 0), chunk(1, 0), chunk(0, 1)], 0);
+        let terrain = HashSet::from([(-32, 32), (-32, 33), (5, 5)]);
+
+        assert_eq!(drop_orphans(&mut region, -1, 1, &terrain), 1);
+        assert_eq!(region.chunks().len(), 2);
+        assert_eq!((region.chunks()[1].x(), region.chunks()[1].z()), (0, 1));
+    }
+
+    #[test]
+    fn test_drop_orphans_per_dimension() {
+        use clap::Parser;
+
+        let folder = std::env::temp_dir().join("bufferedlinear_tools_cleanup_dimensions_test");
+        let _ = fs::remove_dir_all(&folder);
+        let chunk = |x| Chunk::new_from_block_pos(x, 0, 0, Tag::Compound { name: None, value: Vec::new() });
+        let write = |path: &str, xs: &[i32]| {
+            let path = folder.join("world").join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, Region::new(xs.iter().map(|&x| chunk(x)).collect(), 0).to_bytes_mca(6).unwrap()).unwrap();
+        };
+        write("region/r.0.0.mca", &[0]);
+        write("poi/r.0.0.mca", &[0, 1]);
+        // the nether has terrain where the overworld has none
+        write("DIM-1/region/r.0.0.mca", &[1, 2]);
+        write("DIM-1/poi/r.0.0.mca", &[1, 2]);
+
+        let (world, output) = (folder.join("world"), folder.join("output"));
+        let args = ["bufferedlinear_tools", "mca-blinear", "poi", world.to_str().unwrap(), output.to_str().unwrap(), "--drop-orphans", "--dimension-map", "*"];
+        let convert = crate::Cli::try_parse_from(args).unwrap().convert.unwrap();
+        crate::convert_options(&convert).and_then(|options| crate::run_convert(&convert, options)).unwrap();
+
+        let chunks = |path: &str| read_region_file(&output.join(path)).unwrap().1.chunks().len();
+        assert_eq!(chunks("poi/r.0.0.blinear"), 1);
+        assert_eq!(chunks("DIM-1/poi/r.0.0.blinear"), 2);
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
@@ -0,0 +1,33 @@
+use crate::Cli;
+use clap::{Args, CommandFactory};
+use clap_complete::{generate, Shell};
+use std::io::Write;
+
+#[derive(Args)]
+pub struct CompletionsArgs {
+    /// Shell to generate the completion script for
+    pub shell: Shell,
+}
+
+/// Writes the completion script of `shell` for every mode, region type, subcommand and flag.
+pub fn write_completions(shell: Shell, out: &mut dyn Write) {
+    let mut command = Cli::command();
+    let name = command.get_name().to_string();
+    generate(shell, &mut command, name, out);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_completions() {
+        let mut script = Vec::new();
+        write_completions(Shell::Bash, &mut script);
+        let script = String::from_utf8(script).unwrap();
+
+        for word in ["mca-blinear", "blinear-linear", "entities", "export-structure", "--grid-size"] {
+            assert!(script.contains(word), "{word} missing from the completion script");
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use clap::Command;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::PathBuf;
+use thiserror::Error;
+use toml::{Table, Value};
+
+/// Profile used when `--config` is given without `--profile`.
+pub const DEFAULT_PROFILE: &str = "default";
+/// Environment variables used when `--config` and `--profile` are not given.
+pub const CONFIG_ENV: &str = "BLT_CONFIG";
+pub const PROFILE_ENV: &str = "BLT_PROFILE";
+
+/// Environment variables giving the default of a convert argument, by argument id. Applied as
+/// defaults rather than with clap's `env`, which would count as converting and conflict with
+/// every subcommand.
+const ENV_DEFAULTS: [(&str, &str); 6] = [
+    ("output_path", "BLT_OUTPUT"),
+    ("compression_level", "BLT_COMPRESSION_LEVEL"),
+    ("state_db", "BLT_STATE_DB"),
+    ("blinear_version", "BLT_BLINEAR_VERSION"),
+    ("grid_size", "BLT_GRID_SIZE"),
+    ("progress_interval", "BLT_PROGRESS_INTERVAL"),
+];
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Failed to read config file {path} : {source}")]
+    Read { path: PathBuf, source: std::io::Error },
+    #[error("Invalid config file : {0}")]
+    Parse(#[from] toml::de::Error),
+    #[error("Config file has no [profile.{0}] table")]
+    MissingProfile(String),
+    #[error("Value of {0} must be a string, number, boolean, date or an array of those")]
+    UnsupportedValue(String),
+    #[error("{0} is not a convert or global argument")]
+    UnknownSetting(String),
+}
+
+/// Sets the defaults of the arguments in `ENV_DEFAULTS` whose environment variable is set.
+fn env_defaults(command: Command) -> Command {
+    ENV_DEFAULTS.iter().fold(command, |command, (id, variable)| match env::var(variable) {
+        Ok(value) => command.mut_arg(*id, |arg| arg.default_value(value).required(false)),
+        Err(_) => command,
+    })
+}
+
+/// The value of `--<name> VALUE` or `--<name>=VALUE` in the arguments.
+fn option_value(args: &[OsString], name: &str) -> Option<OsString> {
+    let flag = format!("--{name}");
+    let prefix = format!("--{name}=");
+
+    args.iter().enumerate().find_map(|(index, arg)| {
+        let arg = arg.to_str()?;
+        if arg == flag {
+            args.get(index + 1).cloned()
+        } else {
+            arg.strip_prefix(&prefix).map(OsString::from)
+        }
+    })
+}
+
+fn value_string(key: &str, value: &Value) -> Result<String, ConfigError> {
+    match value {
+        Value::String(value) => Ok(value.clone()),
+        Value::Integer(value) => Ok(value.to_string()),
+        Value::Float(value) => Ok(value.to_string()),
+        Value::Datetime(value) => Ok(value.to_string()),
+        _ => Err(ConfigError::UnsupportedValue(key.to_string())),
+    }
+}
+
+/// Arguments of the command line `--config` and `--profile` choose, which a profile can not set.
+const CONFIG_ARGS: [&str; 2] = ["config", "profile"];
+
+/// Defaults of the convert and global arguments of `command` set by a profile, by argument id:
+/// `key = value` sets the default of `--key`, `key = true` turns the flag on and arrays give every
+/// value. Keys may use `_` or `-`.
+pub fn profile_settings(config: &str, profile: &str, command: &Command) -> Result<Vec<(String, Vec<String>)>, ConfigError> {
+    let table: Table = config.parse()?;
+    let settings = table
+        .get("profile")
+        .and_then(Value::as_table)
+        .and_then(|profiles| profiles.get(profile))
+        .and_then(Value::as_table)
+        .ok_or_else(|| ConfigError::MissingProfile(profile.to_string()))?;
+
+    let mut defaults = Vec::new();
+    for (key, value) in settings {
+        let id = key.replace('-', "_");
+        if CONFIG_ARGS.contains(&id.as_str()) || !command.get_arguments().any(|arg| arg.get_id() == id.as_str()) {
+            return Err(ConfigError::UnknownSetting(key.clone()));
+        }
+
+        let values = match value {
+            Value::Boolean(true) => vec![String::from("true")],
+            Value::Boolean(false) => continue,
+            Value::Array(values) => values.iter().map(|value| value_string(key, value)).collect::<Result<_, _>>()?,
+            value => vec![value_string(key, value)?],
+        };
+        defaults.push((id, values));
+    }
+
+    Ok(defaults)
+}
+
+/// Sets argument defaults from the `--profile` (or `default`) profile of the `--config` file,
+/// falling back to `BLT_PROFILE` and `BLT_CONFIG`. Subcommands only take the global arguments of
+/// the profile, conversions the convert arguments too. Flags given on the command line win over
+/// the profile, which wins over the environment.
+fn profile_defaults(command: Command, args: &[OsString], subcommand: bool) -> Result<Command, ConfigError> {
+    let Some(path) = option_value(args, "config").or_else(|| env::var_os(CONFIG_ENV)).map(PathBuf::from) else {
+        return Ok(command);
+    };
+    let profile = option_value(args, "profile").or_else(|| env::var_os(PROFILE_ENV)).and_then(|profile| profile.into_string().ok()).unwrap_or_else(|| String::from(DEFAULT_PROFILE));
+
+    let config = fs::read_to_string(&path).map_err(|source| ConfigError::Read { path: path.clone(), source })?;
+
+    let settings = profile_settings(&config, &profile, &command)?;
+    let applies = |id: &str| !subcommand || command.get_arguments().any(|arg| arg.get_id() == id && arg.is_global_set());
+    let settings: Vec<(String, Vec<String>)> = settings.into_iter().filter(|(id, _)| applies(id)).collect();
+
+    Ok(settings
+        .into_iter()
+        .fold(command, |command, (id, values)| command.mut_arg(id, |arg| arg.default_values(values).env(None).required(false))))
+}
+
+/// Whether the arguments run a subcommand rather than a conversion.
+fn runs_subcommand(command: &Command, args: &[OsString]) -> bool {
+    command.clone().ignore_errors(true).try_get_matches_from(args).is_ok_and(|matches| matches.subcommand_name().is_some())
+}
+
+/// Applies the environment and then the profile defaults. Subcommands get neither for the
+/// convert arguments, as clap checks defaults even of arguments a run does not use, so an invalid
+/// `BLT_COMPRESSION_LEVEL` would fail `stats` too, but still read the config for the global ones.
+pub fn cli_defaults(command: Command, args: &[OsString]) -> Result<Command, ConfigError> {
+    if runs_subcommand(&command, args) {
+        return profile_defaults(command, args, true);
+    }
+
+    profile_defaults(env_defaults(command), args, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Cli;
+    use clap::{CommandFactory, FromArgMatches};
+
+    const CONFIG: &str = r#"
+        [profile.nightly-backup]
+        compression_level = 19
+        grid-size = 8
+        strip_light = true
+        seekable = false
+        purge_entity = ["minecraft:item", "minecraft:arrow"]
+        threads = 4
+    "#;
+
+    #[test]
+    fn test_profile_settings() {
+        let command = Cli::command();
+        let settings = profile_settings(CONFIG, "nightly-backup", &command).unwrap();
+        let settings: Vec<(&str, Vec<&str>)> = settings.iter().map(|(id, values)| (id.as_str(), values.iter().map(String::as_str).collect())).collect();
+        assert_eq!(settings, [
+            ("compression_level", vec!["19"]),
+            ("grid_size", vec!["8"]),
+            ("purge_entity", vec!["minecraft:item", "minecraft:arrow"]),
+            ("strip_light", vec!["true"]),
+            ("threads", vec!["4"]),
+        ]);
+
+        assert!(matches!(profile_settings(CONFIG, "default", &command), Err(ConfigError::MissingProfile(_))));
+        assert!(matches!(profile_settings("[profile.default]\ngrid_size = { a = 1 }", "default", &command), Err(ConfigError::UnsupportedValue(_))));
+        assert!(matches!(profile_settings("[profile.default]\nworlds = 4", "default", &command), Err(ConfigError::UnknownSetting(_))));
+        assert!(matches!(profile_settings("[profile.default]\nprofile = \"other\"", "default", &command), Err(ConfigError::UnknownSetting(_))));
+    }
+
+    /// Parses a command line with the profile of the test config applied.
+    fn parse_with_config(name: &str, args: &[&str]) -> Cli {
+        let path = std::env::temp_dir().join(format!("bufferedlinear_tools_config_{name}.toml"));
+        fs::write(&path, CONFIG).unwrap();
+
+        let args: Vec<OsString> = args.iter().map(OsString::from).chain([OsString::from("--profile=nightly-backup"), OsString::from("--config"), path.clone().into_os_string()]).collect();
+        let matches = cli_defaults(Cli::command(), &args).unwrap().try_get_matches_from(args).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        Cli::from_arg_matches(&matches).unwrap()
+    }
+
+    #[test]
+    fn test_profile_defaults() {
+        let cli = parse_with_config("convert", &["bufferedlinear_tools", "mca-linear", "region", "world", "out", "-c", "3", "--grid-size=16"]);
+        let convert = cli.convert.unwrap();
+        assert_eq!((convert.compression_level, convert.grid_size), (3, 16));
+        assert_eq!(convert.purge_entity, ["minecraft:item", "minecraft:arrow"]);
+        assert!(convert.strip_light && !convert.seekable);
+        assert_eq!(cli.threads, Some(4));
+
+        let cli = parse_with_config("subcommand", &["bufferedlinear_tools", "stats", "world"]);
+        assert!(cli.command.is_some() && cli.convert.is_none());
+        assert_eq!(cli.threads, Some(4));
+
+        let cli = parse_with_config("override", &["bufferedlinear_tools", "stats", "world", "-j", "2"]);
+        assert_eq!(cli.threads, Some(2));
+    }
+
+    #[test]
+    fn test_invalid_defaults_skip_subcommands() {
+        let path = std::env::temp_dir().join("bufferedlinear_tools_config_invalid.toml");
+        fs::write(&path, "[profile.default]\ncompression_level = 30").unwrap();
+        let args = |args: &[&str]| -> Vec<OsString> { args.iter().map(OsString::from).chain([OsString::from("--config"), path.clone().into_os_string()]).collect() };
+
+        let stats = args(&["bufferedlinear_tools", "stats", "world"]);
+        assert!(cli_defaults(Cli::command(), &stats).unwrap().try_get_matches_from(stats).is_ok());
+        let convert = args(&["bufferedlinear_tools", "mca-linear", "region", "world", "out"]);
+        assert!(cli_defaults(Cli::command(), &convert).unwrap().try_get_matches_from(convert).is_err());
+
+        // a config that can not be read fails subcommands as well
+        let missing = ["bufferedlinear_tools", "stats", "world", "--config", "/nonexistent.toml"].map(OsString::from);
+        assert!(matches!(cli_defaults(Cli::command(), &missing), Err(ConfigError::Read { .. })));
+
+        fs::remove_file(&path).unwrap();
+    }
+}
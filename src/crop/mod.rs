@@ -0,0 +1,120 @@
+use crate::region_file::{read_region_file, region_coords_from_path};
+use crate::{folder_name, scan_region_files, RegionType};
+use clap::Args;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Args)]
+pub struct CropArgs {
+    /// Path to the Minecraft world to crop, it is left untouched
+    pub world_path: PathBuf,
+
+    /// Where to write the cropped region, entities and poi folders
+    pub output_path: PathBuf,
+
+    /// One corner of the chunk box, e.g. `-10,4`
+    #[arg(long, required = true, value_parser = crate::parse_chunk_coords, allow_hyphen_values = true)]
+    pub from: (i32, i32),
+
+    /// The opposite corner of the chunk box, included in the box
+    #[arg(long, required = true, value_parser = crate::parse_chunk_coords, allow_hyphen_values = true)]
+    pub to: (i32, i32),
+
+    /// Compression level when writing region files
+    #[arg(short, long, default_value = "6", value_parser = crate::validate_compression_level)]
+    pub compression_level: u32,
+}
+
+/// An inclusive box of global chunk coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChunkBox {
+    min: (i32, i32),
+    max: (i32, i32),
+}
+
+impl ChunkBox {
+    pub fn new((x1, z1): (i32, i32), (x2, z2): (i32, i32)) -> Self {
+        Self { min: (x1.min(x2), z1.min(z2)), max: (x1.max(x2), z1.max(z2)) }
+    }
+
+    pub fn contains(&self, (x, z): (i32, i32)) -> bool {
+        (self.min.0..=self.max.0).contains(&x) && (self.min.1..=self.max.1).contains(&z)
+    }
+
+    /// Whether any chunk of the region lies in the box.
+    pub fn intersects_region(&self, (region_x, region_z): (i32, i32)) -> bool {
+        (self.min.0 >> 5..=self.max.0 >> 5).contains(&region_x) && (self.min.1 >> 5..=self.max.1 >> 5).contains(&region_z)
+    }
+}
+
+fn crop_file(region_file: &Path, output_folder: &Path, chunk_box: &ChunkBox, args: &CropArgs) -> Result<usize, Box<dyn Error>> {
+    let (region_x, region_z) = region_coords_from_path(region_file).ok_or("File name is not r.<x>.<z>.<ext>")?;
+    let (format, mut region) = read_region_file(region_file)?;
+
+    region.retain_chunks(|chunk| chunk_box.contains(chunk.global_position(region_x, region_z)));
+    if region.chunks().is_empty() {
+        return Ok(0);
+    }
+
+    let output = output_folder.join(region_file.file_name().unwrap());
+    fs::write(output, region.to_bytes(format, Some((region_x, region_z)), region.timestamp(), args.compression_level as u8)?)?;
+
+    Ok(region.chunks().len())
+}
+
+pub fn run_crop(args: &CropArgs) -> Result<(), Box<dyn Error>> {
+    let chunk_box = ChunkBox::new(args.from, args.to);
+    let mut total = 0;
+
+    for region_type in [RegionType::REGION, RegionType::ENTITIES, RegionType::POI] {
+        let region_files: Vec<PathBuf> = scan_region_files(args.world_path.join(folder_name(region_type)))
+            .into_iter()
+            .filter(|path| region_coords_from_path(path).is_some_and(|coords| chunk_box.intersects_region(coords)))
+            .collect();
+        if region_files.is_empty() {
+            continue;
+        }
+
+        let output_folder = args.output_path.join(folder_name(region_type));
+        fs::create_dir_all(&output_folder)?;
+
+        let kept: usize = region_files
+            .par_iter()
+            .map(|region_file| match crop_file(region_file, &output_folder, &chunk_box, args) {
+                Ok(chunks) => chunks,
+                Err(err) => {
+                    eprintln!("Failed to crop file {} !, error : {}", region_file.display(), err);
+                    0
+                }
+            })
+            .sum();
+
+        println!("{}: {} chunks kept from {} files", folder_name(region_type), kept, region_files.len());
+        total += kept;
+    }
+
+    println!("{total} chunks written to {}", args.output_path.display());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_box() {
+        let chunk_box = ChunkBox::new((10, -40), (-33, 3));
+
+        assert!(chunk_box.contains((-33, -40)));
+        assert!(chunk_box.contains((10, 3)));
+        assert!(!chunk_box.contains((11, 0)));
+
+        assert!(chunk_box.intersects_region((-2, -2)));
+        assert!(chunk_box.intersects_region((0, 0)));
+        assert!(!chunk_box.intersects_region((1, 0)));
+        assert!(!chunk_box.intersects_region((-3, 0)));
+    }
+}
@@ -0,0 +1,388 @@
+use crate::{config, folder_name, scan_region_files, Cli, ConvertArgs, RegionType};
+use clap::{Args, CommandFactory, FromArgMatches};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::ffi::OsString;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use thiserror::Error;
+
+/// Socket file in the temp folder when `--socket` is not given.
+const SOCKET_FILE: &str = "bufferedlinear_tools.sock";
+
+#[derive(Args)]
+pub struct DaemonArgs {
+    /// Unix socket to accept commands on, one per connection: `convert <convert args>`, `status`,
+    /// `cancel <job>` and `shutdown`. Defaults to `bufferedlinear_tools.sock` in the temp folder
+    #[arg(long, value_name = "PATH")]
+    pub socket: Option<PathBuf>,
+
+    /// World to watch for changed region files, as `<name>=<path>`. Conversions can give the name
+    /// instead of the path. Can be repeated
+    #[arg(long, value_name = "NAME=PATH", value_parser = parse_watched_world)]
+    pub watch: Vec<(String, PathBuf)>,
+
+    /// Seconds between checks of the watched worlds
+    #[arg(long, default_value = "10", value_parser = clap::value_parser!(u64).range(1..))]
+    pub poll_interval: u64,
+}
+
+#[derive(Error, Debug)]
+pub enum DaemonError {
+    #[error("Another daemon is listening on {0}")]
+    Running(PathBuf),
+    #[cfg(not(unix))]
+    #[error("Daemon mode needs unix domain sockets")]
+    Unsupported,
+}
+
+fn parse_watched_world(s: &str) -> Result<(String, PathBuf), String> {
+    match s.split_once('=') {
+        Some((name, path)) if !name.is_empty() && !name.contains(char::is_whitespace) && !path.is_empty() => Ok((name.to_string(), PathBuf::from(path))),
+        _ => Err(String::from("Watched worlds must be given as <name>=<path>")),
+    }
+}
+
+/// Progress of a daemon job, updated by the conversion as files finish, and the flag that stops it
+/// from starting further files.
+#[derive(Default)]
+pub struct JobControl {
+    cancelled: AtomicBool,
+    total_files: AtomicUsize,
+    done_files: AtomicUsize,
+    failed_files: AtomicUsize,
+}
+
+impl JobControl {
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn add_files(&self, count: usize) {
+        self.total_files.fetch_add(count, Ordering::Relaxed);
+    }
+
+    pub fn file_done(&self, converted: bool) {
+        self.done_files.fetch_add(1, Ordering::Relaxed);
+        if !converted {
+            self.failed_files.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+enum JobState {
+    Queued,
+    Running,
+    Done,
+    Failed(String),
+    Cancelled,
+}
+
+impl fmt::Display for JobState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JobState::Queued => write!(f, "queued"),
+            JobState::Running => write!(f, "running"),
+            JobState::Done => write!(f, "done"),
+            JobState::Failed(err) => write!(f, "failed ({err})"),
+            JobState::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+struct Job {
+    id: usize,
+    request: String,
+    state: JobState,
+    control: Arc<JobControl>,
+}
+
+/// A watched world, with the newest modification time of its region files.
+struct WatchedWorld {
+    path: PathBuf,
+    region_files: usize,
+    modified: Option<SystemTime>,
+    /// Start of the last conversion of the world that finished
+    converted: Option<SystemTime>,
+}
+
+impl WatchedWorld {
+    fn refresh(&mut self) {
+        let files: Vec<PathBuf> = [RegionType::REGION, RegionType::POI, RegionType::ENTITIES]
+            .into_iter()
+            .flat_map(|region_type| scan_region_files(self.path.join(folder_name(region_type))))
+            .collect();
+
+        self.region_files = files.len();
+        self.modified = files.iter().filter_map(|file| file.metadata().and_then(|metadata| metadata.modified()).ok()).max();
+    }
+
+    fn changed(&self) -> bool {
+        match (self.modified, self.converted) {
+            (Some(modified), Some(converted)) => modified > converted,
+            (modified, _) => modified.is_some(),
+        }
+    }
+}
+
+/// State shared by the socket, the watcher and the conversion worker.
+#[derive(Default)]
+struct Daemon {
+    worlds: Mutex<BTreeMap<String, WatchedWorld>>,
+    jobs: Mutex<Vec<Job>>,
+}
+
+impl Daemon {
+    /// Parses a `convert` request with the command line's rules, the config file and environment
+    /// defaults included, and replaces a watched world's name by its path.
+    fn parse_convert(&self, words: &[String]) -> Result<ConvertArgs, String> {
+        let args: Vec<OsString> = std::iter::once(String::from(env!("CARGO_PKG_NAME"))).chain(words.iter().cloned()).map(Into::into).collect();
+        let command = config::cli_defaults(Cli::command(), &args).map_err(|err| err.to_string())?;
+        let matches = command.try_get_matches_from(args).map_err(|err| err.render().to_string().trim_start_matches("error: ").to_string())?;
+        let cli = Cli::from_arg_matches(&matches).map_err(|err| err.to_string())?;
+
+        let (None, Some(mut convert)) = (cli.command, cli.convert) else {
+            return Err(String::from("Only conversions can be run by the daemon"));
+        };
+        if let Some(world) = convert.world_path.to_str().and_then(|name| self.worlds.lock().unwrap().get(name).map(|world| world.path.clone())) {
+            convert.world_path = world;
+        }
+        crate::output_format_by_mode(convert.mode).check_compression_level(convert.compression_level as u8).map_err(|err| err.to_string())?;
+
+        Ok(convert)
+    }
+
+    fn status(&self) -> String {
+        let mut lines = Vec::new();
+        for (name, world) in self.worlds.lock().unwrap().iter() {
+            lines.push(format!("world {} files={} changed={} path={}", name, world.region_files, world.changed(), world.path.display()));
+        }
+        for job in self.jobs.lock().unwrap().iter() {
+            let control = &job.control;
+            lines.push(format!(
+                "job {} {} files={}/{} failed={} {}",
+                job.id,
+                job.state,
+                control.done_files.load(Ordering::Relaxed),
+                control.total_files.load(Ordering::Relaxed),
+                control.failed_files.load(Ordering::Relaxed),
+                job.request
+            ));
+        }
+
+        lines.join("\n")
+    }
+
+    /// Stops a job, a queued one never starts and a running one finishes the files it is on.
+    fn cancel(&self, id: usize) -> String {
+        let mut jobs = self.jobs.lock().unwrap();
+        let Some(job) = jobs.iter_mut().find(|job| job.id == id) else {
+            return format!("error no job {id}");
+        };
+
+        match job.state {
+            JobState::Queued => {
+                job.control.cancelled.store(true, Ordering::Relaxed);
+                job.state = JobState::Cancelled;
+                format!("cancelled {id}")
+            }
+            JobState::Running => {
+                job.control.cancelled.store(true, Ordering::Relaxed);
+                format!("cancelling {id}")
+            }
+            _ => format!("error job {id} is {}", job.state),
+        }
+    }
+
+    fn set_state(&self, id: usize, state: JobState) {
+        if let Some(job) = self.jobs.lock().unwrap().iter_mut().find(|job| job.id == id) {
+            job.state = state;
+        }
+    }
+
+    /// Runs one queued conversion on the worker thread, so jobs never compete for the pool.
+    fn run_job(&self, id: usize, convert: ConvertArgs, control: Arc<JobControl>) {
+        if control.is_cancelled() {
+            return;
+        }
+        self.set_state(id, JobState::Running);
+        let started = SystemTime::now();
+
+        let result = crate::convert_options(&convert).and_then(|mut options| {
+            options.control = Some(control.clone());
+            crate::run_convert(&convert, options)
+        });
+
+        let state = match result {
+            Err(err) => JobState::Failed(err),
+            Ok(()) if control.is_cancelled() => JobState::Cancelled,
+            Ok(()) => {
+                let mut worlds = self.worlds.lock().unwrap();
+                if let Some(world) = worlds.values_mut().find(|world| same_path(&world.path, &convert.world_path)) {
+                    world.converted = Some(started);
+                }
+                JobState::Done
+            }
+        };
+        self.set_state(id, state);
+    }
+}
+
+fn same_path(a: &Path, b: &Path) -> bool {
+    a == b || a.canonicalize().is_ok_and(|a| b.canonicalize().is_ok_and(|b| a == b))
+}
+
+/// Splits a request line into words at whitespace, double quotes group words with spaces.
+fn split_request(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quoted = false;
+
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                word.get_or_insert_default();
+            }
+            c if c.is_whitespace() && !quoted => words.extend(word.take()),
+            c => word.get_or_insert_default().push(c),
+        }
+    }
+    if quoted {
+        return Err(String::from("Unterminated quote"));
+    }
+    words.extend(word);
+
+    Ok(words)
+}
+
+#[cfg(unix)]
+pub fn run_daemon(args: &DaemonArgs) -> Result<(), Box<dyn Error>> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::sync::mpsc;
+    use std::time::Duration;
+    use std::{env, fs, thread};
+
+    let socket = args.socket.clone().unwrap_or_else(|| env::temp_dir().join(SOCKET_FILE));
+    if socket.exists() {
+        // left behind by a daemon that did not shut down, unless one still answers
+        if UnixStream::connect(&socket).is_ok() {
+            return Err(Box::new(DaemonError::Running(socket)));
+        }
+        fs::remove_file(&socket)?;
+    }
+    let listener = UnixListener::bind(&socket)?;
+
+    let daemon = Arc::new(Daemon::default());
+    for (name, path) in &args.watch {
+        let mut world = WatchedWorld { path: path.clone(), region_files: 0, modified: None, converted: None };
+        world.refresh();
+        daemon.worlds.lock().unwrap().insert(name.clone(), world);
+    }
+
+    let watcher = daemon.clone();
+    let poll_interval = Duration::from_secs(args.poll_interval);
+    thread::spawn(move || {
+        loop {
+            thread::sleep(poll_interval);
+            for world in watcher.worlds.lock().unwrap().values_mut() {
+                world.refresh();
+            }
+        }
+    });
+
+    let (queue, queued) = mpsc::channel::<(usize, ConvertArgs, Arc<JobControl>)>();
+    let worker_daemon = daemon.clone();
+    let worker = thread::spawn(move || {
+        for (id, convert, control) in queued {
+            worker_daemon.run_job(id, convert, control);
+        }
+    });
+
+    println!("Listening on {}", socket.display());
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                eprintln!("Failed to accept connection !, error : {}", err);
+                continue;
+            }
+        };
+        // a client that never sends its request must not hold up the others
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+
+        let mut line = String::new();
+        if let Err(err) = BufReader::new(&stream).read_line(&mut line) {
+            eprintln!("Failed to read request !, error : {}", err);
+            continue;
+        }
+
+        // connections that send nothing, like the check for a running daemon, get no answer
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let words = split_request(line.trim());
+        let (response, shutdown) = match words.as_deref() {
+            Ok([command]) if command == "status" => (daemon.status(), false),
+            Ok([command]) if command == "shutdown" => (String::from("shutting down"), true),
+            Ok([command, id]) if command == "cancel" => match id.parse() {
+                Ok(id) => (daemon.cancel(id), false),
+                Err(_) => (format!("error invalid job {id}"), false),
+            },
+            Ok([command, request @ ..]) if command == "convert" => match daemon.parse_convert(request) {
+                Ok(convert) => {
+                    let mut jobs = daemon.jobs.lock().unwrap();
+                    let id = jobs.len() + 1;
+                    let control = Arc::new(JobControl::default());
+                    jobs.push(Job { id, request: request.join(" "), state: JobState::Queued, control: control.clone() });
+                    queue.send((id, convert, control))?;
+                    (format!("queued {id}"), false)
+                }
+                Err(err) => (format!("error {}", err.trim_end()), false),
+            },
+            Ok(_) => (String::from("error expected convert <args>, status, cancel <job> or shutdown"), false),
+            Err(err) => (format!("error {err}"), false),
+        };
+
+        if let Err(err) = writeln!(stream, "{response}") {
+            eprintln!("Failed to answer request !, error : {}", err);
+        }
+        if shutdown {
+            break;
+        }
+    }
+
+    // the running job stops after the files it is on, queued ones never start
+    for job in daemon.jobs.lock().unwrap().iter() {
+        job.control.cancelled.store(true, Ordering::Relaxed);
+    }
+    drop(queue);
+    let _ = worker.join();
+    fs::remove_file(&socket)?;
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn run_daemon(_args: &DaemonArgs) -> Result<(), Box<dyn Error>> {
+    Err(Box::new(DaemonError::Unsupported))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_request() {
+        assert_eq!(split_request("convert  mca-linear region \"my world\" out\n").unwrap(), ["convert", "mca-linear", "region", "my world", "out"]);
+        assert_eq!(split_request("cancel \"\"").unwrap(), ["cancel", ""]);
+        assert!(split_request("convert \"world").is_err());
+        assert!(parse_watched_world("survival=/srv/world").is_ok());
+        assert!(parse_watched_world("/srv/world").is_err());
+    }
+}
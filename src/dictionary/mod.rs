@@ -0,0 +1,83 @@
+use crate::region_file::{read_region_file_with_limits, region_coords_from_path, ParseLimits};
+use std::error::Error;
+use std::path::PathBuf;
+
+/// Size of a trained dictionary, zstd's own default.
+const DICTIONARY_SIZE: usize = 110 * 1024;
+/// Chunk NBT gathered for training, zstd suggests about a hundred times the dictionary size.
+const SAMPLE_BUDGET: usize = DICTIONARY_SIZE * 100;
+/// Chunks sampled per region file, so the samples spread over more of the world.
+const SAMPLES_PER_FILE: usize = 64;
+
+/// Trains a zstd dictionary on the uncompressed chunk NBT of the given region files. Files that
+/// can not be read are skipped, they fail later in the conversion anyway.
+pub fn train(region_files: &[PathBuf], limits: &ParseLimits) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut samples = Vec::new();
+    let mut sample_sizes = Vec::new();
+
+    for region_file in region_files.iter().filter(|path| region_coords_from_path(path).is_some()) {
+        if samples.len() >= SAMPLE_BUDGET {
+            break;
+        }
+
+        let Ok((_, region)) = read_region_file_with_limits(region_file, limits) else {
+            continue;
+        };
+
+        for chunk in region.chunks().iter().take(SAMPLES_PER_FILE) {
+            let raw = chunk.to_raw_bytes();
+            sample_sizes.push(raw.len());
+            samples.extend_from_slice(&raw);
+        }
+    }
+
+    if sample_sizes.is_empty() {
+        return Err("No chunks to train the dictionary on".into());
+    }
+
+    // the trainer refuses to build a dictionary larger than its samples can fill
+    let dictionary_size = DICTIONARY_SIZE.min(samples.len() / 10).max(256);
+    Ok(zstd::dict::from_continuous(&samples, &sample_sizes, dictionary_size)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::nbt::tag::Tag;
+    use crate::region_file::{BlinearOptions, Region};
+
+    #[test]
+    fn test_train() {
+        let folder = std::env::temp_dir().join("bufferedlinear_tools_train_test");
+        std::fs::create_dir_all(&folder).unwrap();
+
+        let chunks = (0..256)
+            .map(|slot| {
+                let data = Tag::Compound {
+                    name: None,
+                    value: vec![
+                        Tag::Int { name: Some(String::from("xPos")), value: slot % 32 },
+                        Tag::Int { name: Some(String::from("zPos")), value: slot / 32 },
+                        Tag::String { name: Some(String::from("Status")), value: String::from("minecraft:full") },
+                        Tag::LongArray { name: Some(String::from("heights")), value: (0..64).map(|index| index * slot as i64).collect() },
+                    ],
+                };
+                Chunk::new_from_block_pos(slot % 32, slot / 32, 0, data)
+            })
+            .collect();
+        let region = Region::new(chunks, 0);
+        let path = folder.join("r.0.0.blinear");
+        std::fs::write(&path, region.to_bytes_blinear(0, 3)).unwrap();
+
+        let dictionary = train(&[path, folder.join("notes.txt")], &ParseLimits::DEFAULT).unwrap();
+        let options = BlinearOptions { seekable: true, ..BlinearOptions::default() };
+        let bytes = region.to_bytes_blinear_with_dictionary(0, 3, options, Some(&dictionary));
+        let parsed = Region::from_bytes_blinear_with_dictionary(&bytes, &ParseLimits::DEFAULT, Some(&dictionary)).unwrap();
+        assert_eq!(parsed.chunks().len(), 256);
+
+        assert!(train(&[], &ParseLimits::DEFAULT).is_err());
+
+        std::fs::remove_dir_all(&folder).unwrap();
+    }
+}
@@ -0,0 +1,266 @@
+use crate::chunk::Chunk;
+use crate::nbt::diff::{diff_tags, TagChange};
+use crate::region_file::{read_region_file, region_coords_from_path};
+use crate::{folder_name, scan_region_files, RegionType};
+use clap::Args;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+const MAX_PATHS_PER_CHUNK: usize = 20;
+
+#[derive(Args)]
+pub struct DiffArgs {
+    /// Region file or world folder to compare from
+    pub old_path: PathBuf,
+
+    /// Region file or world folder to compare against, formats may differ from the first one
+    pub new_path: PathBuf,
+
+    /// Region type to compare when diffing worlds
+    #[arg(short = 't', long, value_enum, default_value = "region")]
+    pub region_type: RegionType,
+}
+
+#[derive(Default)]
+struct DiffSummary {
+    added: usize,
+    removed: usize,
+    changed: usize,
+    identical: usize,
+    /// Region files that could not be read, so their chunks were not compared
+    failed: usize,
+}
+
+impl DiffSummary {
+    fn is_identical(&self) -> bool {
+        self.added == 0 && self.removed == 0 && self.changed == 0
+    }
+}
+
+pub enum ChunkDiff {
+    Added,
+    Removed,
+    Changed(Vec<TagChange>),
+}
+
+/// Pairs chunks by global position and compares their NBT. Chunk timestamps are not compared.
+pub fn diff_chunks(
+    old: &[Chunk],
+    old_region: (i32, i32),
+    new: &[Chunk],
+    new_region: (i32, i32),
+) -> (BTreeMap<(i32, i32), ChunkDiff>, usize) {
+    let by_position = |chunks: &[Chunk], (region_x, region_z): (i32, i32)| -> BTreeMap<(i32, i32), usize> {
+        chunks
+            .iter()
+            .enumerate()
+            .map(|(index, chunk)| (chunk.global_position(region_x, region_z), index))
+            .collect()
+    };
+    let old_positions = by_position(old, old_region);
+    let new_positions = by_position(new, new_region);
+
+    let mut diffs = BTreeMap::new();
+    let mut identical = 0;
+
+    for (position, &old_index) in &old_positions {
+        let Some(&new_index) = new_positions.get(position) else {
+            diffs.insert(*position, ChunkDiff::Removed);
+            continue;
+        };
+
+        let changes = diff_tags(old[old_index].get_data(), new[new_index].get_data());
+        if changes.is_empty() {
+            identical += 1;
+        } else {
+            diffs.insert(*position, ChunkDiff::Changed(changes));
+        }
+    }
+
+    for position in new_positions.keys() {
+        if !old_positions.contains_key(position) {
+            diffs.insert(*position, ChunkDiff::Added);
+        }
+    }
+
+    (diffs, identical)
+}
+
+fn diff_region_files(old_path: &Path, new_path: &Path, summary: &mut DiffSummary) -> Result<(), Box<dyn Error>> {
+    let (_, old) = read_region_file(old_path)?;
+    let (_, new) = read_region_file(new_path)?;
+    let old_region = region_coords_from_path(old_path).ok_or("File name is not r.<x>.<z>.<ext>")?;
+    let new_region = region_coords_from_path(new_path).ok_or("File name is not r.<x>.<z>.<ext>")?;
+
+    let (diffs, identical) = diff_chunks(old.chunks(), old_region, new.chunks(), new_region);
+    summary.identical += identical;
+
+    if diffs.is_empty() {
+        return Ok(());
+    }
+
+    println!("{} <-> {}", old_path.display(), new_path.display());
+    for ((x, z), diff) in diffs {
+        match diff {
+            ChunkDiff::Added => {
+                summary.added += 1;
+                println!("  + chunk {x}, {z}");
+            }
+            ChunkDiff::Removed => {
+                summary.removed += 1;
+                println!("  - chunk {x}, {z}");
+            }
+            ChunkDiff::Changed(changes) => {
+                summary.changed += 1;
+                println!("  ~ chunk {x}, {z}");
+                for change in changes.iter().take(MAX_PATHS_PER_CHUNK) {
+                    println!("      {change}");
+                }
+                if changes.len() > MAX_PATHS_PER_CHUNK {
+                    println!("      ... and {} more", changes.len() - MAX_PATHS_PER_CHUNK);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Region files of a world folder keyed by region coordinates, so files of different formats pair up.
+fn world_region_files(world_path: &Path, region_type: RegionType) -> BTreeMap<(i32, i32), PathBuf> {
+    scan_region_files(world_path.join(folder_name(region_type)))
+        .into_iter()
+        .filter_map(|path| Some((region_coords_from_path(&path)?, path)))
+        .collect()
+}
+
+/// Prints a region file only one of the worlds has and returns its chunk count, None if it is
+/// unreadable, which is reported like an unreadable pair.
+fn one_sided_chunks(region_file: &Path, sign: char) -> Option<usize> {
+    match read_region_file(region_file) {
+        Ok((_, region)) => {
+            println!("{} {} ({} chunks)", sign, region_file.display(), region.chunks().len());
+            Some(region.chunks().len())
+        }
+        Err(err) => {
+            eprintln!("Failed to compare file {} !, error : {}", region_file.display(), err);
+            None
+        }
+    }
+}
+
+/// Prints the differences between two regions or worlds and returns whether they are identical.
+/// Fails when any region file could not be compared, as the worlds may differ in it.
+pub fn run_diff(args: &DiffArgs) -> Result<bool, Box<dyn Error>> {
+    let mut summary = DiffSummary::default();
+
+    if args.old_path.is_file() && args.new_path.is_file() {
+        diff_region_files(&args.old_path, &args.new_path, &mut summary)?;
+    } else if args.old_path.is_dir() && args.new_path.is_dir() {
+        let old_files = world_region_files(&args.old_path, args.region_type);
+        let new_files = world_region_files(&args.new_path, args.region_type);
+
+        for (coords, old_file) in &old_files {
+            match new_files.get(coords) {
+                Some(new_file) => {
+                    if let Err(err) = diff_region_files(old_file, new_file, &mut summary) {
+                        eprintln!("Failed to compare file {} !, error : {}", old_file.display(), err);
+                        summary.failed += 1;
+                    }
+                }
+                None => match one_sided_chunks(old_file, '-') {
+                    Some(chunks) => summary.removed += chunks,
+                    None => summary.failed += 1,
+                },
+            }
+        }
+
+        for (coords, new_file) in &new_files {
+            if !old_files.contains_key(coords) {
+                match one_sided_chunks(new_file, '+') {
+                    Some(chunks) => summary.added += chunks,
+                    None => summary.failed += 1,
+                }
+            }
+        }
+    } else {
+        return Err("Both paths must be region files or both must be world folders".into());
+    }
+
+    println!(
+        "{} added, {} removed, {} changed, {} identical chunks",
+        summary.added, summary.removed, summary.changed, summary.identical
+    );
+
+    if summary.failed > 0 {
+        return Err(format!("{} region files could not be compared", summary.failed).into());
+    }
+
+    Ok(summary.is_identical())
+}
+
+/// Exit status of the diff command: 0 when identical, 1 when different, 2 when the comparison failed.
+pub fn exit_status(result: &Result<bool, Box<dyn Error>>) -> i32 {
+    match result {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(_) => 2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nbt::tag::Tag;
+
+    fn chunk(x: i32, z: i32, status: &str) -> Chunk {
+        let data = Tag::Compound {
+            name: None,
+            value: vec![Tag::String { name: Some(String::from("Status")), value: String::from(status) }],
+        };
+        Chunk::new_from_block_pos(x, z, 0, data)
+    }
+
+    #[test]
+    fn test_diff_chunks_by_global_position() {
+        // the same region read once with local chunk positions (mca) and once with global ones (linear)
+        let old = [chunk(0, 0, "full"), chunk(1, 0, "full"), chunk(2, 0, "full")];
+        let new = [chunk(-32, 0, "full"), chunk(-31, 0, "features"), chunk(-29, 0, "full")];
+
+        let (diffs, identical) = diff_chunks(&old, (-1, 0), &new, (-1, 0));
+
+        assert_eq!(identical, 1);
+        assert!(matches!(&diffs[&(-31, 0)], ChunkDiff::Changed(changes) if changes == &[TagChange::Changed(String::from("Status"))]));
+        assert!(matches!(diffs[&(-30, 0)], ChunkDiff::Removed));
+        assert!(matches!(diffs[&(-29, 0)], ChunkDiff::Added));
+    }
+
+    #[test]
+    fn test_unreadable_one_sided_file() {
+        let folder = std::env::temp_dir().join("bufferedlinear_tools_diff_test");
+        let _ = std::fs::remove_dir_all(&folder);
+        for world in ["old", "new"] {
+            std::fs::create_dir_all(folder.join(world).join("region")).unwrap();
+        }
+        std::fs::write(folder.join("old/region/r.0.0.mca"), b"not a region file").unwrap();
+
+        let args = DiffArgs { old_path: folder.join("old"), new_path: folder.join("new"), region_type: RegionType::REGION };
+        let result = run_diff(&args);
+        assert_eq!(result.as_ref().unwrap_err().to_string(), "1 region files could not be compared");
+        assert_eq!(exit_status(&result), 2);
+
+        // a corrupt file on both sides fails the same way
+        std::fs::write(folder.join("new/region/r.0.0.mca"), b"zzz").unwrap();
+        assert_eq!(exit_status(&run_diff(&args)), 2);
+
+        // chunks of files without region coordinates can not be placed in the world
+        let region = crate::region_file::Region::new(vec![chunk(0, 0, "full")], 0).to_bytes_mca(6).unwrap();
+        std::fs::write(folder.join("old/backup.mca"), &region).unwrap();
+        std::fs::write(folder.join("new/backup.mca"), &region).unwrap();
+        let args = DiffArgs { old_path: folder.join("old/backup.mca"), new_path: folder.join("new/backup.mca"), region_type: RegionType::REGION };
+        assert_eq!(run_diff(&args).unwrap_err().to_string(), "File name is not r.<x>.<z>.<ext>");
+
+        std::fs::remove_dir_all(&folder).unwrap();
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Subfolders of a dimension folder holding region files.
+const REGION_FOLDERS: [&str; 3] = ["region", "poi", "entities"];
+/// Levels of folders below `dimensions/<namespace>` searched for dimensions with nested names.
+const MAX_NAME_DEPTH: usize = 4;
+
+/// One `--dimension-map` rule.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DimensionRule {
+    /// `*`, every dimension found in the world, each written to its own place
+    All,
+    /// `<id>` or `<id>=<target id>`
+    Include { source: String, target: Option<String> },
+    /// `!<id>`
+    Exclude(String),
+}
+
+/// Adds the `minecraft` namespace to ids without one.
+pub fn full_id(id: &str) -> Result<String, String> {
+    let id = id.trim();
+    let valid = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "_-./".contains(c));
+
+    let (namespace, path) = id.split_once(':').unwrap_or(("minecraft", id));
+    if !valid(namespace) || !valid(path) || path.split('/').any(|part| part.is_empty() || part == "." || part == "..") {
+        return Err(format!("Invalid dimension id {id}"));
+    }
+
+    Ok(format!("{namespace}:{path}"))
+}
+
+/// Parses `*`, `!<id>`, `<id>` or `<id>=<target id>`, ids without a namespace are `minecraft:` ones.
+pub fn parse_dimension_rule(s: &str) -> Result<DimensionRule, String> {
+    if s == "*" {
+        return Ok(DimensionRule::All);
+    }
+    if let Some(id) = s.strip_prefix('!') {
+        return Ok(DimensionRule::Exclude(full_id(id)?));
+    }
+
+    match s.split_once('=') {
+        Some((source, target)) => Ok(DimensionRule::Include { source: full_id(source)?, target: Some(full_id(target)?) }),
+        None => Ok(DimensionRule::Include { source: full_id(s)?, target: None }),
+    }
+}
+
+/// Folder of a dimension relative to the world folder.
+pub fn dimension_folder(id: &str) -> PathBuf {
+    match id {
+        "minecraft:overworld" => PathBuf::new(),
+        "minecraft:the_nether" => PathBuf::from("DIM-1"),
+        "minecraft:the_end" => PathBuf::from("DIM1"),
+        _ => {
+            let (namespace, path) = id.split_once(':').unwrap_or(("minecraft", id));
+            path.split('/').fold(Path::new("dimensions").join(namespace), |folder, part| folder.join(part))
+        }
+    }
+}
+
+fn has_region_folders(folder: &Path) -> bool {
+    REGION_FOLDERS.iter().any(|name| folder.join(name).is_dir())
+}
+
+fn subfolders(folder: &Path) -> Vec<(String, PathBuf)> {
+    let mut folders: Vec<(String, PathBuf)> = fs::read_dir(folder)
+        .map(|dir| dir.flatten().map(|entry| entry.path()).filter(|path| path.is_dir()).filter_map(|path| Some((path.file_name()?.to_str()?.to_string(), path))).collect())
+        .unwrap_or_default();
+    folders.sort();
+    folders
+}
+
+/// Custom dimensions below a `dimensions/<namespace>` folder, as the path part of their id.
+fn find_named(folder: &Path, prefix: &str, depth: usize, found: &mut Vec<String>) {
+    for (name, path) in subfolders(folder) {
+        if REGION_FOLDERS.contains(&name.as_str()) {
+            continue;
+        }
+
+        let id_path = if prefix.is_empty() { name } else { format!("{prefix}/{name}") };
+        if has_region_folders(&path) {
+            found.push(id_path.clone());
+        }
+        if depth > 1 {
+            find_named(&path, &id_path, depth - 1, found);
+        }
+    }
+}
+
+/// Ids of every dimension of the world that has a region, poi or entities folder: the vanilla
+/// ones and those in `dimensions/<namespace>/<name>`.
+pub fn discover_dimensions(world_folder: &Path) -> Vec<String> {
+    let mut ids: Vec<String> = ["minecraft:overworld", "minecraft:the_nether", "minecraft:the_end"]
+        .into_iter()
+        .filter(|id| has_region_folders(&world_folder.join(dimension_folder(id))))
+        .map(String::from)
+        .collect();
+
+    for (namespace, folder) in subfolders(&world_folder.join("dimensions")) {
+        let mut paths = Vec::new();
+        find_named(&folder, "", MAX_NAME_DEPTH, &mut paths);
+        for id in paths.into_iter().map(|path| format!("{namespace}:{path}")) {
+            // vanilla dimensions are not read from `dimensions/minecraft`
+            if dimension_folder(&id).starts_with("dimensions") {
+                ids.push(id);
+            }
+        }
+    }
+
+    ids
+}
+
+/// The dimensions to convert and the dimension each is written as, applying the rules in order
+/// to the dimensions found in the world.
+pub fn plan_dimensions(discovered: &[String], rules: &[DimensionRule]) -> Result<Vec<(String, String)>, String> {
+    let mut plan: BTreeMap<String, String> = BTreeMap::new();
+
+    for rule in rules {
+        match rule {
+            DimensionRule::All => plan.extend(discovered.iter().map(|id| (id.clone(), id.clone()))),
+            DimensionRule::Include { source, target } => {
+                if !discovered.contains(source) {
+                    return Err(format!("Dimension {source} has no region files in the world"));
+                }
+                plan.insert(source.clone(), target.clone().unwrap_or_else(|| source.clone()));
+            }
+            DimensionRule::Exclude(id) => {
+                plan.remove(id);
+            }
+        }
+    }
+
+    let mut targets: BTreeMap<&String, &String> = BTreeMap::new();
+    for (source, target) in &plan {
+        if let Some(other) = targets.insert(target, source) {
+            return Err(format!("Dimensions {other} and {source} are both written as {target}"));
+        }
+    }
+
+    Ok(plan.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_discover_and_plan() {
+        let world = std::env::temp_dir().join("bufferedlinear_tools_dimensions_test");
+        let _ = fs::remove_dir_all(&world);
+        for folder in ["region", "DIM-1/region", "dimensions/mymod/mining/region", "dimensions/mymod/sky/islands/entities", "dimensions/mymod/empty"] {
+            fs::create_dir_all(world.join(folder)).unwrap();
+        }
+
+        let discovered = discover_dimensions(&world);
+        assert_eq!(discovered, ["minecraft:overworld", "minecraft:the_nether", "mymod:mining", "mymod:sky/islands"]);
+        assert_eq!(dimension_folder("mymod:sky/islands"), Path::new("dimensions/mymod/sky/islands"));
+
+        let rules: Vec<DimensionRule> = ["*", "!the_nether", "mymod:mining=mymod:quarry"].into_iter().map(|rule| parse_dimension_rule(rule).unwrap()).collect();
+        let plan = plan_dimensions(&discovered, &rules).unwrap();
+        let expected = [("minecraft:overworld", "minecraft:overworld"), ("mymod:mining", "mymod:quarry"), ("mymod:sky/islands", "mymod:sky/islands")];
+        assert_eq!(plan, expected.map(|(source, target)| (source.to_string(), target.to_string())));
+
+        assert!(plan_dimensions(&discovered, &[parse_dimension_rule("the_end").unwrap()]).is_err());
+        assert!(plan_dimensions(&discovered, &[DimensionRule::All, parse_dimension_rule("mymod:mining=overworld").unwrap()]).is_err());
+        assert!(parse_dimension_rule("mymod:../escape").is_err());
+
+        fs::remove_dir_all(&world).unwrap();
+    }
+}
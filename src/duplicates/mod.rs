@@ -0,0 +1,98 @@
+use crate::region_file::RegionFormat;
+use clap::ValueEnum;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Which file to convert when a folder holds the same region in several formats, as Paper's
+/// linear implementations sometimes leave behind.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum DuplicatePolicy {
+    /// The most recently modified file
+    Newest,
+    /// The mca file, or the newest if there is none
+    Mca,
+    /// The linear file, or the newest if there is none
+    Linear,
+    /// The blinear file, or the newest if there is none
+    Blinear,
+    /// Fail before converting anything
+    Error,
+}
+
+/// The files left out because another file of the same region was chosen, each with the chosen
+/// file.
+pub fn resolve_duplicates(files: &[PathBuf], policy: DuplicatePolicy) -> Result<HashMap<PathBuf, PathBuf>, String> {
+    let mut regions: BTreeMap<(&Path, &str), Vec<(&PathBuf, RegionFormat)>> = BTreeMap::new();
+    for file in files {
+        let format = file.extension().and_then(|extension| extension.to_str()).and_then(RegionFormat::from_extension);
+        if let (Some(format), Some(folder), Some(name)) = (format, file.parent(), file.file_stem().and_then(|name| name.to_str())) {
+            regions.entry((folder, name)).or_default().push((file, format));
+        }
+    }
+
+    let preferred = match policy {
+        DuplicatePolicy::Mca => Some(RegionFormat::Mca),
+        DuplicatePolicy::Linear => Some(RegionFormat::Linear),
+        DuplicatePolicy::Blinear => Some(RegionFormat::Blinear),
+        DuplicatePolicy::Newest | DuplicatePolicy::Error => None,
+    };
+
+    // ties go to the format listed first, so runs over the same files always agree
+    let order = |format: RegionFormat| RegionFormat::ALL.iter().position(|other| *other == format);
+    let mut skipped = HashMap::new();
+    for ((folder, name), mut copies) in regions.into_iter().filter(|(_, copies)| copies.len() > 1) {
+        copies.sort_by_key(|(_, format)| order(*format));
+        if policy == DuplicatePolicy::Error {
+            let formats: Vec<&str> = copies.iter().map(|(_, format)| format.extension()).collect();
+            return Err(format!("{} in {} exists as {}", name, folder.display(), formats.join(" and ")));
+        }
+
+        let modified = |file: &Path| fs::metadata(file).and_then(|metadata| metadata.modified()).ok();
+        let (chosen, _) = copies
+            .iter()
+            .find(|(_, format)| Some(*format) == preferred)
+            .or_else(|| copies.iter().max_by_key(|(file, format)| (modified(file), Reverse(order(*format)))))
+            .expect("Duplicates have files");
+
+        for (file, _) in &copies {
+            if file != chosen {
+                skipped.insert((*file).clone(), (*chosen).clone());
+            }
+        }
+    }
+
+    Ok(skipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn test_resolve_duplicates() {
+        let folder = std::env::temp_dir().join("bufferedlinear_tools_duplicates_test");
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+
+        let now = SystemTime::now();
+        let file = |name: &str, age: u64| {
+            let path = folder.join(name);
+            File::create(&path).unwrap().set_modified(now - Duration::from_secs(age)).unwrap();
+            path
+        };
+        let files = [file("r.0.0.mca", 60), file("r.0.0.linear", 0), file("r.1.0.mca", 0)];
+
+        let newest = resolve_duplicates(&files, DuplicatePolicy::Newest).unwrap();
+        assert_eq!(newest, HashMap::from([(files[0].clone(), files[1].clone())]));
+        let mca = resolve_duplicates(&files, DuplicatePolicy::Mca).unwrap();
+        assert_eq!(mca, HashMap::from([(files[1].clone(), files[0].clone())]));
+        assert!(resolve_duplicates(&files, DuplicatePolicy::Blinear).unwrap().contains_key(&files[0]));
+        assert!(resolve_duplicates(&files, DuplicatePolicy::Error).is_err());
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
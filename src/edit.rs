@@ -0,0 +1,130 @@
+use crate::chunk::Chunk;
+use crate::nbt::tag::Tag;
+use crate::region_file::{blinear_dictionary_for, linear_compression_level, region_coords_from_path, BlinearOptions, ParseLimits, RawRegion, RegionFormat, ReusedBuckets, WriteError, LINEAR_DEFAULT_GRID_SIZE};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Compression level for Anvil files, which do not record the one they were written with, and
+/// linear files without a non empty bucket.
+const DEFAULT_COMPRESSION_LEVEL: u8 = 6;
+
+/// A change to a single chunk of a region file.
+pub enum ChunkEdit {
+    /// Put this chunk NBT into the slot, replacing the chunk already there
+    Replace(Tag),
+    /// Empty the slot
+    Delete,
+}
+
+/// Applies one edit to the chunk at the given chunk coordinates (global or region local) and
+/// rewrites the file in place, in its own format, compression level, layout, region coordinates
+/// and, for blinear, shared dictionary. Returns the chunk previously in the slot.
+///
+/// The other chunks are kept as the NBT bytes they were read as, and the buckets of linear v2 and
+/// blinear v3 files that do not hold the slot are copied over without compressing them again.
+/// Linear v1 files are written as linear v2, which has no writer of its own.
+///
+/// The file is written next to the original and renamed over it, so an interrupted edit never
+/// leaves a truncated region file.
+pub fn edit_chunk(path: &Path, x: i32, z: i32, edit: ChunkEdit) -> Result<Option<Chunk>, Box<dyn Error>> {
+    let bytes = fs::read(path)?;
+    let format = RegionFormat::detect(path, &bytes).ok_or("Unknown region file format")?;
+    let dictionary = blinear_dictionary_for(path, &bytes)?;
+    let limits = ParseLimits::default();
+    let mut region = match format {
+        RegionFormat::Mca => RawRegion::from_bytes_mca(&bytes, &limits)?,
+        RegionFormat::Linear => RawRegion::from_bytes_linear(&bytes, &limits, false)?,
+        RegionFormat::Blinear => RawRegion::from_bytes_blinear(&bytes, &limits, dictionary.as_deref(), false)?,
+    };
+
+    let sector_index = ((x & 31) + ((z & 31) << 5)) as usize;
+    let previous = match region.take_chunk(sector_index) {
+        Some(raw) => Some(region.parse_chunk(&raw, &limits)?),
+        None => None,
+    };
+
+    if let ChunkEdit::Replace(data) = edit {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+        region.push_chunk(&Chunk::new_from_block_pos(x & 31, z & 31, timestamp, data));
+    }
+
+    let mut changed = vec![false; 1024];
+    changed[sector_index] = true;
+    let reuse = ReusedBuckets::new(&bytes, changed);
+
+    let updated = match format {
+        RegionFormat::Mca => region.to_bytes_mca(DEFAULT_COMPRESSION_LEVEL)?,
+        RegionFormat::Linear => {
+            // only linear v2 records its region and grid size
+            let (region_x, region_z) = region.region().or_else(|| region_coords_from_path(path)).ok_or(WriteError::MissingRegionCoords)?;
+            let grid_size = if region.region().is_some() { bytes[17] } else { LINEAR_DEFAULT_GRID_SIZE };
+            let compression_level = linear_compression_level(&bytes).unwrap_or(DEFAULT_COMPRESSION_LEVEL);
+            region.to_bytes_linear_v2(region_x, region_z, region.timestamp(), compression_level, grid_size, reuse.as_ref())?
+        }
+        RegionFormat::Blinear => region.to_bytes_blinear(region.timestamp(), bytes[17], BlinearOptions::of_file(&bytes), dictionary.as_deref(), reuse.as_ref()),
+    };
+
+    let temp_file = path.with_extension("tmp");
+    fs::write(&temp_file, updated)?;
+    fs::rename(&temp_file, path)?;
+
+    Ok(previous)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::region_file::Region;
+
+    fn data(status: &str) -> Tag {
+        Tag::Compound {
+            name: None,
+            value: vec![Tag::String { name: Some(String::from("Status")), value: String::from(status) }],
+        }
+    }
+
+    fn status(chunk: &Chunk) -> &str {
+        chunk.find_field("Status").and_then(|tag| tag.get_string()).unwrap()
+    }
+
+    #[test]
+    fn test_edit_chunk() {
+        let dir = std::env::temp_dir().join(format!("bufferedlinear_tools_edit_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let chunks = vec![Chunk::new_from_block_pos(1, 0, 7, data("old")), Chunk::new_from_block_pos(2, 0, 7, data("kept"))];
+        let path = dir.join("r.0.0.blinear");
+        fs::write(&path, Region::new(chunks, 0).to_bytes_blinear_seekable(0, 3)).unwrap();
+
+        let previous = edit_chunk(&path, 33, 0, ChunkEdit::Replace(data("new"))).unwrap();
+        assert_eq!(status(&previous.unwrap()), "old");
+        assert_eq!(status(&Region::read_chunk_at(&path, 1, 0).unwrap().unwrap()), "new");
+
+        assert!(edit_chunk(&path, 2, 0, ChunkEdit::Delete).unwrap().is_some());
+        assert!(edit_chunk(&path, 3, 0, ChunkEdit::Delete).unwrap().is_none());
+
+        let bytes = fs::read(&path).unwrap();
+        assert!(BlinearOptions::of_file(&bytes).seekable);
+        assert_eq!(bytes[17], 3);
+        assert_eq!(Region::from_bytes(RegionFormat::Blinear, &bytes).unwrap().chunks().len(), 1);
+
+        // the header's region wins over the file name, the grid size and level are kept
+        let chunks = vec![Chunk::new_from_block_pos(1, 0, 7, data("old")), Chunk::new_from_block_pos(20, 20, 7, data("kept"))];
+        let path = dir.join("r.0.0.linear");
+        fs::write(&path, Region::new(chunks, 0).to_bytes_linear_v2(3, -2, 0, 5, 4).unwrap()).unwrap();
+
+        edit_chunk(&path, 1, 0, ChunkEdit::Replace(data("new"))).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(bytes[17], 4);
+        assert_eq!(linear_compression_level(&bytes), Some(5));
+        let region = RawRegion::from_bytes_linear(&bytes, &ParseLimits::default(), true).unwrap();
+        assert_eq!(region.region(), Some((3, -2)));
+        let statuses: Vec<String> = region.parse(&ParseLimits::default()).unwrap().chunks().iter().map(|chunk| status(chunk).to_string()).collect();
+        assert_eq!(statuses.len(), 2);
+        assert!(statuses.contains(&String::from("new")) && statuses.contains(&String::from("kept")));
+    }
+}
@@ -0,0 +1,131 @@
+use crate::chunk::Chunk;
+use crate::nbt::tag::Tag;
+use crate::region_file::Region;
+use clap::ValueEnum;
+use std::collections::HashMap;
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum EntityStorage {
+    /// Move entities embedded in terrain chunks into entities region files (1.17+ layout)
+    Split,
+    /// Move entities from the world's entities region files into the terrain chunks (pre-1.17 layout)
+    Merge,
+}
+
+fn empty_list(name: &str) -> Tag {
+    Tag::List {
+        name: Some(String::from(name)),
+        value: Vec::new(),
+        tag_type: 10,
+    }
+}
+
+/// Takes the embedded entities out of every terrain chunk and returns them as an entities region,
+/// with one chunk per terrain chunk that had any.
+pub fn split_entities(region: &mut Region, region_x: i32, region_z: i32) -> Region {
+    let mut entity_chunks = Vec::new();
+
+    for chunk in region.chunks_mut() {
+        let Some(entities) = chunk.fields_mut().remove_tag("Entities") else {
+            continue;
+        };
+        if entities.children().is_none_or(|entities| entities.is_empty()) {
+            continue;
+        }
+
+        let (x, z) = chunk.global_position(region_x, region_z);
+        let mut fields = vec![Tag::IntArray {
+            name: Some(String::from("Position")),
+            value: vec![x, z],
+        }];
+        if let Some(data_version) = chunk.find_field("DataVersion") {
+            fields.insert(0, data_version.clone());
+        }
+        fields.push(entities);
+
+        let data = Tag::Compound { name: None, value: fields };
+        entity_chunks.push(Chunk::new_from_block_pos(x, z, chunk.timestamp(), data));
+    }
+
+    Region::new(entity_chunks, region.timestamp())
+}
+
+/// Appends the entities of every entity chunk to the terrain chunk at the same position,
+/// returns how many entity chunks found their terrain chunk.
+pub fn merge_entities(region: &mut Region, region_x: i32, region_z: i32, entities: &Region) -> usize {
+    let entity_lists: HashMap<(i32, i32), &[Tag]> = entities
+        .chunks()
+        .iter()
+        .filter_map(|chunk| {
+            let list = chunk.get_data().find_tag("Entities")?.children()?;
+            Some((chunk.global_position(region_x, region_z), list))
+        })
+        .collect();
+
+    let mut merged = 0;
+    for chunk in region.chunks_mut() {
+        let Some(list) = entity_lists.get(&chunk.global_position(region_x, region_z)) else {
+            continue;
+        };
+
+        let fields = chunk.fields_mut();
+        let mut embedded = fields.remove_tag("Entities").unwrap_or_else(|| empty_list("Entities"));
+        if let Tag::List { value, tag_type, .. } = &mut embedded {
+            value.extend(list.iter().cloned());
+            *tag_type = 10;
+        }
+        fields.insert_tag(embedded);
+        merged += 1;
+    }
+
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn villager() -> Tag {
+        Tag::Compound {
+            name: None,
+            value: vec![Tag::String { name: Some(String::from("id")), value: String::from("minecraft:villager") }],
+        }
+    }
+
+    fn legacy_chunk(x: i32, z: i32, entities: Vec<Tag>) -> Chunk {
+        let level = Tag::Compound {
+            name: Some(String::from("Level")),
+            value: vec![
+                Tag::Int { name: Some(String::from("xPos")), value: x },
+                Tag::List { name: Some(String::from("Entities")), value: entities, tag_type: 10 },
+            ],
+        };
+        let data = Tag::Compound {
+            name: None,
+            value: vec![Tag::Int { name: Some(String::from("DataVersion")), value: 2586 }, level],
+        };
+        Chunk::new_from_block_pos(x & 31, z & 31, 7, data)
+    }
+
+    fn entity_count(chunk: &Chunk) -> usize {
+        chunk.find_field("Entities").and_then(|list| list.children()).map_or(0, |list| list.len())
+    }
+
+    #[test]
+    fn test_split_and_merge_round_trip() {
+        let mut region = Region::new(vec![legacy_chunk(-31, 2, vec![villager(), villager()]), legacy_chunk(-30, 2, Vec::new())], 0);
+
+        let entities = split_entities(&mut region, -1, 0);
+        assert_eq!(entities.chunks().len(), 1);
+        assert_eq!(region.chunks().iter().map(entity_count).sum::<usize>(), 0);
+
+        let entity_chunk = &entities.chunks()[0];
+        assert_eq!(entity_chunk.nbt_position(), Some((-31, 2)));
+        assert_eq!(entity_chunk.find_field("DataVersion").and_then(|tag| tag.get_int()), Some(&2586));
+        assert_eq!(entity_count(entity_chunk), 2);
+
+        assert_eq!(merge_entities(&mut region, -1, 0, &entities), 1);
+        assert_eq!(entity_count(&region.chunks()[0]), 2);
+        assert_eq!(entity_count(&region.chunks()[1]), 0);
+    }
+}
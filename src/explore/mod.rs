@@ -0,0 +1,237 @@
+mod tree;
+
+use crate::chunk::Chunk;
+use crate::explore::tree::TreeView;
+use crate::region_file::{read_region_file, Region};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::error::Error;
+use std::io;
+use std::path::Path;
+
+const PAGE_SIZE: isize = 20;
+
+enum Screen {
+    Chunks,
+    Tree(TreeView),
+}
+
+struct App {
+    title: String,
+    region: Region,
+    chunk_list: ListState,
+    tree_list: ListState,
+    screen: Screen,
+    search_input: Option<String>,
+    last_search: Option<String>,
+    status: String,
+    should_quit: bool,
+}
+
+pub fn run_explore(region_file: &Path) -> Result<(), Box<dyn Error>> {
+    let (format, region) = read_region_file(region_file)?;
+
+    let mut app = App {
+        title: format!(
+            "{} ({:?}, {} chunks, timestamp {})",
+            region_file.display(),
+            format,
+            region.chunks().len(),
+            region.timestamp()
+        ),
+        region,
+        chunk_list: ListState::default().with_selected(Some(0)),
+        tree_list: ListState::default(),
+        screen: Screen::Chunks,
+        search_input: None,
+        last_search: None,
+        status: String::new(),
+        should_quit: false,
+    };
+
+    let mut terminal = ratatui::init();
+    let result = app.run(&mut terminal);
+    ratatui::restore();
+
+    Ok(result?)
+}
+
+impl App {
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        while !self.should_quit {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            if let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+            {
+                self.handle_key(key);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        if self.search_input.is_some() {
+            self.handle_search_key(key);
+            return;
+        }
+
+        match &mut self.screen {
+            Screen::Chunks => match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => self.should_quit = true,
+                KeyCode::Down | KeyCode::Char('j') => self.chunk_list.select_next(),
+                KeyCode::Up | KeyCode::Char('k') => self.chunk_list.select_previous(),
+                KeyCode::PageDown => self.chunk_list.scroll_down_by(PAGE_SIZE as u16),
+                KeyCode::PageUp => self.chunk_list.scroll_up_by(PAGE_SIZE as u16),
+                KeyCode::Home => self.chunk_list.select_first(),
+                KeyCode::End => self.chunk_list.select_last(),
+                KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => self.open_selected_chunk(),
+                _ => {}
+            },
+            Screen::Tree(tree) => match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => {
+                    self.screen = Screen::Chunks;
+                    self.status.clear();
+                }
+                KeyCode::Down | KeyCode::Char('j') => tree.select_relative(1),
+                KeyCode::Up | KeyCode::Char('k') => tree.select_relative(-1),
+                KeyCode::PageDown => tree.select_relative(PAGE_SIZE),
+                KeyCode::PageUp => tree.select_relative(-PAGE_SIZE),
+                KeyCode::Home => tree.select_first(),
+                KeyCode::End => tree.select_last(),
+                KeyCode::Right | KeyCode::Char('l') => tree.expand_selected(),
+                KeyCode::Left | KeyCode::Char('h') => tree.collapse_selected(),
+                KeyCode::Enter | KeyCode::Char(' ') => tree.toggle_selected(),
+                KeyCode::Char('/') => self.search_input = Some(String::new()),
+                KeyCode::Char('n') => self.repeat_search(true),
+                KeyCode::Char('N') => self.repeat_search(false),
+                _ => {}
+            },
+        }
+    }
+
+    fn handle_search_key(&mut self, key: KeyEvent) {
+        let Some(input) = &mut self.search_input else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Esc => self.search_input = None,
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Char(c) => input.push(c),
+            KeyCode::Enter => {
+                let query = self.search_input.take().unwrap_or_default();
+                if !query.is_empty() {
+                    self.last_search = Some(query);
+                    self.repeat_search(true);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn repeat_search(&mut self, forward: bool) {
+        let (Screen::Tree(tree), Some(query)) = (&mut self.screen, &self.last_search) else {
+            return;
+        };
+
+        self.status = if tree.search(query, forward) {
+            format!("/{query}")
+        } else {
+            format!("No key matches \"{query}\"")
+        };
+    }
+
+    fn open_selected_chunk(&mut self) {
+        let selected = self.chunk_list.selected().unwrap_or(0);
+
+        if let Some(chunk) = self.region.chunks().get(selected) {
+            self.screen = Screen::Tree(TreeView::new(chunk.get_data().clone()));
+            self.tree_list = ListState::default();
+            self.status.clear();
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main_area, status_area] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        let highlight = Style::default().add_modifier(Modifier::REVERSED);
+
+        match &self.screen {
+            Screen::Chunks => {
+                let items: Vec<ListItem> = self
+                    .region
+                    .chunks()
+                    .iter()
+                    .map(|chunk| ListItem::new(chunk_summary(chunk)))
+                    .collect();
+                let list = List::new(items)
+                    .block(Block::bordered().title(self.title.as_str()))
+                    .highlight_style(highlight);
+
+                frame.render_stateful_widget(list, main_area, &mut self.chunk_list);
+            }
+            Screen::Tree(tree) => {
+                let items: Vec<ListItem> = tree
+                    .rows()
+                    .iter()
+                    .map(|row| {
+                        let marker = match (row.expandable, row.expanded) {
+                            (_, true) => "▾ ",
+                            (true, false) => "▸ ",
+                            (false, false) => "  ",
+                        };
+                        ListItem::new(format!("{}{}{}", "  ".repeat(row.depth), marker, row.label))
+                    })
+                    .collect();
+                let list = List::new(items)
+                    .block(Block::bordered().title(self.title.as_str()))
+                    .highlight_style(highlight);
+                self.tree_list.select(Some(tree.selected()));
+
+                frame.render_stateful_widget(list, main_area, &mut self.tree_list);
+            }
+        }
+
+        let status = match (&self.search_input, &self.screen) {
+            (Some(input), _) => format!("/{input}"),
+            (None, _) if !self.status.is_empty() => self.status.clone(),
+            (None, Screen::Chunks) => String::from("↑↓ move  enter open chunk  q quit"),
+            (None, Screen::Tree(_)) => {
+                String::from("↑↓ move  ←→ collapse/expand  / search  n/N next/prev  q back")
+            }
+        };
+        frame.render_widget(Paragraph::new(status), status_area);
+    }
+}
+
+fn chunk_summary(chunk: &Chunk) -> String {
+    let data_version = chunk
+        .find_field("DataVersion")
+        .and_then(|tag| tag.get_int())
+        .map_or(String::from("-"), |version| version.to_string());
+    let status = chunk
+        .find_field("Status")
+        .and_then(|tag| tag.get_string())
+        .map_or("-", |status| status.as_str());
+    let last_update = chunk
+        .find_field("LastUpdate")
+        .and_then(|tag| tag.get_long())
+        .map_or(String::from("-"), |tick| tick.to_string());
+
+    format!(
+        "chunk {:>4}, {:>4}  timestamp {}  DataVersion {}  Status {}  LastUpdate {}",
+        chunk.x(),
+        chunk.z(),
+        chunk.timestamp(),
+        data_version,
+        status,
+        last_update
+    )
+}
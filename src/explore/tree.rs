@@ -0,0 +1,270 @@
+use crate::nbt::tag::Tag;
+use std::collections::HashSet;
+
+/// A tag's position in the tree, as child indices walked from the root.
+pub type TagPath = Vec<usize>;
+
+pub struct TreeRow {
+    pub path: TagPath,
+    pub depth: usize,
+    pub label: String,
+    pub expandable: bool,
+    pub expanded: bool,
+}
+
+pub struct TreeView {
+    root: Tag,
+    expanded: HashSet<TagPath>,
+    rows: Vec<TreeRow>,
+    selected: usize,
+}
+
+impl TreeView {
+    pub fn new(root: Tag) -> Self {
+        let mut expanded = HashSet::new();
+        expanded.insert(Vec::new());
+
+        let mut view = Self {
+            root,
+            expanded,
+            rows: Vec::new(),
+            selected: 0,
+        };
+        view.rebuild_rows();
+        view
+    }
+
+    pub fn rows(&self) -> &[TreeRow] {
+        &self.rows
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn select_relative(&mut self, delta: isize) {
+        let last = self.rows.len().saturating_sub(1) as isize;
+        self.selected = (self.selected as isize + delta).clamp(0, last) as usize;
+    }
+
+    pub fn select_first(&mut self) {
+        self.selected = 0;
+    }
+
+    pub fn select_last(&mut self) {
+        self.selected = self.rows.len().saturating_sub(1);
+    }
+
+    pub fn expand_selected(&mut self) {
+        let row = &self.rows[self.selected];
+        if row.expandable && !row.expanded {
+            let path = row.path.clone();
+            self.expanded.insert(path.clone());
+            self.rebuild_rows_keeping(&path);
+        }
+    }
+
+    /// Collapses the selected tag, or moves to its parent when there is nothing to collapse.
+    pub fn collapse_selected(&mut self) {
+        let row = &self.rows[self.selected];
+        let path = row.path.clone();
+
+        if row.expanded {
+            self.expanded.remove(&path);
+            self.rebuild_rows_keeping(&path);
+        } else if !path.is_empty() {
+            self.select_path(&path[..path.len() - 1]);
+        }
+    }
+
+    pub fn toggle_selected(&mut self) {
+        if self.rows[self.selected].expanded {
+            self.collapse_selected();
+        } else {
+            self.expand_selected();
+        }
+    }
+
+    /// Selects the next (or previous) tag whose name contains `query`, expanding its ancestors.
+    /// Wraps around the tree and returns false when nothing matches.
+    pub fn search(&mut self, query: &str, forward: bool) -> bool {
+        let query = query.to_lowercase();
+        let mut matches = Vec::new();
+        collect_matches(&self.root, &mut Vec::new(), &query, &mut matches);
+
+        // pre-order traversal order is exactly the lexicographic order of the paths
+        let current = &self.rows[self.selected].path;
+        let target = if forward {
+            matches.iter().find(|path| *path > current).or(matches.first())
+        } else {
+            matches.iter().rev().find(|path| *path < current).or(matches.last())
+        };
+
+        let Some(target) = target.cloned() else {
+            return false;
+        };
+
+        for depth in 0..target.len() {
+            self.expanded.insert(target[..depth].to_vec());
+        }
+        self.rebuild_rows_keeping(&target);
+
+        true
+    }
+
+    fn select_path(&mut self, path: &[usize]) {
+        if let Some(index) = self.rows.iter().position(|row| row.path == path) {
+            self.selected = index;
+        }
+    }
+
+    fn rebuild_rows_keeping(&mut self, path: &[usize]) {
+        self.rebuild_rows();
+        self.select_path(path);
+    }
+
+    fn rebuild_rows(&mut self) {
+        let mut rows = Vec::new();
+        push_rows(&self.root, None, &mut Vec::new(), &self.expanded, &mut rows);
+
+        self.rows = rows;
+        self.selected = self.selected.min(self.rows.len().saturating_sub(1));
+    }
+}
+
+fn push_rows(
+    tag: &Tag,
+    list_index: Option<usize>,
+    path: &mut TagPath,
+    expanded: &HashSet<TagPath>,
+    rows: &mut Vec<TreeRow>,
+) {
+    let children = tag.children();
+    let is_expanded = children.is_some() && expanded.contains(path);
+
+    rows.push(TreeRow {
+        path: path.clone(),
+        depth: path.len(),
+        label: describe(tag, list_index),
+        expandable: children.is_some_and(|children| !children.is_empty()),
+        expanded: is_expanded,
+    });
+
+    if !is_expanded {
+        return;
+    }
+
+    let is_list = matches!(tag, Tag::List { .. });
+    for (index, child) in children.unwrap().iter().enumerate() {
+        path.push(index);
+        push_rows(child, is_list.then_some(index), path, expanded, rows);
+        path.pop();
+    }
+}
+
+fn collect_matches(tag: &Tag, path: &mut TagPath, query: &str, matches: &mut Vec<TagPath>) {
+    if tag
+        .get_name()
+        .is_some_and(|name| name.to_lowercase().contains(query))
+    {
+        matches.push(path.clone());
+    }
+
+    for (index, child) in tag.children().unwrap_or_default().iter().enumerate() {
+        path.push(index);
+        collect_matches(child, path, query, matches);
+        path.pop();
+    }
+}
+
+pub fn describe(tag: &Tag, list_index: Option<usize>) -> String {
+    let name = match list_index {
+        Some(index) => format!("[{index}]"),
+        None => tag.get_name().unwrap_or_else(|| String::from("<root>")),
+    };
+
+    format!("{name} ({}): {}", tag.type_description(), tag.value_summary())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Tag {
+        Tag::Compound {
+            name: None,
+            value: vec![
+                Tag::Int {
+                    name: Some(String::from("DataVersion")),
+                    value: 3953,
+                },
+                Tag::List {
+                    name: Some(String::from("sections")),
+                    tag_type: 10,
+                    value: vec![Tag::Compound {
+                        name: None,
+                        value: vec![Tag::Byte {
+                            name: Some(String::from("Y")),
+                            value: -4,
+                        }],
+                    }],
+                },
+            ],
+        }
+    }
+
+    fn labels(view: &TreeView) -> Vec<&str> {
+        view.rows().iter().map(|row| row.label.as_str()).collect()
+    }
+
+    #[test]
+    fn test_root_starts_expanded() {
+        let view = TreeView::new(sample());
+
+        assert_eq!(
+            labels(&view),
+            [
+                "<root> (Compound): 2 entries",
+                "DataVersion (Int): 3953",
+                "sections (List of Compound): 1 entries",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_expand_and_collapse() {
+        let mut view = TreeView::new(sample());
+        view.select_last();
+        view.expand_selected();
+
+        assert_eq!(view.rows().len(), 4);
+        assert_eq!(view.rows()[3].label, "[0] (Compound): 1 entries");
+
+        view.select_relative(1);
+        view.collapse_selected();
+        assert_eq!(view.selected(), 2);
+
+        view.collapse_selected();
+        assert_eq!(view.rows().len(), 3);
+    }
+
+    #[test]
+    fn test_search_expands_ancestors() {
+        let mut view = TreeView::new(sample());
+
+        assert!(view.search("y", true));
+        assert_eq!(view.rows()[view.selected()].path, vec![1, 0, 0]);
+        assert_eq!(view.rows()[view.selected()].label, "Y (Byte): -4");
+
+        assert!(!view.search("missing", true));
+    }
+
+    #[test]
+    fn test_search_wraps_around() {
+        let mut view = TreeView::new(sample());
+        view.select_last();
+
+        assert!(view.search("data", true));
+        assert_eq!(view.rows()[view.selected()].path, vec![0]);
+    }
+}
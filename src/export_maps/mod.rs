@@ -0,0 +1,139 @@
+use crate::image::RgbImage;
+use crate::inject::read_nbt;
+use crate::nbt::tag::Tag;
+use clap::Args;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const MAP_SIZE: u32 = 128;
+/// Drawn where the map has nothing, the color of blank map paper.
+const PAPER: [u8; 3] = [214, 190, 150];
+
+/// The vanilla map base colors, indexed by the color id in the upper six bits of a map pixel.
+const BASE_COLORS: [u32; 62] = [
+    0x000000, 0x7FB238, 0xF7E9A3, 0xC7C7C7, 0xFF0000, 0xA0A0FF, 0xA7A7A7, 0x007C00,
+    0xFFFFFF, 0xA4A8B8, 0x976D4D, 0x707070, 0x4040FF, 0x8F7748, 0xFFFCF5, 0xD87F33,
+    0xB24CD8, 0x6699D8, 0xE5E533, 0x7FCC19, 0xF27FA5, 0x4C4C4C, 0x999999, 0x4C7F99,
+    0x7F3FB2, 0x334CB2, 0x664C33, 0x667F33, 0x993333, 0x191919, 0xFAEE4D, 0x5CDBD5,
+    0x4A80FF, 0x00D93A, 0x815631, 0x700200, 0xD1B1A1, 0x9F5224, 0x95576C, 0x706C8A,
+    0xBA8524, 0x677535, 0xA04D4E, 0x392923, 0x876B62, 0x575C5C, 0x7A4958, 0x4C3E5C,
+    0x4C3223, 0x4C522A, 0x8E3C2E, 0x251610, 0xBD3031, 0x943F61, 0x5C191D, 0x167E86,
+    0x3A8E8C, 0x562C3E, 0x14B485, 0x646464, 0xD8AF93, 0x7FA796,
+];
+
+/// Brightness of the shade in the lower two bits of a map pixel, out of 255.
+const SHADES: [u32; 4] = [180, 220, 255, 135];
+
+#[derive(Args)]
+pub struct ExportMapsArgs {
+    /// Path to your Minecraft world
+    pub world_path: PathBuf,
+
+    /// Folder receiving one `map_<n>.png` per map item
+    pub output_path: PathBuf,
+}
+
+/// The color of one map pixel, `None` where the map is transparent.
+fn map_color(pixel: i8) -> Option<[u8; 3]> {
+    let pixel = pixel as u8;
+    let base = *BASE_COLORS.get((pixel >> 2) as usize)?;
+    if pixel >> 2 == 0 {
+        return None;
+    }
+
+    let shade = SHADES[(pixel & 3) as usize];
+    let channel = |shift: u32| ((base >> shift & 0xFF) * shade / 255) as u8;
+    Some([channel(16), channel(8), channel(0)])
+}
+
+fn map_image(root: &Tag) -> Result<RgbImage, Box<dyn Error>> {
+    let colors = root
+        .find_tag("data")
+        .and_then(|data| data.find_tag("colors"))
+        .ok_or("The map has no data.colors")?;
+    let Tag::ByteArray { value: colors, .. } = colors else {
+        return Err("data.colors is not a byte array".into());
+    };
+    if colors.len() != (MAP_SIZE * MAP_SIZE) as usize {
+        return Err(format!("data.colors holds {} pixels, expected {}", colors.len(), MAP_SIZE * MAP_SIZE).into());
+    }
+
+    let mut image = RgbImage::new(MAP_SIZE, MAP_SIZE, PAPER);
+    for (index, pixel) in colors.iter().enumerate() {
+        if let Some(color) = map_color(*pixel) {
+            image.put_pixel(index as u32 % MAP_SIZE, index as u32 / MAP_SIZE, color);
+        }
+    }
+
+    Ok(image)
+}
+
+fn scan_map_files(data_folder: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(data_folder) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+            name.starts_with("map_") && name.ends_with(".dat")
+        })
+        .collect()
+}
+
+fn export_map(map_file: &Path, output_path: &Path) -> Result<(), Box<dyn Error>> {
+    let image = map_image(&read_nbt(&fs::read(map_file)?)?)?;
+    image.save_png(&output_path.join(map_file.with_extension("png").file_name().unwrap()))?;
+
+    Ok(())
+}
+
+pub fn run_export_maps(args: &ExportMapsArgs) -> Result<(), Box<dyn Error>> {
+    let map_files = scan_map_files(&args.world_path.join("data"));
+    if map_files.is_empty() {
+        return Err("No data/map_*.dat files in the world".into());
+    }
+
+    fs::create_dir_all(&args.output_path)?;
+
+    map_files.par_iter().for_each(|map_file| {
+        match export_map(map_file, &args.output_path) {
+            Ok(()) => println!("Exported map {}", map_file.display()),
+            Err(err) => eprintln!("Failed to export map {} !, error : {}", map_file.display(), err),
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_color() {
+        assert_eq!(map_color(0), None);
+        assert_eq!(map_color(3), None);
+        // grass at full brightness, then water darkened
+        assert_eq!(map_color(1 << 2 | 2), Some([0x7F, 0xB2, 0x38]));
+        assert_eq!(map_color(12 << 2), Some([45, 45, 180]));
+        // deepslate and beyond are stored as negative bytes
+        assert_eq!(map_color((59u8 << 2 | 2) as i8), Some([100, 100, 100]));
+        assert_eq!(map_color(-1), None);
+    }
+
+    #[test]
+    fn test_map_image_checks_colors() {
+        let map = |colors: Vec<i8>| Tag::Compound {
+            name: None,
+            value: vec![Tag::Compound { name: Some(String::from("data")), value: vec![Tag::ByteArray { name: Some(String::from("colors")), value: colors }] }],
+        };
+
+        assert!(map_image(&map(vec![4; 128 * 128])).is_ok());
+        assert!(map_image(&map(vec![4; 100])).is_err());
+        assert!(map_image(&Tag::Compound { name: None, value: Vec::new() }).is_err());
+    }
+}
@@ -0,0 +1,326 @@
+use crate::chunk::Chunk;
+use crate::chunk_data::blocks::{block_sections, BlockSection, BlockState};
+use crate::crop::ChunkBox;
+use crate::nbt::tag::Tag;
+use crate::region_file::{read_region_file, region_coords_from_path};
+use crate::{folder_name, scan_region_files, RegionType};
+use clap::Args;
+use flate2::write::GzEncoder;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const SPONGE_VERSION: i32 = 3;
+const AIR: &str = "minecraft:air";
+
+#[derive(Args)]
+pub struct ExportSchemArgs {
+    /// Path to your Minecraft world
+    pub world_path: PathBuf,
+
+    /// The `.schem` file to write
+    pub output: PathBuf,
+
+    /// One corner of the area in block coordinates, e.g. `-120,64`
+    #[arg(long, required = true, value_parser = crate::parse_chunk_coords, allow_hyphen_values = true)]
+    pub from: (i32, i32),
+
+    /// The opposite corner of the area, included in it
+    #[arg(long, required = true, value_parser = crate::parse_chunk_coords, allow_hyphen_values = true)]
+    pub to: (i32, i32),
+
+    /// Lowest block y to export, defaults to the lowest section of the area
+    #[arg(long, allow_hyphen_values = true)]
+    pub min_y: Option<i32>,
+
+    /// Highest block y to export, defaults to the top of the highest section of the area
+    #[arg(long, allow_hyphen_values = true)]
+    pub max_y: Option<i32>,
+}
+
+/// An inclusive box of block coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BlockBox {
+    pub min: (i32, i32, i32),
+    pub max: (i32, i32, i32),
+}
+
+impl BlockBox {
+    pub fn size(&self) -> (usize, usize, usize) {
+        let len = |min: i32, max: i32| (max - min + 1) as usize;
+        (len(self.min.0, self.max.0), len(self.min.1, self.max.1), len(self.min.2, self.max.2))
+    }
+
+    pub fn contains(&self, (x, y, z): (i32, i32, i32)) -> bool {
+        (self.min.0..=self.max.0).contains(&x) && (self.min.1..=self.max.1).contains(&y) && (self.min.2..=self.max.2).contains(&z)
+    }
+}
+
+/// The decoded block sections of the chunks of an area, by global chunk position.
+pub struct AreaBlocks {
+    sections: HashMap<(i32, i32), Vec<BlockSection>>,
+}
+
+impl AreaBlocks {
+    pub fn new(chunks: &[Chunk]) -> Self {
+        Self {
+            sections: chunks
+                .iter()
+                .filter_map(|chunk| Some((chunk.nbt_position()?, block_sections(chunk))))
+                .collect(),
+        }
+    }
+
+    /// The block at global block coordinates, `None` where the chunk or section is missing.
+    pub fn block_at(&self, x: i32, y: i32, z: i32) -> Option<&BlockState> {
+        let section = self.sections.get(&(x >> 4, z >> 4))?.iter().find(|section| section.y == y >> 4)?;
+        Some(section.block_at((x & 15) as usize, (y & 15) as usize, (z & 15) as usize))
+    }
+
+    /// The box of the columns from `(x1, z1)` to `(x2, z2)`, between the given heights or
+    /// the lowest and highest section present.
+    pub fn block_box(&self, (x1, z1): (i32, i32), (x2, z2): (i32, i32), min_y: Option<i32>, max_y: Option<i32>) -> Result<BlockBox, Box<dyn Error>> {
+        let section_ys = self.sections.values().flatten().map(|section| section.y);
+        let min_y = min_y.or_else(|| section_ys.clone().min().map(|y| y * 16)).ok_or("No chunks in the area")?;
+        let max_y = max_y.or_else(|| section_ys.max().map(|y| y * 16 + 15)).ok_or("No chunks in the area")?;
+        if min_y > max_y {
+            return Err("--min-y is above --max-y".into());
+        }
+
+        Ok(BlockBox { min: (x1.min(x2), min_y, z1.min(z2)), max: (x1.max(x2), max_y, z1.max(z2)) })
+    }
+}
+
+/// The block entities of a chunk inside the box, with their global position.
+pub fn block_entities_in<'a>(chunk: &'a Chunk, block_box: &BlockBox) -> Vec<((i32, i32, i32), &'a Tag)> {
+    let entities = chunk
+        .find_field("block_entities")
+        .or_else(|| chunk.find_field("TileEntities"))
+        .and_then(Tag::children)
+        .unwrap_or_default();
+
+    entities
+        .iter()
+        .filter_map(|entity| {
+            let coordinate = |name: &str| entity.find_tag(name).and_then(Tag::get_int).copied();
+            let position = (coordinate("x")?, coordinate("y")?, coordinate("z")?);
+            block_box.contains(position).then_some((position, entity))
+        })
+        .collect()
+}
+
+/// Reads the chunks of a world's terrain covering the columns from `from` to `to`.
+pub fn read_area_chunks(world_path: &Path, from: (i32, i32), to: (i32, i32)) -> Result<Vec<Chunk>, Box<dyn Error>> {
+    let chunk_box = ChunkBox::new((from.0 >> 4, from.1 >> 4), (to.0 >> 4, to.1 >> 4));
+
+    let mut chunks = Vec::new();
+    for region_file in scan_region_files(world_path.join(folder_name(RegionType::REGION))) {
+        let Some((region_x, region_z)) = region_coords_from_path(&region_file).filter(|coords| chunk_box.intersects_region(*coords)) else {
+            continue;
+        };
+
+        let (_, mut region) = read_region_file(&region_file).map_err(|err| format!("{}: {}", region_file.display(), err))?;
+        region.retain_chunks(|chunk| chunk_box.contains(chunk.global_position(region_x, region_z)));
+        chunks.extend(region.into_chunks());
+    }
+
+    Ok(chunks)
+}
+
+/// The block state as `minecraft:name[key=value,...]`, the way Sponge palettes key them.
+pub fn palette_key(state: &BlockState) -> String {
+    if state.properties.is_empty() {
+        return state.name.clone();
+    }
+
+    let properties: Vec<String> = state.properties.iter().map(|(key, value)| format!("{key}={value}")).collect();
+    format!("{}[{}]", state.name, properties.join(","))
+}
+
+fn write_varint(mut value: u32, out: &mut Vec<i8>) {
+    while value >= 0x80 {
+        out.push((value as u8 | 0x80) as i8);
+        value >>= 7;
+    }
+    out.push(value as i8);
+}
+
+/// Block entities of a chunk inside the box, as Sponge entries with positions relative to it.
+fn block_entities(chunk: &Chunk, block_box: &BlockBox) -> Vec<Tag> {
+    block_entities_in(chunk, block_box)
+        .into_iter()
+        .filter_map(|(position, entity)| {
+            let id = entity.find_tag("id").and_then(Tag::get_string)?.clone();
+            let data = entity
+                .children()?
+                .iter()
+                .filter(|tag| !matches!(tag.get_name().as_deref(), Some("id" | "x" | "y" | "z")))
+                .cloned()
+                .collect();
+            let relative = vec![position.0 - block_box.min.0, position.1 - block_box.min.1, position.2 - block_box.min.2];
+
+            Some(Tag::Compound {
+                name: None,
+                value: vec![
+                    Tag::IntArray { name: Some(String::from("Pos")), value: relative },
+                    Tag::String { name: Some(String::from("Id")), value: id },
+                    Tag::Compound { name: Some(String::from("Data")), value: data },
+                ],
+            })
+        })
+        .collect()
+}
+
+/// Builds a Sponge schematic (version 3) of the box from the chunks covering it. Blocks of
+/// missing chunks and sections are air.
+fn schematic(chunks: &[Chunk], from: (i32, i32), to: (i32, i32), min_y: Option<i32>, max_y: Option<i32>) -> Result<Tag, Box<dyn Error>> {
+    let area = AreaBlocks::new(chunks);
+    let block_box = area.block_box(from, to, min_y, max_y)?;
+    let (width, height, length) = block_box.size();
+    if [width, height, length].iter().any(|size| *size > u16::MAX as usize) {
+        return Err(format!("The area is {width}x{height}x{length} blocks, schematics hold at most {} per axis", u16::MAX).into());
+    }
+
+    let mut palette: HashMap<String, i32> = HashMap::from([(String::from(AIR), 0)]);
+    let mut data = Vec::with_capacity(width * height * length);
+
+    for y in block_box.min.1..=block_box.max.1 {
+        for z in block_box.min.2..=block_box.max.2 {
+            for x in block_box.min.0..=block_box.max.0 {
+                let key = area.block_at(x, y, z).map_or_else(|| String::from(AIR), palette_key);
+
+                let next = palette.len() as i32;
+                write_varint(*palette.entry(key).or_insert(next) as u32, &mut data);
+            }
+        }
+    }
+
+    let entities: Vec<Tag> = chunks.iter().flat_map(|chunk| block_entities(chunk, &block_box)).collect();
+    let data_version = chunks.iter().filter_map(Chunk::data_version).max().unwrap_or_default();
+
+    let mut palette: Vec<(String, i32)> = palette.into_iter().collect();
+    palette.sort_by_key(|(_, index)| *index);
+    let palette = palette.into_iter().map(|(key, index)| Tag::Int { name: Some(key), value: index }).collect();
+
+    let blocks = vec![
+        Tag::Compound { name: Some(String::from("Palette")), value: palette },
+        Tag::ByteArray { name: Some(String::from("Data")), value: data },
+        Tag::List { name: Some(String::from("BlockEntities")), value: entities, tag_type: 10 },
+    ];
+
+    Ok(Tag::Compound {
+        name: None,
+        value: vec![Tag::Compound {
+            name: Some(String::from("Schematic")),
+            value: vec![
+                Tag::Int { name: Some(String::from("Version")), value: SPONGE_VERSION },
+                Tag::Int { name: Some(String::from("DataVersion")), value: data_version },
+                Tag::Short { name: Some(String::from("Width")), value: width as u16 as i16 },
+                Tag::Short { name: Some(String::from("Height")), value: height as u16 as i16 },
+                Tag::Short { name: Some(String::from("Length")), value: length as u16 as i16 },
+                Tag::IntArray { name: Some(String::from("Offset")), value: vec![block_box.min.0, block_box.min.1, block_box.min.2] },
+                Tag::Compound { name: Some(String::from("Blocks")), value: blocks },
+            ],
+        }],
+    })
+}
+
+pub fn run_export_schem(args: &ExportSchemArgs) -> Result<(), Box<dyn Error>> {
+    let chunks = read_area_chunks(&args.world_path, args.from, args.to)?;
+    let schematic = schematic(&chunks, args.from, args.to, args.min_y, args.max_y)?;
+
+    let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&schematic.to_bytes())?;
+    fs::write(&args.output, encoder.finish()?)?;
+
+    println!("Wrote {} chunks to {}", chunks.len(), args.output.display());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn named(name: &str) -> Option<String> {
+        Some(String::from(name))
+    }
+
+    /// A chunk whose section 0 is all stone, with a chest at 15, 1, 15.
+    fn stone_chunk(x: i32, z: i32) -> Chunk {
+        let section = Tag::Compound {
+            name: None,
+            value: vec![
+                Tag::Byte { name: named("Y"), value: 0 },
+                Tag::Compound {
+                    name: named("block_states"),
+                    value: vec![Tag::List {
+                        name: named("palette"),
+                        value: vec![Tag::Compound { name: None, value: vec![Tag::String { name: named("Name"), value: String::from("minecraft:stone") }] }],
+                        tag_type: 10,
+                    }],
+                },
+            ],
+        };
+        let chest = Tag::Compound {
+            name: None,
+            value: vec![
+                Tag::String { name: named("id"), value: String::from("minecraft:chest") },
+                Tag::Int { name: named("x"), value: x * 16 + 15 },
+                Tag::Int { name: named("y"), value: 1 },
+                Tag::Int { name: named("z"), value: z * 16 + 15 },
+                Tag::String { name: named("CustomName"), value: String::from("\"loot\"") },
+            ],
+        };
+
+        Chunk::new_from_block_pos(x, z, 0, Tag::Compound {
+            name: None,
+            value: vec![
+                Tag::Int { name: named("DataVersion"), value: 3953 },
+                Tag::Int { name: named("xPos"), value: x },
+                Tag::Int { name: named("zPos"), value: z },
+                Tag::List { name: named("sections"), value: vec![section], tag_type: 10 },
+                Tag::List { name: named("block_entities"), value: vec![chest], tag_type: 10 },
+            ],
+        })
+    }
+
+    #[test]
+    fn test_schematic() {
+        // 4x4 columns around the corner of chunk 0, 0 where it meets three missing chunks
+        let root = schematic(&[stone_chunk(0, 0)], (14, 17), (17, 14), Some(1), Some(2)).unwrap();
+        let schematic = root.find_tag("Schematic").unwrap();
+
+        let short = |name: &str| match schematic.find_tag(name) {
+            Some(Tag::Short { value, .. }) => *value,
+            _ => panic!("{name} is not a short"),
+        };
+        assert_eq!((short("Width"), short("Height"), short("Length")), (4, 2, 4));
+        assert_eq!(schematic.find_tag("Offset"), Some(&Tag::IntArray { name: named("Offset"), value: vec![14, 1, 14] }));
+
+        let blocks = schematic.find_tag("Blocks").unwrap();
+        assert_eq!(blocks.find_tag("Palette").and_then(|palette| palette.find_tag("minecraft:stone")).and_then(Tag::get_int), Some(&1));
+
+        let Some(Tag::ByteArray { value: data, .. }) = blocks.find_tag("Data") else {
+            panic!("Data is not a byte array");
+        };
+        let row = [1, 1, 0, 0];
+        let layer: Vec<i8> = [row, row, [0; 4], [0; 4]].concat();
+        assert_eq!(data, &[layer.clone(), layer].concat());
+
+        let entities = blocks.find_tag("BlockEntities").and_then(Tag::children).unwrap();
+        assert_eq!(entities.len(), 1);
+        assert_eq!(entities[0].find_tag("Pos"), Some(&Tag::IntArray { name: named("Pos"), value: vec![1, 0, 1] }));
+        assert_eq!(entities[0].find_tag("Data").and_then(Tag::children).map(<[Tag]>::len), Some(1));
+
+        assert!(schematic_fails(&[], None));
+        assert!(schematic_fails(&[stone_chunk(0, 0)], Some((5, 4))));
+    }
+
+    fn schematic_fails(chunks: &[Chunk], y_range: Option<(i32, i32)>) -> bool {
+        let (min_y, max_y) = y_range.map_or((None, None), |(min, max)| (Some(min), Some(max)));
+        schematic(chunks, (0, 0), (1, 1), min_y, max_y).is_err()
+    }
+}
@@ -0,0 +1,221 @@
+use crate::chunk::Chunk;
+use crate::export_schem::{block_entities_in, palette_key, read_area_chunks, AreaBlocks, BlockBox};
+use crate::nbt::tag::Tag;
+use clap::Args;
+use flate2::write::GzEncoder;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Largest structure a vanilla structure block loads, per axis.
+const MAX_STRUCTURE_SIZE: u16 = 48;
+
+#[derive(Args)]
+pub struct ExportStructureArgs {
+    /// Path to your Minecraft world
+    pub world_path: PathBuf,
+
+    /// Folder receiving the structure files, e.g. `<world>/generated/minecraft/structures`
+    pub output_path: PathBuf,
+
+    /// One corner of the area in block coordinates, e.g. `-120,64`
+    #[arg(long, required = true, value_parser = crate::parse_chunk_coords, allow_hyphen_values = true)]
+    pub from: (i32, i32),
+
+    /// The opposite corner of the area, included in it
+    #[arg(long, required = true, value_parser = crate::parse_chunk_coords, allow_hyphen_values = true)]
+    pub to: (i32, i32),
+
+    /// Lowest block y to export, defaults to the lowest section of the area
+    #[arg(long, allow_hyphen_values = true)]
+    pub min_y: Option<i32>,
+
+    /// Highest block y to export, defaults to the top of the highest section of the area
+    #[arg(long, allow_hyphen_values = true)]
+    pub max_y: Option<i32>,
+
+    /// Structure name, areas larger than one structure are written as `<name>_<x>_<y>_<z>.nbt`
+    #[arg(long, default_value = "export")]
+    pub name: String,
+
+    /// Size of each structure per axis, larger areas are split into several
+    #[arg(long, default_value = "48", value_parser = clap::value_parser!(u16).range(1..=MAX_STRUCTURE_SIZE as i64))]
+    pub max_size: u16,
+}
+
+/// Splits the box into boxes of at most `max_size` blocks per axis, with their index along each
+/// axis.
+fn split(block_box: &BlockBox, max_size: u16) -> Vec<((usize, usize, usize), BlockBox)> {
+    let starts = |min: i32, max: i32| (min..=max).step_by(max_size as usize).enumerate().map(move |(index, start)| (index, start, (start + max_size as i32 - 1).min(max)));
+
+    let mut boxes = Vec::new();
+    for (ix, x, x_end) in starts(block_box.min.0, block_box.max.0) {
+        for (iy, y, y_end) in starts(block_box.min.1, block_box.max.1) {
+            for (iz, z, z_end) in starts(block_box.min.2, block_box.max.2) {
+                boxes.push(((ix, iy, iz), BlockBox { min: (x, y, z), max: (x_end, y_end, z_end) }));
+            }
+        }
+    }
+    boxes
+}
+
+fn int_list(name: &str, values: [i32; 3]) -> Tag {
+    Tag::List {
+        name: Some(String::from(name)),
+        value: values.iter().map(|value| Tag::Int { name: None, value: *value }).collect(),
+        tag_type: 3,
+    }
+}
+
+/// Builds the structure template of one box. Blocks of missing chunks are left out, so loading
+/// the structure keeps what the target world has there.
+fn structure(area: &AreaBlocks, chunks: &[Chunk], block_box: &BlockBox) -> Tag {
+    let entities: HashMap<(i32, i32, i32), &Tag> = chunks.iter().flat_map(|chunk| block_entities_in(chunk, block_box)).collect();
+
+    let mut palette: Vec<Tag> = Vec::new();
+    let mut palette_indices: HashMap<String, i32> = HashMap::new();
+    let mut blocks = Vec::new();
+
+    for y in block_box.min.1..=block_box.max.1 {
+        for z in block_box.min.2..=block_box.max.2 {
+            for x in block_box.min.0..=block_box.max.0 {
+                let Some(state) = area.block_at(x, y, z) else {
+                    continue;
+                };
+
+                let index = *palette_indices.entry(palette_key(state)).or_insert_with(|| {
+                    let mut entry = vec![Tag::String { name: Some(String::from("Name")), value: state.name.clone() }];
+                    if !state.properties.is_empty() {
+                        let properties = state.properties.iter().map(|(key, value)| Tag::String { name: Some(key.clone()), value: value.clone() }).collect();
+                        entry.push(Tag::Compound { name: Some(String::from("Properties")), value: properties });
+                    }
+                    palette.push(Tag::Compound { name: None, value: entry });
+                    palette.len() as i32 - 1
+                });
+
+                let mut block = vec![
+                    int_list("pos", [x - block_box.min.0, y - block_box.min.1, z - block_box.min.2]),
+                    Tag::Int { name: Some(String::from("state")), value: index },
+                ];
+                if let Some(Tag::Compound { value, .. }) = entities.get(&(x, y, z)) {
+                    let nbt = value.iter().filter(|tag| !matches!(tag.get_name().as_deref(), Some("x" | "y" | "z"))).cloned().collect();
+                    block.push(Tag::Compound { name: Some(String::from("nbt")), value: nbt });
+                }
+                blocks.push(Tag::Compound { name: None, value: block });
+            }
+        }
+    }
+
+    let (width, height, length) = block_box.size();
+    let data_version = chunks.iter().filter_map(Chunk::data_version).max().unwrap_or_default();
+
+    Tag::Compound {
+        name: None,
+        value: vec![
+            Tag::Int { name: Some(String::from("DataVersion")), value: data_version },
+            int_list("size", [width as i32, height as i32, length as i32]),
+            Tag::List { name: Some(String::from("palette")), value: palette, tag_type: 10 },
+            Tag::List { name: Some(String::from("blocks")), value: blocks, tag_type: 10 },
+            Tag::List { name: Some(String::from("entities")), value: Vec::new(), tag_type: 10 },
+        ],
+    }
+}
+
+pub fn run_export_structure(args: &ExportStructureArgs) -> Result<(), Box<dyn Error>> {
+    let chunks = read_area_chunks(&args.world_path, args.from, args.to)?;
+    let area = AreaBlocks::new(&chunks);
+    let boxes = split(&area.block_box(args.from, args.to, args.min_y, args.max_y)?, args.max_size);
+
+    fs::create_dir_all(&args.output_path)?;
+
+    for ((ix, iy, iz), block_box) in &boxes {
+        let name = match boxes.len() {
+            1 => format!("{}.nbt", args.name),
+            _ => format!("{}_{}_{}_{}.nbt", args.name, ix, iy, iz),
+        };
+
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&structure(&area, &chunks, block_box).to_bytes())?;
+        fs::write(args.output_path.join(&name), encoder.finish()?)?;
+
+        println!("Wrote structure {} at {}, {}, {}", name, block_box.min.0, block_box.min.1, block_box.min.2);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn named(name: &str) -> Option<String> {
+        Some(String::from(name))
+    }
+
+    #[test]
+    fn test_split() {
+        let boxes = split(&BlockBox { min: (-10, 0, 5), max: (89, 15, 5) }, 48);
+
+        assert_eq!(boxes.len(), 3);
+        assert_eq!(boxes[0], ((0, 0, 0), BlockBox { min: (-10, 0, 5), max: (37, 15, 5) }));
+        assert_eq!(boxes[2], ((2, 0, 0), BlockBox { min: (86, 0, 5), max: (89, 15, 5) }));
+    }
+
+    #[test]
+    fn test_structure() {
+        let section = Tag::Compound {
+            name: None,
+            value: vec![
+                Tag::Byte { name: named("Y"), value: 0 },
+                Tag::Compound {
+                    name: named("block_states"),
+                    value: vec![Tag::List {
+                        name: named("palette"),
+                        value: vec![Tag::Compound {
+                            name: None,
+                            value: vec![
+                                Tag::String { name: named("Name"), value: String::from("minecraft:furnace") },
+                                Tag::Compound { name: named("Properties"), value: vec![Tag::String { name: named("lit"), value: String::from("true") }] },
+                            ],
+                        }],
+                        tag_type: 10,
+                    }],
+                },
+            ],
+        };
+        let furnace = Tag::Compound {
+            name: None,
+            value: vec![
+                Tag::String { name: named("id"), value: String::from("minecraft:furnace") },
+                Tag::Int { name: named("x"), value: 16 },
+                Tag::Int { name: named("y"), value: 0 },
+                Tag::Int { name: named("z"), value: 0 },
+            ],
+        };
+        let chunk = Chunk::new_from_block_pos(1, 0, 0, Tag::Compound {
+            name: None,
+            value: vec![
+                Tag::Int { name: named("xPos"), value: 1 },
+                Tag::Int { name: named("zPos"), value: 0 },
+                Tag::List { name: named("sections"), value: vec![section], tag_type: 10 },
+                Tag::List { name: named("block_entities"), value: vec![furnace], tag_type: 10 },
+            ],
+        });
+
+        // two columns in chunk 1, 0 and two in the missing chunk 0, 0
+        let chunks = [chunk];
+        let block_box = BlockBox { min: (14, 0, 0), max: (17, 0, 0) };
+        let structure = structure(&AreaBlocks::new(&chunks), &chunks, &block_box);
+
+        assert_eq!(structure.find_tag("size"), Some(&int_list("size", [4, 1, 1])));
+        assert_eq!(structure.find_tag("palette").and_then(Tag::children).map(<[Tag]>::len), Some(1));
+
+        let blocks = structure.find_tag("blocks").and_then(Tag::children).unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].find_tag("pos"), Some(&int_list("pos", [2, 0, 0])));
+        assert_eq!(blocks[0].find_tag("nbt").and_then(|nbt| nbt.find_tag("id")).and_then(Tag::get_string).unwrap(), "minecraft:furnace");
+        assert!(blocks[1].find_tag("nbt").is_none());
+    }
+}
@@ -0,0 +1,39 @@
+use crate::nbt::snbt::to_snbt;
+use crate::region_file::{region_coords_from_path, Region};
+use clap::Args;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct ExtractArgs {
+    /// Region file in any of the supported formats (mca, linear, blinear)
+    pub region_file: PathBuf,
+
+    /// Global chunk coordinates of the chunk to extract, e.g. `-3,12`
+    #[arg(long, required = true, value_parser = crate::parse_chunk_coords, allow_hyphen_values = true)]
+    pub chunk: (i32, i32),
+
+    /// File to write, SNBT text when it ends in `.snbt`, uncompressed binary NBT otherwise
+    #[arg(long)]
+    pub out: PathBuf,
+}
+
+pub fn run_extract(args: &ExtractArgs) -> Result<(), Box<dyn Error>> {
+    let (region_x, region_z) = region_coords_from_path(&args.region_file).ok_or("File name is not r.<x>.<z>.<ext>")?;
+
+    let (x, z) = args.chunk;
+    if (x >> 5, z >> 5) != (region_x, region_z) {
+        return Err(format!("Chunk {x}, {z} is not in region {region_x}, {region_z}").into());
+    }
+
+    let chunk = Region::read_chunk_at(&args.region_file, x, z)?.ok_or_else(|| format!("Chunk {x}, {z} is not in this region"))?;
+
+    let snbt = args.out.extension().is_some_and(|extension| extension == "snbt");
+    let bytes = if snbt { to_snbt(chunk.get_data()).into_bytes() } else { chunk.to_raw_bytes() };
+    fs::write(&args.out, &bytes)?;
+
+    println!("Wrote chunk {x}, {z} ({} bytes) to {}", bytes.len(), args.out.display());
+
+    Ok(())
+}
@@ -0,0 +1,174 @@
+//! C interface for embedding the converter in other processes, e.g. through JNI. The header is
+//! generated into `include/bufferedlinear_tools.h` when building with the `ffi` feature.
+//!
+//! Functions return null or a negative value on failure, `blt_last_error` then describes the
+//! error. Buffers and regions handed out must be released with the matching `blt_*_free`.
+
+use crate::region_file::{convert_region_file, read_region_file, Region};
+use std::cell::RefCell;
+use std::error::Error;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::path::PathBuf;
+use std::ptr;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// A parsed region file.
+pub struct BltRegion {
+    region: Region,
+}
+
+fn set_last_error(err: impl ToString) {
+    let message = CString::new(err.to_string().replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+unsafe fn path_from_c(path: *const c_char) -> Result<PathBuf, Box<dyn Error>> {
+    if path.is_null() {
+        return Err("Path is null".into());
+    }
+
+    Ok(PathBuf::from(unsafe { CStr::from_ptr(path) }.to_str()?))
+}
+
+/// The message of the last error on this thread, or null. Valid until the next failing call.
+#[unsafe(no_mangle)]
+pub extern "C" fn blt_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr()))
+}
+
+/// Reads and parses a region file in any supported format.
+///
+/// # Safety
+/// `path` must be a valid nul terminated UTF-8 string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn blt_region_open(path: *const c_char) -> *mut BltRegion {
+    let opened = unsafe { path_from_c(path) }.and_then(|path| read_region_file(&path));
+
+    match opened {
+        Ok((_, region)) => Box::into_raw(Box::new(BltRegion { region })),
+        Err(err) => {
+            set_last_error(err);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Number of chunks in the region, or -1 if `region` is null.
+///
+/// # Safety
+/// `region` must be null or returned by `blt_region_open` and not freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn blt_region_chunk_count(region: *const BltRegion) -> c_int {
+    match unsafe { region.as_ref() } {
+        Some(region) => region.region.chunks().len() as c_int,
+        None => -1,
+    }
+}
+
+/// The uncompressed NBT of the chunk in the slot of the given chunk coordinates, its length is
+/// written to `out_len`. Returns null without setting an error if the slot is empty.
+///
+/// # Safety
+/// `region` must be returned by `blt_region_open` and not freed, `out_len` must be writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn blt_region_get_chunk(region: *const BltRegion, x: c_int, z: c_int, out_len: *mut usize) -> *mut u8 {
+    let (Some(region), false) = (unsafe { region.as_ref() }, out_len.is_null()) else {
+        set_last_error("Region or out_len is null");
+        return ptr::null_mut();
+    };
+
+    let sector_index = (x & 31) + ((z & 31) << 5);
+    let Some(chunk) = region.region.chunks().iter().find(|chunk| chunk.position_to_sector_index() == sector_index) else {
+        return ptr::null_mut();
+    };
+
+    let bytes = chunk.to_raw_bytes().into_boxed_slice();
+    unsafe { *out_len = bytes.len() };
+    Box::into_raw(bytes) as *mut u8
+}
+
+/// Releases a region returned by `blt_region_open`.
+///
+/// # Safety
+/// `region` must be null or returned by `blt_region_open` and not freed before.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn blt_region_free(region: *mut BltRegion) {
+    if !region.is_null() {
+        drop(unsafe { Box::from_raw(region) });
+    }
+}
+
+/// Releases a buffer returned by `blt_region_get_chunk`.
+///
+/// # Safety
+/// `buffer` must be null or returned by `blt_region_get_chunk` with this `len`, and not freed before.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn blt_buffer_free(buffer: *mut u8, len: usize) {
+    if !buffer.is_null() {
+        drop(unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(buffer, len)) });
+    }
+}
+
+/// Converts a region file into the format named by the output file's extension. Returns 0 on
+/// success and -1 on failure.
+///
+/// # Safety
+/// `input` and `output` must be valid nul terminated UTF-8 strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn blt_convert_file(input: *const c_char, output: *const c_char, compression_level: u8) -> c_int {
+    let converted = unsafe { path_from_c(input) }
+        .and_then(|input| Ok((input, unsafe { path_from_c(output) }?)))
+        .and_then(|(input, output)| convert_region_file(&input, &output, compression_level));
+
+    match converted {
+        Ok(()) => 0,
+        Err(err) => {
+            set_last_error(err);
+            -1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::nbt::tag::Tag;
+    use std::path::Path;
+
+    #[test]
+    fn test_open_convert_and_read() {
+        let folder = std::env::temp_dir().join("bufferedlinear_tools_ffi_test");
+        std::fs::create_dir_all(&folder).unwrap();
+        let input = folder.join("r.0.0.blinear");
+        let output = folder.join("r.0.0.mca");
+
+        let data = Tag::Compound { name: None, value: vec![Tag::Int { name: Some(String::from("xPos")), value: 3 }] };
+        std::fs::write(&input, Region::new(vec![Chunk::new_from_block_pos(3, 4, 0, data.clone())], 0).to_bytes_blinear(0, 3)).unwrap();
+
+        let c_path = |path: &Path| CString::new(path.to_str().unwrap()).unwrap();
+        unsafe {
+            assert_eq!(blt_convert_file(c_path(&input).as_ptr(), c_path(&output).as_ptr(), 6), 0);
+
+            let region = blt_region_open(c_path(&output).as_ptr());
+            assert!(!region.is_null());
+            assert_eq!(blt_region_chunk_count(region), 1);
+
+            let mut len = 0;
+            let buffer = blt_region_get_chunk(region, 3, 4, &mut len);
+            assert_eq!(std::slice::from_raw_parts(buffer, len), data.to_bytes().as_slice());
+            assert!(blt_region_get_chunk(region, 0, 0, &mut len).is_null());
+
+            blt_buffer_free(buffer, len);
+            blt_region_free(region);
+
+            assert!(blt_region_open(c_path(&folder.join("missing.mca")).as_ptr()).is_null());
+            assert!(!blt_last_error().is_null());
+        }
+
+        std::fs::remove_dir_all(&folder).unwrap();
+    }
+}
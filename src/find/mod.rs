@@ -0,0 +1,204 @@
+use crate::chunk::Chunk;
+use crate::nbt::query::Query;
+use crate::nbt::tag::Tag;
+use crate::region_file::{read_region_file, region_coords_from_path};
+use crate::{folder_name, scan_region_files, RegionType};
+use clap::Args;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+#[derive(Args)]
+pub struct FindArgs {
+    /// Path to your Minecraft world
+    pub world_path: PathBuf,
+
+    /// Region type to scan, defaults to `entities` when searching for entities and `region` otherwise
+    #[arg(short = 't', long, value_enum)]
+    pub region_type: Option<RegionType>,
+
+    /// Match chunks containing a block entity with this id
+    #[arg(long)]
+    pub block_entity: Option<String>,
+
+    /// Match chunks containing an entity with this id
+    #[arg(long)]
+    pub entity: Option<String>,
+
+    /// Match chunks where any tag reached by the query has the given value, e.g.
+    /// `Status=minecraft:full` or `sections[*].block_states.palette[*].Name=minecraft:diamond_ore`
+    #[arg(long, value_parser = parse_tag_equals)]
+    pub tag_equals: Vec<(Query, String)>,
+
+    /// Match chunks where the query reaches at least one tag, e.g. `structures.starts.*`
+    #[arg(long)]
+    pub has: Vec<Query>,
+}
+
+fn parse_tag_equals(s: &str) -> Result<(Query, String), String> {
+    let (query, value) = s.split_once('=').ok_or_else(|| String::from("Expected <query>=<value>"))?;
+    let query = Query::parse(query).map_err(|err| err.to_string())?;
+
+    Ok((query, String::from(value)))
+}
+
+fn count_with_id(list: Option<&Tag>, id: &str) -> usize {
+    list.and_then(|list| list.children())
+        .unwrap_or_default()
+        .iter()
+        .filter(|tag| tag.find_tag("id").and_then(|id| id.get_string()).is_some_and(|found| found == id))
+        .count()
+}
+
+/// Checks every criterion against a chunk and describes the match, or returns None if any fails.
+fn match_chunk(chunk: &Chunk, args: &FindArgs) -> Option<String> {
+    let mut details = Vec::new();
+
+    if let Some(id) = &args.block_entity {
+        let block_entities = chunk.find_field("block_entities").or_else(|| chunk.find_field("TileEntities"));
+        let count = count_with_id(block_entities, id);
+        if count == 0 {
+            return None;
+        }
+        details.push(format!("{count} x {id}"));
+    }
+
+    if let Some(id) = &args.entity {
+        let count = count_with_id(chunk.find_field("Entities"), id);
+        if count == 0 {
+            return None;
+        }
+        details.push(format!("{count} x {id}"));
+    }
+
+    for (query, expected) in &args.tag_equals {
+        let found = query
+            .evaluate(chunk.get_data())
+            .iter()
+            .any(|tag| tag.value_to_string().is_some_and(|value| value == *expected));
+        if !found {
+            return None;
+        }
+        details.push(format!("{query}={expected}"));
+    }
+
+    for query in &args.has {
+        let count = query.evaluate(chunk.get_data()).len();
+        if count == 0 {
+            return None;
+        }
+        details.push(format!("{count} x {query}"));
+    }
+
+    Some(details.join(", "))
+}
+
+fn find_in_region(region_file: &Path, args: &FindArgs) -> Result<Vec<String>, Box<dyn Error>> {
+    let (_, region) = read_region_file(region_file)?;
+    let (region_x, region_z) = region_coords_from_path(region_file).ok_or("File name is not r.<x>.<z>.<ext>")?;
+    let file_name = region_file.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+
+    Ok(region
+        .chunks()
+        .iter()
+        .filter_map(|chunk| {
+            let details = match_chunk(chunk, args)?;
+            let (x, z) = chunk.global_position(region_x, region_z);
+            Some(format!("chunk {:>6}, {:>6} ({})  {}", x, z, file_name, details))
+        })
+        .collect())
+}
+
+pub fn run_find(args: &FindArgs) -> Result<(), Box<dyn Error>> {
+    if args.block_entity.is_none() && args.entity.is_none() && args.tag_equals.is_empty() && args.has.is_empty() {
+        return Err("Nothing to find, pass --block-entity, --entity, --tag-equals or --has".into());
+    }
+
+    let region_type = args.region_type.unwrap_or(if args.entity.is_some() {
+        RegionType::ENTITIES
+    } else {
+        RegionType::REGION
+    });
+
+    let mut scanned = scan_region_files(args.world_path.join(folder_name(region_type)));
+    scanned.sort();
+
+    let per_file: Vec<Vec<String>> = scanned
+        .par_iter()
+        .filter_map(|region_file| match find_in_region(region_file, args) {
+            Ok(matches) => Some(matches),
+            Err(err) => {
+                eprintln!("Failed to read file {} !, error : {}", region_file.display(), err);
+                None
+            }
+        })
+        .collect();
+
+    let matches: Vec<String> = per_file.into_iter().flatten().collect();
+    for line in &matches {
+        println!("{line}");
+    }
+    println!("{} matching chunks", matches.len());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find_args(block_entity: Option<&str>, tag_equals: &[&str]) -> FindArgs {
+        FindArgs {
+            world_path: PathBuf::new(),
+            region_type: None,
+            block_entity: block_entity.map(String::from),
+            entity: None,
+            tag_equals: tag_equals.iter().map(|s| parse_tag_equals(s).unwrap()).collect(),
+            has: Vec::new(),
+        }
+    }
+
+    fn chunk() -> Chunk {
+        let chest = Tag::Compound {
+            name: None,
+            value: vec![Tag::String { name: Some(String::from("id")), value: String::from("minecraft:chest") }],
+        };
+        let level = Tag::Compound {
+            name: Some(String::from("Level")),
+            value: vec![
+                Tag::String { name: Some(String::from("Status")), value: String::from("full") },
+                Tag::List { name: Some(String::from("TileEntities")), tag_type: 10, value: vec![chest.clone(), chest] },
+            ],
+        };
+
+        Chunk::new_from_block_pos(0, 0, 0, Tag::Compound { name: None, value: vec![level] })
+    }
+
+    #[test]
+    fn test_match_legacy_chunk() {
+        let matched = match_chunk(&chunk(), &find_args(Some("minecraft:chest"), &["Level.Status=full"]));
+
+        assert_eq!(matched.as_deref(), Some("2 x minecraft:chest, Level.Status=full"));
+    }
+
+    #[test]
+    fn test_all_criteria_must_match() {
+        assert!(match_chunk(&chunk(), &find_args(Some("minecraft:barrel"), &[])).is_none());
+        assert!(match_chunk(&chunk(), &find_args(None, &["Level.Status=empty"])).is_none());
+        assert!(match_chunk(&chunk(), &find_args(None, &["Status=full"])).is_none());
+    }
+
+    #[test]
+    fn test_match_any_query_result() {
+        let matched = match_chunk(&chunk(), &find_args(None, &["Level.TileEntities[*].id=minecraft:chest"]));
+
+        assert!(matched.is_some());
+    }
+
+    #[test]
+    fn test_parse_tag_equals() {
+        assert_eq!(parse_tag_equals("a.b=c=d"), Ok((Query::parse("a.b").unwrap(), String::from("c=d"))));
+        assert!(parse_tag_equals("a.b").is_err());
+        assert!(parse_tag_equals("a[=b").is_err());
+    }
+}
@@ -0,0 +1,63 @@
+use crate::inject::read_nbt;
+use crate::nbt::tag::Tag;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// Files of a dimension's `data` folder recording force-loaded chunks, `chunks.dat` since 1.14 and
+/// `forceload.dat` before.
+const FORCELOAD_FILES: [&str; 2] = ["chunks.dat", "forceload.dat"];
+
+/// Collects the chunk positions of every `Forced` long array in the tree. Each long packs the chunk
+/// x into its low and z into its high 32 bits.
+fn forced_chunks(tag: &Tag, positions: &mut HashSet<(i32, i32)>) {
+    if let Tag::LongArray { name: Some(name), value } = tag
+        && name == "Forced"
+    {
+        positions.extend(value.iter().map(|&packed| (packed as i32, (packed >> 32) as i32)));
+    }
+
+    for child in tag.children().unwrap_or_default() {
+        forced_chunks(child, positions);
+    }
+}
+
+/// Global positions of the force-loaded chunks of a dimension folder, empty if it has none. Fails
+/// if a forceload file can not be read, since pruning would otherwise drop its chunks.
+pub fn forceloaded_chunks(dimension_folder: &Path) -> Result<HashSet<(i32, i32)>, Box<dyn Error>> {
+    let mut positions = HashSet::new();
+
+    for file in FORCELOAD_FILES {
+        let path = dimension_folder.join("data").join(file);
+        if !path.is_file() {
+            continue;
+        }
+
+        let tag = read_nbt(&fs::read(&path)?).map_err(|err| format!("{}: {}", path.display(), err))?;
+        forced_chunks(&tag, &mut positions);
+    }
+
+    Ok(positions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forced_chunks() {
+        let packed = |x: i32, z: i32| (x as u32 as i64) | ((z as i64) << 32);
+        let tag = Tag::Compound {
+            name: None,
+            value: vec![Tag::Compound {
+                name: Some(String::from("data")),
+                value: vec![Tag::LongArray { name: Some(String::from("Forced")), value: vec![packed(-1, 2), packed(300, -45)] }],
+            }],
+        };
+
+        let mut positions = HashSet::new();
+        forced_chunks(&tag, &mut positions);
+        assert_eq!(positions, HashSet::from([(-1, 2), (300, -45)]));
+    }
+}
@@ -0,0 +1,148 @@
+use crate::region_file::{blinear_dictionary_for, region_coords_from_path, salvage, Damage, ParseLimits, RegionFormat};
+use crate::scan_region_files;
+use clap::Args;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Args)]
+pub struct FsckArgs {
+    /// Folder of region files in any of the supported formats, or a single region file
+    pub path: PathBuf,
+
+    /// Write a repaired copy of every damaged file into this folder, in the format of the input,
+    /// holding every chunk that could be read intact
+    #[arg(long, value_name = "FOLDER")]
+    pub repair: Option<PathBuf>,
+
+    /// Parse with resource limits, as for untrusted worlds
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Compression level when writing repaired files
+    #[arg(short, long, default_value = "6", value_parser = crate::validate_compression_level)]
+    pub compression_level: u32,
+}
+
+struct FileReport {
+    chunks: usize,
+    damage: Vec<Damage>,
+    /// Chunk coordinates of the region's slot 0, when the file name tells them
+    origin: (i32, i32),
+    repaired: Option<PathBuf>,
+}
+
+/// Checks one region file, `None` when it is not one.
+fn check_file(region_file: &Path, args: &FsckArgs, limits: &ParseLimits) -> Result<Option<FileReport>, Box<dyn Error>> {
+    let bytes = fs::read(region_file)?;
+    let Some(format) = RegionFormat::detect(region_file, &bytes) else {
+        return Ok(None);
+    };
+
+    let dictionary = blinear_dictionary_for(region_file, &bytes)?;
+    let mut salvaged = salvage(format, &bytes, dictionary.as_deref(), limits)?;
+    let coords = region_coords_from_path(region_file);
+    let origin = coords.map_or((0, 0), |(region_x, region_z)| (region_x * 32, region_z * 32));
+
+    let mut repaired = None;
+    if let (Some(folder), false) = (&args.repair, salvaged.damage.is_empty()) {
+        let region = &mut salvaged.region;
+        if let Some((region_x, region_z)) = coords {
+            for chunk in region.chunks_mut() {
+                let (x, z) = chunk.global_position(region_x, region_z);
+                chunk.set_position(x, z);
+            }
+        }
+
+        let output = folder.join(region_file.file_name().ok_or("Region file has no file name")?);
+        fs::create_dir_all(folder)?;
+        fs::write(&output, region.to_bytes(format, coords, region.timestamp(), args.compression_level as u8)?)?;
+        repaired = Some(output);
+    }
+
+    Ok(Some(FileReport { chunks: salvaged.region.chunks().len(), damage: salvaged.damage, origin, repaired }))
+}
+
+/// Checks every region file, printing each problem with the chunks lost to it, and returns
+/// whether all files were intact.
+pub fn run_fsck(args: &FsckArgs) -> Result<bool, Box<dyn Error>> {
+    let limits = if args.strict { ParseLimits::STRICT } else { ParseLimits::DEFAULT };
+
+    let mut region_files = if args.path.is_file() { vec![args.path.clone()] } else { scan_region_files(args.path.clone()) };
+    region_files.sort();
+
+    let reports: Vec<(&PathBuf, Result<Option<FileReport>, String>)> = region_files
+        .par_iter()
+        .map(|region_file| (region_file, check_file(region_file, args, &limits).map_err(|err| err.to_string())))
+        .collect();
+
+    let (mut files, mut chunks, mut damaged, mut unreadable, mut lost) = (0, 0, 0, 0, 0);
+
+    for (region_file, report) in reports {
+        let report = match report {
+            Ok(Some(report)) => report,
+            Ok(None) => continue,
+            Err(err) => {
+                files += 1;
+                unreadable += 1;
+                println!("{}: unreadable, {}", region_file.display(), err);
+                continue;
+            }
+        };
+
+        files += 1;
+        chunks += report.chunks;
+        if report.damage.is_empty() {
+            continue;
+        }
+
+        damaged += 1;
+        for damage in &report.damage {
+            println!("{}: {}", region_file.display(), damage.problem);
+
+            lost += damage.lost.len();
+            for slot in &damage.lost {
+                println!("  lost chunk {}, {}", report.origin.0 + (slot % 32) as i32, report.origin.1 + (slot / 32) as i32);
+            }
+        }
+        if let Some(repaired) = &report.repaired {
+            println!("{}: {} intact chunks written to {}", region_file.display(), report.chunks, repaired.display());
+        }
+    }
+
+    println!("{} files, {} intact chunks, {} damaged files, {} chunks lost, {} unreadable files", files, chunks, damaged, lost, unreadable);
+
+    Ok(damaged == 0 && unreadable == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::nbt::tag::Tag;
+    use crate::region_file::Region;
+
+    #[test]
+    fn test_repair_copy() {
+        let folder = std::env::temp_dir().join("bufferedlinear_tools_fsck_test");
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+
+        let chunks = (0..3).map(|x| Chunk::new_from_block_pos(32 + x, 0, 1, Tag::Compound { name: None, value: Vec::new() })).collect();
+        let mut bytes = Region::new(chunks, 1).to_bytes(RegionFormat::Mca, None, 1, 6).unwrap();
+        // the chunk in slot 2 claims to be zlib but is not
+        let sector = (u32::from_be_bytes(bytes[8..12].try_into().unwrap()) >> 8) as usize;
+        bytes[sector * 4096 + 5] ^= 0xFF;
+        fs::write(folder.join("r.1.0.mca"), bytes).unwrap();
+
+        let args = FsckArgs { path: folder.clone(), repair: Some(folder.join("repaired")), strict: false, compression_level: 6 };
+        assert!(!run_fsck(&args).unwrap());
+
+        let report = check_file(&folder.join("repaired/r.1.0.mca"), &args, &ParseLimits::DEFAULT).unwrap().unwrap();
+        assert!(report.damage.is_empty());
+        assert_eq!(report.chunks, 2);
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
@@ -0,0 +1,239 @@
+use crate::backup::{list_backups, parse_region_bytes, Decryption, Manifest, ManifestEntry};
+use crate::chunk_store::{ChunkStore, MANIFEST_EXTENSION};
+use crate::incremental::hash;
+use crate::nbt::binary_reader::BinaryReader;
+use crate::nbt::parse::{parse_tag, ParseOptions};
+use crate::nbt::snbt::to_snbt;
+use crate::region_file::RegionFormat;
+use crate::{dimensions, throttle};
+use chrono::{DateTime, Local};
+use clap::Args;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Args)]
+pub struct HistoryArgs {
+    /// Folder of the backups written by `backup`, or with `--store` the folder whose subfolders
+    /// are conversions written with `--chunk-store`
+    pub backups: PathBuf,
+
+    /// Global chunk coordinates of the chunk to follow, e.g. `-3,12`
+    #[arg(long, required = true, value_parser = crate::parse_chunk_coords, allow_hyphen_values = true)]
+    pub chunk: (i32, i32),
+
+    /// Dimension of the chunk
+    #[arg(long, default_value = "minecraft:overworld", value_parser = dimensions::full_id)]
+    pub dimension: String,
+
+    /// Chunk store the snapshots' `.chunks` manifests refer to
+    #[arg(long, value_name = "DIR")]
+    pub store: Option<PathBuf>,
+
+    /// age identity file decrypting backups encrypted to a recipient, backups encrypted with a
+    /// passphrase are read with `BLT_BACKUP_PASSPHRASE`
+    #[arg(long, value_name = "FILE")]
+    pub identity: Option<PathBuf>,
+
+    /// Number of the listed version to write to `--out`
+    #[arg(long, value_name = "VERSION", requires = "out", value_parser = clap::value_parser!(u32).range(1..))]
+    pub extract: Option<u32>,
+
+    /// File the extracted version is written to, SNBT text when it ends in `.snbt`, uncompressed
+    /// binary NBT otherwise
+    #[arg(long, requires = "extract")]
+    pub out: Option<PathBuf>,
+}
+
+/// The chunk as one backup holds it.
+struct Snapshot {
+    backup: String,
+    created: DateTime<Local>,
+    timestamp: i64,
+    data: Vec<u8>,
+}
+
+/// A run of backups holding the chunk unchanged.
+struct Version {
+    first: (String, DateTime<Local>),
+    last: (String, DateTime<Local>),
+    backups: usize,
+    timestamp: i64,
+    hash: u64,
+    data: Vec<u8>,
+}
+
+/// Adds the chunk of the next backup, extending the newest version when the chunk did not change.
+fn push_snapshot(versions: &mut Vec<Version>, snapshot: Snapshot) {
+    let chunk_hash = hash(&snapshot.data);
+    if let Some(version) = versions.last_mut()
+        && version.hash == chunk_hash
+    {
+        version.last = (snapshot.backup, snapshot.created);
+        version.backups += 1;
+        return;
+    }
+
+    versions.push(Version {
+        first: (snapshot.backup.clone(), snapshot.created),
+        last: (snapshot.backup, snapshot.created),
+        backups: 1,
+        timestamp: snapshot.timestamp,
+        hash: chunk_hash,
+        data: snapshot.data,
+    });
+}
+
+/// Path of the chunk's region file below a world folder, without extension.
+fn region_source(args: &HistoryArgs) -> String {
+    let (x, z) = args.chunk;
+    let folder = dimensions::dimension_folder(&args.dimension).join("region");
+    let folder = folder.components().map(|component| component.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/");
+    format!("{}/r.{}.{}", folder, x >> 5, z >> 5)
+}
+
+/// The region file entry of a backup manifest holding the chunk, in any format.
+fn find_entry<'a>(manifest: &'a Manifest, source: &str) -> Option<&'a ManifestEntry> {
+    manifest.files.iter().find(|entry| {
+        entry
+            .source
+            .strip_prefix(source)
+            .and_then(|extension| extension.strip_prefix('.'))
+            .is_some_and(|extension| RegionFormat::from_extension(extension).is_some())
+    })
+}
+
+/// The chunk in one backup, `None` if the backup does not have it.
+fn backup_chunk(backups_folder: &Path, (backup, created): (&str, DateTime<Local>), entry: &ManifestEntry, decryption: &Decryption, (x, z): (i32, i32)) -> Result<Option<Snapshot>, Box<dyn Error>> {
+    let folder = backups_folder.join(entry.stored_in.as_deref().unwrap_or(backup));
+    let stored = folder.join(entry.plain_path());
+    let mut bytes = throttle::read(folder.join(&entry.path))?;
+    if entry.is_encrypted() {
+        bytes = decryption.decrypt(&bytes).map_err(|err| format!("Failed to decrypt {} : {}", folder.join(&entry.path).display(), err))?;
+    }
+
+    let format = RegionFormat::detect(&stored, &bytes).ok_or_else(|| format!("{} is not a region file", stored.display()))?;
+    let region = parse_region_bytes(&stored, &bytes, format)?;
+    let chunk = region.chunks().iter().find(|chunk| chunk.global_position(x >> 5, z >> 5) == (x, z));
+
+    Ok(chunk.map(|chunk| Snapshot { backup: backup.to_string(), created, timestamp: chunk.timestamp(), data: chunk.to_raw_bytes() }))
+}
+
+/// The chunk in every backup written by `backup`, oldest first.
+fn backup_snapshots(args: &HistoryArgs) -> Result<Vec<Snapshot>, Box<dyn Error>> {
+    let decryption = Decryption::new(args.identity.as_deref())?;
+    let source = region_source(args);
+
+    let mut backups = list_backups(&args.backups)?;
+    backups.sort_by_key(|(_, created)| *created);
+
+    let mut snapshots = Vec::new();
+    for (backup, created) in backups {
+        let manifest = Manifest::read(&args.backups.join(&backup))?;
+        let Some(entry) = find_entry(&manifest, &source) else {
+            continue;
+        };
+
+        match backup_chunk(&args.backups, (&backup, created), entry, &decryption, args.chunk) {
+            Ok(Some(snapshot)) => snapshots.push(snapshot),
+            Ok(None) => {}
+            Err(err) => eprintln!("Failed to read backup {} !, error : {}", backup, err),
+        }
+    }
+
+    Ok(snapshots)
+}
+
+/// The chunk in every conversion written to the chunk store, oldest first.
+fn store_snapshots(args: &HistoryArgs, store: &ChunkStore) -> Result<Vec<Snapshot>, Box<dyn Error>> {
+    let source = format!("{}.{}", region_source(args), MANIFEST_EXTENSION);
+    let (x, z) = args.chunk;
+    let slot = ((x & 31) + ((z & 31) << 5)) as usize;
+
+    let mut snapshots = Vec::new();
+    for entry in fs::read_dir(&args.backups)? {
+        let folder = entry?.path();
+        let manifest_file = folder.join(&source);
+        if !manifest_file.is_file() {
+            continue;
+        }
+        let backup = folder.file_name().unwrap_or_default().to_string_lossy().to_string();
+
+        match store.read_chunk(&manifest_file, slot) {
+            Ok(Some(chunk)) => {
+                let created = DateTime::from_timestamp_millis(chunk.written).unwrap_or_default().with_timezone(&Local);
+                snapshots.push(Snapshot { backup, created, timestamp: chunk.timestamp, data: chunk.data });
+            }
+            Ok(None) => {}
+            Err(err) => eprintln!("Failed to read snapshot {} !, error : {}", backup, err),
+        }
+    }
+    snapshots.sort_by_key(|snapshot| snapshot.created);
+
+    Ok(snapshots)
+}
+
+pub fn run_history(args: &HistoryArgs) -> Result<(), Box<dyn Error>> {
+    let snapshots = match &args.store {
+        Some(store) => store_snapshots(args, &ChunkStore::new(store.clone()))?,
+        None => backup_snapshots(args)?,
+    };
+
+    let found = snapshots.len();
+    let mut versions = Vec::new();
+    for snapshot in snapshots {
+        push_snapshot(&mut versions, snapshot);
+    }
+
+    let (x, z) = args.chunk;
+    println!("Chunk {x}, {z} is in {found} backups, {} versions", versions.len());
+    let time = |time: &DateTime<Local>| time.format("%Y-%m-%d %H:%M:%S").to_string();
+    for (number, version) in versions.iter().enumerate() {
+        let saved = DateTime::from_timestamp(version.timestamp, 0).map_or_else(|| version.timestamp.to_string(), |saved| time(&saved.with_timezone(&Local)));
+        println!(
+            "{:>4}  {} ({}) to {} ({}), {} backups, saved {}, hash {:016x}",
+            number + 1,
+            version.first.0,
+            time(&version.first.1),
+            version.last.0,
+            time(&version.last.1),
+            version.backups,
+            saved,
+            version.hash
+        );
+    }
+
+    if let (Some(number), Some(out)) = (args.extract, &args.out) {
+        let version = versions.get(number as usize - 1).ok_or_else(|| format!("There is no version {number}"))?;
+        let snbt = out.extension().is_some_and(|extension| extension == "snbt");
+        let bytes = if snbt {
+            to_snbt(&parse_tag(&mut BinaryReader::new(&version.data), &ParseOptions::STRICT)?).into_bytes()
+        } else {
+            version.data.clone()
+        };
+        fs::write(out, &bytes)?;
+
+        println!("Wrote version {number} of chunk {x}, {z} ({} bytes) to {}", bytes.len(), out.display());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_snapshot() {
+        let snapshot = |backup: &str, data: &[u8]| Snapshot { backup: backup.to_string(), created: Local::now(), timestamp: 0, data: data.to_vec() };
+
+        let mut versions = Vec::new();
+        for (backup, data) in [("a", b"one"), ("b", b"one"), ("c", b"two"), ("d", b"one")] {
+            push_snapshot(&mut versions, snapshot(backup, data));
+        }
+
+        assert_eq!(versions.len(), 3);
+        assert_eq!((versions[0].first.0.as_str(), versions[0].last.0.as_str(), versions[0].backups), ("a", "b", 2));
+        assert_eq!(versions[2].data, b"one");
+    }
+}
@@ -0,0 +1,108 @@
+use png::{BitDepth, ColorType, Encoder, EncodingError};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+/// Largest image the renderers will allocate, in pixels.
+pub const MAX_PIXELS: usize = 1 << 28;
+
+pub struct RgbImage {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl RgbImage {
+    pub fn new(width: u32, height: u32, background: [u8; 3]) -> Self {
+        Self {
+            width,
+            height,
+            pixels: background.repeat(width as usize * height as usize),
+        }
+    }
+
+    pub fn put_pixel(&mut self, x: u32, y: u32, color: [u8; 3]) {
+        let offset = (y as usize * self.width as usize + x as usize) * 3;
+        self.pixels[offset..offset + 3].copy_from_slice(&color);
+    }
+
+    pub fn save_png(&self, path: &Path) -> Result<(), EncodingError> {
+        let mut encoder = Encoder::new(BufWriter::new(File::create(path)?), self.width, self.height);
+        encoder.set_color(ColorType::Rgb);
+        encoder.set_depth(BitDepth::Eight);
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels)?;
+        writer.finish()
+    }
+}
+
+pub struct GrayImage16 {
+    width: u32,
+    height: u32,
+    pixels: Vec<u16>,
+}
+
+impl GrayImage16 {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width as usize * height as usize],
+        }
+    }
+
+    pub fn put_pixel(&mut self, x: u32, y: u32, value: u16) {
+        self.pixels[y as usize * self.width as usize + x as usize] = value;
+    }
+
+    pub fn save_png(&self, path: &Path) -> Result<(), EncodingError> {
+        let mut encoder = Encoder::new(BufWriter::new(File::create(path)?), self.width, self.height);
+        encoder.set_color(ColorType::Grayscale);
+        encoder.set_depth(BitDepth::Sixteen);
+
+        // PNG stores 16 bit samples big endian
+        let data: Vec<u8> = self.pixels.iter().flat_map(|value| value.to_be_bytes()).collect();
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&data)?;
+        writer.finish()
+    }
+}
+
+/// Maps `t` in `0.0..=1.0` onto a blue - green - yellow - red ramp.
+pub fn heat_color(t: f64) -> [u8; 3] {
+    let t = t.clamp(0.0, 1.0);
+    let stops: [[f64; 3]; 4] = [[40.0, 70.0, 200.0], [40.0, 180.0, 80.0], [230.0, 210.0, 40.0], [210.0, 40.0, 30.0]];
+
+    let scaled = t * (stops.len() - 1) as f64;
+    let index = (scaled.floor() as usize).min(stops.len() - 2);
+    let fraction = scaled - index as f64;
+
+    let mut color = [0u8; 3];
+    for channel in 0..3 {
+        let value = stops[index][channel] + (stops[index + 1][channel] - stops[index][channel]) * fraction;
+        color[channel] = value.round() as u8;
+    }
+    color
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heat_color_endpoints() {
+        assert_eq!(heat_color(0.0), [40, 70, 200]);
+        assert_eq!(heat_color(1.0), [210, 40, 30]);
+        assert_eq!(heat_color(2.0), heat_color(1.0));
+    }
+
+    #[test]
+    fn test_put_pixel() {
+        let mut image = RgbImage::new(2, 2, [0, 0, 0]);
+        image.put_pixel(1, 1, [1, 2, 3]);
+
+        assert_eq!(image.pixels, [0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 2, 3]);
+    }
+}
@@ -0,0 +1,223 @@
+use crate::region_file::{RawRegion, ReusedBuckets};
+use crate::throttle;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::hash::Hasher;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use twox_hash::XxHash64;
+
+/// Chunk hashes of the previous incremental run, kept next to the files it wrote.
+pub const CACHE_FILE: &str = "incremental.cache";
+
+/// What the previous run wrote to one output file.
+#[derive(Clone, Debug, PartialEq)]
+struct FileEntry {
+    /// Hash of the whole output file, so files changed since are not reused
+    output_hash: u64,
+    /// Hash of each chunk's timestamp and NBT, by slot
+    chunks: Vec<(u16, u64)>,
+}
+
+/// Per chunk content hashes of the files an incremental conversion wrote into one output
+/// folder. Only valid for runs with the same output settings, told apart by `fingerprint`.
+pub struct HashCache {
+    path: PathBuf,
+    fingerprint: u64,
+    files: Mutex<HashMap<String, FileEntry>>,
+}
+
+pub fn hash(bytes: &[u8]) -> u64 {
+    let mut hasher = XxHash64::with_seed(0);
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+/// The hash of every occupied slot of the region.
+fn chunk_hashes(region: &RawRegion) -> Vec<(u16, u64)> {
+    region
+        .slots()
+        .iter()
+        .enumerate()
+        .filter_map(|(slot, chunk)| {
+            let chunk = chunk.as_ref()?;
+            let mut hasher = XxHash64::with_seed(0);
+            hasher.write(&chunk.timestamp.to_be_bytes());
+            hasher.write(&chunk.data);
+            Some((slot as u16, hasher.finish()))
+        })
+        .collect()
+}
+
+/// Slots whose chunk was added, removed or changed between two hash lists.
+fn changed_slots(previous: &[(u16, u64)], current: &[(u16, u64)]) -> Vec<bool> {
+    let mut hashes = vec![(None, None); 1024];
+    for (slot, hash) in previous {
+        hashes[*slot as usize].0 = Some(*hash);
+    }
+    for (slot, hash) in current {
+        hashes[*slot as usize].1 = Some(*hash);
+    }
+
+    hashes.iter().map(|(previous, current)| previous != current).collect()
+}
+
+impl HashCache {
+    /// The cache in `folder`, empty if it is missing, unreadable or was written with other
+    /// settings.
+    pub fn load(folder: &Path, fingerprint: u64) -> Self {
+        let path = folder.join(CACHE_FILE);
+        let files = fs::read(&path).ok().and_then(|bytes| parse_cache(&bytes, fingerprint)).unwrap_or_default();
+
+        Self { path, fingerprint, files: Mutex::new(files) }
+    }
+
+    /// Produces the bytes of `output` for `region` with `write`, handing it the buckets of the
+    /// previous output whose chunks did not change. `None` when no chunk changed at all and the
+    /// previous output can stay as it is.
+    pub fn convert(&self, output: &Path, region: &RawRegion, write: impl FnOnce(Option<&ReusedBuckets>) -> Result<Vec<u8>, Box<dyn Error>>) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+        // relative to the cache folder, which is just the file name outside of subfolders
+        let relative = self.path.parent().and_then(|folder| output.strip_prefix(folder).ok()).unwrap_or(output);
+        let name = relative.to_str().ok_or("Output path is not UTF-8")?.replace('\\', "/");
+        let chunks = chunk_hashes(region);
+
+        let previous_bytes = fs::read(output).ok();
+        let previous = match (&previous_bytes, self.files.lock().unwrap().get(&name)) {
+            (Some(bytes), Some(entry)) if entry.output_hash == hash(bytes) => Some(entry.clone()),
+            _ => None,
+        };
+
+        if previous.as_ref().is_some_and(|previous| previous.chunks == chunks) {
+            return Ok(None);
+        }
+
+        let reuse = match (&previous_bytes, &previous) {
+            (Some(bytes), Some(previous)) => ReusedBuckets::new(bytes, changed_slots(&previous.chunks, &chunks)),
+            _ => None,
+        };
+        let bytes = write(reuse.as_ref())?;
+
+        self.files.lock().unwrap().insert(name, FileEntry { output_hash: hash(&bytes), chunks });
+        Ok(Some(bytes))
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let files = self.files.lock().unwrap();
+
+        let mut bytes = self.fingerprint.to_be_bytes().to_vec();
+        for (name, entry) in files.iter() {
+            bytes.extend_from_slice(&(name.len() as u16).to_be_bytes());
+            bytes.extend_from_slice(name.as_bytes());
+            bytes.extend_from_slice(&entry.output_hash.to_be_bytes());
+            bytes.extend_from_slice(&(entry.chunks.len() as u16).to_be_bytes());
+            for (slot, hash) in &entry.chunks {
+                bytes.extend_from_slice(&slot.to_be_bytes());
+                bytes.extend_from_slice(&hash.to_be_bytes());
+            }
+        }
+
+        throttle::write(&self.path, bytes)
+    }
+}
+
+fn parse_cache(bytes: &[u8], fingerprint: u64) -> Option<HashMap<String, FileEntry>> {
+    let mut pointer = 0;
+    let mut take = |length: usize| {
+        let taken = bytes.get(pointer..pointer + length)?;
+        pointer += length;
+        Some(taken)
+    };
+
+    if u64::from_be_bytes(take(8)?.try_into().unwrap()) != fingerprint {
+        return None;
+    }
+
+    let mut files = HashMap::new();
+    while let Some(name_length) = take(2) {
+        let name = String::from_utf8(take(u16::from_be_bytes(name_length.try_into().unwrap()) as usize)?.to_vec()).ok()?;
+        let output_hash = u64::from_be_bytes(take(8)?.try_into().unwrap());
+
+        let count = u16::from_be_bytes(take(2)?.try_into().unwrap());
+        let mut chunks = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let slot = u16::from_be_bytes(take(2)?.try_into().unwrap());
+            let hash = u64::from_be_bytes(take(8)?.try_into().unwrap());
+            if slot >= 1024 {
+                return None;
+            }
+            chunks.push((slot, hash));
+        }
+
+        files.insert(name, FileEntry { output_hash, chunks });
+    }
+
+    Some(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::nbt::tag::Tag;
+    use crate::region_file::{BlinearOptions, Region};
+
+    fn region(marker: i32) -> RawRegion {
+        let chunks = (0..4)
+            .map(|x| {
+                let value = if x == 3 { marker } else { x };
+                Chunk::new_from_block_pos(x, 0, 1, Tag::Compound { name: None, value: vec![Tag::Int { name: Some(String::from("value")), value }] })
+            })
+            .collect();
+        Region::new(chunks, 0).to_raw()
+    }
+
+    #[test]
+    fn test_convert() {
+        let folder = std::env::temp_dir().join("bufferedlinear_tools_incremental_test");
+        fs::create_dir_all(&folder).unwrap();
+        let output = folder.join("r.0.0.blinear");
+        let _ = fs::remove_file(&output);
+        let options = BlinearOptions { grid_size: Some(2), ..BlinearOptions::default() };
+
+        let convert = |cache: &HashCache, region: &RawRegion| {
+            let mut reused = false;
+            let bytes = cache
+                .convert(&output, region, |reuse| {
+                    reused = reuse.is_some();
+                    Ok(region.to_bytes_blinear(0, 3, options, None, reuse))
+                })
+                .unwrap();
+            if let Some(bytes) = &bytes {
+                fs::write(&output, bytes).unwrap();
+            }
+            (bytes, reused)
+        };
+
+        let cache = HashCache::load(&folder, 7);
+        assert!(matches!(convert(&cache, &region(3)), (Some(_), false)));
+        assert!(matches!(convert(&cache, &region(3)), (None, false)));
+        cache.save().unwrap();
+
+        // a new run with the saved cache reuses the previous output
+        let cache = HashCache::load(&folder, 7);
+        let (bytes, reused) = convert(&cache, &region(4));
+        assert!(reused);
+        assert_eq!(bytes.unwrap(), region(4).to_bytes_blinear(0, 3, options, None, None));
+
+        // an output changed since is not reused
+        fs::write(&output, b"changed").unwrap();
+        assert!(matches!(convert(&cache, &region(5)), (Some(_), false)));
+
+        // other settings start over
+        assert!(HashCache::load(&folder, 8).files.lock().unwrap().is_empty());
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn test_changed_slots() {
+        let changed = changed_slots(&[(0, 1), (1, 2), (5, 3)], &[(0, 1), (1, 9), (7, 3)]);
+        assert_eq!(changed.iter().enumerate().filter(|(_, changed)| **changed).map(|(slot, _)| slot).collect::<Vec<_>>(), [1, 5, 7]);
+    }
+}
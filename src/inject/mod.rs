@@ -0,0 +1,129 @@
+use crate::chunk::Chunk;
+use crate::nbt::binary_reader::BinaryReader;
+use crate::nbt::parse::{parse_tag, ParseOptions};
+use crate::nbt::tag::Tag;
+use crate::region_file::{read_region_file, region_coords_from_path, Region};
+use clap::Args;
+use flate2::read::GzDecoder;
+use std::error::Error;
+use std::fs;
+use std::io::Read;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct InjectArgs {
+    /// Region file to modify in place, in any of the supported formats (mca, linear, blinear)
+    pub region_file: PathBuf,
+
+    /// Global chunk coordinates of the chunk to replace or add, e.g. `-3,12`
+    #[arg(long, required = true, value_parser = crate::parse_chunk_coords, allow_hyphen_values = true)]
+    pub chunk: (i32, i32),
+
+    /// Binary NBT file with the chunk data, plain or gzip compressed as written by `extract`
+    #[arg(long)]
+    pub from: PathBuf,
+
+    /// Compression level when rewriting the region file
+    #[arg(short, long, default_value = "6", value_parser = crate::validate_compression_level)]
+    pub compression_level: u32,
+}
+
+/// Parses a binary NBT file, gzip compressed files are recognized by their magic bytes.
+pub fn read_nbt(bytes: &[u8]) -> Result<Tag, Box<dyn Error>> {
+    if bytes.starts_with(&[0x1f, 0x8b]) {
+        let mut decompressed = Vec::new();
+        GzDecoder::new(bytes).read_to_end(&mut decompressed)?;
+        return Ok(parse_tag(&mut BinaryReader::new(&decompressed), &ParseOptions::STRICT)?);
+    }
+
+    Ok(parse_tag(&mut BinaryReader::new(bytes), &ParseOptions::STRICT)?)
+}
+
+/// Puts the chunk at the given global position, replacing the chunk already there. Fails if the
+/// position is outside the region or the NBT records another position. Returns whether a chunk
+/// was replaced.
+fn inject_chunk(region: &mut Region, region_x: i32, region_z: i32, (x, z): (i32, i32), data: Tag) -> Result<bool, String> {
+    if (x >> 5, z >> 5) != (region_x, region_z) {
+        return Err(format!("Chunk {x}, {z} is not in region {region_x}, {region_z}"));
+    }
+
+    let existing = region.chunks().iter().find(|chunk| chunk.global_position(region_x, region_z) == (x, z));
+    let timestamp = existing.map_or(region.timestamp(), |chunk| chunk.timestamp());
+    let replaced = existing.is_some();
+
+    let chunk = Chunk::new_from_block_pos(x, z, timestamp, data);
+    if let Some((nbt_x, nbt_z)) = chunk.nbt_position()
+        && (nbt_x, nbt_z) != (x, z)
+    {
+        return Err(format!("The NBT records position {nbt_x}, {nbt_z}, not {x}, {z}"));
+    }
+
+    region.retain_chunks(|chunk| chunk.global_position(region_x, region_z) != (x, z));
+    region.push_chunk(chunk);
+
+    Ok(replaced)
+}
+
+pub fn run_inject(args: &InjectArgs) -> Result<(), Box<dyn Error>> {
+    let data = read_nbt(&fs::read(&args.from)?)?;
+    let (format, mut region) = read_region_file(&args.region_file)?;
+    let (region_x, region_z) = region_coords_from_path(&args.region_file).ok_or("File name is not r.<x>.<z>.<ext>")?;
+
+    let replaced = inject_chunk(&mut region, region_x, region_z, args.chunk, data)?;
+
+    // write next to the original and swap, so an interrupted run never leaves a truncated file
+    let bytes = region.to_bytes(format, Some((region_x, region_z)), region.timestamp(), args.compression_level as u8)?;
+    let temp_file = args.region_file.with_extension("tmp");
+    fs::write(&temp_file, bytes)?;
+    fs::rename(&temp_file, &args.region_file)?;
+
+    let (x, z) = args.chunk;
+    let action = if replaced { "Replaced" } else { "Added" };
+    println!("{action} chunk {x}, {z} in {}", args.region_file.display());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk_data(x: i32, z: i32, status: &str) -> Tag {
+        Tag::Compound {
+            name: None,
+            value: vec![
+                Tag::Int { name: Some(String::from("xPos")), value: x },
+                Tag::Int { name: Some(String::from("zPos")), value: z },
+                Tag::String { name: Some(String::from("Status")), value: String::from(status) },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_inject_chunk() {
+        let mut region = Region::new(vec![Chunk::new_from_block_pos(0, 1, 9, chunk_data(-32, 33, "minecraft:empty"))], 0);
+
+        assert_eq!(inject_chunk(&mut region, -1, 1, (-32, 33), chunk_data(-32, 33, "minecraft:full")), Ok(true));
+        assert_eq!(inject_chunk(&mut region, -1, 1, (-31, 33), chunk_data(-31, 33, "minecraft:full")), Ok(false));
+        assert_eq!(region.chunks().len(), 2);
+        assert_eq!(region.chunks()[0].timestamp(), 9);
+        assert!(region.chunks().iter().all(|chunk| chunk.find_field("Status").and_then(|tag| tag.get_string()).unwrap() == "minecraft:full"));
+
+        assert!(inject_chunk(&mut region, -1, 1, (0, 33), chunk_data(0, 33, "minecraft:full")).is_err());
+        assert!(inject_chunk(&mut region, -1, 1, (-30, 33), chunk_data(-32, 33, "minecraft:full")).is_err());
+        assert_eq!(region.chunks().len(), 2);
+    }
+
+    #[test]
+    fn test_read_gzip_nbt() {
+        use flate2::write::GzEncoder;
+        use std::io::Write;
+
+        let data = chunk_data(1, 2, "minecraft:full");
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&data.to_bytes()).unwrap();
+
+        assert_eq!(read_nbt(&encoder.finish().unwrap()).unwrap(), data);
+        assert_eq!(read_nbt(&data.to_bytes()).unwrap(), data);
+    }
+}
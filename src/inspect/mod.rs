@@ -0,0 +1,87 @@
+use crate::inject::read_nbt;
+use crate::nbt::query::Query;
+use crate::nbt::tag::Tag;
+use crate::region_file::{read_region_file, region_coords_from_path};
+use clap::Args;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Args)]
+pub struct InspectArgs {
+    /// Region file in any of the supported formats (mca, linear, blinear), or a standalone NBT
+    /// `.dat` file such as `level.dat`, `playerdata/<uuid>.dat` or `data/raids.dat`
+    pub region_file: PathBuf,
+
+    /// Only inspect the chunk at these global chunk coordinates, e.g. `-3,12`
+    #[arg(long, value_parser = crate::parse_chunk_coords, allow_hyphen_values = true)]
+    pub chunk: Option<(i32, i32)>,
+
+    /// Print the tags reached by this query instead of the chunk root, e.g. `sections[*].Y`
+    #[arg(short, long)]
+    pub query: Option<Query>,
+}
+
+fn print_matches(query: &Query, root: &Tag) {
+    for tag in query.evaluate(root) {
+        let name = tag.get_name().map_or(String::new(), |name| format!("{name} "));
+        println!("  {name}({}): {}", tag.type_description(), tag.value_summary());
+    }
+}
+
+fn is_nbt_file(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "dat" || extension == "dat_old")
+}
+
+fn inspect_nbt_file(args: &InspectArgs) -> Result<(), Box<dyn Error>> {
+    if args.chunk.is_some() {
+        return Err("--chunk only applies to region files".into());
+    }
+
+    let root = read_nbt(&fs::read(&args.region_file)?)?;
+    println!("{}: {} {}", args.region_file.display(), root.type_description(), root.value_summary());
+
+    if let Some(query) = &args.query {
+        println!("{} matches for {query}", query.evaluate(&root).len());
+        print_matches(query, &root);
+    }
+
+    Ok(())
+}
+
+pub fn run_inspect(args: &InspectArgs) -> Result<(), Box<dyn Error>> {
+    if is_nbt_file(&args.region_file) {
+        return inspect_nbt_file(args);
+    }
+
+    let (format, region) = read_region_file(&args.region_file)?;
+    let (region_x, region_z) = region_coords_from_path(&args.region_file).ok_or("File name is not r.<x>.<z>.<ext>")?;
+
+    println!("{} ({:?}, {} chunks)", args.region_file.display(), format, region.chunks().len());
+
+    let mut inspected = 0;
+    for chunk in region.chunks() {
+        let (x, z) = chunk.global_position(region_x, region_z);
+        if args.chunk.is_some_and(|selected| selected != (x, z)) {
+            continue;
+        }
+        inspected += 1;
+
+        let Some(query) = &args.query else {
+            let root = chunk.get_data();
+            println!("chunk {x}, {z}: {} {}", root.type_description(), root.value_summary());
+            continue;
+        };
+
+        println!("chunk {x}, {z}: {} matches for {query}", query.evaluate(chunk.get_data()).len());
+        print_matches(query, chunk.get_data());
+    }
+
+    if let Some((x, z)) = args.chunk
+        && inspected == 0
+    {
+        return Err(format!("Chunk {x}, {z} is not in this region").into());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,205 @@
+use crate::inject::read_nbt;
+use crate::nbt::query::Query;
+use crate::nbt::snbt::to_snbt;
+use crate::nbt::tag::Tag;
+use clap::{Args, Subcommand};
+use flate2::write::GzEncoder;
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+#[derive(Args)]
+pub struct LevelDatArgs {
+    #[command(subcommand)]
+    pub action: LevelDatAction,
+}
+
+#[derive(Subcommand)]
+pub enum LevelDatAction {
+    /// Print level.dat as SNBT
+    Show {
+        /// level.dat, or the world folder containing it
+        level_dat: PathBuf,
+
+        /// Print the tags reached by this query instead of the whole file, e.g. `Data.GameRules`
+        #[arg(short, long)]
+        query: Option<Query>,
+    },
+    /// Change existing fields, keeping their types, e.g. `Data.WorldGenSettings.seed=42`,
+    /// `Data.SpawnX=100` or `Data.GameRules.keepInventory=true`
+    Set {
+        /// level.dat, or the world folder containing it
+        level_dat: PathBuf,
+
+        /// `<nbt path>=<value>`, can be repeated
+        #[arg(required = true)]
+        assignments: Vec<Assignment>,
+    },
+}
+
+impl LevelDatAction {
+    pub fn level_dat(&self) -> &Path {
+        match self {
+            LevelDatAction::Show { level_dat, .. } | LevelDatAction::Set { level_dat, .. } => level_dat,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Assignment {
+    query: Query,
+    value: String,
+}
+
+impl FromStr for Assignment {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (query, value) = s.split_once('=').ok_or("Expected <nbt path>=<value>")?;
+        let query = Query::parse(query).map_err(|err| err.to_string())?;
+
+        Ok(Self { query, value: String::from(value) })
+    }
+}
+
+fn level_dat_path(path: &Path) -> PathBuf {
+    if path.is_dir() { path.join("level.dat") } else { path.to_path_buf() }
+}
+
+fn write_level_dat(path: &Path, root: &Tag) -> Result<(), Box<dyn Error>> {
+    let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&root.to_bytes())?;
+
+    // write next to the original and swap, so an interrupted run never leaves a truncated file
+    let temp_file = path.with_extension("dat.tmp");
+    fs::write(&temp_file, encoder.finish()?)?;
+    fs::rename(&temp_file, path)?;
+
+    Ok(())
+}
+
+/// Replaces the value of a tag with `text` parsed as the tag's own type. Bytes also take `true`
+/// and `false`, numbers may carry their SNBT suffix.
+fn set_value(tag: &mut Tag, text: &str) -> Result<(), String> {
+    let type_description = tag.type_description();
+    let invalid = || format!("`{text}` is not a valid {type_description}");
+    let number = text.trim_end_matches(['b', 'B', 's', 'S', 'l', 'L', 'f', 'F', 'd', 'D']);
+
+    match tag {
+        Tag::Byte { value, .. } => {
+            *value = match text {
+                "true" => 1,
+                "false" => 0,
+                _ => number.parse().map_err(|_| invalid())?,
+            }
+        }
+        Tag::Short { value, .. } => *value = number.parse().map_err(|_| invalid())?,
+        Tag::Int { value, .. } => *value = number.parse().map_err(|_| invalid())?,
+        Tag::Long { value, .. } => *value = number.parse().map_err(|_| invalid())?,
+        Tag::Float { value, .. } => *value = number.parse().map_err(|_| invalid())?,
+        Tag::Double { value, .. } => *value = number.parse().map_err(|_| invalid())?,
+        Tag::String { value, .. } => *value = String::from(text),
+        _ => return Err(format!("Cannot set a {type_description} from text")),
+    }
+
+    Ok(())
+}
+
+fn apply(root: &mut Tag, assignment: &Assignment) -> Result<usize, String> {
+    let targets = assignment.query.evaluate_mut(root);
+    if targets.is_empty() {
+        return Err(format!("Nothing at {}", assignment.query));
+    }
+
+    let count = targets.len();
+    for tag in targets {
+        set_value(tag, &assignment.value)?;
+    }
+
+    Ok(count)
+}
+
+pub fn run_level_dat(args: &LevelDatArgs) -> Result<(), Box<dyn Error>> {
+    let path = level_dat_path(args.action.level_dat());
+    let mut root = read_nbt(&fs::read(&path)?)?;
+
+    match &args.action {
+        LevelDatAction::Show { query: None, .. } => println!("{}", to_snbt(&root)),
+        LevelDatAction::Show { query: Some(query), .. } => {
+            for tag in query.evaluate(&root) {
+                let name = tag.get_name().map_or(String::new(), |name| format!("{name}: "));
+                println!("{name}{}", to_snbt(tag));
+            }
+        }
+        LevelDatAction::Set { assignments, .. } => {
+            for assignment in assignments {
+                let count = apply(&mut root, assignment)?;
+                println!("Set {} to {} ({} tags)", assignment.query, assignment.value, count);
+            }
+
+            write_level_dat(&path, &root)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn named<T>(name: &str, make: impl FnOnce(Option<String>) -> T) -> T {
+        make(Some(String::from(name)))
+    }
+
+    fn sample() -> Tag {
+        Tag::Compound {
+            name: None,
+            value: vec![Tag::Compound {
+                name: Some(String::from("Data")),
+                value: vec![
+                    named("SpawnX", |name| Tag::Int { name, value: 0 }),
+                    named("hardcore", |name| Tag::Byte { name, value: 0 }),
+                    named("WorldGenSettings", |name| Tag::Compound { name, value: vec![named("seed", |name| Tag::Long { name, value: 1 })] }),
+                    named("GameRules", |name| Tag::Compound { name, value: vec![named("keepInventory", |name| Tag::String { name, value: String::from("false") })] }),
+                ],
+            }],
+        }
+    }
+
+    fn get<'a>(root: &'a Tag, query: &str) -> &'a Tag {
+        Query::parse(query).unwrap().evaluate(root)[0]
+    }
+
+    #[test]
+    fn test_apply() {
+        let mut root = sample();
+
+        for assignment in ["Data.SpawnX=-120", "Data.hardcore=true", "Data.WorldGenSettings.seed=-4172144997902289642L", "Data.GameRules.keepInventory=true"] {
+            assert_eq!(apply(&mut root, &assignment.parse().unwrap()), Ok(1));
+        }
+
+        assert_eq!(get(&root, "Data.SpawnX").get_int(), Some(&-120));
+        assert_eq!(get(&root, "Data.hardcore"), &named("hardcore", |name| Tag::Byte { name, value: 1 }));
+        assert_eq!(get(&root, "Data.WorldGenSettings.seed").get_long(), Some(&-4172144997902289642));
+        assert_eq!(get(&root, "Data.GameRules.keepInventory").get_string().unwrap(), "true");
+
+        assert!(apply(&mut root, &"Data.SpawnX=north".parse().unwrap()).is_err());
+        assert!(apply(&mut root, &"Data.SpawnY=64".parse().unwrap()).is_err());
+        assert!(apply(&mut root, &"Data=1".parse().unwrap()).is_err());
+        assert!("Data.SpawnX".parse::<Assignment>().is_err());
+    }
+
+    #[test]
+    fn test_level_dat_round_trip() {
+        let folder = std::env::temp_dir().join("bufferedlinear_tools_level_dat_test");
+        fs::create_dir_all(&folder).unwrap();
+
+        write_level_dat(&level_dat_path(&folder), &sample()).unwrap();
+        assert_eq!(read_nbt(&fs::read(folder.join("level.dat")).unwrap()).unwrap(), sample());
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
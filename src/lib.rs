@@ -0,0 +1,19 @@
+//! Reading and writing Minecraft region files in the Anvil (mca), linear and blinear formats.
+
+#[cfg(all(feature = "async", not(target_arch = "wasm32")))]
+pub mod async_io;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod cache;
+pub mod chunk;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod edit;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod nbt;
+pub mod parallel;
+#[cfg(feature = "python")]
+mod python;
+pub mod region_file;
+pub mod timings;
+#[cfg(feature = "wasm")]
+mod wasm;
@@ -0,0 +1,67 @@
+use crate::region_file::RegionFormat;
+use crate::{input_format_by_mode, output_format_by_mode, Mode};
+use clap::ValueEnum;
+
+fn versions(versions: &[u8]) -> String {
+    match versions {
+        [] => String::from("none"),
+        versions => versions.iter().map(u8::to_string).collect::<Vec<_>>().join(", "),
+    }
+}
+
+/// The lines describing one format.
+fn describe(format: RegionFormat) -> Vec<String> {
+    let magic = match format.magic() {
+        Some(magic) => magic.iter().map(|byte| format!("{byte:02x}")).collect::<Vec<_>>().join(" "),
+        None => String::from("none, detected by the .mca extension"),
+    };
+
+    vec![
+        format!("{}:", format.extension()),
+        format!("  magic: {}", magic),
+        format!("  read versions: {}", versions(format.read_versions())),
+        format!("  write versions: {}", versions(format.write_versions())),
+        format!("  chunk compressions: {}", format.compressions().join(", ")),
+    ]
+}
+
+/// The convert modes with the formats they read and write.
+fn directions() -> Vec<String> {
+    Mode::value_variants()
+        .iter()
+        .filter_map(|mode| {
+            let name = mode.to_possible_value()?.get_name().to_string();
+            Some(format!("  {:<16} {} -> {}", name, input_format_by_mode(*mode).extension(), output_format_by_mode(*mode).extension()))
+        })
+        .collect()
+}
+
+pub fn run_list_formats() {
+    for format in RegionFormat::ALL {
+        for line in describe(format) {
+            println!("{}", line);
+        }
+    }
+
+    println!("Conversions:");
+    for line in directions() {
+        println!("{}", line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_formats() {
+        let linear = describe(RegionFormat::Linear);
+        assert_eq!(linear[1], "  magic: c3 ff 13 18 3c ca 9d 9a");
+        assert_eq!(linear[2], "  read versions: 1, 2, 3");
+        assert_eq!(describe(RegionFormat::Mca)[4], "  chunk compressions: gzip, zlib, uncompressed");
+
+        let directions = directions();
+        assert_eq!(directions.len(), Mode::value_variants().len());
+        assert!(directions.contains(&format!("  {:<16} blinear -> linear", "blinear-linear")));
+    }
+}
@@ -1,17 +1,110 @@
-use crate::region_file::{ParseError, Region};
-use chrono::Local;
-use clap::{Parser, ValueEnum};
-use rayon::iter::IntoParallelRefIterator;
+use crate::diff::diff_chunks;
+use crate::region_file::{blinear_dictionary_for, chunk_index_path, ChunkIndex, CHUNK_INDEX_EXTENSION, read_region_file_with_limits, region_coords_from_path, BlinearOptions, ParseError, RawRegion, ReusedBuckets, BLINEAR_DICTIONARY_FILE, LINEAR_DEFAULT_GRID_SIZE, LINEAR_GRID_SIZES, ParseLimits, Region, RegionFormat, WriteError};
+#[cfg(feature = "bedrock")]
+use crate::bedrock::BedrockArgs;
+use crate::backup::BackupArgs;
+use crate::bench_compress::BenchCompressArgs;
+use crate::chunk_store::{ChunkStore, MaterializeArgs};
+use crate::cleanup::CleanupArgs;
+use crate::completions::CompletionsArgs;
+use crate::crop::CropArgs;
+use crate::daemon::{DaemonArgs, JobControl};
+use crate::diff::DiffArgs;
+use crate::dimensions::DimensionRule;
+use crate::duplicates::DuplicatePolicy;
+use crate::entity_storage::EntityStorage;
+use crate::export_maps::ExportMapsArgs;
+use crate::export_schem::ExportSchemArgs;
+use crate::export_structure::ExportStructureArgs;
+use crate::extract::ExtractArgs;
+use crate::find::FindArgs;
+use crate::fsck::FsckArgs;
+use crate::history::HistoryArgs;
+use crate::incremental::HashCache;
+use crate::inject::InjectArgs;
+use crate::inspect::InspectArgs;
+use crate::level_dat::LevelDatArgs;
+use crate::map::MapArgs;
+use crate::merge::MergeArgs;
+use crate::nbt::query::Query;
+use crate::priority::CpuSet;
+use crate::progress::Progress;
+use crate::offset::OffsetArgs;
+use crate::render::RenderArgs;
+use crate::repair::RepairArgs;
+use crate::restore::RestoreArgs;
+use crate::selftest::SelftestArgs;
+use crate::state_db::StateDb;
+use crate::stats::StatsArgs;
+use crate::timing_report::{FileTimings, TimingReport};
+use crate::transform::Transforms;
+use crate::verify::{DataVersionRange, VerifyArgs};
+use bufferedlinear_tools::chunk::Chunk;
+use bufferedlinear_tools::{chunk, nbt, parallel, region_file, timings};
+use chrono::{DateTime, Local, NaiveDate, NaiveTime};
+use clap::error::ErrorKind;
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use rayon::iter::ParallelBridge;
 use rayon::iter::ParallelIterator;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::error::Error;
+use std::ffi::OsString;
 use std::fs;
-use std::fs::read;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::exit;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
-mod region_file;
-mod chunk;
-mod nbt;
+mod backup;
+#[cfg(feature = "bedrock")]
+mod bedrock;
+mod bench_compress;
+mod chunk_store;
+mod chunk_data;
+mod cleanup;
+mod completions;
+mod config;
+mod crop;
+mod daemon;
+mod dictionary;
+mod diff;
+mod dimensions;
+mod duplicates;
+mod entity_storage;
+mod explore;
+mod export_maps;
+mod export_schem;
+mod export_structure;
+mod extract;
+mod find;
+mod forceload;
+mod fsck;
+mod history;
+mod image;
+mod incremental;
+mod inject;
+mod inspect;
+mod level_dat;
+mod list_formats;
+mod map;
+mod merge;
+mod offset;
+mod priority;
+mod progress;
+mod render;
+mod repair;
+mod restore;
+mod selftest;
+mod stats;
+mod state_db;
+mod throttle;
+mod timing_report;
+mod transform;
+mod upgrade;
+mod verify;
 
 #[derive(Parser)]
 #[command(
@@ -19,41 +112,396 @@ mod nbt;
     version = "2.0",
     about = "Buffered linear region format convertor.",
     long_about = None,
+    args_conflicts_with_subcommands = true,
 )]
 pub struct Cli {
-    /// Convertor mode (mca2blinear, blinear2mca, linear2mca, linear2blinear, blinear2mca, blinear2linear)
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub convert: Option<ConvertArgs>,
+
+    /// TOML file with named sets of default flags in `[profile.<name>]` tables, flags given on the
+    /// command line override them
+    #[arg(long, global = true, value_name = "PATH", env = config::CONFIG_ENV)]
+    pub config: Option<PathBuf>,
+
+    /// Profile of the `--config` file to use
+    #[arg(long, global = true, env = config::PROFILE_ENV, default_value = config::DEFAULT_PROFILE)]
+    pub profile: String,
+
+    /// Worker threads for converting and other parallel work, defaults to one per CPU
+    #[arg(short = 'j', long, global = true, env = "BLT_THREADS")]
+    pub threads: Option<usize>,
+
+    /// Scheduling priority, from -20 (highest) to 19 (lowest), e.g. 10 to leave a game server on
+    /// the same host the CPU it needs. Unix only
+    #[arg(long, global = true, allow_negative_numbers = true, value_parser = clap::value_parser!(i32).range(-20..=19))]
+    pub nice: Option<i32>,
+
+    /// CPUs to run on, as a list like `4-7,10` or a hex mask like `0xf0`. Without `--threads`, one
+    /// worker thread is started per CPU of the set. Linux only
+    #[arg(long, global = true, value_name = "CPUS", value_parser = priority::parse_cpu_set)]
+    pub cpu_set: Option<CpuSet>,
+
+    /// Limit reading world and backup files to this many megabytes per second, across all threads
+    #[arg(long, global = true, value_name = "MBPS", value_parser = throttle::parse_mbps)]
+    pub max_read_mbps: Option<f64>,
+
+    /// Limit writing converted, backed up and restored files to this many megabytes per second,
+    /// across all threads
+    #[arg(long, global = true, value_name = "MBPS", value_parser = throttle::parse_mbps)]
+    pub max_write_mbps: Option<f64>,
+
+    /// fsync every converted, backed up and restored file and the folder it is written to, so a
+    /// power loss right after a run can not leave empty or partly written files
+    #[arg(long, global = true)]
+    pub fsync: bool,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Browse the chunks of a region file and their NBT interactively
+    Explore {
+        /// Region file in any of the supported formats (mca, linear, blinear)
+        region_file: PathBuf,
+    },
+    /// Report chunk statistics for a world
+    Stats(StatsArgs),
+    /// Render a world overview image
+    Map(MapArgs),
+    /// Render a top-down image of every region in a world
+    Render(RenderArgs),
+    /// List the chunks of a world matching all given criteria
+    Find(FindArgs),
+    /// Print chunk NBT of a region file, or the NBT of a .dat file, optionally selected by a path query
+    Inspect(InspectArgs),
+    /// Compare the chunk NBT of two region files or worlds, exits with 1 when they differ
+    Diff(DiffArgs),
+    /// Convert a sample region file through every format chain and check all chunks survive
+    Selftest(SelftestArgs),
+    /// Check that every region file parses and chunk positions match their slots, exits with 1 on problems
+    Verify(VerifyArgs),
+    /// Write a repaired copy of a region file
+    Repair(RepairArgs),
+    /// Check region files for overlapping sectors, bad offsets and lengths, checksum failures and
+    /// unparsable chunks, optionally writing repaired copies, exits with 1 on problems
+    Fsck(FsckArgs),
+    /// Remove poi and entities chunks that have no terrain chunk
+    Cleanup(CleanupArgs),
+    /// Combine the region files of two worlds, keeping the newer chunk where both have one
+    Merge(MergeArgs),
+    /// Write the NBT of one chunk to a file
+    Extract(ExtractArgs),
+    /// Replace or add one chunk of a region file with NBT read from a file
+    Inject(InjectArgs),
+    /// Write a copy of a world shifted by whole regions, with chunk, entity and block coordinates updated
+    Offset(OffsetArgs),
+    /// Write a copy of the chunks of a world inside a chunk box
+    Crop(CropArgs),
+    /// Compress a region file at a range of compression levels and print size and timings for each
+    BenchCompress(BenchCompressArgs),
+    /// Write a copy of a world, or of the files changed since the last backup, into a new
+    /// timestamped folder of a backup destination, optionally converting its region files and
+    /// pruning older backups
+    Backup(BackupArgs),
+    /// Reassemble the world of a backup, optionally converting its region files to another format
+    Restore(RestoreArgs),
+    /// Write the region files of `.chunks` manifests from their chunk store
+    Materialize(MaterializeArgs),
+    /// List every version of one chunk across dated backups, and extract one of them
+    History(HistoryArgs),
+    /// Stay resident, watch worlds and run conversions requested over a unix socket
+    Daemon(DaemonArgs),
+    /// Print or change the fields of a world's level.dat
+    #[command(name = "leveldat")]
+    LevelDat(LevelDatArgs),
+    /// Write every map item of a world (`data/map_<n>.dat`) as a PNG image
+    ExportMaps(ExportMapsArgs),
+    /// Write the blocks of an area of a world as a Sponge schematic
+    ExportSchem(ExportSchemArgs),
+    /// Write the blocks of an area of a world as structure block templates, split to the vanilla size limit
+    ExportStructure(ExportStructureArgs),
+    /// Convert the chunks of a Bedrock world's LevelDB store into Java region files
+    #[cfg(feature = "bedrock")]
+    Bedrock(BedrockArgs),
+    /// Print every region format with its magic bytes, versions and chunk compressions, and the
+    /// conversion modes between them
+    ListFormats,
+    /// Print a completion script for bash, zsh, fish, elvish or PowerShell, e.g.
+    /// `bufferedlinear_tools completions bash > /etc/bash_completion.d/bufferedlinear_tools`
+    Completions(CompletionsArgs),
+}
+
+#[derive(Args)]
+pub struct ConvertArgs {
+    /// Convertor mode (mca2blinear, blinear2mca, linear2mca, linear2blinear, blinear2mca, blinear2linear, blinear2blinear)
     #[arg(value_enum, required = true)]
     pub mode: Mode,
 
+    /// Region type folder to convert, `all` converts region, poi and entities together
     #[arg(value_enum, required = true)]
-    pub region_type: RegionType,
+    pub region_type: ConvertRegionType,
 
     /// Path to your Minecraft Worlds containing `regions` or `entities` or `poi` file
     #[arg(required = true)]
     pub world_path: PathBuf,
 
+    /// Folder receiving the converted world, defaults to `BLT_OUTPUT`
     #[arg(required = true)]
     pub output_path: PathBuf,
 
-    /// Compression level when writing region files
+    /// Compression level when writing region files, defaults to `BLT_COMPRESSION_LEVEL`
     #[arg(short, long, default_value = "6", value_parser = validate_compression_level)]
     pub compression_level: u32,
+
+    /// Re-read every written file and its source file and fail it unless all chunks match in NBT
+    /// and timestamp, does not combine with the options that drop or change chunks
+    #[arg(long)]
+    pub verify_against_source: bool,
+
+    /// Read every written file back and parse all of its chunks, failing it unless the chunk count
+    /// matches what was written, to catch truncated writes on full or flaky disks
+    #[arg(long)]
+    pub verify_writes: bool,
+
+    /// Check the stored chunk and bucket hashes when linear and blinear files are converted
+    /// without parsing their chunks
+    #[arg(long)]
+    pub verify_checksums: bool,
+
+    /// Keep chunk hashes in `incremental.cache` next to the output, so the next run into the same
+    /// output skips files without changed chunks and only recompresses linear and blinear v3
+    /// buckets whose chunks changed
+    #[arg(long)]
+    pub incremental: bool,
+
+    /// Write a `.idx` file next to every output file, listing each chunk's position, timestamp and
+    /// NBT hash, for comparing worlds without decompressing them
+    #[arg(long)]
+    pub chunk_index: bool,
+
+    /// Experimental: store each distinct chunk once in this folder, keyed by its hash, and write
+    /// `.chunks` manifests naming them instead of region files. `materialize` turns them back
+    /// into region files
+    #[arg(long, value_name = "DIR")]
+    pub chunk_store: Option<PathBuf>,
+
+    /// SQLite database recording the hash of every converted source and output file and the
+    /// settings used, files already converted the same way are skipped on later runs. Defaults to
+    /// `BLT_STATE_DB`
+    #[arg(long, value_name = "PATH")]
+    pub state_db: Option<PathBuf>,
+
+    /// Parse with resource limits (decompressed size, NBT depth and lengths, chunk count) for untrusted worlds
+    #[arg(long)]
+    pub strict: bool,
+
+    /// When converting poi or entities, leave out chunks that have no terrain chunk in the world
+    #[arg(long)]
+    pub drop_orphans: bool,
+
+    /// Let `--drop-orphans`, the DataVersion and the timestamp filters drop chunks the world's
+    /// `chunks.dat` or `forceload.dat` lists as force-loaded
+    #[arg(long)]
+    pub ignore_forceloaded: bool,
+
+    /// When converting terrain, move entities between terrain chunks and entities region files
+    #[arg(long, value_enum)]
+    pub entities: Option<EntityStorage>,
+
+    /// Leave out chunks with a lower DataVersion
+    #[arg(long)]
+    pub min_data_version: Option<i32>,
+
+    /// Leave out chunks with a higher DataVersion
+    #[arg(long)]
+    pub max_data_version: Option<i32>,
+
+    /// Keep only chunks last saved after this date or RFC 3339 time, e.g. `2024-01-01`
+    #[arg(long, value_name = "TIME", value_parser = parse_timestamp)]
+    pub newer_than: Option<i64>,
+
+    /// Keep only chunks last saved before this date or RFC 3339 time
+    #[arg(long, value_name = "TIME", value_parser = parse_timestamp)]
+    pub older_than: Option<i64>,
+
+    /// Insert blending_data into every chunk, so newer game versions blend its terrain with new chunks
+    #[arg(long)]
+    pub force_blending: bool,
+
+    /// Delete the tags at this NBT path from every chunk, e.g. `Heightmaps`, can be repeated
+    #[arg(long, value_name = "NBT_PATH")]
+    pub strip_tag: Vec<Query>,
+
+    /// Remove entities with this id, e.g. `minecraft:item`, from every chunk, can be repeated
+    #[arg(long, value_name = "ID")]
+    pub purge_entity: Vec<String>,
+
+    /// Remove block and sky light from every chunk so the server recalculates it on load
+    #[arg(long)]
+    pub strip_light: bool,
+
+    /// Remove the heightmaps, light and empty block and fluid tick lists of every chunk, which the
+    /// server rebuilds on load
+    #[arg(long)]
+    pub strip_recomputable: bool,
+
+    /// Migrate chunks to the layout of this DataVersion with the known upgrades, e.g. 2844 moves
+    /// 1.16 and 1.17 chunks out of `Level`, the game upgrades the rest on load
+    #[arg(long, value_name = "DATA_VERSION")]
+    pub upgrade_to: Option<i32>,
+
+    /// When writing blinear, compress each chunk as its own zstd frame behind an index, so single
+    /// chunks can be read without decompressing the whole region
+    #[arg(long)]
+    pub seekable: bool,
+
+    /// When writing blinear, store chunks along a Hilbert curve instead of row by row, which keeps
+    /// neighbouring chunks together and compresses terrain better. Needs a reader that knows the flag
+    #[arg(long)]
+    pub hilbert_order: bool,
+
+    /// Experimental: when writing blinear, store chunks as a delta against their west or north
+    /// neighbour where that is smaller. Needs a reader that knows the flag
+    #[arg(long)]
+    pub neighbor_delta: bool,
+
+    /// When writing blinear, train a zstd dictionary on the input chunks first and compress every
+    /// file with it. The dictionary is written once as `world.dict` next to the output files
+    #[arg(long)]
+    pub dictionary: bool,
+
+    /// Print how long reading, decompressing, parsing, serializing, compressing and writing took,
+    /// summed over all files
+    #[arg(long)]
+    pub timings: bool,
+
+    /// Blinear version to write: 2 compresses a region as a whole, 3 splits it into `--grid-size`
+    /// buckets that can be read and decompressed on their own. Defaults to `BLT_BLINEAR_VERSION`
+    #[arg(long, default_value = "2", value_parser = clap::value_parser!(u8).range(2..=3))]
+    pub blinear_version: u8,
+
+    /// When writing linear or blinear v3, split each region into GRID_SIZE × GRID_SIZE buckets (1, 2, 4, 8, 16 or 32).
+    /// Smaller buckets make reading single chunks cheaper, larger ones compress better. Defaults to `BLT_GRID_SIZE`
+    #[arg(long, default_value_t = LINEAR_DEFAULT_GRID_SIZE, value_parser = validate_grid_size)]
+    pub grid_size: u8,
+
+    /// Print files and MB per second and the estimated time remaining at most this often, 0 to disable. Defaults
+    /// to `BLT_PROGRESS_INTERVAL`
+    #[arg(long, value_name = "SECONDS", default_value = "10")]
+    pub progress_interval: u64,
+
+    /// Also convert the region files in subfolders of the region type folders, up to this many
+    /// levels deep, into the same subfolders of the output
+    #[arg(long, value_name = "DEPTH", default_value = "0")]
+    pub recursive: usize,
+
+    /// Try to convert every file found, instead of only non-empty files with the input format's
+    /// extension that are not hidden or temporary
+    #[arg(long)]
+    pub no_filter: bool,
+
+    /// Which file to convert when a folder holds the same region in several formats, by default
+    /// only files of the input format are converted and the others are ignored
+    #[arg(long, value_enum, value_name = "POLICY")]
+    pub on_duplicate: Option<DuplicatePolicy>,
+
+    /// Dimensions to convert, applied in order: `*` for every dimension of the world, `<id>` for one
+    /// (ids without a namespace are `minecraft:` ones, custom ones live in `dimensions/<namespace>/<name>`),
+    /// `<id>=<id>` to write it as another dimension and `!<id>` to leave one out. Only the overworld
+    /// is converted without it
+    #[arg(long, value_name = "RULE", value_parser = dimensions::parse_dimension_rule)]
+    pub dimension_map: Vec<DimensionRule>,
+}
+
+/// Bounds on the chunk timestamps kept, in seconds since the epoch, both exclusive.
+#[derive(Clone, Copy, Default)]
+pub struct TimestampRange {
+    pub newer_than: Option<i64>,
+    pub older_than: Option<i64>,
+}
+
+impl TimestampRange {
+    fn is_set(&self) -> bool {
+        self.newer_than.is_some() || self.older_than.is_some()
+    }
+
+    fn contains(&self, timestamp: i64) -> bool {
+        self.newer_than.is_none_or(|newer_than| timestamp > newer_than) && self.older_than.is_none_or(|older_than| timestamp < older_than)
+    }
+}
+
+/// Settings shared by every file of a conversion run.
+pub struct ConvertOptions {
+    pub mode: Mode,
+    pub compression_level: u8,
+    pub verify_against_source: bool,
+    pub verify_writes: bool,
+    pub verify_checksums: bool,
+    /// Skip unchanged files and reuse unchanged buckets of the previous run's output
+    pub incremental: bool,
+    /// Files converted before, skipped while their source, output and settings are unchanged
+    pub state: Option<StateDb>,
+    /// Write a chunk index next to every output file
+    pub chunk_index: bool,
+    /// Store chunks here and write manifests instead of region files
+    pub chunk_store: Option<ChunkStore>,
+    pub limits: ParseLimits,
+    /// Terrain chunk positions of the dimension being converted when orphaned poi and entities
+    /// chunks are dropped
+    pub terrain_chunks: Option<HashSet<(i32, i32)>>,
+    /// Force-loaded chunk positions of the dimension being converted, never dropped by the filters
+    pub forceloaded: HashSet<(i32, i32)>,
+    pub entity_storage: Option<EntityStorage>,
+    pub data_versions: DataVersionRange,
+    pub timestamps: TimestampRange,
+    pub transforms: Transforms,
+    pub blinear: BlinearOptions,
+    /// Shared zstd dictionary blinear output is compressed with
+    pub dictionary: Option<Vec<u8>>,
+    /// Collects per phase timings of every file when set
+    pub timings: Option<TimingReport>,
+    pub progress_interval: Duration,
+    /// Buckets per side of written linear and blinear v3 files
+    pub grid_size: u8,
+    /// Levels of subfolders of the region type folders scanned for region files
+    pub scan_depth: usize,
+    /// Only convert files with the input format's extension, skipping empty, hidden and temporary files
+    pub filter_scan: bool,
+    /// Picks one file of regions present in several formats
+    pub on_duplicate: Option<DuplicatePolicy>,
+    /// Progress and cancellation of a daemon job
+    pub control: Option<Arc<JobControl>>,
+}
+
+impl ConvertOptions {
+    /// Whether any option drops chunks, so force-loaded ones need protecting.
+    fn prunes(&self) -> bool {
+        self.terrain_chunks.is_some() || self.data_versions.is_set() || self.timestamps.is_set()
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
-enum Mode {
+pub enum Mode {
     LinearMca,
     McaLinear,
     McaBlinear,
     BlinearMca,
     BlinearLinear,
-    LinearBlinear
+    LinearBlinear,
+    /// Rewrite blinear files, e.g. to move them to another `--blinear-version`
+    BlinearBlinear
 }
 
 #[derive(Error, Debug)]
 pub enum ConverseError {
     #[error("I/O error")]
     ReadError,
+    #[error("Output differs from source in {count} chunks, first at chunk {x}, {z}")]
+    VerifyMismatch { count: usize, x: i32, z: i32 },
+    #[error("Read back {found} chunks from the written file, expected {expected}")]
+    WrittenChunkCount { expected: usize, found: usize },
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
@@ -63,6 +511,45 @@ pub enum RegionType{
     ENTITIES
 }
 
+/// Region format written by the subcommands that take `--format`.
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Mca,
+    Linear,
+    Blinear,
+}
+
+impl From<OutputFormat> for RegionFormat {
+    fn from(format: OutputFormat) -> Self {
+        match format {
+            OutputFormat::Mca => RegionFormat::Mca,
+            OutputFormat::Linear => RegionFormat::Linear,
+            OutputFormat::Blinear => RegionFormat::Blinear,
+        }
+    }
+}
+
+/// The region type folders a conversion covers.
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum ConvertRegionType {
+    REGION,
+    POI,
+    ENTITIES,
+    ALL,
+}
+
+impl ConvertRegionType {
+    fn region_types(self) -> Vec<RegionType> {
+        match self {
+            ConvertRegionType::REGION => vec![RegionType::REGION],
+            ConvertRegionType::POI => vec![RegionType::POI],
+            ConvertRegionType::ENTITIES => vec![RegionType::ENTITIES],
+            ConvertRegionType::ALL => vec![RegionType::REGION, RegionType::POI, RegionType::ENTITIES],
+        }
+    }
+}
+
+/// Checks against the widest range of any codec, the output format decides the actual range.
 fn validate_compression_level(s: &str) -> Result<u32, String> {
     match s.parse::<u32>() {
         Ok(level) if level <= 22 => Ok(level),
@@ -70,6 +557,32 @@ fn validate_compression_level(s: &str) -> Result<u32, String> {
     }
 }
 
+fn validate_grid_size(s: &str) -> Result<u8, String> {
+    match s.parse::<u8>() {
+        Ok(grid_size) if LINEAR_GRID_SIZES.contains(&grid_size) => Ok(grid_size),
+        _ => Err("Grid size must be one of 1, 2, 4, 8, 16 or 32".to_string()),
+    }
+}
+
+fn parse_chunk_coords(s: &str) -> Result<(i32, i32), String> {
+    s.split_once(',')
+        .and_then(|(x, z)| Some((x.trim().parse().ok()?, z.trim().parse().ok()?)))
+        .ok_or_else(|| String::from("Chunk coordinates must be given as <x>,<z>"))
+}
+
+/// Seconds since the epoch of an RFC 3339 time, or of local midnight starting a `YYYY-MM-DD` date.
+fn parse_timestamp(s: &str) -> Result<i64, String> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+        return Ok(time.timestamp());
+    }
+
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_time(NaiveTime::MIN).and_local_timezone(Local).earliest())
+        .map(|time| time.timestamp())
+        .ok_or_else(|| String::from("Time must be a date like 2024-01-01 or an RFC 3339 time like 2024-01-01T12:00:00Z"))
+}
+
 fn folder_name(region_type: RegionType) -> String {
     match region_type {
         RegionType::REGION => String::from("region"),
@@ -78,16 +591,33 @@ fn folder_name(region_type: RegionType) -> String {
     }
 }
 
-fn output_file_extension_by_mode(mode: Mode) -> String{
+fn input_format_by_mode(mode: Mode) -> RegionFormat {
     match mode {
-        Mode::McaBlinear => String::from(".blinear"),
-        Mode::LinearBlinear => String::from("blinear"),
-        _ => todo!("toto") // TODO: MCA和Linear的一坨
+        Mode::McaLinear | Mode::McaBlinear => RegionFormat::Mca,
+        Mode::LinearMca | Mode::LinearBlinear => RegionFormat::Linear,
+        Mode::BlinearMca | Mode::BlinearLinear | Mode::BlinearBlinear => RegionFormat::Blinear,
     }
 }
 
-fn scan_region_files(region_folder: PathBuf) -> Vec<PathBuf>{
-    fs::read_dir(region_folder)
+fn output_format_by_mode(mode: Mode) -> RegionFormat {
+    match mode {
+        Mode::LinearMca | Mode::BlinearMca => RegionFormat::Mca,
+        Mode::McaLinear | Mode::BlinearLinear => RegionFormat::Linear,
+        Mode::McaBlinear | Mode::LinearBlinear | Mode::BlinearBlinear => RegionFormat::Blinear,
+    }
+}
+
+/// The file of the same region in another region type folder of the same world,
+/// e.g. `world/entities/r.0.0.mca` for `world/region/r.0.0.mca`.
+fn sibling_region_file(region_file: &Path, region_type: RegionType, extension: &str) -> Option<PathBuf> {
+    let world_folder = region_file.parent()?.parent()?;
+    let file_stem = region_file.file_stem()?.to_str()?;
+
+    Some(world_folder.join(folder_name(region_type)).join(format!("{file_stem}.{extension}")))
+}
+
+fn list_folder(folder: PathBuf) -> Vec<PathBuf> {
+    fs::read_dir(folder)
         .map(|dir| {
             dir.flatten()
                 .map(|entry| entry.path())
@@ -96,65 +626,444 @@ fn scan_region_files(region_folder: PathBuf) -> Vec<PathBuf>{
         .unwrap_or_default()
 }
 
-fn get_input_call<'a>(mode: Mode, data: &'a [u8]) -> Box<dyn FnMut() -> Result<Region, ParseError> + 'a> {
-    match mode {
-        Mode::LinearMca => Box::new(|| Region::from_bytes_linear_v2(data)),
-        Mode::LinearBlinear => Box::new(|| Region::from_bytes_linear_v2(data)),
-        Mode::BlinearLinear => Box::new(|| Region::from_bytes_blinear(data)),
-        Mode::BlinearMca => Box::new(|| Region::from_bytes_blinear(data)),
-        _ => Box::new(|| todo!()), // TODO: MCA的一坨
+/// Whether a path is worth reading as a region file: a non-empty file with the extension of
+/// one of the formats. Hidden files, like the temporary files of rsync, are skipped, and so are
+/// editor and download leftovers ending in `.tmp` or `.partial` by their extension.
+fn is_region_file(path: &Path, formats: &[RegionFormat]) -> bool {
+    let hidden = path.file_name().and_then(|name| name.to_str()).is_none_or(|name| name.starts_with('.'));
+    let extension = path.extension().and_then(|extension| extension.to_str()).and_then(RegionFormat::from_extension);
+
+    !hidden && extension.is_some_and(|format| formats.contains(&format)) && fs::metadata(path).is_ok_and(|metadata| metadata.is_file() && metadata.len() > 0)
+}
+
+/// The region files of any format in a folder.
+fn scan_region_files(region_folder: PathBuf) -> Vec<PathBuf>{
+    list_folder(region_folder).into_iter().filter(|path| is_region_file(path, &RegionFormat::ALL)).collect()
+}
+
+/// The files of a folder and of its subfolders up to `max_depth` levels deep, only the region
+/// files of the given formats unless `formats` is `None`.
+fn scan_region_files_recursive(region_folder: PathBuf, max_depth: usize, formats: Option<&[RegionFormat]>) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for path in list_folder(region_folder) {
+        if max_depth > 0 && path.is_dir() {
+            files.extend(scan_region_files_recursive(path, max_depth - 1, formats));
+        } else if formats.is_none_or(|formats| is_region_file(&path, formats)) {
+            files.push(path);
+        }
     }
+    files
 }
 
-fn get_output_call<'a>(mode: Mode, region: &'a Region, timestamp: i64, compression_level: &'a u8) -> Box<dyn FnMut() -> Vec<u8> + 'a> {
-    match mode {
-        Mode::LinearBlinear => Box::new(move || Region::to_bytes_blinear(region, timestamp, *compression_level)),
-        Mode::McaBlinear => Box::new(move || Region::to_bytes_blinear(region, timestamp, *compression_level)),
-        _ => Box::new(|| todo!()), // TODO: MCA和Linear的一坨
+fn get_input_call<'a>(mode: Mode, data: &'a [u8], limits: &'a ParseLimits, dictionary: Option<&'a [u8]>) -> Box<dyn FnMut() -> Result<Region, ParseError> + 'a> {
+    match input_format_by_mode(mode) {
+        RegionFormat::Linear => Box::new(|| Region::from_bytes_linear(data, limits)),
+        RegionFormat::Blinear => Box::new(move || Region::from_bytes_blinear_with_dictionary(data, limits, dictionary)),
+        RegionFormat::Mca => Box::new(|| Region::from_bytes_mca(data, limits)),
+    }
+}
+
+fn get_output_call<'a>(options: &'a ConvertOptions, region: &'a Region, timestamp: i64, (region_x, region_z): (i32, i32)) -> Box<dyn FnMut() -> Result<Vec<u8>, WriteError> + 'a> {
+    let compression_level = options.compression_level;
+
+    match output_format_by_mode(options.mode) {
+        RegionFormat::Blinear => Box::new(move || Ok(region.to_bytes_blinear_with_dictionary(timestamp, compression_level, options.blinear, options.dictionary.as_deref()))),
+        RegionFormat::Linear => Box::new(move || region.to_bytes_linear_v2(region_x, region_z, timestamp, compression_level, options.grid_size)),
+        format => Box::new(move || region.to_bytes(format, Some((region_x, region_z)), timestamp, compression_level)),
+    }
+}
+
+/// Parses the source file and the written file from disk again and compares their chunks, NBT
+/// and timestamps, so a parse or serialize bug shows even when it changes both the same way.
+fn verify_against_source(input: &Path, output: &Path, limits: &ParseLimits) -> Result<(), Box<dyn Error>> {
+    let coords = region_coords_from_path(input).ok_or("File name is not r.<x>.<z>.<ext>")?;
+    let (_, source) = read_region_file_with_limits(input, limits)?;
+    let (_, written) = read_region_file_with_limits(output, limits)?;
+
+    let (diffs, _) = diff_chunks(source.chunks(), coords, written.chunks(), coords);
+    let timestamps = |region: &Region| -> BTreeMap<(i32, i32), i64> {
+        region.chunks().iter().map(|chunk| (chunk.global_position(coords.0, coords.1), chunk.timestamp())).collect()
+    };
+    let written_timestamps = timestamps(&written);
+
+    let mut mismatched: BTreeSet<(i32, i32)> = diffs.into_keys().collect();
+    mismatched.extend(
+        timestamps(&source)
+            .into_iter()
+            .filter(|(position, timestamp)| written_timestamps.get(position).is_some_and(|written| written != timestamp))
+            .map(|(position, _)| position),
+    );
+
+    match mismatched.first() {
+        None => Ok(()),
+        Some(&(x, z)) => Err(Box::new(ConverseError::VerifyMismatch { count: mismatched.len(), x, z })),
+    }
+}
+
+/// Reads a written file back and parses every chunk.
+fn verify_written(output: &Path, expected: usize, options: &ConvertOptions) -> Result<(), Box<dyn Error>> {
+    let (_, written) = read_region_file_with_limits(output, &options.limits)?;
+    if written.chunks().len() != expected {
+        return Err(Box::new(ConverseError::WrittenChunkCount { expected, found: written.chunks().len() }));
+    }
+
+    Ok(())
+}
+
+/// Whether files can move between linear and blinear without parsing their chunks, which only
+/// works while no option needs to look into them.
+fn container_only(options: &ConvertOptions, region_type: RegionType) -> bool {
+    matches!(options.mode, Mode::LinearBlinear | Mode::BlinearLinear | Mode::BlinearBlinear)
+        && !options.verify_against_source
+        && (options.terrain_chunks.is_none() || region_type == RegionType::REGION)
+        && options.entity_storage.is_none()
+        && !options.data_versions.is_set()
+        && options.transforms.is_empty()
+}
+
+/// Reads a linear or blinear file keeping every chunk's NBT bytes, for [`container_only`] runs.
+fn read_container(input: &Path, (region_x, region_z): (i32, i32), read_bytes: &[u8], dictionary: Option<&[u8]>, options: &ConvertOptions) -> Result<RawRegion, Box<dyn Error>> {
+    let mut region = match options.mode {
+        Mode::LinearBlinear => RawRegion::from_bytes_linear(read_bytes, &options.limits, options.verify_checksums)?,
+        _ => RawRegion::from_bytes_blinear(read_bytes, &options.limits, dictionary, options.verify_checksums)?,
+    };
+
+    if options.timestamps.is_set() {
+        let forceloaded = |sector_index: usize| {
+            options.forceloaded.contains(&(region_x * 32 + (sector_index & 31) as i32, region_z * 32 + (sector_index >> 5) as i32))
+        };
+        let before = region.chunks().len();
+        region.retain_chunks(|chunk| forceloaded(chunk.sector_index) || options.timestamps.contains(chunk.timestamp));
+        if region.chunks().len() < before {
+            println!("Dropped {} chunks outside the timestamp range from {}", before - region.chunks().len(), input.display());
+        }
     }
+
+    Ok(region)
+}
+
+/// Writes the chunk NBT bytes into a linear or blinear file, copying the buckets `reuse` has
+/// unchanged.
+fn container_output((region_x, region_z): (i32, i32), region: &RawRegion, timestamp: i64, options: &ConvertOptions, reuse: Option<&ReusedBuckets>) -> Result<Vec<u8>, Box<dyn Error>> {
+    Ok(match output_format_by_mode(options.mode) {
+        RegionFormat::Linear => region.to_bytes_linear_v2(region_x, region_z, timestamp, options.compression_level, options.grid_size, reuse)?,
+        _ => region.to_bytes_blinear(timestamp, options.compression_level, options.blinear, options.dictionary.as_deref(), reuse),
+    })
+}
+
+/// Writes the converted file, unless an incremental run found no chunk changed. Returns the hash
+/// of the written bytes, `None` when the file was kept.
+fn write_converted(input: &Path, output: &Path, converted_bytes: Option<Vec<u8>>) -> std::io::Result<Option<u64>> {
+    match converted_bytes {
+        Some(bytes) => {
+            throttle::write(output, &bytes)?;
+            Ok(Some(incremental::hash(&bytes)))
+        }
+        None => {
+            println!("No chunk of {} changed, kept {}", input.display(), output.display());
+            Ok(None)
+        }
+    }
+}
+
+/// Everything besides the source file that decides what a conversion writes. `None` when other
+/// files of the world play a part, as the state database can not tell whether those changed.
+fn conversion_settings(options: &ConvertOptions, region_type: RegionType) -> Option<String> {
+    if options.entity_storage.is_some() || (options.terrain_chunks.is_some() && region_type != RegionType::REGION) {
+        return None;
+    }
+
+    let transforms = &options.transforms;
+    let strip_tags: Vec<String> = transforms.strip_tags.iter().map(Query::to_string).collect();
+    Some(format!(
+        "{:?} level={} grid={} blinear={:?} dictionary={:x} data_versions={:?}..{:?} timestamps={:?}..{:?} blending={} strip={:?} purge={:?} unlit={}{}",
+        output_format_by_mode(options.mode),
+        options.compression_level,
+        options.grid_size,
+        options.blinear,
+        incremental::hash(options.dictionary.as_deref().unwrap_or_default()),
+        options.data_versions.min_data_version,
+        options.data_versions.max_data_version,
+        options.timestamps.newer_than,
+        options.timestamps.older_than,
+        transforms.force_blending,
+        strip_tags,
+        transforms.purge_entities,
+        transforms.strip_light,
+        if options.chunk_index { " index" } else { "" },
+    ))
+    .map(|settings| if transforms.strip_recomputable { format!("{settings} recomputable") } else { settings })
+    .map(|settings| match transforms.upgrade_to {
+        Some(target) => format!("{settings} upgrade={target}"),
+        None => settings,
+    })
+    .map(|settings| match &options.chunk_store {
+        Some(store) => format!("{settings} store={}", store.folder().display()),
+        None => settings,
+    })
 }
 
+/// Records a converted file in the state database, if there is one. `written` is the hash of the
+/// written bytes, `None` when the previous output was kept.
+fn record_converted(options: &ConvertOptions, state_key: &Option<(String, u64)>, input: &Path, output: &Path, written: Option<u64>) -> Result<(), Box<dyn Error>> {
+    let (Some(state), Some((settings, source_hash))) = (&options.state, state_key) else {
+        return Ok(());
+    };
 
-fn do_converse_single(input: &PathBuf, output: &PathBuf, mode: Mode, compression_level: u8) -> Result<(), Box<dyn Error>>{
-    let read_bytes = read(&input)?;
-    let mut reader_processor = get_input_call(mode, &read_bytes);
+    let output_hash = match written {
+        Some(output_hash) => output_hash,
+        None => incremental::hash(&fs::read(output)?),
+    };
+    state.record(input, *source_hash, output, output_hash, settings, Local::now().timestamp())?;
+
+    Ok(())
+}
+
+/// Writes the chunk index of an output file next to it.
+fn write_chunk_index((region_x, region_z): (i32, i32), output: &Path, region: &RawRegion) -> Result<(), Box<dyn Error>> {
+    throttle::write(chunk_index_path(output), ChunkIndex::new(region, region_x, region_z).to_bytes())?;
+    Ok(())
+}
+
+/// Identifies the output settings, so incremental runs only reuse output written the same way.
+fn output_fingerprint(options: &ConvertOptions) -> u64 {
+    let settings = format!("{:?} {} {} {:?}", output_format_by_mode(options.mode), options.compression_level, options.grid_size, options.blinear);
+    incremental::hash(&[settings.as_bytes(), options.dictionary.as_deref().unwrap_or_default()].concat())
+}
+
+fn do_converse_single(input: &PathBuf, output: &Path, region_type: RegionType, cache: Option<&HashCache>, options: &ConvertOptions) -> Result<FileTimings, Box<dyn Error>>{
+    let started = Instant::now();
+    // drop phases left behind by a file that failed on this thread
+    timings::take();
+
+    // linear headers, chunk indexes, manifests and the chunk filters all need the region
+    let region_coords = region_coords_from_path(input).ok_or("File name is not r.<x>.<z>.<ext>")?;
+    let (region_x, region_z) = region_coords;
+
+    let read_bytes = match input_format_by_mode(options.mode) {
+        RegionFormat::Mca => region_file::read_mca_sparse(&mut throttle::open(input)?)?,
+        _ => throttle::read(input)?,
+    };
+    let read_time = started.elapsed();
+
+    let state_key = match (&options.state, conversion_settings(options, region_type)) {
+        (Some(state), Some(settings)) => {
+            let source_hash = incremental::hash(&read_bytes);
+            if state.is_converted(input, source_hash, output, &settings)? {
+                println!("{} is already converted to {}, skipped", input.display(), output.display());
+                return Ok(FileTimings { read: read_time, phases: timings::take(), write: Duration::ZERO, total: started.elapsed() });
+            }
+            Some((settings, source_hash))
+        }
+        _ => None,
+    };
+    let input_dictionary = blinear_dictionary_for(input, &read_bytes)?;
+
+    if container_only(options, region_type) {
+        let region = read_container(input, region_coords, &read_bytes, input_dictionary.as_deref(), options)?;
+        let new_timestamp = Local::now().timestamp_millis();
+        let converted_bytes = match (&options.chunk_store, cache) {
+            (Some(store), _) => Some(store.put_region(&region, output_format_by_mode(options.mode), region_coords, new_timestamp)?),
+            (None, Some(cache)) => cache.convert(output, &region, |reuse| container_output(region_coords, &region, new_timestamp, options, reuse))?,
+            (None, None) => Some(container_output(region_coords, &region, new_timestamp, options, None)?),
+        };
+
+        let write_started = Instant::now();
+        let written = write_converted(input, output, converted_bytes)?;
+        if options.verify_writes && written.is_some() {
+            verify_written(output, region.slots().iter().flatten().count(), options)?;
+        }
+        if options.chunk_index {
+            write_chunk_index(region_coords, output, &region)?;
+        }
+        let write_time = write_started.elapsed();
+
+        record_converted(options, &state_key, input, output, written)?;
+        return Ok(FileTimings { read: read_time, phases: timings::take(), write: write_time, total: started.elapsed() });
+    }
+
+    let mut reader_processor = get_input_call(options.mode, &read_bytes, &options.limits, input_dictionary.as_deref());
 
     let region_result: Result<Region, ParseError> = reader_processor();
-    let region = region_result?;
+    let mut region = region_result?;
+
+    if let (Some(terrain), RegionType::POI | RegionType::ENTITIES) = (&options.terrain_chunks, region_type) {
+        let before = region.chunks().len();
+        region.retain_chunks(|chunk| {
+            let position = chunk.global_position(region_x, region_z);
+            terrain.contains(&position) || options.forceloaded.contains(&position)
+        });
+        if region.chunks().len() < before {
+            println!("Dropped {} orphaned chunks from {}", before - region.chunks().len(), input.display());
+        }
+    }
+
+    let forceloaded = |chunk: &Chunk| options.forceloaded.contains(&chunk.global_position(region_x, region_z));
+
+    if options.data_versions.is_set() {
+        let before = region.chunks().len();
+        region.retain_chunks(|chunk| forceloaded(chunk) || options.data_versions.contains(chunk.data_version()));
+        if region.chunks().len() < before {
+            println!("Dropped {} chunks with unexpected DataVersion from {}", before - region.chunks().len(), input.display());
+        }
+    }
+
+    if options.timestamps.is_set() {
+        let before = region.chunks().len();
+        region.retain_chunks(|chunk| forceloaded(chunk) || options.timestamps.contains(chunk.timestamp()));
+        if region.chunks().len() < before {
+            println!("Dropped {} chunks outside the timestamp range from {}", before - region.chunks().len(), input.display());
+        }
+    }
 
     let new_timestamp = Local::now().timestamp_millis();
 
-    let mut output_processor = get_output_call(mode, &region, new_timestamp, &compression_level);
-    let converted_bytes = output_processor();
+    match options.entity_storage {
+        Some(EntityStorage::Split) => {
+            let entities = entity_storage::split_entities(&mut region, region_x, region_z);
 
-    fs::write(output, converted_bytes)?;
+            if !entities.chunks().is_empty() {
+                let format = output_format_by_mode(options.mode);
+                let entities_output = sibling_region_file(output, RegionType::ENTITIES, format.extension()).ok_or("Output has no world folder")?;
 
-    Ok(())
+                fs::create_dir_all(entities_output.parent().unwrap())?;
+                let entities_bytes = match format {
+                    RegionFormat::Linear => entities.to_bytes_linear_v2(region_x, region_z, new_timestamp, options.compression_level, options.grid_size)?,
+                    _ => entities.to_bytes(format, Some((region_x, region_z)), new_timestamp, options.compression_level)?,
+                };
+                throttle::write(&entities_output, entities_bytes)?;
+            }
+        }
+        Some(EntityStorage::Merge) => {
+            let entities_input = [RegionFormat::Mca, RegionFormat::Linear, RegionFormat::Blinear]
+                .iter()
+                .filter_map(|format| sibling_region_file(input, RegionType::ENTITIES, format.extension()))
+                .find(|path| path.exists());
+
+            if let Some(entities_input) = entities_input {
+                let (_, entities) = read_region_file_with_limits(&entities_input, &options.limits)?;
+                entity_storage::merge_entities(&mut region, region_x, region_z, &entities);
+            }
+        }
+        None => {}
+    }
+
+    if let Some(summary) = options.transforms.apply(&mut region).summary() {
+        println!("{}: {}", input.display(), summary);
+    }
+
+    let raw = (cache.is_some() || options.chunk_index || options.chunk_store.is_some()).then(|| region.to_raw());
+    let converted_bytes = match (&options.chunk_store, cache, &raw) {
+        (Some(store), _, Some(raw)) => Some(store.put_region(raw, output_format_by_mode(options.mode), region_coords, new_timestamp)?),
+        (None, Some(cache), Some(raw)) => {
+            cache.convert(output, raw, |reuse| match output_format_by_mode(options.mode) {
+                RegionFormat::Mca => Ok(region.to_bytes_mca(options.compression_level)?),
+                _ => container_output(region_coords, raw, new_timestamp, options, reuse),
+            })?
+        }
+        _ => Some(get_output_call(options, &region, new_timestamp, region_coords)()?),
+    };
+
+    let write_started = Instant::now();
+    let written = write_converted(input, output, converted_bytes)?;
+    if options.verify_writes && written.is_some() {
+        verify_written(output, region.chunks().len(), options)?;
+    }
+    if let Some(raw) = raw.as_ref().filter(|_| options.chunk_index) {
+        write_chunk_index(region_coords, output, raw)?;
+    }
+    let write_time = write_started.elapsed();
+
+    if options.verify_against_source {
+        verify_against_source(input, output, &options.limits)?;
+    }
+    record_converted(options, &state_key, input, output, written)?;
+
+    Ok(FileTimings { read: read_time, phases: timings::take(), write: write_time, total: started.elapsed() })
 }
 
-fn do_converse_all(mode: Mode, world_folder: PathBuf, output_folder: PathBuf, region_type: RegionType, compression_level: u8) {
-    let region_folder = folder_name(region_type);
-    let input_folder_actual = world_folder.join(&region_folder);
+fn do_converse_all(world_folder: PathBuf, output_folder: PathBuf, region_types: &[RegionType], options: &ConvertOptions) -> Result<(), String> {
+    fs::create_dir_all(&output_folder).map_err(|err| format!("Failed to create folder {} !, error : {}", output_folder.display(), err))?;
+
+    // every folder goes into one job list, so small poi and entities files share the pool with the
+    // region files instead of each folder warming it up again
+    let mut groups = Vec::new();
+    for region_type in region_types {
+        let region_folder = folder_name(*region_type);
+
+        let input_folder = world_folder.join(&region_folder);
+        let input_formats = [input_format_by_mode(options.mode)];
+        let mut scanned = scan_region_files_recursive(input_folder.clone(), options.scan_depth, options.filter_scan.then_some(&input_formats[..]));
+        scanned.retain(|path| {
+            !path.ends_with(BLINEAR_DICTIONARY_FILE) && !path.ends_with(incremental::CACHE_FILE) && path.extension().is_none_or(|extension| extension != CHUNK_INDEX_EXTENSION)
+        });
+        if let Some(policy) = options.on_duplicate {
+            let all_formats = scan_region_files_recursive(input_folder.clone(), options.scan_depth, Some(&RegionFormat::ALL));
+            let skipped = duplicates::resolve_duplicates(&all_formats, policy).map_err(|err| format!("Failed to scan {} !, error : {}", input_folder.display(), err))?;
+            scanned.retain(|path| match skipped.get(path) {
+                Some(chosen) => {
+                    println!("Skipping {}, {} of the same region is preferred", path.display(), chosen.display());
+                    false
+                }
+                None => true,
+            });
+        }
+        let actual_output_folder = output_folder.join(&region_folder);
+
+        fs::create_dir_all(&actual_output_folder).map_err(|err| format!("Failed to create folder {} !, error : {}", actual_output_folder.display(), err))?;
 
+        if let Some(dictionary) = &options.dictionary {
+            let dictionary_file = actual_output_folder.join(BLINEAR_DICTIONARY_FILE);
+            throttle::write(&dictionary_file, dictionary).map_err(|err| format!("Failed to write dictionary {} !, error : {}", dictionary_file.display(), err))?;
+        }
 
-    if !output_folder.exists() {
-        fs::create_dir_all(&output_folder).expect("Failed to create dirs!");
+        let cache = options.incremental.then(|| HashCache::load(&actual_output_folder, output_fingerprint(options)));
+        groups.push((region_folder, scanned, input_folder, actual_output_folder, *region_type, cache));
     }
 
-    let scanned = scan_region_files(input_folder_actual);
-    let actual_output_folder = output_folder.join(&region_folder);
+    let progress_groups: Vec<(String, &[PathBuf])> = groups.iter().map(|(name, scanned, _, _, _, _)| (name.clone(), scanned.as_slice())).collect();
+    let progress = Progress::new(&progress_groups, options.progress_interval);
+
+    let mut jobs: Vec<(usize, &PathBuf)> = groups.iter().enumerate().flat_map(|(group, (_, scanned, _, _, _, _))| scanned.iter().map(move |file| (group, file))).collect();
 
-    if !actual_output_folder.exists() {
-        fs::create_dir_all(&actual_output_folder).expect("Failed to create region typed dirs!");
+    // largest files first, taken off the queue in order, so the run does not end waiting on a
+    // large file started late
+    jobs.sort_by_cached_key(|(_, file)| Reverse(fs::metadata(file).map_or(0, |metadata| metadata.len())));
+    if let Some(control) = &options.control {
+        control.add_files(jobs.len());
     }
+    let waiting = AtomicUsize::new(jobs.len());
+    let threads = rayon::current_num_threads();
 
-    scanned.par_iter().for_each(|region_file| {
+    jobs.into_iter().par_bridge().for_each(|(group, region_file)| {
+        // once fewer files are left than threads, idle threads help with the buckets and chunks
+        // of the files still being written
+        if waiting.fetch_sub(1, Ordering::Relaxed) <= threads {
+            parallel::set_intra_file(true);
+        }
+
+        if options.control.as_ref().is_some_and(|control| control.is_cancelled()) {
+            return;
+        }
+
+        let (_, _, input_folder, actual_output_folder, region_type, cache) = &groups[group];
         let file_name = String::from(region_file.file_stem().unwrap().to_str().unwrap());
-        let output_file = file_name + "." + &*output_file_extension_by_mode(mode);
+        let extension = if options.chunk_store.is_some() { chunk_store::MANIFEST_EXTENSION } else { output_format_by_mode(options.mode).extension() };
+        let output_file = file_name + "." + extension;
 
-        let output_pathbuf = actual_output_folder.join(output_file);
+        // files found in subfolders keep their place below the region type folder
+        let subfolder = region_file.parent().and_then(|parent| parent.strip_prefix(input_folder).ok()).unwrap_or(Path::new(""));
+        let output_pathbuf = actual_output_folder.join(subfolder).join(output_file);
+        if let Err(err) = fs::create_dir_all(output_pathbuf.parent().unwrap()) {
+            eprintln!("Failed to create folder for {} !, error : {}", output_pathbuf.display(), err);
+            return;
+        }
 
-        let convert_result = do_converse_single(region_file, &output_pathbuf, mode, compression_level);
+        let convert_result = do_converse_single(region_file, &output_pathbuf, *region_type, cache.as_ref(), options);
+        progress.file_done(group, region_file, convert_result.is_ok());
+        if let Some(control) = &options.control {
+            control.file_done(convert_result.is_ok());
+        }
 
         if convert_result.is_err() {
             let err = convert_result.err().unwrap();
@@ -163,14 +1072,397 @@ fn do_converse_all(mode: Mode, world_folder: PathBuf, output_folder: PathBuf, re
             return;
         }
         
+        if let (Ok(file_timings), Some(report)) = (&convert_result, &options.timings) {
+            report.add(*file_timings);
+        }
+
         if convert_result.is_ok() {
             println!("Done conversation for file {}", region_file.as_path().display());
         }
-    })
+    });
+    progress.finish();
+    parallel::set_intra_file(false);
+
+    for (_, _, _, actual_output_folder, _, cache) in &groups {
+        if let Some(Err(err)) = cache.as_ref().map(HashCache::save) {
+            eprintln!("Failed to write incremental cache in {} !, error : {}", actual_output_folder.display(), err);
+        }
+    }
+
+    if let Some(report) = &options.timings {
+        report.print();
+    }
+
+    Ok(())
+}
+
+/// Checks the convert flags and loads what the run needs up front, the dictionary and the state
+/// database. Errors are the messages to print.
+fn convert_options(convert: &ConvertArgs) -> Result<ConvertOptions, String> {
+    if convert.drop_orphans && convert.region_type == ConvertRegionType::REGION {
+        return Err(String::from("--drop-orphans only applies to poi and entities"));
+    }
+
+    if convert.entities.is_some() && convert.region_type != ConvertRegionType::REGION {
+        return Err(String::from("--entities only applies when converting region"));
+    }
+
+    if convert.blinear_version == 3 && (convert.seekable || convert.hilbert_order || convert.neighbor_delta) {
+        return Err(String::from("--seekable, --hilbert-order and --neighbor-delta only apply to blinear version 2"));
+    }
+
+    if convert.dictionary && output_format_by_mode(convert.mode) != RegionFormat::Blinear {
+        return Err(String::from("--dictionary only applies when writing blinear"));
+    }
+
+    if convert.chunk_store.is_some() && (convert.incremental || convert.verify_against_source || convert.verify_writes || convert.entities == Some(EntityStorage::Split)) {
+        return Err(String::from("--chunk-store does not work with --incremental, --verify-against-source, --verify-writes or --entities split"));
+    }
+
+    if let Some(target) = convert.upgrade_to
+        && upgrade::upgrades_to(target).next().is_none()
+    {
+        return Err(format!("No known upgrade reaches DataVersion {}, the first one migrates to {}", target, upgrade::UPGRADES[0].to));
+    }
+
+    let limits = if convert.strict { ParseLimits::STRICT } else { ParseLimits::DEFAULT };
+    let dictionary = if convert.dictionary {
+        let samples: Vec<PathBuf> = convert.region_type.region_types().into_iter().flat_map(|region_type| scan_region_files(convert.world_path.join(folder_name(region_type)))).collect();
+        let dictionary = dictionary::train(&samples, &limits)
+            .map_err(|err| format!("Failed to train dictionary for {} !, error : {}", convert.world_path.display(), err))?;
+        Some(dictionary)
+    } else {
+        None
+    };
+
+    let state = match &convert.state_db {
+        Some(path) => Some(StateDb::open(path).map_err(|err| format!("Failed to open state database {} !, error : {}", path.display(), err))?),
+        None => None,
+    };
+
+    let options = ConvertOptions {
+        mode: convert.mode,
+        compression_level: convert.compression_level as u8,
+        verify_against_source: convert.verify_against_source,
+        verify_writes: convert.verify_writes,
+        verify_checksums: convert.verify_checksums,
+        incremental: convert.incremental,
+        state,
+        chunk_index: convert.chunk_index,
+        chunk_store: convert.chunk_store.clone().map(ChunkStore::new),
+        limits,
+        // collected per dimension by run_convert
+        terrain_chunks: convert.drop_orphans.then(HashSet::new),
+        forceloaded: HashSet::new(),
+        entity_storage: convert.entities,
+        data_versions: DataVersionRange {
+            min_data_version: convert.min_data_version,
+            max_data_version: convert.max_data_version,
+        },
+        timestamps: TimestampRange { newer_than: convert.newer_than, older_than: convert.older_than },
+        transforms: Transforms {
+            force_blending: convert.force_blending,
+            strip_tags: convert.strip_tag.clone(),
+            purge_entities: convert.purge_entity.iter().map(|id| transform::entity_id(id)).collect(),
+            strip_light: convert.strip_light,
+            strip_recomputable: convert.strip_recomputable,
+            upgrade_to: convert.upgrade_to,
+        },
+        blinear: BlinearOptions {
+            seekable: convert.seekable,
+            hilbert_order: convert.hilbert_order,
+            neighbor_delta: convert.neighbor_delta,
+            grid_size: (convert.blinear_version == 3).then_some(convert.grid_size),
+        },
+        dictionary,
+        timings: convert.timings.then(|| {
+            timings::enable();
+            TimingReport::default()
+        }),
+        progress_interval: Duration::from_secs(convert.progress_interval),
+        grid_size: convert.grid_size,
+        scan_depth: convert.recursive,
+        filter_scan: !convert.no_filter,
+        on_duplicate: convert.on_duplicate,
+        control: None,
+    };
+
+    if options.verify_against_source && (options.prunes() || options.entity_storage.is_some() || !options.transforms.is_empty()) {
+        return Err(String::from("--verify-against-source only works when every chunk is converted unchanged, not with filters, transforms or --entities"));
+    }
+
+    Ok(options)
+}
+
+/// Converts every dimension the `--dimension-map` rules select, or just the overworld.
+fn run_convert(convert: &ConvertArgs, mut options: ConvertOptions) -> Result<(), String> {
+    let overworld = String::from("minecraft:overworld");
+    let plan = if convert.dimension_map.is_empty() {
+        vec![(overworld.clone(), overworld)]
+    } else {
+        match dimensions::plan_dimensions(&dimensions::discover_dimensions(&convert.world_path), &convert.dimension_map) {
+            Ok(plan) if !plan.is_empty() => plan,
+            Ok(_) => return Err(format!("Failed to map dimensions !, error : no dimension of {} is selected", convert.world_path.display())),
+            Err(err) => return Err(format!("Failed to map dimensions !, error : {}", err)),
+        }
+    };
+
+    if let Some(target) = convert.upgrade_to {
+        let steps: Vec<&str> = upgrade::upgrades_to(target).map(|step| step.name).collect();
+        println!("Upgrading chunks up to DataVersion {} with {}", target, steps.join(", "));
+    }
+
+    for (source, target) in plan {
+        if !convert.dimension_map.is_empty() {
+            println!("Converting dimension {} as {}", source, target);
+        }
+        let world_folder = convert.world_path.join(dimensions::dimension_folder(&source));
+        let output_folder = convert.output_path.join(dimensions::dimension_folder(&target));
+        if convert.drop_orphans {
+            let terrain = cleanup::terrain_chunks(&world_folder)
+                .map_err(|err| format!("Failed to collect terrain chunks of {} !, error : {}", world_folder.display(), err))?;
+            options.terrain_chunks = Some(terrain);
+        }
+        if options.prunes() && !convert.ignore_forceloaded {
+            options.forceloaded = forceload::forceloaded_chunks(&world_folder)
+                .map_err(|err| format!("Failed to read force-loaded chunks of {} !, error : {}", world_folder.display(), err))?;
+            if !options.forceloaded.is_empty() {
+                println!("Keeping {} force-loaded chunks of {}", options.forceloaded.len(), world_folder.display());
+            }
+        }
+        do_converse_all(world_folder, output_folder, &convert.region_type.region_types(), &options)?;
+    }
+
+    Ok(())
 }
 
 fn main() {
-    let cli = Cli::parse();
+    let args: Vec<OsString> = std::env::args_os().collect();
+    let command = match config::cli_defaults(Cli::command(), &args) {
+        Ok(command) => command,
+        Err(err) => {
+            eprintln!("Failed to load config !, error : {}", err);
+            exit(1);
+        }
+    };
+    let matches = command.get_matches_from(args);
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+
+    if let Some(level) = cli.nice
+        && let Err(err) = priority::set_nice(level)
+    {
+        eprintln!("Failed to set priority {} !, error : {}", level, err);
+        exit(1);
+    }
+
+    throttle::set_limits(cli.max_read_mbps, cli.max_write_mbps);
+    throttle::set_fsync(cli.fsync);
+
+    // before the worker threads start, which inherit the affinity
+    if let Some(cpus) = &cli.cpu_set
+        && let Err(err) = priority::set_cpu_set(cpus)
+    {
+        eprintln!("Failed to set CPU set {:?} !, error : {}", cpus.0, err);
+        exit(1);
+    }
+
+    if let Some(threads) = cli.threads
+        && let Err(err) = rayon::ThreadPoolBuilder::new().num_threads(threads).build_global()
+    {
+        eprintln!("Failed to start {} worker threads !, error : {}", threads, err);
+        exit(1);
+    }
+
+    match cli.command {
+        Some(Command::Explore { region_file }) => {
+            if let Err(err) = explore::run_explore(&region_file) {
+                eprintln!("Failed to explore file {} !, error : {}", region_file.display(), err);
+                exit(1);
+            }
+        }
+        Some(Command::Stats(args)) => {
+            if let Err(err) = stats::run_stats(&args) {
+                eprintln!("Failed to collect stats for {} !, error : {}", args.world_path.display(), err);
+                exit(1);
+            }
+        }
+        Some(Command::Map(args)) => {
+            if let Err(err) = map::run_map(&args) {
+                eprintln!("Failed to render map for {} !, error : {}", args.world_path.display(), err);
+                exit(1);
+            }
+        }
+        Some(Command::Render(args)) => {
+            if let Err(err) = render::run_render(&args) {
+                eprintln!("Failed to render {} !, error : {}", args.world_path.display(), err);
+                exit(1);
+            }
+        }
+        Some(Command::Find(args)) => {
+            if let Err(err) = find::run_find(&args) {
+                eprintln!("Failed to search {} !, error : {}", args.world_path.display(), err);
+                exit(1);
+            }
+        }
+        Some(Command::Inspect(args)) => {
+            if let Err(err) = inspect::run_inspect(&args) {
+                eprintln!("Failed to inspect file {} !, error : {}", args.region_file.display(), err);
+                exit(1);
+            }
+        }
+        Some(Command::Diff(args)) => {
+            let result = diff::run_diff(&args);
+            if let Err(err) = &result {
+                eprintln!("Failed to compare {} !, error : {}", args.old_path.display(), err);
+            }
+            exit(diff::exit_status(&result));
+        }
+        Some(Command::Selftest(args)) => match selftest::run_selftest(&args) {
+            Ok(true) => {}
+            Ok(false) => exit(1),
+            Err(err) => {
+                eprintln!("Failed to run selftest on {} !, error : {}", args.region_file.display(), err);
+                exit(2);
+            }
+        },
+        Some(Command::Verify(args)) => match verify::run_verify(&args) {
+            Ok(true) => {}
+            Ok(false) => exit(1),
+            Err(err) => {
+                eprintln!("Failed to verify {} !, error : {}", args.path.display(), err);
+                exit(2);
+            }
+        },
+        Some(Command::Fsck(args)) => match fsck::run_fsck(&args) {
+            Ok(true) => {}
+            Ok(false) => exit(1),
+            Err(err) => {
+                eprintln!("Failed to check {} !, error : {}", args.path.display(), err);
+                exit(2);
+            }
+        },
+        Some(Command::Repair(args)) => {
+            if let Err(err) = repair::run_repair(&args) {
+                eprintln!("Failed to repair file {} !, error : {}", args.region_file.display(), err);
+                exit(1);
+            }
+        }
+        Some(Command::Cleanup(args)) => {
+            if let Err(err) = cleanup::run_cleanup(&args) {
+                eprintln!("Failed to clean up {} !, error : {}", args.world_path.display(), err);
+                exit(1);
+            }
+        }
+        Some(Command::Merge(args)) => {
+            if let Err(err) = merge::run_merge(&args) {
+                eprintln!("Failed to merge {} and {} !, error : {}", args.world_a.display(), args.world_b.display(), err);
+                exit(1);
+            }
+        }
+        Some(Command::Extract(args)) => {
+            if let Err(err) = extract::run_extract(&args) {
+                eprintln!("Failed to extract chunk from {} !, error : {}", args.region_file.display(), err);
+                exit(1);
+            }
+        }
+        Some(Command::Inject(args)) => {
+            if let Err(err) = inject::run_inject(&args) {
+                eprintln!("Failed to inject chunk into {} !, error : {}", args.region_file.display(), err);
+                exit(1);
+            }
+        }
+        Some(Command::LevelDat(args)) => {
+            if let Err(err) = level_dat::run_level_dat(&args) {
+                eprintln!("Failed to edit level.dat {} !, error : {}", args.action.level_dat().display(), err);
+                exit(1);
+            }
+        }
+        Some(Command::ExportMaps(args)) => {
+            if let Err(err) = export_maps::run_export_maps(&args) {
+                eprintln!("Failed to export maps of {} !, error : {}", args.world_path.display(), err);
+                exit(1);
+            }
+        }
+        #[cfg(feature = "bedrock")]
+        Some(Command::Bedrock(args)) => {
+            if let Err(err) = bedrock::run_bedrock(&args) {
+                eprintln!("Failed to convert Bedrock world {} !, error : {}", args.world_path.display(), err);
+                exit(1);
+            }
+        }
+        Some(Command::ExportSchem(args)) => {
+            if let Err(err) = export_schem::run_export_schem(&args) {
+                eprintln!("Failed to export schematic from {} !, error : {}", args.world_path.display(), err);
+                exit(1);
+            }
+        }
+        Some(Command::ListFormats) => list_formats::run_list_formats(),
+        Some(Command::Completions(args)) => completions::write_completions(args.shell, &mut std::io::stdout()),
+        Some(Command::ExportStructure(args)) => {
+            if let Err(err) = export_structure::run_export_structure(&args) {
+                eprintln!("Failed to export structures from {} !, error : {}", args.world_path.display(), err);
+                exit(1);
+            }
+        }
+        Some(Command::Offset(args)) => {
+            if let Err(err) = offset::run_offset(&args) {
+                eprintln!("Failed to offset {} !, error : {}", args.world_path.display(), err);
+                exit(1);
+            }
+        }
+        Some(Command::Crop(args)) => {
+            if let Err(err) = crop::run_crop(&args) {
+                eprintln!("Failed to crop {} !, error : {}", args.world_path.display(), err);
+                exit(1);
+            }
+        }
+        Some(Command::Backup(args)) => {
+            if let Err(err) = backup::run_backup(&args) {
+                eprintln!("Failed to back up world {} !, error : {}", args.world_path.display(), err);
+                exit(1);
+            }
+        }
+        Some(Command::Restore(args)) => {
+            if let Err(err) = restore::run_restore(&args) {
+                eprintln!("Failed to restore backup {} !, error : {}", args.backup.display(), err);
+                exit(1);
+            }
+        }
+        Some(Command::Materialize(args)) => {
+            if let Err(err) = chunk_store::run_materialize(&args) {
+                eprintln!("Failed to materialize {} !, error : {}", args.input.display(), err);
+                exit(1);
+            }
+        }
+        Some(Command::History(args)) => {
+            if let Err(err) = history::run_history(&args) {
+                eprintln!("Failed to read history of chunk {}, {} !, error : {}", args.chunk.0, args.chunk.1, err);
+                exit(1);
+            }
+        }
+        Some(Command::Daemon(args)) => {
+            if let Err(err) = daemon::run_daemon(&args) {
+                eprintln!("Failed to run daemon !, error : {}", err);
+                exit(1);
+            }
+        }
+        Some(Command::BenchCompress(args)) => {
+            if let Err(err) = bench_compress::run_bench_compress(&args) {
+                eprintln!("Failed to benchmark file {} !, error : {}", args.region_file.display(), err);
+                exit(1);
+            }
+        }
+        None => {
+            if let Some(convert) = cli.convert {
+                if let Err(err) = output_format_by_mode(convert.mode).check_compression_level(convert.compression_level as u8) {
+                    Cli::command().error(ErrorKind::ValueValidation, err).exit();
+                }
 
-    do_converse_all(cli.mode, cli.world_path, cli.output_path, cli.region_type, cli.compression_level as u8);
+                if let Err(message) = convert_options(&convert).and_then(|options| run_convert(&convert, options)) {
+                    eprintln!("{}", message);
+                    exit(1);
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,90 @@
+use crate::image::{heat_color, RgbImage, MAX_PIXELS};
+use crate::stats::{collect_world_stats, ChunkRecord, CollectOptions};
+use crate::RegionType;
+use clap::{Args, ValueEnum};
+use std::error::Error;
+use std::path::PathBuf;
+
+const BACKGROUND: [u8; 3] = [20, 20, 24];
+const PRESENT: [u8; 3] = [90, 190, 90];
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum OccupancyColor {
+    /// Every stored chunk gets the same color
+    Presence,
+    /// Older chunk timestamps are blue, newer ones red
+    Age,
+    /// Small compressed chunks are blue, large ones red
+    Size,
+}
+
+#[derive(Args)]
+pub struct MapArgs {
+    /// Path to your Minecraft world
+    pub world_path: PathBuf,
+
+    #[arg(short = 't', long, value_enum, default_value = "region")]
+    pub region_type: RegionType,
+
+    /// Write a PNG with one pixel per chunk to this file
+    #[arg(long)]
+    pub occupancy: PathBuf,
+
+    #[arg(long, value_enum, default_value = "presence")]
+    pub color_by: OccupancyColor,
+}
+
+fn normalize(value: i64, min: i64, max: i64) -> f64 {
+    if max <= min {
+        return 1.0;
+    }
+
+    (value - min) as f64 / (max - min) as f64
+}
+
+pub fn render_occupancy(records: &[ChunkRecord], color_by: OccupancyColor) -> Result<RgbImage, Box<dyn Error>> {
+    let min_x = records.iter().map(|record| record.x).min().ok_or("No chunks found")?;
+    let max_x = records.iter().map(|record| record.x).max().unwrap_or(min_x);
+    let min_z = records.iter().map(|record| record.z).min().unwrap_or(0);
+    let max_z = records.iter().map(|record| record.z).max().unwrap_or(min_z);
+
+    let width = (max_x as i64 - min_x as i64 + 1) as u32;
+    let height = (max_z as i64 - min_z as i64 + 1) as u32;
+    if width as usize * height as usize > MAX_PIXELS {
+        return Err(format!("A {width}x{height} map is too large to render").into());
+    }
+
+    let value_of = |record: &ChunkRecord| match color_by {
+        OccupancyColor::Presence => 0,
+        OccupancyColor::Age => record.timestamp,
+        OccupancyColor::Size => record.compressed_size as i64,
+    };
+    let min_value = records.iter().map(value_of).min().unwrap_or(0);
+    let max_value = records.iter().map(value_of).max().unwrap_or(0);
+
+    let mut image = RgbImage::new(width, height, BACKGROUND);
+    for record in records {
+        let color = match color_by {
+            OccupancyColor::Presence => PRESENT,
+            _ => heat_color(normalize(value_of(record), min_value, max_value)),
+        };
+
+        image.put_pixel((record.x - min_x) as u32, (record.z - min_z) as u32, color);
+    }
+
+    Ok(image)
+}
+
+pub fn run_map(args: &MapArgs) -> Result<(), Box<dyn Error>> {
+    let records: Vec<ChunkRecord> = collect_world_stats(&args.world_path, args.region_type, &CollectOptions::default())
+        .into_iter()
+        .flat_map(|stats| stats.chunks)
+        .collect();
+
+    let image = render_occupancy(&records, args.color_by)?;
+    image.save_png(&args.occupancy)?;
+
+    println!("Wrote occupancy map of {} chunks to {}", records.len(), args.occupancy.display());
+
+    Ok(())
+}
@@ -0,0 +1,199 @@
+use crate::chunk::Chunk;
+use crate::region_file::{read_region_file, region_coords_from_path, Region};
+use crate::{folder_name, scan_region_files, RegionType};
+use clap::{Args, ValueEnum};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Args)]
+pub struct MergeArgs {
+    /// World whose chunks win ties
+    pub world_a: PathBuf,
+
+    /// World merged into the first one
+    pub world_b: PathBuf,
+
+    /// Where to write the merged region files
+    pub output_path: PathBuf,
+
+    #[arg(short = 't', long, value_enum, default_value = "region")]
+    pub region_type: RegionType,
+
+    /// Which chunk to keep where both worlds contain one
+    #[arg(long, value_enum, default_value = "newer")]
+    pub prefer: MergePolicy,
+
+    /// Compression level when writing merged region files
+    #[arg(short, long, default_value = "6", value_parser = crate::validate_compression_level)]
+    pub compression_level: u32,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum MergePolicy {
+    /// The chunk with the newer timestamp, the first world's on equal timestamps
+    Newer,
+    /// Always the first world's chunk
+    A,
+    /// Always the second world's chunk
+    B,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct MergeReport {
+    from_a: usize,
+    from_b: usize,
+    /// Chunks present in both worlds
+    conflicts: usize,
+}
+
+/// Combines the chunks of the same region from both worlds, resolving chunks present in both
+/// with the given policy.
+fn merge_regions(a: Region, b: Region, region_x: i32, region_z: i32, policy: MergePolicy) -> (Region, MergeReport) {
+    let mut report = MergeReport::default();
+    let timestamp = a.timestamp().max(b.timestamp());
+
+    let mut chunks: HashMap<(i32, i32), (bool, Chunk)> = HashMap::new();
+    for chunk in a.into_chunks() {
+        chunks.insert(chunk.global_position(region_x, region_z), (true, chunk));
+    }
+
+    for chunk in b.into_chunks() {
+        let position = chunk.global_position(region_x, region_z);
+        let keep_b = match chunks.get(&position) {
+            None => true,
+            Some((_, existing)) => {
+                report.conflicts += 1;
+                match policy {
+                    MergePolicy::Newer => chunk.timestamp() > existing.timestamp(),
+                    MergePolicy::A => false,
+                    MergePolicy::B => true,
+                }
+            }
+        };
+
+        if keep_b {
+            chunks.insert(position, (false, chunk));
+        }
+    }
+
+    let merged = chunks
+        .into_values()
+        .map(|(from_a, chunk)| {
+            if from_a {
+                report.from_a += 1;
+            } else {
+                report.from_b += 1;
+            }
+            chunk
+        })
+        .collect();
+
+    (Region::new(merged, timestamp), report)
+}
+
+/// Region files of a world by region coordinates, files not named r.<x>.<z>.<ext> are ignored.
+fn region_files_by_coords(world_path: &Path, region_type: RegionType) -> BTreeMap<(i32, i32), PathBuf> {
+    scan_region_files(world_path.join(folder_name(region_type)))
+        .into_iter()
+        .filter_map(|path| Some((region_coords_from_path(&path)?, path)))
+        .collect()
+}
+
+fn merge_file(a: &Path, b: &Path, output_folder: &Path, args: &MergeArgs) -> Result<MergeReport, Box<dyn Error>> {
+    let (region_x, region_z) = region_coords_from_path(a).ok_or("File name is not r.<x>.<z>.<ext>")?;
+    let (format, region_a) = read_region_file(a)?;
+    let (_, region_b) = read_region_file(b)?;
+
+    let (merged, report) = merge_regions(region_a, region_b, region_x, region_z, args.prefer);
+
+    let output = output_folder.join(format!("r.{}.{}.{}", region_x, region_z, format.extension()));
+    fs::write(output, merged.to_bytes(format, Some((region_x, region_z)), merged.timestamp(), args.compression_level as u8)?)?;
+
+    Ok(report)
+}
+
+pub fn run_merge(args: &MergeArgs) -> Result<(), Box<dyn Error>> {
+    let files_a = region_files_by_coords(&args.world_a, args.region_type);
+    let mut files_b = region_files_by_coords(&args.world_b, args.region_type);
+
+    let output_folder = args.output_path.join(folder_name(args.region_type));
+    fs::create_dir_all(&output_folder)?;
+
+    // regions only one world has are copied as they are
+    let mut pairs = Vec::new();
+    for (coords, file_a) in files_a {
+        match files_b.remove(&coords) {
+            Some(file_b) => pairs.push((file_a, file_b)),
+            None => {
+                fs::copy(&file_a, output_folder.join(file_a.file_name().unwrap()))?;
+            }
+        }
+    }
+    for file_b in files_b.values() {
+        fs::copy(file_b, output_folder.join(file_b.file_name().unwrap()))?;
+    }
+
+    let reports: Vec<Option<MergeReport>> = pairs
+        .par_iter()
+        .map(|(file_a, file_b)| match merge_file(file_a, file_b, &output_folder, args) {
+            Ok(report) => Some(report),
+            Err(err) => {
+                eprintln!("Failed to merge file {} !, error : {}", file_a.display(), err);
+                None
+            }
+        })
+        .collect();
+
+    let merged: Vec<&MergeReport> = reports.iter().flatten().collect();
+    println!(
+        "{} region files merged, {} chunks from {}, {} chunks from {}, {} chunks in both",
+        merged.len(),
+        merged.iter().map(|report| report.from_a).sum::<usize>(),
+        args.world_a.display(),
+        merged.iter().map(|report| report.from_b).sum::<usize>(),
+        args.world_b.display(),
+        merged.iter().map(|report| report.conflicts).sum::<usize>()
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nbt::tag::Tag;
+
+    fn region(chunks: &[(i32, i32, i64)]) -> Region {
+        let chunks = chunks
+            .iter()
+            .map(|&(x, z, timestamp)| Chunk::new_from_block_pos(x, z, timestamp, Tag::Compound { name: None, value: Vec::new() }))
+            .collect();
+        Region::new(chunks, 0)
+    }
+
+    fn timestamps(region: &Region) -> Vec<(i32, i32, i64)> {
+        let mut timestamps: Vec<_> = region.chunks().iter().map(|chunk| (chunk.x(), chunk.z(), chunk.timestamp())).collect();
+        timestamps.sort();
+        timestamps
+    }
+
+    #[test]
+    fn test_merge_regions() {
+        let a = || region(&[(0, 0, 10), (1, 0, 10), (2, 0, 5)]);
+        let b = || region(&[(1, 0, 20), (2, 0, 5), (3, 0, 1)]);
+
+        let (merged, report) = merge_regions(a(), b(), 0, 0, MergePolicy::Newer);
+        assert_eq!(timestamps(&merged), [(0, 0, 10), (1, 0, 20), (2, 0, 5), (3, 0, 1)]);
+        assert_eq!(report, MergeReport { from_a: 2, from_b: 2, conflicts: 2 });
+
+        let (merged, report) = merge_regions(a(), b(), 0, 0, MergePolicy::A);
+        assert_eq!(timestamps(&merged)[1], (1, 0, 10));
+        assert_eq!(report.from_b, 1);
+
+        let (_, report) = merge_regions(a(), b(), 0, 0, MergePolicy::B);
+        assert_eq!(report, MergeReport { from_a: 1, from_b: 3, conflicts: 2 });
+    }
+}
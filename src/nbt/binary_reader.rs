@@ -1,29 +1,28 @@
-use std::string::FromUtf8Error;
+use crate::nbt::mutf8;
+use crate::nbt::parse::NbtError;
 
 macro_rules! impl_read_number {
     ($fn_name:ident, $type:ty) => {
-        pub fn $fn_name(&mut self) -> $type {
+        pub fn $fn_name(&mut self) -> Result<$type, NbtError> {
             let size = std::mem::size_of::<$type>();
-            let bytes = &self.raw[self.index..self.index + size];
-            let integer = <$type>::from_be_bytes(bytes.try_into().unwrap());
-            self.index += size;
-            integer
+            let bytes = self.take(size)?;
+            Ok(<$type>::from_be_bytes(bytes.try_into().unwrap()))
         }
     };
 }
 
 macro_rules! impl_read_array {
     ($fn_name:ident, $type:ty, $reader:ident) => {
-        pub fn $fn_name(&mut self) -> Vec<$type> {
-            let size = self.read_i32() as usize;
+        pub fn $fn_name(&mut self, max_elements: usize) -> Result<Vec<$type>, NbtError> {
+            let size = self.read_length(std::mem::size_of::<$type>(), max_elements)?;
             let mut values = Vec::with_capacity(size);
 
             for _ in 0..size {
-                let next_tag = self.$reader();
+                let next_tag = self.$reader()?;
                 values.push(next_tag);
             }
 
-            values
+            Ok(values)
         }
     };
 }
@@ -38,18 +37,46 @@ impl<'a> BinaryReader<'a> {
         Self { raw, index: 0 }
     }
 
-    pub fn read_string(&mut self) -> Result<String, FromUtf8Error> {
-        let size = self.read_u16() as usize;
-        let bytes = &self.raw[self.index..self.index + size];
+    fn take(&mut self, size: usize) -> Result<&'a [u8], NbtError> {
+        let bytes = self.raw.get(self.index..self.index + size).ok_or(NbtError::UnexpectedEof)?;
         self.index += size;
-        String::from_utf8(bytes.to_vec())
+        Ok(bytes)
     }
 
-    pub fn read_name(&mut self) -> Option<String> {
-        self.read_string().ok().filter(|s| !s.is_empty())
+    pub fn remaining(&self) -> usize {
+        self.raw.len() - self.index
     }
 
-    pub fn read_type(&mut self) -> u8 {
+    /// Reads an i32 element count, rejecting counts that are negative, above `max_elements` or
+    /// larger than the remaining input could hold, so a corrupt length never drives an allocation.
+    pub fn read_length(&mut self, element_size: usize, max_elements: usize) -> Result<usize, NbtError> {
+        let length = self.read_i32()?;
+        if length < 0 {
+            return Err(NbtError::NegativeLength(length));
+        }
+
+        let length = length as usize;
+        if length > max_elements {
+            return Err(NbtError::TooManyElements(length));
+        }
+        if length.saturating_mul(element_size) > self.remaining() {
+            return Err(NbtError::UnexpectedEof);
+        }
+
+        Ok(length)
+    }
+
+    pub fn read_string(&mut self) -> Result<String, NbtError> {
+        let size = self.read_u16()? as usize;
+        let bytes = self.take(size)?;
+        mutf8::decode(bytes).ok_or(NbtError::InvalidString)
+    }
+
+    pub fn read_name(&mut self) -> Result<Option<String>, NbtError> {
+        Ok(Some(self.read_string()?).filter(|s| !s.is_empty()))
+    }
+
+    pub fn read_type(&mut self) -> Result<u8, NbtError> {
         self.read_u8()
     }
 
@@ -74,35 +101,44 @@ mod tests {
     fn test_read_i8() {
         let data = [0x7F];
         let mut reader = BinaryReader::new(&data);
-        assert_eq!(reader.read_i8(), 127);
+        assert_eq!(reader.read_i8().unwrap(), 127);
     }
 
     #[test]
     fn test_read_i16() {
         let data = [0x7F, 0xFF];
         let mut reader = BinaryReader::new(&data);
-        assert_eq!(reader.read_i16(), 32767);
+        assert_eq!(reader.read_i16().unwrap(), 32767);
     }
 
     #[test]
     fn test_read_u16() {
         let data = [0x0F, 0xFF];
         let mut reader = BinaryReader::new(&data);
-        assert_eq!(reader.read_u16(), 4095);
+        assert_eq!(reader.read_u16().unwrap(), 4095);
+    }
+
+    #[test]
+    fn test_read_name() {
+        let data = [0, 1, 0x78, 0, 0, 0, 1, 0xFF];
+        let mut reader = BinaryReader::new(&data);
+        assert_eq!(reader.read_name().unwrap().as_deref(), Some("x"));
+        assert_eq!(reader.read_name().unwrap(), None);
+        assert_eq!(reader.read_name(), Err(NbtError::InvalidString));
     }
 
     #[test]
     fn test_read_i32() {
         let data = [0x7F, 0xFF, 0xFF, 0xFF];
         let mut reader = BinaryReader::new(&data);
-        assert_eq!(reader.read_i32(), 2147483647);
+        assert_eq!(reader.read_i32().unwrap(), 2147483647);
     }
 
     #[test]
     fn test_read_f32() {
         let data = [0x3F, 0x80, 0x00, 0x00];
         let mut reader = BinaryReader::new(&data);
-        assert_eq!(reader.read_f32(), 1.0);
+        assert_eq!(reader.read_f32().unwrap(), 1.0);
     }
 
     #[test]
@@ -113,4 +149,34 @@ mod tests {
 
         assert_eq!(parsed, "HELLO");
     }
+
+    #[test]
+    fn test_read_modified_utf8_string() {
+        // NUL and U+1F5E1 in Modified UTF-8
+        let data = [0, 8, 0xC0, 0x80, 0xED, 0xA0, 0xBD, 0xED, 0xB7, 0xA1];
+        let mut reader = BinaryReader::new(&data);
+
+        assert_eq!(reader.read_string().unwrap(), "\0🗡");
+    }
+
+    #[test]
+    fn test_read_past_end() {
+        let data = [0x7F, 0xFF];
+        let mut reader = BinaryReader::new(&data);
+        assert_eq!(reader.read_i32(), Err(NbtError::UnexpectedEof));
+    }
+
+    #[test]
+    fn test_array_length_checks() {
+        let negative = [0xFF, 0xFF, 0xFF, 0xFF];
+        assert_eq!(BinaryReader::new(&negative).read_long_array(usize::MAX), Err(NbtError::NegativeLength(-1)));
+
+        // claims 2^31 - 1 longs but only carries one
+        let truncated = [0x7F, 0xFF, 0xFF, 0xFF, 0, 0, 0, 0, 0, 0, 0, 1];
+        assert_eq!(BinaryReader::new(&truncated).read_long_array(usize::MAX), Err(NbtError::UnexpectedEof));
+
+        let long = [0, 0, 0, 2, 1, 2];
+        assert_eq!(BinaryReader::new(&long).read_byte_array(1), Err(NbtError::TooManyElements(2)));
+        assert_eq!(BinaryReader::new(&long).read_byte_array(2), Ok(vec![1, 2]));
+    }
 }
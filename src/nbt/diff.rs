@@ -0,0 +1,176 @@
+use crate::nbt::tag::Tag;
+use std::fmt;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum TagChange {
+    Added(String),
+    Removed(String),
+    Changed(String),
+}
+
+impl fmt::Display for TagChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TagChange::Added(path) => write!(f, "+ {path}"),
+            TagChange::Removed(path) => write!(f, "- {path}"),
+            TagChange::Changed(path) => write!(f, "~ {path}"),
+        }
+    }
+}
+
+/// Compares two tag trees semantically and returns the paths that differ, in query syntax.
+///
+/// Compound entries are matched by name so key order does not matter, list elements by index.
+/// Floating point values are compared bitwise so a NaN written back unchanged is not a change.
+pub fn diff_tags(old: &Tag, new: &Tag) -> Vec<TagChange> {
+    let mut changes = Vec::new();
+    diff_into(old, new, &mut String::new(), &mut changes);
+    changes
+}
+
+fn diff_into(old: &Tag, new: &Tag, path: &mut String, changes: &mut Vec<TagChange>) {
+    match (old, new) {
+        (Tag::Compound { value: old, .. }, Tag::Compound { value: new, .. }) => {
+            for old_child in old {
+                let name = old_child.get_name().unwrap_or_default();
+                let length = path.len();
+                push_key(path, &name);
+
+                match new.iter().find(|tag| tag.get_name().is_some_and(|found| found == name)) {
+                    Some(new_child) => diff_into(old_child, new_child, path, changes),
+                    None => changes.push(TagChange::Removed(path.clone())),
+                }
+                path.truncate(length);
+            }
+
+            for new_child in new {
+                let name = new_child.get_name().unwrap_or_default();
+                if !old.iter().any(|tag| tag.get_name().is_some_and(|found| found == name)) {
+                    let length = path.len();
+                    push_key(path, &name);
+                    changes.push(TagChange::Added(path.clone()));
+                    path.truncate(length);
+                }
+            }
+        }
+        (
+            Tag::List { value: old, tag_type: old_type, .. },
+            Tag::List { value: new, tag_type: new_type, .. },
+        ) if element_type(*old_type, old) != element_type(*new_type, new) => changes.push(TagChange::Changed(path.clone())),
+        (Tag::List { value: old, .. }, Tag::List { value: new, .. }) => {
+            for index in 0..old.len().max(new.len()) {
+                let length = path.len();
+                path.push_str(&format!("[{index}]"));
+
+                match (old.get(index), new.get(index)) {
+                    (Some(old), Some(new)) => diff_into(old, new, path, changes),
+                    (Some(_), None) => changes.push(TagChange::Removed(path.clone())),
+                    (None, _) => changes.push(TagChange::Added(path.clone())),
+                }
+                path.truncate(length);
+            }
+        }
+        _ => {
+            if !same_value(old, new) {
+                changes.push(TagChange::Changed(path.clone()));
+            }
+        }
+    }
+}
+
+/// The element type a list is written with, which follows its elements when it has any.
+fn element_type(tag_type: u8, elements: &[Tag]) -> u8 {
+    elements.first().map_or(tag_type, Tag::get_tag_type)
+}
+
+fn push_key(path: &mut String, name: &str) {
+    if !path.is_empty() {
+        path.push('.');
+    }
+    path.push_str(name);
+}
+
+fn same_value(old: &Tag, new: &Tag) -> bool {
+    match (old, new) {
+        (Tag::Float { value: old, .. }, Tag::Float { value: new, .. }) => old.to_bits() == new.to_bits(),
+        (Tag::Double { value: old, .. }, Tag::Double { value: new, .. }) => old.to_bits() == new.to_bits(),
+        (Tag::Byte { value: old, .. }, Tag::Byte { value: new, .. }) => old == new,
+        (Tag::Short { value: old, .. }, Tag::Short { value: new, .. }) => old == new,
+        (Tag::Int { value: old, .. }, Tag::Int { value: new, .. }) => old == new,
+        (Tag::Long { value: old, .. }, Tag::Long { value: new, .. }) => old == new,
+        (Tag::String { value: old, .. }, Tag::String { value: new, .. }) => old == new,
+        (Tag::ByteArray { value: old, .. }, Tag::ByteArray { value: new, .. }) => old == new,
+        (Tag::IntArray { value: old, .. }, Tag::IntArray { value: new, .. }) => old == new,
+        (Tag::LongArray { value: old, .. }, Tag::LongArray { value: new, .. }) => old == new,
+        (Tag::End, Tag::End) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn int(name: &str, value: i32) -> Tag {
+        Tag::Int { name: Some(String::from(name)), value }
+    }
+
+    fn compound(name: Option<&str>, value: Vec<Tag>) -> Tag {
+        Tag::Compound { name: name.map(String::from), value }
+    }
+
+    fn list(name: &str, value: Vec<Tag>) -> Tag {
+        Tag::List { name: Some(String::from(name)), tag_type: 10, value }
+    }
+
+    #[test]
+    fn test_key_order_is_ignored() {
+        let old = compound(None, vec![int("a", 1), int("b", 2)]);
+        let new = compound(None, vec![int("b", 2), int("a", 1)]);
+
+        assert!(diff_tags(&old, &new).is_empty());
+    }
+
+    #[test]
+    fn test_reports_paths() {
+        let section = |y| compound(None, vec![int("Y", y)]);
+        let old = compound(None, vec![int("a", 1), int("gone", 0), list("sections", vec![section(0), section(1)])]);
+        let new = compound(
+            None,
+            vec![
+                int("a", 2),
+                list("sections", vec![section(0), section(5), section(2)]),
+                compound(Some("extra"), Vec::new()),
+            ],
+        );
+
+        assert_eq!(
+            diff_tags(&old, &new),
+            [
+                TagChange::Changed(String::from("a")),
+                TagChange::Removed(String::from("gone")),
+                TagChange::Changed(String::from("sections[1].Y")),
+                TagChange::Added(String::from("sections[2]")),
+                TagChange::Added(String::from("extra")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_type_change() {
+        let old = compound(None, vec![int("a", 1)]);
+        let new = compound(None, vec![Tag::Long { name: Some(String::from("a")), value: 1 }]);
+
+        assert_eq!(diff_tags(&old, &new), [TagChange::Changed(String::from("a"))]);
+    }
+
+    #[test]
+    fn test_list_type_change() {
+        let ints = |value: Vec<Tag>| compound(None, vec![Tag::List { name: Some(String::from("l")), tag_type: 3, value }]);
+        let empty_longs = compound(None, vec![Tag::List { name: Some(String::from("l")), tag_type: 4, value: Vec::new() }]);
+
+        assert_eq!(diff_tags(&ints(Vec::new()), &empty_longs), [TagChange::Changed(String::from("l"))]);
+        assert_eq!(diff_tags(&empty_longs, &ints(vec![int("", 1)])), [TagChange::Changed(String::from("l"))]);
+        assert_eq!(diff_tags(&ints(Vec::new()), &ints(vec![int("", 1)])), [TagChange::Added(String::from("l[0]"))]);
+    }
+}
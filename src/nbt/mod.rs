@@ -1,5 +1,9 @@
 pub mod binary_reader;
+pub mod diff;
+mod mutf8;
 pub mod parse;
 mod parsers;
+pub mod query;
+pub mod snbt;
 pub mod tag;
 mod writers;
@@ -0,0 +1,98 @@
+//! Java's Modified UTF-8, the string encoding of NBT. It differs from UTF-8 only in writing NUL as
+//! the two bytes `C0 80` and characters outside the BMP as two three byte encoded surrogates.
+
+use std::borrow::Cow;
+
+/// Decodes Modified UTF-8. Plain UTF-8, as written by some third party tools, is accepted too.
+/// Unpaired surrogates, which Java strings may hold, are rejected: a Rust string can not hold
+/// them, and a replacement character would not encode back to the same bytes.
+pub fn decode(bytes: &[u8]) -> Option<String> {
+    // valid UTF-8 never holds C0 80 or encoded surrogates, so it decodes the same either way
+    if let Ok(string) = std::str::from_utf8(bytes) {
+        return Some(string.to_owned());
+    }
+
+    let continuation = |index: usize| bytes.get(index).filter(|byte| *byte & 0xC0 == 0x80).map(|byte| (byte & 0x3F) as u32);
+
+    let mut units = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let lead = bytes[index] as u32;
+        match lead {
+            0x00..=0x7F => {
+                units.push(lead as u16);
+                index += 1;
+            }
+            0xC0..=0xDF => {
+                units.push(((lead & 0x1F) << 6 | continuation(index + 1)?) as u16);
+                index += 2;
+            }
+            0xE0..=0xEF => {
+                units.push(((lead & 0x0F) << 12 | continuation(index + 1)? << 6 | continuation(index + 2)?) as u16);
+                index += 3;
+            }
+            0xF0..=0xF7 => {
+                let code_point = (lead & 0x07) << 18 | continuation(index + 1)? << 12 | continuation(index + 2)? << 6 | continuation(index + 3)?;
+                units.extend(char::from_u32(code_point)?.encode_utf16(&mut [0; 2]).iter());
+                index += 4;
+            }
+            _ => return None,
+        }
+    }
+
+    String::from_utf16(&units).ok()
+}
+
+/// Encodes a string as Modified UTF-8, borrowing it when that is identical to its UTF-8.
+pub fn encode(string: &str) -> Cow<'_, [u8]> {
+    if !string.chars().any(|char| char == '\0' || char as u32 > 0xFFFF) {
+        return Cow::Borrowed(string.as_bytes());
+    }
+
+    let mut bytes = Vec::with_capacity(string.len() + 8);
+    for char in string.chars() {
+        match char {
+            '\0' => bytes.extend_from_slice(&[0xC0, 0x80]),
+            char if char as u32 > 0xFFFF => {
+                for unit in char.encode_utf16(&mut [0; 2]) {
+                    let unit = *unit as u32;
+                    bytes.extend_from_slice(&[0xE0 | (unit >> 12) as u8, 0x80 | (unit >> 6 & 0x3F) as u8, 0x80 | (unit & 0x3F) as u8]);
+                }
+            }
+            char => bytes.extend_from_slice(char.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+
+    Cow::Owned(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        for string in ["HELLO", "Grüße 한국어", "nul\0byte", "lore 🗡 text", ""] {
+            assert_eq!(decode(&encode(string)).unwrap(), string);
+        }
+
+        assert!(matches!(encode("Grüße"), Cow::Borrowed(_)));
+        assert_eq!(encode("a\0"), [0x61, 0xC0, 0x80].as_slice());
+        // U+1F5E1 as the surrogates D83D DDE1
+        assert_eq!(encode("🗡"), [0xED, 0xA0, 0xBD, 0xED, 0xB7, 0xA1].as_slice());
+    }
+
+    #[test]
+    fn test_decode_edge_cases() {
+        // lone surrogates as Java can write them, which could not be written back unchanged
+        assert_eq!(decode(&[0x41, 0xED, 0xA0, 0xBD]), None);
+        assert_eq!(decode(&[0xED, 0xB7, 0xA1, 0x41]), None);
+        // the same surrogates paired round trip
+        let paired = [0xED, 0xA0, 0xBD, 0xED, 0xB7, 0xA1];
+        assert_eq!(encode(&decode(&paired).unwrap()), paired.as_slice());
+        // plain UTF-8 supplementary characters next to a Modified UTF-8 NUL
+        assert_eq!(decode(&[0xF0, 0x9F, 0x97, 0xA1, 0xC0, 0x80]).unwrap(), "🗡\0");
+        assert_eq!(decode(&[0xC3]), None);
+        assert_eq!(decode(&[0xFF]), None);
+    }
+}
@@ -1,8 +1,208 @@
 use crate::nbt::binary_reader::BinaryReader;
 use crate::nbt::parsers::parse_with_type::parse_with_type;
 use crate::nbt::tag::Tag;
+use thiserror::Error;
 
-pub fn parse_tag(reader: &mut BinaryReader) -> Tag {
-    let tag_type = reader.read_type();
-    parse_with_type(reader, tag_type, false)
+#[derive(Error, Debug, PartialEq)]
+pub enum NbtError {
+    #[error("Unexpected end of NBT data")]
+    UnexpectedEof,
+    #[error("Unknown tag type {0}")]
+    UnknownTagType(u8),
+    #[error("Invalid Modified UTF-8 string")]
+    InvalidString,
+    #[error("Negative list or array length {0}")]
+    NegativeLength(i32),
+    #[error("List or array of {0} elements exceeds the limit")]
+    TooManyElements(usize),
+    #[error("Tags nested deeper than {0} levels")]
+    TooDeep(usize),
+    #[error("NBT data larger than {0} bytes")]
+    TooLarge(usize),
+}
+
+/// Limits applied while parsing NBT, for inputs that can not be trusted.
+#[derive(Clone, Copy, Debug)]
+pub struct ParseOptions {
+    /// Maximum nesting of compounds and lists
+    pub max_depth: usize,
+    /// Maximum number of elements of a single list or array
+    pub max_elements: usize,
+    /// Maximum size of the encoded input
+    pub max_bytes: usize,
+}
+
+impl ParseOptions {
+    pub const UNLIMITED: Self = Self {
+        max_depth: usize::MAX,
+        max_elements: usize::MAX,
+        max_bytes: usize::MAX,
+    };
+
+    /// Limits no chunk written by the game comes close to; the depth matches the game's own.
+    pub const STRICT: Self = Self {
+        max_depth: 512,
+        max_elements: 1 << 20,
+        max_bytes: 64 * 1024 * 1024,
+    };
+
+    /// The default, generous enough for heavily modded worlds while still keeping a corrupt
+    /// length or nesting from exhausting the stack or memory.
+    pub const DEFAULT: Self = Self {
+        max_depth: 512,
+        max_elements: 1 << 24,
+        max_bytes: 256 * 1024 * 1024,
+    };
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+pub fn parse_tag(reader: &mut BinaryReader, options: &ParseOptions) -> Result<Tag, NbtError> {
+    if reader.remaining() > options.max_bytes {
+        return Err(NbtError::TooLarge(options.max_bytes));
+    }
+
+    let tag_type = reader.read_type()?;
+    parse_with_type(reader, tag_type, false, options, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nested_lists(depth: usize) -> Vec<u8> {
+        // a root list whose single element is a list, `depth` times
+        let mut bytes = vec![9, 0, 0];
+        for _ in 0..depth {
+            bytes.extend_from_slice(&[9, 0, 0, 0, 1]);
+        }
+        bytes.extend_from_slice(&[0, 0, 0, 0, 0]);
+        bytes
+    }
+
+    #[test]
+    fn test_depth_limit() {
+        let options = ParseOptions { max_depth: 8, ..ParseOptions::UNLIMITED };
+
+        assert!(parse_tag(&mut BinaryReader::new(&nested_lists(7)), &options).is_ok());
+        assert_eq!(
+            parse_tag(&mut BinaryReader::new(&nested_lists(8)), &options),
+            Err(NbtError::TooDeep(8))
+        );
+    }
+
+    fn named(name: &str) -> Option<String> {
+        Some(String::from(name))
+    }
+
+    fn round_trip(bytes: &[u8]) -> Vec<u8> {
+        parse_tag(&mut BinaryReader::new(bytes), &ParseOptions::STRICT).unwrap().to_bytes()
+    }
+
+    #[test]
+    fn test_round_trip_every_tag_type() {
+        let empty_compound = Tag::Compound { name: None, value: Vec::new() };
+        let tag = Tag::Compound {
+            name: named("root"),
+            value: vec![
+                Tag::Byte { name: named("byte"), value: -128 },
+                Tag::Short { name: named("short"), value: i16::MIN },
+                Tag::Int { name: named("int"), value: i32::MAX },
+                Tag::Long { name: named("long"), value: i64::MIN },
+                Tag::Float { name: named("float"), value: f32::MIN_POSITIVE },
+                Tag::Double { name: named("double"), value: -0.0 },
+                Tag::ByteArray { name: named("bytes"), value: vec![-1, 0, 1] },
+                Tag::ByteArray { name: named("no bytes"), value: Vec::new() },
+                Tag::String { name: named("string"), value: String::from("lore 🗡\0") },
+                Tag::String { name: None, value: String::new() },
+                Tag::List { name: named("empty"), value: Vec::new(), tag_type: 0 },
+                Tag::List { name: named("empty ints"), value: Vec::new(), tag_type: 3 },
+                Tag::List {
+                    name: named("lists"),
+                    value: vec![
+                        Tag::List { name: None, value: vec![Tag::Long { name: None, value: 1 }], tag_type: 4 },
+                        Tag::List { name: None, value: vec![Tag::String { name: None, value: String::from("a") }], tag_type: 8 },
+                        Tag::List { name: None, value: Vec::new(), tag_type: 0 },
+                    ],
+                    tag_type: 9,
+                },
+                Tag::List {
+                    name: named("compounds"),
+                    value: vec![
+                        empty_compound.clone(),
+                        Tag::Compound { name: None, value: vec![Tag::IntArray { name: named("ints"), value: vec![i32::MIN] }, empty_compound] },
+                    ],
+                    tag_type: 10,
+                },
+                Tag::IntArray { name: named("no ints"), value: Vec::new() },
+                Tag::LongArray { name: named("longs"), value: vec![i64::MAX, -1] },
+            ],
+        };
+
+        let bytes = tag.to_bytes();
+        assert_eq!(parse_tag(&mut BinaryReader::new(&bytes), &ParseOptions::STRICT).unwrap(), tag);
+        assert_eq!(round_trip(&bytes), bytes);
+
+        assert_eq!(Tag::End.to_bytes(), [0]);
+        assert_eq!(round_trip(&[0]), [0]);
+    }
+
+    #[test]
+    fn test_round_trip_is_byte_identical() {
+        let corpus: [&[u8]; 4] = [
+            // unnamed root compound holding an empty list of End
+            &[10, 0, 0, 9, 0, 1, 0x6C, 0, 0, 0, 0, 0, 0],
+            // an empty list of Int
+            &[9, 0, 0, 3, 0, 0, 0, 0],
+            // NaN float with a payload, which must survive bit for bit
+            &[5, 0, 1, 0x66, 0x7F, 0xC0, 0x12, 0x34],
+            // Modified UTF-8 NUL in a root name
+            &[8, 0, 2, 0xC0, 0x80, 0, 1, 0x78],
+        ];
+
+        for bytes in corpus {
+            assert_eq!(round_trip(bytes), bytes);
+        }
+    }
+
+    #[test]
+    fn test_list_type_follows_elements() {
+        let list = Tag::List { name: None, value: vec![Tag::Int { name: None, value: 7 }], tag_type: 10 };
+        assert_eq!(list.to_bytes(), [9, 0, 0, 3, 0, 0, 0, 1, 0, 0, 0, 7]);
+
+        // End elements have no payload, the list is read and written as empty
+        let ends = Tag::List { name: None, value: vec![Tag::End, Tag::End], tag_type: 0 };
+        assert_eq!(ends.to_bytes(), [9, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(round_trip(&[9, 0, 0, 0, 0, 0, 0, 3]), [9, 0, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_size_limit() {
+        let bytes = Tag::LongArray { name: None, value: vec![0; 16] }.to_bytes();
+        let options = ParseOptions { max_bytes: 64, ..ParseOptions::DEFAULT };
+
+        assert_eq!(parse_tag(&mut BinaryReader::new(&bytes), &options), Err(NbtError::TooLarge(64)));
+        assert!(parse_tag(&mut BinaryReader::new(&bytes), &ParseOptions::default()).is_ok());
+    }
+
+    #[test]
+    fn test_malformed_input_is_an_error() {
+        assert_eq!(parse_tag(&mut BinaryReader::new(&[10, 0, 0, 1, 0]), &ParseOptions::default()), Err(NbtError::UnexpectedEof));
+        assert_eq!(parse_tag(&mut BinaryReader::new(&[42, 0, 0]), &ParseOptions::default()), Err(NbtError::UnknownTagType(42)));
+    }
+
+    #[test]
+    fn test_invalid_strings_are_an_error() {
+        // a compound key holding a lone surrogate
+        let name = [10, 0, 0, 1, 0, 3, 0xED, 0xA0, 0xBD, 7, 0];
+        assert_eq!(parse_tag(&mut BinaryReader::new(&name), &ParseOptions::default()), Err(NbtError::InvalidString));
+
+        // a string value cut inside a two byte sequence
+        let value = [8, 0, 1, 0x73, 0, 1, 0xC3];
+        assert_eq!(parse_tag(&mut BinaryReader::new(&value), &ParseOptions::default()), Err(NbtError::InvalidString));
+    }
 }
@@ -1,17 +1,19 @@
 use crate::nbt::binary_reader::BinaryReader;
-use crate::nbt::parse::parse_tag;
+use crate::nbt::parse::{NbtError, ParseOptions};
+use crate::nbt::parsers::parse_with_type::parse_with_type;
 use crate::nbt::tag::Tag;
 
-pub fn parse_compound_tag(reader: &mut BinaryReader) -> Vec<Tag> {
+pub fn parse_compound_tag(reader: &mut BinaryReader, options: &ParseOptions, depth: usize) -> Result<Vec<Tag>, NbtError> {
     let mut values = Vec::new();
 
     loop {
-        let next_tag = parse_tag(reader);
+        let tag_type = reader.read_type()?;
+        let next_tag = parse_with_type(reader, tag_type, false, options, depth)?;
         if next_tag == Tag::End {
             break;
         }
         values.push(next_tag);
     }
 
-    values
+    Ok(values)
 }
@@ -1,20 +1,28 @@
 use crate::nbt::binary_reader::BinaryReader;
+use crate::nbt::parse::{NbtError, ParseOptions};
 use crate::nbt::parsers::parse_with_type::parse_with_type;
 use crate::nbt::tag::Tag;
 
-pub fn parse_list_tag(reader: &mut BinaryReader) -> (u8, Vec<Tag>) {
-    let mut values = Vec::new();
+pub fn parse_list_tag(reader: &mut BinaryReader, options: &ParseOptions, depth: usize) -> Result<(u8, Vec<Tag>), NbtError> {
+    let tag_type = reader.read_type()?;
 
-    let tag_type = reader.read_type();
-    let list_length = reader.read_i32();
-    if list_length <= 0 && tag_type == 0 {
-        return (tag_type, values);
+    // End elements carry no payload and the game only writes such lists empty
+    if tag_type == 0 {
+        reader.read_i32()?;
+        return Ok((tag_type, Vec::new()));
     }
 
+    // a negative length reads as an empty list, every element takes at least one byte
+    let list_length = match reader.read_length(1, options.max_elements) {
+        Err(NbtError::NegativeLength(_)) => 0,
+        other => other?,
+    };
+
+    let mut values = Vec::with_capacity(list_length);
     for _ in 0..list_length {
-        let next_tag = parse_with_type(reader, tag_type, true);
+        let next_tag = parse_with_type(reader, tag_type, true, options, depth)?;
         values.push(next_tag);
     }
 
-    (tag_type, values)
+    Ok((tag_type, values))
 }
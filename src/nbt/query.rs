@@ -0,0 +1,317 @@
+use crate::nbt::tag::Tag;
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum QueryError {
+    #[error("Query is empty")]
+    Empty,
+    #[error("Empty key at position {0}")]
+    EmptyKey(usize),
+    #[error("Unclosed `[` at position {0}")]
+    UnclosedIndex(usize),
+    #[error("Invalid index `{0}`")]
+    InvalidIndex(String),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Segment {
+    /// A compound child by name
+    Key(String),
+    /// Every child of a compound
+    AnyKey,
+    /// A list element, negative indices count from the end
+    Index(i64),
+    /// Every element of a list
+    AnyIndex,
+}
+
+/// A path into an NBT tree such as `Level.Sections[2].block_states.palette[*].Name`.
+///
+/// Keys are separated by `.`, `*` matches every child of a compound and `[n]` / `[*]` select
+/// list elements. Evaluating a query returns every tag the path reaches.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Query {
+    segments: Vec<Segment>,
+}
+
+impl Query {
+    pub fn parse(query: &str) -> Result<Self, QueryError> {
+        if query.is_empty() {
+            return Err(QueryError::Empty);
+        }
+
+        let mut segments = Vec::new();
+        let mut position = 0;
+
+        for part in query.split('.') {
+            let key_end = part.find('[').unwrap_or(part.len());
+            let key = &part[..key_end];
+
+            // only a query starting with `[n]` may omit the key, it indexes the root itself
+            let leading_index = position == 0 && key_end < part.len();
+
+            match key {
+                "" if leading_index => {}
+                "" => return Err(QueryError::EmptyKey(position)),
+                "*" => segments.push(Segment::AnyKey),
+                _ => segments.push(Segment::Key(String::from(key))),
+            }
+
+            let mut rest = &part[key_end..];
+            while !rest.is_empty() {
+                if !rest.starts_with('[') {
+                    return Err(QueryError::InvalidIndex(String::from(rest)));
+                }
+
+                let bracket_position = position + part.len() - rest.len();
+                let close = rest.find(']').ok_or(QueryError::UnclosedIndex(bracket_position))?;
+
+                let index = &rest[1..close];
+                segments.push(match index {
+                    "*" => Segment::AnyIndex,
+                    _ => Segment::Index(index.parse().map_err(|_| QueryError::InvalidIndex(String::from(index)))?),
+                });
+                rest = &rest[close + 1..];
+            }
+
+            position += part.len() + 1;
+        }
+
+        Ok(Self { segments })
+    }
+
+    pub fn evaluate<'a>(&self, root: &'a Tag) -> Vec<&'a Tag> {
+        let mut current = vec![root];
+
+        for segment in &self.segments {
+            current = current
+                .into_iter()
+                .flat_map(|tag| select(tag, segment))
+                .collect();
+        }
+
+        current
+    }
+
+    /// Like [`Query::evaluate`], for changing the tags in place.
+    pub fn evaluate_mut<'a>(&self, root: &'a mut Tag) -> Vec<&'a mut Tag> {
+        let mut current = vec![root];
+
+        for segment in &self.segments {
+            current = current
+                .into_iter()
+                .flat_map(|tag| select_mut(tag, segment))
+                .collect();
+        }
+
+        current
+    }
+
+    /// Deletes every tag the path reaches, returns how many were removed.
+    pub fn remove(&self, root: &mut Tag) -> usize {
+        remove_at(root, &self.segments)
+    }
+}
+
+fn remove_at(tag: &mut Tag, segments: &[Segment]) -> usize {
+    match segments {
+        [] => 0,
+        [last] => match (last, tag) {
+            (Segment::Key(key), tag @ Tag::Compound { .. }) => tag.remove_tag(key).map_or(0, |_| 1),
+            (Segment::AnyKey, Tag::Compound { value, .. }) | (Segment::AnyIndex, Tag::List { value, .. }) => {
+                value.drain(..).count()
+            }
+            (Segment::Index(index), Tag::List { value, .. }) => match resolve_index(*index, value.len()) {
+                Some(index) => {
+                    value.remove(index);
+                    1
+                }
+                None => 0,
+            },
+            _ => 0,
+        },
+        [first, rest @ ..] => select_mut(tag, first)
+            .into_iter()
+            .map(|child| remove_at(child, rest))
+            .sum(),
+    }
+}
+
+fn resolve_index(index: i64, len: usize) -> Option<usize> {
+    let resolved = if index < 0 { len as i64 + index } else { index };
+    usize::try_from(resolved).ok().filter(|index| *index < len)
+}
+
+fn select_mut<'a>(tag: &'a mut Tag, segment: &Segment) -> Vec<&'a mut Tag> {
+    match (segment, tag) {
+        (Segment::Key(key), tag @ Tag::Compound { .. }) => tag.find_tag_mut(key).into_iter().collect(),
+        (Segment::AnyKey, Tag::Compound { value, .. }) | (Segment::AnyIndex, Tag::List { value, .. }) => value.iter_mut().collect(),
+        (Segment::Index(index), Tag::List { value, .. }) => {
+            let len = value.len();
+            resolve_index(*index, len).and_then(|index| value.get_mut(index)).into_iter().collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn select<'a>(tag: &'a Tag, segment: &Segment) -> Vec<&'a Tag> {
+    match (segment, tag) {
+        (Segment::Key(key), Tag::Compound { .. }) => tag.find_tag(key).into_iter().collect(),
+        (Segment::AnyKey, Tag::Compound { value, .. }) => value.iter().collect(),
+        (Segment::AnyIndex, Tag::List { value, .. }) => value.iter().collect(),
+        (Segment::Index(index), Tag::List { value, .. }) => {
+            resolve_index(*index, value.len()).and_then(|index| value.get(index)).into_iter().collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+impl FromStr for Query {
+    type Err = QueryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (position, segment) in self.segments.iter().enumerate() {
+            match segment {
+                Segment::Key(key) if position == 0 => write!(f, "{key}")?,
+                Segment::Key(key) => write!(f, ".{key}")?,
+                Segment::AnyKey if position == 0 => write!(f, "*")?,
+                Segment::AnyKey => write!(f, ".*")?,
+                Segment::Index(index) => write!(f, "[{index}]")?,
+                Segment::AnyIndex => write!(f, "[*]")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(name: Option<&str>, value: &str) -> Tag {
+        Tag::String {
+            name: name.map(String::from),
+            value: String::from(value),
+        }
+    }
+
+    fn palette_entry(block: &str) -> Tag {
+        Tag::Compound {
+            name: None,
+            value: vec![string(Some("Name"), block)],
+        }
+    }
+
+    fn sample() -> Tag {
+        let section = |blocks: &[&str]| Tag::Compound {
+            name: None,
+            value: vec![Tag::Compound {
+                name: Some(String::from("block_states")),
+                value: vec![Tag::List {
+                    name: Some(String::from("palette")),
+                    tag_type: 10,
+                    value: blocks.iter().map(|block| palette_entry(block)).collect(),
+                }],
+            }],
+        };
+
+        Tag::Compound {
+            name: None,
+            value: vec![
+                string(Some("Status"), "minecraft:full"),
+                Tag::List {
+                    name: Some(String::from("sections")),
+                    tag_type: 10,
+                    value: vec![
+                        section(&["minecraft:stone"]),
+                        section(&["minecraft:air", "minecraft:dirt"]),
+                    ],
+                },
+            ],
+        }
+    }
+
+    fn names(tags: Vec<&Tag>) -> Vec<&str> {
+        tags.into_iter().filter_map(|tag| tag.get_string()).map(String::as_str).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let query = Query::parse("Level.Sections[2].palette[*].Name").unwrap();
+
+        assert_eq!(
+            query.segments,
+            [
+                Segment::Key(String::from("Level")),
+                Segment::Key(String::from("Sections")),
+                Segment::Index(2),
+                Segment::Key(String::from("palette")),
+                Segment::AnyIndex,
+                Segment::Key(String::from("Name")),
+            ]
+        );
+        assert_eq!(query.to_string(), "Level.Sections[2].palette[*].Name");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(Query::parse(""), Err(QueryError::Empty));
+        assert_eq!(Query::parse("a..b"), Err(QueryError::EmptyKey(2)));
+        assert_eq!(Query::parse("a[1"), Err(QueryError::UnclosedIndex(1)));
+        assert_eq!(Query::parse("a[x]"), Err(QueryError::InvalidIndex(String::from("x"))));
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let root = sample();
+
+        let all = Query::parse("sections[*].block_states.palette[*].Name").unwrap();
+        assert_eq!(names(all.evaluate(&root)), ["minecraft:stone", "minecraft:air", "minecraft:dirt"]);
+
+        let last = Query::parse("sections[-1].block_states.palette[1].Name").unwrap();
+        assert_eq!(names(last.evaluate(&root)), ["minecraft:dirt"]);
+
+        let any_key = Query::parse("*").unwrap();
+        assert_eq!(any_key.evaluate(&root).len(), 2);
+
+        let missing = Query::parse("sections[5].Name").unwrap();
+        assert!(missing.evaluate(&root).is_empty());
+    }
+
+    #[test]
+    fn test_evaluate_mut() {
+        let mut root = sample();
+
+        for tag in Query::parse("sections[*].block_states.palette[0].Name").unwrap().evaluate_mut(&mut root) {
+            *tag = string(Some("Name"), "minecraft:glass");
+        }
+
+        let all = Query::parse("sections[*].block_states.palette[*].Name").unwrap();
+        assert_eq!(names(all.evaluate(&root)), ["minecraft:glass", "minecraft:glass", "minecraft:dirt"]);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut root = sample();
+
+        let palette_names = Query::parse("sections[*].block_states.palette[*].Name").unwrap();
+        assert_eq!(Query::parse("sections[-1].block_states.palette[0]").unwrap().remove(&mut root), 1);
+        assert_eq!(names(palette_names.evaluate(&root)), ["minecraft:stone", "minecraft:dirt"]);
+
+        assert_eq!(palette_names.remove(&mut root), 2);
+        assert!(palette_names.evaluate(&root).is_empty());
+
+        assert_eq!(Query::parse("Status").unwrap().remove(&mut root), 1);
+        assert_eq!(Query::parse("Status").unwrap().remove(&mut root), 0);
+        assert_eq!(Query::parse("sections[*]").unwrap().remove(&mut root), 2);
+    }
+}
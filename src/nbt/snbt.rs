@@ -0,0 +1,132 @@
+use crate::nbt::tag::Tag;
+use std::fmt::Write;
+
+const INDENT: &str = "    ";
+
+/// Renders a tag as indented SNBT, the text format of the game's commands and `/data get`.
+/// Compounds and lists of containers are spread over lines, everything else stays inline.
+pub fn to_snbt(tag: &Tag) -> String {
+    let mut output = String::new();
+    write_value(&mut output, tag, 0);
+    output
+}
+
+fn write_value(output: &mut String, tag: &Tag, depth: usize) {
+    match tag {
+        Tag::End => {}
+        Tag::Byte { value, .. } => write!(output, "{value}b").unwrap(),
+        Tag::Short { value, .. } => write!(output, "{value}s").unwrap(),
+        Tag::Int { value, .. } => write!(output, "{value}").unwrap(),
+        Tag::Long { value, .. } => write!(output, "{value}L").unwrap(),
+        Tag::Float { value, .. } => write!(output, "{value}f").unwrap(),
+        Tag::Double { value, .. } => write!(output, "{value}d").unwrap(),
+        Tag::String { value, .. } => write_quoted(output, value),
+        Tag::ByteArray { value, .. } => write_array(output, "B", value.iter().map(|value| format!("{value}b"))),
+        Tag::IntArray { value, .. } => write_array(output, "I", value.iter().map(i32::to_string)),
+        Tag::LongArray { value, .. } => write_array(output, "L", value.iter().map(|value| format!("{value}L"))),
+        Tag::List { value, .. } if value.iter().all(|element| element.children().is_none()) => {
+            output.push('[');
+            for (index, element) in value.iter().enumerate() {
+                if index > 0 {
+                    output.push_str(", ");
+                }
+                write_value(output, element, depth);
+            }
+            output.push(']');
+        }
+        Tag::List { value, .. } => write_container(output, ('[', ']'), value, depth),
+        Tag::Compound { value, .. } => write_container(output, ('{', '}'), value, depth),
+    }
+}
+
+fn write_container(output: &mut String, (open, close): (char, char), children: &[Tag], depth: usize) {
+    output.push(open);
+    if children.is_empty() {
+        output.push(close);
+        return;
+    }
+
+    for (index, child) in children.iter().enumerate() {
+        output.push_str(if index == 0 { "\n" } else { ",\n" });
+        output.push_str(&INDENT.repeat(depth + 1));
+
+        if open == '{' {
+            write_key(output, &child.get_name().unwrap_or_default());
+            output.push_str(": ");
+        }
+        write_value(output, child, depth + 1);
+    }
+
+    output.push('\n');
+    output.push_str(&INDENT.repeat(depth));
+    output.push(close);
+}
+
+fn write_array(output: &mut String, prefix: &str, values: impl Iterator<Item = String>) {
+    let values: Vec<String> = values.collect();
+    write!(output, "[{prefix};{}]", values.join(", ")).unwrap();
+}
+
+fn write_key(output: &mut String, key: &str) {
+    let bare = !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '+'));
+
+    if bare {
+        output.push_str(key);
+    } else {
+        write_quoted(output, key);
+    }
+}
+
+fn write_quoted(output: &mut String, value: &str) {
+    output.push('"');
+    for c in value.chars() {
+        if matches!(c, '"' | '\\') {
+            output.push('\\');
+        }
+        output.push(c);
+    }
+    output.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_snbt() {
+        let root = Tag::Compound {
+            name: None,
+            value: vec![
+                Tag::Int { name: Some(String::from("DataVersion")), value: 3953 },
+                Tag::String { name: Some(String::from("Status")), value: String::from("say \"hi\"") },
+                Tag::LongArray { name: Some(String::from("minecraft:motion blocking")), value: vec![1, -2] },
+                Tag::List {
+                    name: Some(String::from("Pos")),
+                    value: vec![Tag::Double { name: None, value: 0.5 }, Tag::Double { name: None, value: 64.0 }],
+                    tag_type: 6,
+                },
+                Tag::Compound {
+                    name: Some(String::from("section")),
+                    value: vec![Tag::Byte { name: Some(String::from("Y")), value: -4 }],
+                },
+                Tag::List { name: Some(String::from("Entities")), value: Vec::new(), tag_type: 10 },
+            ],
+        };
+
+        assert_eq!(
+            to_snbt(&root),
+            concat!(
+                "{\n",
+                "    DataVersion: 3953,\n",
+                "    Status: \"say \\\"hi\\\"\",\n",
+                "    \"minecraft:motion blocking\": [L;1L, -2L],\n",
+                "    Pos: [0.5d, 64d],\n",
+                "    section: {\n",
+                "        Y: -4b\n",
+                "    },\n",
+                "    Entities: []\n",
+                "}"
+            )
+        );
+    }
+}
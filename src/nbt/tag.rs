@@ -56,6 +56,25 @@ pub enum Tag {
     },
 }
 
+pub fn tag_type_name(tag_type: u8) -> &'static str {
+    match tag_type {
+        0 => "End",
+        1 => "Byte",
+        2 => "Short",
+        3 => "Int",
+        4 => "Long",
+        5 => "Float",
+        6 => "Double",
+        7 => "ByteArray",
+        8 => "String",
+        9 => "List",
+        10 => "Compound",
+        11 => "IntArray",
+        12 => "LongArray",
+        _ => "Unknown",
+    }
+}
+
 impl Tag {
     pub fn to_bytes(&self) -> Vec<u8> {
         self.to_bytes_tag(false, false)
@@ -92,7 +111,81 @@ impl Tag {
         }
     }
 
-    fn get_tag_type(&self) -> u8 {
+    pub fn find_tag_mut(&mut self, name: &str) -> Option<&mut Tag> {
+        match self {
+            Self::Compound { value, .. } => value
+                .iter_mut()
+                .find(|v| v.get_name().is_some_and(|v| v == name)),
+            _ => None,
+        }
+    }
+
+    /// Removes a compound child by name and returns it.
+    pub fn remove_tag(&mut self, name: &str) -> Option<Tag> {
+        match self {
+            Self::Compound { value, .. } => {
+                let index = value.iter().position(|v| v.get_name().is_some_and(|v| v == name))?;
+                Some(value.remove(index))
+            }
+            _ => None,
+        }
+    }
+
+    /// Adds a child to a compound, replacing any child of the same name. Does nothing on other tags.
+    pub fn insert_tag(&mut self, tag: Tag) {
+        let name = tag.get_name();
+        if let Self::Compound { value, .. } = self {
+            match value.iter_mut().find(|v| v.get_name() == name) {
+                Some(existing) => *existing = tag,
+                None => value.push(tag),
+            }
+        }
+    }
+
+    /// Renders a primitive tag's value the way it would be typed on a command line.
+    pub fn value_to_string(&self) -> Option<String> {
+        match self {
+            Tag::Byte { value, .. } => Some(value.to_string()),
+            Tag::Short { value, .. } => Some(value.to_string()),
+            Tag::Int { value, .. } => Some(value.to_string()),
+            Tag::Long { value, .. } => Some(value.to_string()),
+            Tag::Float { value, .. } => Some(value.to_string()),
+            Tag::Double { value, .. } => Some(value.to_string()),
+            Tag::String { value, .. } => Some(value.clone()),
+            _ => None,
+        }
+    }
+
+    /// The tag type for display, including the element type of lists, e.g. `List of Compound`.
+    pub fn type_description(&self) -> String {
+        match self {
+            Tag::List { tag_type, .. } => format!("List of {}", tag_type_name(*tag_type)),
+            _ => String::from(tag_type_name(self.get_tag_type())),
+        }
+    }
+
+    /// A one line rendering of the value: primitives in full, containers by their size.
+    pub fn value_summary(&self) -> String {
+        match self {
+            Tag::String { value, .. } => format!("{value:?}"),
+            Tag::ByteArray { value, .. } => format!("{} values", value.len()),
+            Tag::IntArray { value, .. } => format!("{} values", value.len()),
+            Tag::LongArray { value, .. } => format!("{} values", value.len()),
+            Tag::List { value, .. } => format!("{} entries", value.len()),
+            Tag::Compound { value, .. } => format!("{} entries", value.len()),
+            _ => self.value_to_string().unwrap_or_default(),
+        }
+    }
+
+    pub fn children(&self) -> Option<&[Tag]> {
+        match self {
+            Tag::List { value, .. } => Some(value),
+            Tag::Compound { value, .. } => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn get_tag_type(&self) -> u8 {
         match self {
             Tag::End => 0,
             Tag::Byte { .. } => 1,
@@ -110,7 +203,7 @@ impl Tag {
         }
     }
 
-    fn get_name(&self) -> Option<String> {
+    pub fn get_name(&self) -> Option<String> {
         match self {
             Tag::End => None,
             Tag::Byte { name, .. } => name.clone(),
@@ -128,6 +221,24 @@ impl Tag {
         }
     }
 
+    pub fn set_name(&mut self, new_name: Option<String>) {
+        match self {
+            Tag::End => {}
+            Tag::Byte { name, .. }
+            | Tag::Short { name, .. }
+            | Tag::Int { name, .. }
+            | Tag::Long { name, .. }
+            | Tag::Float { name, .. }
+            | Tag::Double { name, .. }
+            | Tag::ByteArray { name, .. }
+            | Tag::String { name, .. }
+            | Tag::List { name, .. }
+            | Tag::Compound { name, .. }
+            | Tag::IntArray { name, .. }
+            | Tag::LongArray { name, .. } => *name = new_name,
+        }
+    }
+
     fn serialize_name(&self) -> Vec<u8> {
         match self.get_name() {
             None => Vec::from([0, 0]),
@@ -176,7 +287,11 @@ impl Tag {
             Tag::List {
                 value, tag_type, ..
             } => {
-                let mut serialized_value: Vec<u8> = Vec::from([*tag_type]);
+                // the elements decide, a stale tag_type would make the list unreadable
+                let element_type = value.first().map_or(*tag_type, Tag::get_tag_type);
+                // End elements have no payload to read back, such lists are written empty
+                let value = if element_type == 0 { &[][..] } else { value.as_slice() };
+                let mut serialized_value: Vec<u8> = Vec::from([element_type]);
                 let size_bytes = size_to_i32_bytes(value.len());
                 serialized_value.extend_from_slice(&size_bytes);
                 for next_tag in value {
@@ -1,3 +1,5 @@
+use crate::nbt::mutf8;
+
 fn size_to_u16_bytes(size: usize) -> [u8; 2] {
     (size as u16).to_be_bytes()
 }
@@ -12,11 +14,18 @@ fn create_initial_buffer<T>(size: usize) -> Vec<u8> {
     buffer
 }
 
+/// Writes a length prefixed Modified UTF-8 string. Strings longer than the u16 length allows are
+/// cut at the last character that fits, where Java would refuse to write them.
 pub fn write_string(input: String) -> Vec<u8> {
-    let input_bytes = input.as_bytes();
+    let mut input_bytes = mutf8::encode(&input);
+    if input_bytes.len() > u16::MAX as usize {
+        let end = (0..=u16::MAX as usize).rev().find(|index| input_bytes[*index] & 0xC0 != 0x80).unwrap();
+        input_bytes.to_mut().truncate(end);
+    }
+
     let mut buffer = Vec::with_capacity(input_bytes.len() + 2);
-    buffer.extend_from_slice(&size_to_u16_bytes(input.len()));
-    buffer.extend(input_bytes);
+    buffer.extend_from_slice(&size_to_u16_bytes(input_bytes.len()));
+    buffer.extend_from_slice(&input_bytes);
     buffer
 }
 
@@ -47,6 +56,16 @@ mod tests {
         let parsed = write_string("HELLO".to_string());
 
         assert_eq!(parsed, &[0, 5, 72, 69, 76, 76, 79]);
+        assert_eq!(write_string("\0".to_string()), &[0, 2, 0xC0, 0x80]);
+    }
+
+    #[test]
+    fn test_write_long_string() {
+        // 2 bytes per character, the cut must not split one
+        let parsed = write_string("ß".repeat(40000));
+
+        assert_eq!(parsed[0..2], [0xFF, 0xFE]);
+        assert_eq!(parsed.len(), 2 + 65534);
     }
 
     #[test]
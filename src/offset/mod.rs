@@ -0,0 +1,199 @@
+use crate::chunk::Chunk;
+use crate::nbt::tag::Tag;
+use crate::region_file::{read_region_file, region_coords_from_path};
+use crate::{folder_name, scan_region_files, RegionType};
+use clap::Args;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Args)]
+pub struct OffsetArgs {
+    /// Path to the Minecraft world to shift, it is left untouched
+    pub world_path: PathBuf,
+
+    /// Where to write the shifted region, entities and poi folders
+    pub output_path: PathBuf,
+
+    /// Number of regions to shift by along x and z, e.g. `2,-1` moves everything 1024 blocks east and 512 north
+    #[arg(long, required = true, value_parser = crate::parse_chunk_coords, allow_hyphen_values = true)]
+    pub regions: (i32, i32),
+
+    /// Compression level when writing region files
+    #[arg(short, long, default_value = "6", value_parser = crate::validate_compression_level)]
+    pub compression_level: u32,
+}
+
+/// Lists of compounds with block coordinates in `x`/`y`/`z` int fields.
+const BLOCK_POSITION_LISTS: [&str; 6] = ["block_entities", "TileEntities", "block_ticks", "fluid_ticks", "TileTicks", "LiquidTicks"];
+
+fn shift_int(tag: &mut Tag, name: &str, delta: i32) {
+    if let Some(Tag::Int { value, .. }) = tag.find_tag_mut(name) {
+        *value += delta;
+    }
+}
+
+/// Shifts an entity's `Pos` and those of its passengers.
+fn shift_entity(entity: &mut Tag, dx: i32, dz: i32) {
+    if let Some(Tag::List { value, .. }) = entity.find_tag_mut("Pos")
+        && let [Tag::Double { value: x, .. }, _, Tag::Double { value: z, .. }] = value.as_mut_slice()
+    {
+        *x += dx as f64;
+        *z += dz as f64;
+    }
+
+    if let Some(Tag::List { value, .. }) = entity.find_tag_mut("Passengers") {
+        for passenger in value {
+            shift_entity(passenger, dx, dz);
+        }
+    }
+}
+
+/// Moves a chunk's NBT by a number of chunks: its recorded position, entity positions, block
+/// entities, scheduled ticks and poi records. Other stored coordinates, such as structure
+/// references or entity memories, are left as they are.
+pub fn offset_chunk(chunk: &mut Chunk, chunks_x: i32, chunks_z: i32) {
+    if let Some((x, z)) = chunk.nbt_position() {
+        chunk.set_nbt_position(x + chunks_x, z + chunks_z);
+    }
+
+    let (dx, dz) = (chunks_x * 16, chunks_z * 16);
+    let fields = chunk.fields_mut();
+
+    if let Some(Tag::List { value, .. }) = fields.find_tag_mut("Entities") {
+        for entity in value {
+            shift_entity(entity, dx, dz);
+        }
+    }
+
+    for list in BLOCK_POSITION_LISTS {
+        if let Some(Tag::List { value, .. }) = fields.find_tag_mut(list) {
+            for element in value {
+                shift_int(element, "x", dx);
+                shift_int(element, "z", dz);
+            }
+        }
+    }
+
+    // poi chunks keep their records per section, keyed by section Y
+    if let Some(Tag::Compound { value: sections, .. }) = fields.find_tag_mut("Sections") {
+        for section in sections {
+            let Some(Tag::List { value: records, .. }) = section.find_tag_mut("Records") else {
+                continue;
+            };
+            for record in records {
+                if let Some(Tag::IntArray { value, .. }) = record.find_tag_mut("pos")
+                    && value.len() == 3
+                {
+                    value[0] += dx;
+                    value[2] += dz;
+                }
+            }
+        }
+    }
+}
+
+fn offset_file(region_file: &Path, output_folder: &Path, args: &OffsetArgs) -> Result<usize, Box<dyn Error>> {
+    let (region_x, region_z) = region_coords_from_path(region_file).ok_or("File name is not r.<x>.<z>.<ext>")?;
+    let (format, mut region) = read_region_file(region_file)?;
+    let (regions_x, regions_z) = args.regions;
+
+    for chunk in region.chunks_mut() {
+        let (x, z) = chunk.global_position(region_x, region_z);
+        chunk.set_position(x + regions_x * 32, z + regions_z * 32);
+        offset_chunk(chunk, regions_x * 32, regions_z * 32);
+    }
+
+    let output = output_folder.join(format!("r.{}.{}.{}", region_x + regions_x, region_z + regions_z, format.extension()));
+    fs::write(output, region.to_bytes(format, Some((region_x + regions_x, region_z + regions_z)), region.timestamp(), args.compression_level as u8)?)?;
+
+    Ok(region.chunks().len())
+}
+
+pub fn run_offset(args: &OffsetArgs) -> Result<(), Box<dyn Error>> {
+    let mut total = 0;
+
+    for region_type in [RegionType::REGION, RegionType::ENTITIES, RegionType::POI] {
+        let region_files: Vec<PathBuf> = scan_region_files(args.world_path.join(folder_name(region_type)))
+            .into_iter()
+            .filter(|path| region_coords_from_path(path).is_some())
+            .collect();
+        if region_files.is_empty() {
+            continue;
+        }
+
+        let output_folder = args.output_path.join(folder_name(region_type));
+        fs::create_dir_all(&output_folder)?;
+
+        let shifted: usize = region_files
+            .par_iter()
+            .map(|region_file| match offset_file(region_file, &output_folder, args) {
+                Ok(chunks) => chunks,
+                Err(err) => {
+                    eprintln!("Failed to offset file {} !, error : {}", region_file.display(), err);
+                    0
+                }
+            })
+            .sum();
+
+        println!("{}: {} files, {} chunks shifted", folder_name(region_type), region_files.len(), shifted);
+        total += shifted;
+    }
+
+    let (regions_x, regions_z) = args.regions;
+    println!("{total} chunks shifted by {} blocks along x and {} blocks along z", regions_x * 512, regions_z * 512);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn int(name: &str, value: i32) -> Tag {
+        Tag::Int { name: Some(String::from(name)), value }
+    }
+
+    fn list(name: &str, value: Vec<Tag>, tag_type: u8) -> Tag {
+        Tag::List { name: Some(String::from(name)), value, tag_type }
+    }
+
+    fn entity(x: f64, z: f64, passengers: Vec<Tag>) -> Tag {
+        let pos = [x, 64.0, z].map(|value| Tag::Double { name: None, value });
+        Tag::Compound { name: None, value: vec![list("Pos", pos.to_vec(), 6), list("Passengers", passengers, 10)] }
+    }
+
+    fn pos(tag: &Tag) -> Vec<f64> {
+        let Some(Tag::List { value, .. }) = tag.find_tag("Pos") else { panic!("no Pos") };
+        value.iter().map(|tag| if let Tag::Double { value, .. } = tag { *value } else { panic!() }).collect()
+    }
+
+    #[test]
+    fn test_offset_chunk() {
+        let block_entity = Tag::Compound { name: None, value: vec![int("x", 5), int("y", 70), int("z", -3)] };
+        let data = Tag::Compound {
+            name: None,
+            value: vec![
+                int("xPos", 0),
+                int("zPos", -1),
+                list("Entities", vec![entity(1.5, -2.5, vec![entity(1.5, -2.5, Vec::new())])], 10),
+                list("block_entities", vec![block_entity], 10),
+            ],
+        };
+        let mut chunk = Chunk::new_from_block_pos(0, 31, 0, data);
+
+        offset_chunk(&mut chunk, 32, -64);
+
+        assert_eq!(chunk.nbt_position(), Some((32, -65)));
+
+        let entity = &chunk.find_field("Entities").and_then(|list| list.children()).unwrap()[0];
+        assert_eq!(pos(entity), [513.5, 64.0, -1026.5]);
+        assert_eq!(pos(&entity.find_tag("Passengers").and_then(|list| list.children()).unwrap()[0]), [513.5, 64.0, -1026.5]);
+
+        let block_entity = &chunk.find_field("block_entities").and_then(|list| list.children()).unwrap()[0];
+        assert_eq!(block_entity.find_tag("x").and_then(|tag| tag.get_int()), Some(&517));
+        assert_eq!(block_entity.find_tag("y").and_then(|tag| tag.get_int()), Some(&70));
+        assert_eq!(block_entity.find_tag("z").and_then(|tag| tag.get_int()), Some(&-1027));
+    }
+}
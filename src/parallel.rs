@@ -0,0 +1,63 @@
+//! Splitting the encoding of one region file across threads. Off by default, so a program
+//! converting many files at once keeps each file on one thread. Once fewer files are left than
+//! threads, the converter turns it on and the buckets and chunks of the files still being written
+//! spread over the idle threads.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static INTRA_FILE: AtomicBool = AtomicBool::new(false);
+
+/// Turns splitting single files across threads on or off, for every thread.
+pub fn set_intra_file(enabled: bool) {
+    INTRA_FILE.store(enabled, Ordering::Relaxed);
+}
+
+pub fn intra_file() -> bool {
+    INTRA_FILE.load(Ordering::Relaxed)
+}
+
+/// Maps the items in order on this thread, handing the rest to the thread pool once intra-file
+/// parallelism is turned on. Timings of work done on other threads are added to this one's.
+pub(crate) fn map<T: Send, R: Send>(items: Vec<T>, f: impl Fn(T) -> R + Sync) -> Vec<R> {
+    let mut results = Vec::with_capacity(items.len());
+    let mut items = items.into_iter();
+
+    while cfg!(target_arch = "wasm32") || !intra_file() {
+        match items.next() {
+            Some(item) => results.push(f(item)),
+            None => return results,
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        use crate::timings;
+        use rayon::prelude::*;
+
+        let rest: Vec<(R, timings::PhaseTimings)> = items.collect::<Vec<T>>().into_par_iter().map(|item| timings::isolated(|| f(item))).collect();
+        for (result, spent) in rest {
+            results.push(result);
+            timings::add(spent);
+        }
+    }
+
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_keeps_order() {
+        let items: Vec<usize> = (0..100).collect();
+        let sequential = map(items.clone(), |item| item * 2);
+
+        set_intra_file(true);
+        let parallel = map(items, |item| item * 2);
+        set_intra_file(false);
+
+        assert_eq!(sequential, parallel);
+        assert_eq!(parallel[99], 198);
+    }
+}
@@ -0,0 +1,77 @@
+use std::io;
+
+/// CPUs the process may run on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CpuSet(pub Vec<usize>);
+
+/// Parses a CPU set given as a hex mask (`0xf0`) or a list of CPUs and ranges (`4-7,10`).
+pub fn parse_cpu_set(s: &str) -> Result<CpuSet, String> {
+    let mut cpus = Vec::new();
+
+    if let Some(mask) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        let mask = u128::from_str_radix(mask, 16).map_err(|_| format!("Invalid CPU mask {s}"))?;
+        cpus.extend((0..128).filter(|cpu| mask & (1 << cpu) != 0));
+    } else {
+        for part in s.split(',') {
+            let parse = |cpu: &str| cpu.trim().parse::<usize>().map_err(|_| format!("Invalid CPU {cpu} in {s}"));
+            match part.split_once('-') {
+                Some((first, last)) => cpus.extend(parse(first)?..=parse(last)?),
+                None => cpus.push(parse(part)?),
+            }
+        }
+    }
+
+    cpus.sort_unstable();
+    cpus.dedup();
+    if cpus.is_empty() {
+        return Err(format!("CPU set {s} is empty"));
+    }
+
+    Ok(CpuSet(cpus))
+}
+
+/// Lowers (or with privileges raises) the scheduling priority of the process, -20 to 19.
+#[cfg(unix)]
+pub fn set_nice(level: i32) -> io::Result<()> {
+    let result = unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, level) };
+    if result == -1 { Err(io::Error::last_os_error()) } else { Ok(()) }
+}
+
+#[cfg(not(unix))]
+pub fn set_nice(_level: i32) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "--nice is only supported on Unix"))
+}
+
+/// Confines the calling thread, and the threads it starts afterwards, to the given CPUs.
+#[cfg(target_os = "linux")]
+pub fn set_cpu_set(cpus: &CpuSet) -> io::Result<()> {
+    let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    for cpu in &cpus.0 {
+        if *cpu >= libc::CPU_SETSIZE as usize {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("CPU {cpu} is out of range")));
+        }
+        unsafe { libc::CPU_SET(*cpu, &mut set) };
+    }
+
+    let result = unsafe { libc::sched_setaffinity(0, size_of::<libc::cpu_set_t>(), &set) };
+    if result == -1 { Err(io::Error::last_os_error()) } else { Ok(()) }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn set_cpu_set(_cpus: &CpuSet) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "--cpu-set is only supported on Linux"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cpu_set() {
+        assert_eq!(parse_cpu_set("4-7,10").unwrap().0, [4, 5, 6, 7, 10]);
+        assert_eq!(parse_cpu_set("0xf0").unwrap().0, [4, 5, 6, 7]);
+        assert_eq!(parse_cpu_set("3,1,3").unwrap().0, [1, 3]);
+        assert!(parse_cpu_set("0x0").is_err());
+        assert!(parse_cpu_set("two").is_err());
+    }
+}
@@ -0,0 +1,158 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Input files of one region type folder, counted separately in the progress lines and the
+/// final summary.
+struct Group {
+    name: String,
+    total_files: usize,
+    done_files: AtomicUsize,
+    failed_files: AtomicUsize,
+}
+
+/// Tracks how many input files and bytes a conversion run got through, and prints the rate and
+/// estimated time remaining at most once per interval.
+pub struct Progress {
+    groups: Vec<Group>,
+    total_files: usize,
+    total_bytes: u64,
+    done_files: AtomicUsize,
+    done_bytes: AtomicU64,
+    started: Instant,
+    interval: Duration,
+    last_report: Mutex<Instant>,
+}
+
+impl Progress {
+    /// Progress over the given groups of input files, sized from their metadata.
+    pub fn new(groups: &[(String, &[PathBuf])], interval: Duration) -> Self {
+        let started = Instant::now();
+        let files = || groups.iter().flat_map(|(_, files)| files.iter());
+
+        Self {
+            groups: groups
+                .iter()
+                .map(|(name, files)| Group { name: name.clone(), total_files: files.len(), done_files: AtomicUsize::new(0), failed_files: AtomicUsize::new(0) })
+                .collect(),
+            total_files: files().count(),
+            total_bytes: files().filter_map(|file| file.metadata().ok()).map(|metadata| metadata.len()).sum(),
+            done_files: AtomicUsize::new(0),
+            done_bytes: AtomicU64::new(0),
+            started,
+            interval,
+            last_report: Mutex::new(started),
+        }
+    }
+
+    /// Counts one finished input file of the group at `group`, printing a progress line if the
+    /// interval has passed since the last one.
+    pub fn file_done(&self, group: usize, file: &Path, converted: bool) {
+        self.groups[group].done_files.fetch_add(1, Ordering::Relaxed);
+        if !converted {
+            self.groups[group].failed_files.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let bytes = file.metadata().map(|metadata| metadata.len()).unwrap_or_default();
+        let done_files = self.done_files.fetch_add(1, Ordering::Relaxed) + 1;
+        let done_bytes = self.done_bytes.fetch_add(bytes, Ordering::Relaxed) + bytes;
+
+        if self.interval.is_zero() {
+            return;
+        }
+
+        let mut last_report = self.last_report.lock().unwrap();
+        if last_report.elapsed() < self.interval {
+            return;
+        }
+        *last_report = Instant::now();
+
+        println!("{}", self.line(done_files, done_bytes, self.started.elapsed()));
+    }
+
+    /// Prints the final rate, unless progress reporting is disabled, and how many files of each
+    /// group failed when there is more than one.
+    pub fn finish(&self) {
+        if !self.interval.is_zero() {
+            println!("{}", self.line(self.done_files.load(Ordering::Relaxed), self.done_bytes.load(Ordering::Relaxed), self.started.elapsed()));
+        }
+
+        if self.groups.len() > 1 {
+            for group in &self.groups {
+                let failed = group.failed_files.load(Ordering::Relaxed);
+                println!("{}: {} of {} files converted, {} failed", group.name, group.total_files - failed, group.total_files, failed);
+            }
+        }
+    }
+
+    fn line(&self, done_files: usize, done_bytes: u64, elapsed: Duration) -> String {
+        let rate = done_bytes as f64 / elapsed.as_secs_f64().max(f64::EPSILON);
+        let eta = match done_bytes {
+            0 => String::from("unknown"),
+            _ => format_duration(Duration::from_secs_f64(self.total_bytes.saturating_sub(done_bytes) as f64 / rate)),
+        };
+
+        let breakdown = match self.groups.len() {
+            0 | 1 => String::new(),
+            _ => {
+                let groups: Vec<String> = self.groups.iter().map(|group| format!("{} {}/{}", group.name, group.done_files.load(Ordering::Relaxed), group.total_files)).collect();
+                format!(" ({})", groups.join(", "))
+            }
+        };
+
+        format!(
+            "Progress: {}/{} files{}, {:.1} of {:.1} MB, {:.1} MB/s, {:.1} files/s, ETA {}",
+            done_files,
+            self.total_files,
+            breakdown,
+            done_bytes as f64 / 1_000_000.0,
+            self.total_bytes as f64 / 1_000_000.0,
+            rate / 1_000_000.0,
+            done_files as f64 / elapsed.as_secs_f64().max(f64::EPSILON),
+            eta,
+        )
+    }
+}
+
+/// `h:mm:ss`
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line() {
+        let group = |name: &str, total_files, done_files| Group {
+            name: String::from(name),
+            total_files,
+            done_files: AtomicUsize::new(done_files),
+            failed_files: AtomicUsize::new(0),
+        };
+        let mut progress = Progress {
+            groups: vec![group("region", 10, 2)],
+            total_files: 10,
+            total_bytes: 50_000_000,
+            done_files: AtomicUsize::new(0),
+            done_bytes: AtomicU64::new(0),
+            started: Instant::now(),
+            interval: Duration::ZERO,
+            last_report: Mutex::new(Instant::now()),
+        };
+
+        assert_eq!(
+            progress.line(2, 10_000_000, Duration::from_secs(4)),
+            "Progress: 2/10 files, 10.0 of 50.0 MB, 2.5 MB/s, 0.5 files/s, ETA 0:00:16"
+        );
+        assert!(progress.line(0, 0, Duration::from_secs(1)).ends_with("ETA unknown"));
+
+        progress.groups = vec![group("region", 6, 2), group("poi", 4, 0)];
+        assert!(progress.line(2, 10_000_000, Duration::from_secs(4)).starts_with("Progress: 2/10 files (region 2/6, poi 0/4), 10.0 of"));
+
+        assert_eq!(format_duration(Duration::from_secs(2 * 3600 + 61)), "2:01:01");
+    }
+}
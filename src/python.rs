@@ -0,0 +1,192 @@
+//! Python bindings, built with the `python` feature, e.g. through `maturin develop --features python`.
+//!
+//! Chunk NBT is handed to Python as plain values: compounds become dicts, lists and arrays become
+//! lists, numbers become ints and floats.
+
+use crate::nbt::tag::Tag;
+use crate::region_file::{convert_region_file, read_region_file, region_coords_from_path, Region, RegionFormat};
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict, PyList};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+fn tag_to_py<'py>(py: Python<'py>, tag: &Tag) -> PyResult<Bound<'py, PyAny>> {
+    Ok(match tag {
+        Tag::End => py.None().into_bound(py),
+        Tag::Byte { value, .. } => value.into_pyobject(py)?.into_any(),
+        Tag::Short { value, .. } => value.into_pyobject(py)?.into_any(),
+        Tag::Int { value, .. } => value.into_pyobject(py)?.into_any(),
+        Tag::Long { value, .. } => value.into_pyobject(py)?.into_any(),
+        Tag::Float { value, .. } => value.into_pyobject(py)?.into_any(),
+        Tag::Double { value, .. } => value.into_pyobject(py)?.into_any(),
+        Tag::String { value, .. } => value.into_pyobject(py)?.into_any(),
+        Tag::ByteArray { value, .. } => PyList::new(py, value)?.into_any(),
+        Tag::IntArray { value, .. } => PyList::new(py, value)?.into_any(),
+        Tag::LongArray { value, .. } => PyList::new(py, value)?.into_any(),
+        Tag::List { value, .. } => {
+            let elements = value.iter().map(|element| tag_to_py(py, element)).collect::<PyResult<Vec<_>>>()?;
+            PyList::new(py, elements)?.into_any()
+        }
+        Tag::Compound { value, .. } => {
+            let dict = PyDict::new(py);
+            for child in value {
+                dict.set_item(child.get_name().unwrap_or_default(), tag_to_py(py, child)?)?;
+            }
+            dict.into_any()
+        }
+    })
+}
+
+fn parse_format(format: &str) -> PyResult<RegionFormat> {
+    RegionFormat::from_extension(format).ok_or_else(|| PyValueError::new_err(format!("Unknown region format {format}, expected mca, linear or blinear")))
+}
+
+/// A parsed region file.
+#[pyclass(name = "Region", frozen)]
+struct PyRegion {
+    region: Arc<Region>,
+    /// Region coordinates of the file name the region was read from
+    region_coords: Option<(i32, i32)>,
+}
+
+/// One chunk of a region.
+#[pyclass(name = "Chunk", frozen)]
+struct PyChunk {
+    region: Arc<Region>,
+    index: usize,
+}
+
+#[pymethods]
+impl PyRegion {
+    /// Reads a region file in any supported format.
+    #[staticmethod]
+    fn open(path: PathBuf) -> PyResult<Self> {
+        let (_, region) = read_region_file(&path).map_err(|err| PyIOError::new_err(err.to_string()))?;
+        Ok(Self { region: Arc::new(region), region_coords: region_coords_from_path(&path) })
+    }
+
+    /// Parses region file bytes of the given format (`mca`, `linear` or `blinear`).
+    #[staticmethod]
+    fn from_bytes(format: &str, data: &[u8]) -> PyResult<Self> {
+        let region = Region::from_bytes(parse_format(format)?, data).map_err(|err| PyValueError::new_err(err.to_string()))?;
+        Ok(Self { region: Arc::new(region), region_coords: None })
+    }
+
+    #[getter]
+    fn timestamp(&self) -> i64 {
+        self.region.timestamp()
+    }
+
+    fn __len__(&self) -> usize {
+        self.region.chunks().len()
+    }
+
+    fn chunks(&self) -> Vec<PyChunk> {
+        (0..self.region.chunks().len())
+            .map(|index| PyChunk { region: Arc::clone(&self.region), index })
+            .collect()
+    }
+
+    /// The chunk in the slot of the given chunk coordinates, or None.
+    fn chunk(&self, x: i32, z: i32) -> Option<PyChunk> {
+        let sector_index = (x & 31) + ((z & 31) << 5);
+        let index = self.region.chunks().iter().position(|chunk| chunk.position_to_sector_index() == sector_index)?;
+
+        Some(PyChunk { region: Arc::clone(&self.region), index })
+    }
+
+    /// Serializes the region. Linear files record the region coordinates, `region` as `(x, z)`
+    /// defaults to those of the file name the region was opened from.
+    #[pyo3(signature = (format, compression_level = 6, region = None))]
+    fn to_bytes<'py>(&self, py: Python<'py>, format: &str, compression_level: u8, region: Option<(i32, i32)>) -> PyResult<Bound<'py, PyBytes>> {
+        let bytes = self
+            .region
+            .to_bytes(parse_format(format)?, region.or(self.region_coords), self.region.timestamp(), compression_level)
+            .map_err(|err| PyValueError::new_err(err.to_string()))?;
+
+        Ok(PyBytes::new(py, &bytes))
+    }
+}
+
+#[pymethods]
+impl PyChunk {
+    /// Chunk x, region local for Anvil and blinear files, global for linear files.
+    #[getter]
+    fn x(&self) -> i32 {
+        self.region.chunks()[self.index].x()
+    }
+
+    #[getter]
+    fn z(&self) -> i32 {
+        self.region.chunks()[self.index].z()
+    }
+
+    #[getter]
+    fn timestamp(&self) -> i64 {
+        self.region.chunks()[self.index].timestamp()
+    }
+
+    #[getter]
+    fn data_version(&self) -> Option<i32> {
+        self.region.chunks()[self.index].data_version()
+    }
+
+    /// The chunk NBT as nested dicts and lists.
+    #[getter]
+    fn nbt<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        tag_to_py(py, self.region.chunks()[self.index].get_data())
+    }
+
+    /// The uncompressed binary NBT of the chunk.
+    fn raw_bytes<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.region.chunks()[self.index].to_raw_bytes())
+    }
+}
+
+/// Converts a region file into the format named by the output file's extension.
+#[pyfunction]
+#[pyo3(signature = (input, output, compression_level = 6))]
+fn convert(input: PathBuf, output: PathBuf, compression_level: u8) -> PyResult<()> {
+    convert_region_file(&input, &output, compression_level).map_err(|err| PyIOError::new_err(err.to_string()))
+}
+
+#[pymodule]
+fn bufferedlinear_tools(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyRegion>()?;
+    module.add_class::<PyChunk>()?;
+    module.add_function(wrap_pyfunction!(convert, module)?)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tag_to_py() {
+        let tag = Tag::Compound {
+            name: None,
+            value: vec![
+                Tag::Int { name: Some(String::from("DataVersion")), value: 3953 },
+                Tag::List {
+                    name: Some(String::from("Pos")),
+                    value: vec![Tag::Double { name: None, value: 0.5 }],
+                    tag_type: 6,
+                },
+                Tag::LongArray { name: Some(String::from("heights")), value: vec![1, 2] },
+            ],
+        };
+
+        Python::initialize();
+        Python::attach(|py| {
+            let value = tag_to_py(py, &tag).unwrap();
+            let dict = value.cast::<PyDict>().unwrap();
+
+            assert_eq!(dict.get_item("DataVersion").unwrap().unwrap().extract::<i32>().unwrap(), 3953);
+            assert_eq!(dict.get_item("Pos").unwrap().unwrap().extract::<Vec<f64>>().unwrap(), [0.5]);
+            assert_eq!(dict.get_item("heights").unwrap().unwrap().extract::<Vec<i64>>().unwrap(), [1, 2]);
+        });
+    }
+}
@@ -1,10 +1,76 @@
 use crate::chunk::Chunk;
-use crate::nbt::binary_reader::BinaryReader;
-use crate::nbt::parse::parse_tag;
+use crate::nbt::parse::{NbtError, ParseOptions};
+use crate::parallel;
 use crate::region_file::ParseError::VersionError;
+use crate::timings::{time, Phase};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+#[cfg(not(target_arch = "wasm32"))]
+use std::error::Error;
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs::{read, File};
 use std::hash::Hasher;
+use std::io::{Read, Write};
+use std::ops::RangeInclusive;
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::{Seek, SeekFrom};
+use std::path::Path;
 use thiserror::Error;
-use twox_hash::XxHash32;
+use twox_hash::{XxHash32, XxHash64};
+
+mod chunk_index;
+mod salvage;
+pub use chunk_index::{chunk_index_path, ChunkIndex, ChunkIndexEntry, CHUNK_INDEX_EXTENSION};
+pub use salvage::{salvage, Damage, Salvaged};
+
+const LINEAR_FILE_HEAD: u64 = 0xc3ff13183cca9d9a;
+/// Linear v1 files carry version 1 or 2, both with the same layout.
+const LINEAR_V1_VERSIONS: [u8; 2] = [0x01, 0x02];
+const LINEAR_V2: u8 = 0x03;
+const LINEAR_READ_VERSIONS: [u8; 3] = [LINEAR_V1_VERSIONS[0], LINEAR_V1_VERSIONS[1], LINEAR_V2];
+/// Grid sizes tiling a region into square buckets of whole chunks
+pub const LINEAR_GRID_SIZES: [u8; 6] = [1, 2, 4, 8, 16, 32];
+pub const LINEAR_DEFAULT_GRID_SIZE: u8 = 8;
+const BLINEAR_FILE_HEAD: i64 = -0x200812250269;
+const BLINEAR_HEADER_SIZE: usize = 18;
+const BLINEAR_V2: u8 = 0x02;
+/// Chunks are grouped into grid buckets, each compressed on its own behind an index of them
+const BLINEAR_V3: u8 = 0x03;
+const BLINEAR_VERSIONS: [u8; 2] = [BLINEAR_V2, BLINEAR_V3];
+/// A zstd skippable frame magic, marks the chunk frame index of seekable blinear files
+const BLINEAR_SEEK_INDEX_MAGIC: u32 = 0x184D2A5B;
+const BLINEAR_SEEK_INDEX_SIZE: usize = 1024 * 8;
+/// Format flags live in the high nibble of the blinear version byte, so v2 readers that predate
+/// them reject flagged files instead of misreading them
+const BLINEAR_VERSION_MASK: u8 = 0x0F;
+/// Chunks are stored along a Hilbert curve over the region instead of in slot order
+const BLINEAR_FLAG_HILBERT_ORDER: u8 = 0x10;
+/// Experimental: chunk data starts with a `DELTA_*` kind byte and may be stored as the XOR
+/// against the chunk west or north of it
+const BLINEAR_FLAG_NEIGHBOR_DELTA: u8 = 0x20;
+/// The zstd data was compressed with the world's shared dictionary, stored next to the region
+/// files as [`BLINEAR_DICTIONARY_FILE`]
+const BLINEAR_FLAG_DICTIONARY: u8 = 0x40;
+/// A skippable frame holding the linear v2 NBT features of the region follows the header
+const BLINEAR_FLAG_FEATURES: u8 = 0x80;
+const BLINEAR_KNOWN_FLAGS: u8 = BLINEAR_FLAG_HILBERT_ORDER | BLINEAR_FLAG_NEIGHBOR_DELTA | BLINEAR_FLAG_DICTIONARY | BLINEAR_FLAG_FEATURES;
+/// The bucketed v3 layout has no room for the slot order and delta flags
+const BLINEAR_V3_KNOWN_FLAGS: u8 = BLINEAR_FLAG_DICTIONARY | BLINEAR_FLAG_FEATURES;
+/// A zstd skippable frame magic, marks the frame of NBT features in blinear files
+const BLINEAR_FEATURES_MAGIC: u32 = 0x184D2A5C;
+/// File name of the shared zstd dictionary, in the folder of the region files using it
+pub const BLINEAR_DICTIONARY_FILE: &str = "world.dict";
+/// Anvil chunk compression ids, written chunks are always zlib compressed
+const MCA_GZIP: u8 = 1;
+const MCA_ZLIB: u8 = 2;
+const MCA_UNCOMPRESSED: u8 = 3;
+const MAX_ZLIB_LEVEL: u8 = 9;
+const MAX_ZSTD_LEVEL: u8 = 22;
+const MCA_COMPRESSIONS: [(u8, &str); 3] = [(MCA_GZIP, "gzip"), (MCA_ZLIB, "zlib"), (MCA_UNCOMPRESSED, "uncompressed")];
+const DELTA_RAW: u8 = 0;
+const DELTA_WEST: u8 = 1;
+const DELTA_NORTH: u8 = 2;
 
 #[derive(Error, Debug)]
 pub enum ParseError {
@@ -13,241 +79,2148 @@ pub enum ParseError {
     #[error("Invalid file header!")]
     HeaderError,
     #[error("Target version is not supported!")]
-    VersionError
+    VersionError,
+    #[error("Unsupported chunk compression type {0}!")]
+    UnsupportedCompression(u8),
+    #[error("Decompressed data exceeds the limit of {0} bytes!")]
+    DecompressedTooLarge(usize),
+    #[error("Region holds more than {0} chunks!")]
+    TooManyChunks(usize),
+    #[error("Grid size {0} does not divide a region into whole buckets!")]
+    InvalidGridSize(u8),
+    #[error("Stored checksum does not match the data!")]
+    ChecksumMismatch,
+    #[error("Chunk delta references a missing neighbour chunk!")]
+    DeltaReference,
+    #[error("Region was compressed with a shared dictionary, but no {} was found!", BLINEAR_DICTIONARY_FILE)]
+    MissingDictionary,
+    #[error("Invalid chunk NBT: {0}")]
+    Nbt(#[from] NbtError)
 }
 
-pub struct Region {
-    chunks: Vec<Chunk>,
-    timestamp: i64
+/// Resource limits applied while parsing region files, for inputs that can not be trusted.
+#[derive(Clone, Copy, Debug)]
+pub struct ParseLimits {
+    /// Maximum size of a single decompressed block: an Anvil chunk, a linear bucket or a whole
+    /// blinear region
+    pub max_decompressed_size: usize,
+    /// Maximum number of chunks in one region
+    pub max_chunks: usize,
+    pub nbt: ParseOptions,
 }
 
-impl Region {
-    pub fn from_bytes_linear_v2(bytes: &[u8]) -> Result<Self, ParseError> {
-        let file_head = 0xc3ff13183cca9d9au64;
-        let version = 0x03;
+impl ParseLimits {
+    pub const UNLIMITED: Self = Self {
+        max_decompressed_size: usize::MAX,
+        max_chunks: usize::MAX,
+        nbt: ParseOptions::UNLIMITED,
+    };
+
+    pub const STRICT: Self = Self {
+        max_decompressed_size: 256 * 1024 * 1024,
+        max_chunks: 1024,
+        nbt: ParseOptions::STRICT,
+    };
+
+    /// Only the NBT of each chunk is limited, see `ParseOptions::DEFAULT`.
+    pub const DEFAULT: Self = Self {
+        nbt: ParseOptions::DEFAULT,
+        ..Self::UNLIMITED
+    };
+}
 
-        let file_head_got = u64::from_be_bytes(bytes[0..8].try_into().unwrap());
-        if file_head_got != file_head {
-            return Err(ParseError::HeaderError);
+impl Default for ParseLimits {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum WriteError {
+    #[error("Writing {0:?} region files is not supported yet!")]
+    UnsupportedFormat(RegionFormat),
+    #[error("Chunk {0}, {1} does not fit into 255 sectors!")]
+    ChunkTooLarge(i32, i32),
+    #[error("Grid size {0} does not divide a region into whole buckets, expected 1, 2, 4, 8, 16 or 32!")]
+    InvalidGridSize(u8),
+    #[error("Compression level {level} is out of range for {} ({} output), expected 0 to {}!", .format.codec(), .format.extension(), .format.compression_levels().end())]
+    InvalidCompressionLevel { level: u8, format: RegionFormat },
+    #[error("Linear region files record their region coordinates, which are unknown for this region!")]
+    MissingRegionCoords,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum RegionFormat {
+    Mca,
+    Linear,
+    Blinear
+}
+
+impl RegionFormat {
+    pub const ALL: [RegionFormat; 3] = [RegionFormat::Mca, RegionFormat::Linear, RegionFormat::Blinear];
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            RegionFormat::Mca => "mca",
+            RegionFormat::Linear => "linear",
+            RegionFormat::Blinear => "blinear"
         }
+    }
 
-        let version_got = bytes[8];
-        if version_got != version {
-            return Err(VersionError);
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "mca" => Some(RegionFormat::Mca),
+            "linear" => Some(RegionFormat::Linear),
+            "blinear" => Some(RegionFormat::Blinear),
+            _ => None
         }
+    }
 
-        let timestamp = i64::from_be_bytes(bytes[9..17].try_into().unwrap());
+    /// The bytes every file of the format starts with, Anvil files have none.
+    pub fn magic(self) -> Option<[u8; 8]> {
+        match self {
+            RegionFormat::Mca => None,
+            RegionFormat::Linear => Some(LINEAR_FILE_HEAD.to_be_bytes()),
+            RegionFormat::Blinear => Some(BLINEAR_FILE_HEAD.to_be_bytes()),
+        }
+    }
 
-        let grid_size = bytes[17];
-        let region_x = i32::from_be_bytes(bytes[18..22].try_into().unwrap());
-        let region_z = i32::from_be_bytes(bytes[22..26].try_into().unwrap());
+    /// Versions the readers accept, from the version byte after the magic.
+    pub fn read_versions(self) -> &'static [u8] {
+        match self {
+            RegionFormat::Mca => &[],
+            RegionFormat::Linear => &LINEAR_READ_VERSIONS,
+            RegionFormat::Blinear => &BLINEAR_VERSIONS,
+        }
+    }
 
-        let mut curr_read_pointer = 26 + 128;
+    /// Versions the writers produce.
+    pub fn write_versions(self) -> &'static [u8] {
+        match self {
+            RegionFormat::Mca => &[],
+            RegionFormat::Linear => &[LINEAR_V2],
+            RegionFormat::Blinear => &BLINEAR_VERSIONS,
+        }
+    }
 
-        loop {
-            let feature_name_length = bytes[curr_read_pointer];
-            curr_read_pointer += 1;
+    /// Chunk compressions the reader accepts. Linear and blinear compress whole regions or
+    /// buckets rather than single chunks.
+    pub fn compressions(self) -> Vec<&'static str> {
+        match self {
+            RegionFormat::Mca => MCA_COMPRESSIONS.iter().map(|(_, name)| *name).collect(),
+            RegionFormat::Linear | RegionFormat::Blinear => vec!["zstd"],
+        }
+    }
 
-            if feature_name_length == 0 {
-                break;
-            }
+    /// The codec chunks are written with.
+    pub fn codec(self) -> &'static str {
+        match self {
+            RegionFormat::Mca => "zlib",
+            RegionFormat::Linear | RegionFormat::Blinear => "zstd",
+        }
+    }
+
+    /// Compression levels the codec of the format knows.
+    pub fn compression_levels(self) -> RangeInclusive<u8> {
+        match self {
+            RegionFormat::Mca => 0..=MAX_ZLIB_LEVEL,
+            RegionFormat::Linear | RegionFormat::Blinear => 0..=MAX_ZSTD_LEVEL,
+        }
+    }
 
-            curr_read_pointer += feature_name_length as usize;
-            curr_read_pointer += 4;
+    pub fn check_compression_level(self, level: u8) -> Result<(), WriteError> {
+        match self.compression_levels().contains(&level) {
+            true => Ok(()),
+            false => Err(WriteError::InvalidCompressionLevel { level, format: self }),
         }
+    }
+
+    /// Detects the container format from the file magic, falling back to the extension for
+    /// Anvil files which have no magic of their own.
+    pub fn detect(path: &Path, bytes: &[u8]) -> Option<Self> {
+        if let Some(format) = Self::ALL.into_iter().find(|format| format.magic().is_some_and(|magic| bytes.starts_with(&magic))) {
+            return Some(format);
+        }
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("mca") => Some(RegionFormat::Mca),
+            _ => None
+        }
+    }
+}
+
+/// Reads a region file of any supported format.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_region_file(path: &Path) -> Result<(RegionFormat, Region), Box<dyn Error>> {
+    read_region_file_with_limits(path, &ParseLimits::default())
+}
+
+/// Converts a region file into the format named by the output file's extension.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn convert_region_file(input: &Path, output: &Path, compression_level: u8) -> Result<(), Box<dyn Error>> {
+    let format = output
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .and_then(RegionFormat::from_extension)
+        .ok_or("Output extension must be mca, linear or blinear")?;
 
-        let mut bucket_sizes: Vec<i32> = Vec::new();
-        let mut bucket_compression_levels: Vec<u8> = Vec::new();
+    let (_, region) = read_region_file(input)?;
+    std::fs::write(output, region.to_bytes(format, region_coords_from_path(input), region.timestamp(), compression_level)?)?;
 
-        for _ in 0..(grid_size as usize * grid_size as usize) {
-            let size_this_bucket = i32::from_be_bytes(bytes[curr_read_pointer..curr_read_pointer + 4].try_into().unwrap());
-            curr_read_pointer += 4;
+    Ok(())
+}
 
-            let compression_level_this_bucket = bytes[curr_read_pointer];
-            curr_read_pointer += 1;
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_region_file_with_limits(path: &Path, limits: &ParseLimits) -> Result<(RegionFormat, Region), Box<dyn Error>> {
+    let bytes = match path.extension() {
+        Some(extension) if extension == "mca" => read_mca_sparse(&mut File::open(path)?)?,
+        _ => read(path)?,
+    };
+    let format = RegionFormat::detect(path, &bytes).ok_or("Unknown region file format")?;
+    let region = match format {
+        RegionFormat::Blinear => Region::from_bytes_blinear_with_dictionary(&bytes, limits, blinear_dictionary_for(path, &bytes)?.as_deref())?,
+        _ => Region::from_bytes_with_limits(format, &bytes, limits)?,
+    };
+
+    Ok((format, region))
+}
 
-            curr_read_pointer += 8;
+/// Reads the sectors of an Anvil file its location table points at, leaving the free sectors
+/// between them as zeros without reading them, so fragmented files cost only their live data.
+/// The result parses like the whole file. Files starting with the magic of another format are
+/// read whole.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_mca_sparse(reader: &mut (impl Read + Seek)) -> std::io::Result<Vec<u8>> {
+    let length = reader.seek(SeekFrom::End(0))? as usize;
+    let mut bytes = vec![0; length];
+    reader.seek(SeekFrom::Start(0))?;
+    reader.read_exact(&mut bytes[..length.min(8192)])?;
+
+    let other_format = RegionFormat::ALL.into_iter().any(|format| format.magic().is_some_and(|magic| bytes.starts_with(&magic)));
+    if length <= 8192 || other_format {
+        reader.read_exact(&mut bytes[length.min(8192)..])?;
+        return Ok(bytes);
+    }
 
-            bucket_sizes.push(size_this_bucket);
-            bucket_compression_levels.push(compression_level_this_bucket);
+    let mut ranges: Vec<(usize, usize)> = (0..1024)
+        .filter_map(|sector_index| {
+            let location = u32::from_be_bytes(bytes[sector_index * 4..sector_index * 4 + 4].try_into().unwrap());
+            let start = (location >> 8) as usize * 4096;
+            let end = (start + (location & 0xFF) as usize * 4096).min(length);
+            (start >= 8192 && start < end).then_some((start, end))
+        })
+        .collect();
+    ranges.sort_unstable();
+
+    // neighbouring chunks are read in one go
+    let mut merged: Vec<(usize, usize)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
         }
+    }
+
+    for (start, end) in merged {
+        reader.seek(SeekFrom::Start(start as u64))?;
+        reader.read_exact(&mut bytes[start..end])?;
+    }
 
-        let mut chunks = Vec::with_capacity(1024);
+    Ok(bytes)
+}
 
-        for x in 0..(grid_size as i32) {
-            for z in 0..(grid_size as i32) {
-                let index = (x * grid_size as i32 + z) as usize;
+/// Reads the shared dictionary a blinear file was compressed with from the file's folder.
+/// Returns `None` for files written without one, or if the dictionary file is missing.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn blinear_dictionary_for(path: &Path, bytes: &[u8]) -> std::io::Result<Option<Vec<u8>>> {
+    if bytes.get(8).is_none_or(|version| version & BLINEAR_FLAG_DICTIONARY == 0) {
+        return Ok(None);
+    }
 
-                let bucket_data_len = *bucket_sizes.get(index).unwrap_or(&0);
-                if bucket_data_len <= 0 {
-                    continue;
-                }
+    match read(path.with_file_name(BLINEAR_DICTIONARY_FILE)) {
+        Ok(dictionary) => Ok(Some(dictionary)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
 
-                let bucket_data_compressed = &bytes[curr_read_pointer..curr_read_pointer + bucket_data_len as usize];
-                curr_read_pointer += bucket_data_len as usize;
+/// Parses the region coordinates out of a `r.<x>.<z>.<ext>` file name.
+pub fn region_coords_from_path(path: &Path) -> Option<(i32, i32)> {
+    let file_name = path.file_name()?.to_str()?;
+    let mut parts = file_name.split('.');
 
-                let decompressed = zstd::decode_all(bucket_data_compressed).unwrap();
+    if parts.next()? != "r" {
+        return None;
+    }
 
-                let mut read_pointer_this_loop = 0usize;
-                let bucket_dim = 32 / grid_size as i32;
+    let region_x = parts.next()?.parse().ok()?;
+    let region_z = parts.next()?.parse().ok()?;
 
-                for ix in 0..bucket_dim {
-                    for iz in 0..bucket_dim {
-                        let chunk_index = (x * bucket_dim + ix) + (z * bucket_dim + iz) * 32;
+    Some((region_x, region_z))
+}
 
-                        if read_pointer_this_loop + 12 > decompressed.len() {
-                            break;
-                        }
+/// Layout choices when writing blinear files.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BlinearOptions {
+    /// Compress each chunk as its own zstd frame behind an index
+    pub seekable: bool,
+    /// Store chunks along a Hilbert curve, so neighbouring chunks are close in the zstd stream
+    pub hilbert_order: bool,
+    /// Experimental: store chunks as a delta against their west or north neighbour where that
+    /// is smaller
+    pub neighbor_delta: bool,
+    /// Write blinear v3, with the region split into `grid_size` × `grid_size` buckets that are
+    /// compressed and read on their own. The other options only apply to v2.
+    pub grid_size: Option<u8>,
+}
 
-                        let chunk_size = i32::from_be_bytes(
-                            decompressed[read_pointer_this_loop..read_pointer_this_loop + 4]
-                                .try_into().unwrap()
-                        );
-                        read_pointer_this_loop += 4;
+impl BlinearOptions {
+    /// The layout of an existing blinear file.
+    pub fn of_file(bytes: &[u8]) -> Self {
+        Self {
+            seekable: is_seekable_blinear(bytes),
+            hilbert_order: bytes.get(8).is_some_and(|version| version & BLINEAR_FLAG_HILBERT_ORDER != 0),
+            neighbor_delta: bytes.get(8).is_some_and(|version| version & BLINEAR_FLAG_NEIGHBOR_DELTA != 0),
+            grid_size: match bytes.get(8) {
+                Some(version) if version & BLINEAR_VERSION_MASK == BLINEAR_V3 => blinear_body_offset(bytes).and_then(|body| bytes.get(body)).copied(),
+                _ => None,
+            },
+        }
+    }
 
-                        let chunk_timestamp = i64::from_be_bytes(
-                            decompressed[read_pointer_this_loop..read_pointer_this_loop + 8]
-                                .try_into().unwrap()
-                        );
-                        read_pointer_this_loop += 8;
+    fn flags(self) -> u8 {
+        let mut flags = 0;
+        if self.hilbert_order {
+            flags |= BLINEAR_FLAG_HILBERT_ORDER;
+        }
+        if self.neighbor_delta {
+            flags |= BLINEAR_FLAG_NEIGHBOR_DELTA;
+        }
 
-                        if chunk_size <= 0 {
-                            continue;
-                        }
+        flags
+    }
+}
 
-                        let chunk_data_size = (chunk_size - 8) as usize;
+pub struct Region {
+    chunks: Vec<Chunk>,
+    timestamp: i64,
+    features: Vec<(String, i32)>
+}
 
-                        let chunk_data = &decompressed[read_pointer_this_loop..read_pointer_this_loop + chunk_data_size];
-                        read_pointer_this_loop += chunk_data_size;
+impl Region {
+    pub fn new(chunks: Vec<Chunk>, timestamp: i64) -> Self {
+        Self { chunks, timestamp, features: Vec::new() }
+    }
+
+    pub fn from_bytes(format: RegionFormat, bytes: &[u8]) -> Result<Self, ParseError> {
+        Self::from_bytes_with_limits(format, bytes, &ParseLimits::default())
+    }
 
-                        let global_x = 32 * region_x + (chunk_index % 32);
-                        let global_z = 32 * region_z + (chunk_index / 32);
+    pub fn from_bytes_with_limits(format: RegionFormat, bytes: &[u8], limits: &ParseLimits) -> Result<Self, ParseError> {
+        match format {
+            RegionFormat::Mca => Self::from_bytes_mca(bytes, limits),
+            RegionFormat::Linear => Self::from_bytes_linear(bytes, limits),
+            RegionFormat::Blinear => Self::from_bytes_blinear(bytes, limits)
+        }
+    }
 
-                        let parsed_data = parse_tag(&mut BinaryReader::new(&chunk_data));
+    /// Serializes the region in the given format. Linear files record the region coordinates,
+    /// usually those of the file name, the other formats ignore them.
+    pub fn to_bytes(&self, format: RegionFormat, region_coords: Option<(i32, i32)>, timestamp: i64, compression_level: u8) -> Result<Vec<u8>, WriteError> {
+        format.check_compression_level(compression_level)?;
 
-                        chunks.push(Chunk::new_from_block_pos(global_x, global_z, chunk_timestamp, parsed_data));
-                    }
-                }
+        match format {
+            RegionFormat::Mca => self.to_bytes_mca(compression_level),
+            RegionFormat::Linear => {
+                let (region_x, region_z) = region_coords.ok_or(WriteError::MissingRegionCoords)?;
+                self.to_bytes_linear_v2(region_x, region_z, timestamp, compression_level, LINEAR_DEFAULT_GRID_SIZE)
             }
+            RegionFormat::Blinear => Ok(self.to_bytes_blinear(timestamp, compression_level))
         }
+    }
 
-        Ok(Self {
-            chunks,
-            timestamp
-        })
+    pub fn chunks(&self) -> &[Chunk] {
+        &self.chunks
+    }
+
+    pub fn chunks_mut(&mut self) -> &mut [Chunk] {
+        &mut self.chunks
+    }
+
+    pub fn into_chunks(self) -> Vec<Chunk> {
+        self.chunks
+    }
+
+    /// Removes and returns the chunk in the given slot.
+    pub fn take_chunk(&mut self, sector_index: i32) -> Option<Chunk> {
+        let position = self.chunks.iter().position(|chunk| chunk.position_to_sector_index() == sector_index)?;
+        Some(self.chunks.remove(position))
+    }
+
+    pub fn push_chunk(&mut self, chunk: Chunk) {
+        self.chunks.push(chunk);
+    }
+
+    pub fn retain_chunks(&mut self, keep: impl FnMut(&Chunk) -> bool) {
+        self.chunks.retain(keep);
+    }
+
+    pub fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+
+    /// The named values of the linear v2 NBT features section, kept in blinear files too so
+    /// they survive conversions between the two.
+    pub fn features(&self) -> &[(String, i32)] {
+        &self.features
+    }
+
+    pub fn set_features(&mut self, features: Vec<(String, i32)>) {
+        self.features = features;
+    }
+
+    pub fn from_bytes_mca(bytes: &[u8], limits: &ParseLimits) -> Result<Self, ParseError> {
+        RawRegion::from_bytes_mca(bytes, limits)?.parse(limits)
+    }
+
+    /// Writes an Anvil file with zlib compressed chunks, the compression level is capped at 9.
+    pub fn to_bytes_mca(&self, compression_level: u8) -> Result<Vec<u8>, WriteError> {
+        let compressed_chunks = parallel::map(self.chunks.iter().collect(), |chunk: &Chunk| McaChunk {
+            position: (chunk.x(), chunk.z()),
+            sector_index: chunk.position_to_sector_index() as usize,
+            timestamp: chunk.timestamp(),
+            compressed: compress_zlib(&chunk.to_raw_bytes(), compression_level),
+        });
+
+        mca_bytes(compressed_chunks)
+    }
+
+    /// Reads a linear file of either version, picked by the version byte.
+    pub fn from_bytes_linear(bytes: &[u8], limits: &ParseLimits) -> Result<Self, ParseError> {
+        match bytes.get(8) {
+            Some(version) if LINEAR_V1_VERSIONS.contains(version) => Self::from_bytes_linear_v1(bytes, limits),
+            _ => Self::from_bytes_linear_v2(bytes, limits),
+        }
+    }
+
+    /// Reads linear v1: the whole region in one zstd frame, starting with a size and timestamp
+    /// for each of the 1024 chunks followed by their data. Chunk positions are region local.
+    pub fn from_bytes_linear_v1(bytes: &[u8], limits: &ParseLimits) -> Result<Self, ParseError> {
+        RawRegion::from_bytes_linear_v1(bytes, limits)?.parse(limits)
+    }
+
+    pub fn from_bytes_linear_v2(bytes: &[u8], limits: &ParseLimits) -> Result<Self, ParseError> {
+        RawRegion::from_bytes_linear_v2(bytes, limits, false)?.parse(limits)
+    }
+
+    /// Writes linear v2 with the region split into `grid_size` × `grid_size` zstd compressed
+    /// buckets. Smaller buckets let readers decompress less for a single chunk, larger ones
+    /// compress better.
+    pub fn to_bytes_linear_v2(&self, region_x: i32, region_z: i32, timestamp: i64, compression_level: u8, grid_size: u8) -> Result<Vec<u8>, WriteError> {
+        self.to_raw().to_bytes_linear_v2(region_x, region_z, timestamp, compression_level, grid_size, None)
     }
 
     pub fn to_bytes_blinear(&self, timestamp: i64, compression_level: u8) -> Vec<u8>{
-        let mut result = Vec::new();
+        self.to_bytes_blinear_with(timestamp, compression_level, BlinearOptions::default())
+    }
 
-        let file_head = -0x200812250269i64;
-        let version = 0x02u8;
-        let hash_seed = 0x0721i32 as u32;
+    /// Writes blinear with every chunk in its own zstd frame, preceded by a skippable frame holding
+    /// the offset and length of each chunk's frame. Readers unaware of the index decode the frames
+    /// as one stream, so the file stays a valid blinear v2 file.
+    pub fn to_bytes_blinear_seekable(&self, timestamp: i64, compression_level: u8) -> Vec<u8> {
+        self.to_bytes_blinear_with(timestamp, compression_level, BlinearOptions { seekable: true, ..BlinearOptions::default() })
+    }
 
-        // whole file head part
-        // 8 + 1 + 8 + 1
-        let mut file_header = [0_u8; 18];
+    pub fn to_bytes_blinear_with(&self, timestamp: i64, compression_level: u8, options: BlinearOptions) -> Vec<u8> {
+        self.to_bytes_blinear_with_dictionary(timestamp, compression_level, options, None)
+    }
 
-        file_header[0..8].copy_from_slice(&file_head.to_be_bytes()); // superblock
-        file_header[8..9].copy_from_slice(&version.to_be_bytes()); // version
-        file_header[9..17].copy_from_slice(&timestamp.to_be_bytes()); // master file timestamp
-        file_header[17..18].copy_from_slice(&compression_level.to_be_bytes()); // compression level
+    /// Writes blinear compressed with a shared zstd dictionary, which readers need to find in
+    /// [`BLINEAR_DICTIONARY_FILE`] next to the file.
+    ///
+    /// Panics if `options.grid_size` is not one of [`LINEAR_GRID_SIZES`].
+    pub fn to_bytes_blinear_with_dictionary(&self, timestamp: i64, compression_level: u8, options: BlinearOptions, dictionary: Option<&[u8]>) -> Vec<u8> {
+        self.to_raw().to_bytes_blinear(timestamp, compression_level, options, dictionary, None)
+    }
 
-        result.extend_from_slice(&file_header); // append file head
+    /// Reads the single chunk in the slot of the given chunk coordinates. Seekable blinear files
+    /// only have that chunk's frame read and decompressed, other files, and seekable ones storing
+    /// neighbour deltas, are parsed completely.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn read_chunk_at(path: &Path, x: i32, z: i32) -> Result<Option<Chunk>, Box<dyn Error>> {
+        let sector_index = (x & 31) + ((z & 31) << 5);
+
+        let mut file = File::open(path)?;
+        let mut header = vec![0u8; BLINEAR_HEADER_SIZE + 8];
+        let mut seekable = file.read_exact(&mut header).is_ok();
+
+        // a features frame sits between the header and the frame index
+        let file_len = file.metadata()?.len();
+        let body = blinear_body_offset(&header).filter(|body| *body as u64 + 8 <= file_len).unwrap_or(BLINEAR_HEADER_SIZE);
+        if seekable && body > BLINEAR_HEADER_SIZE {
+            header.resize(body + 8, 0);
+            seekable = file.read_exact(&mut header[BLINEAR_HEADER_SIZE + 8..]).is_ok();
+        }
+        let bucketed = seekable && i64::from_be_bytes(header[0..8].try_into().unwrap()) == BLINEAR_FILE_HEAD && header[8] & BLINEAR_VERSION_MASK == BLINEAR_V3;
+        seekable = seekable && is_seekable_blinear(&header) && header[8] & BLINEAR_FLAG_NEIGHBOR_DELTA == 0;
 
-        let mut region_data = Vec::new();
+        if !seekable && !bucketed {
+            let (_, region) = read_region_file(path)?;
+            return Ok(region.into_chunks().into_iter().find(|chunk| chunk.position_to_sector_index() == sector_index));
+        }
 
-        for index in 0..1024 {
-            let mut target_chunk = None;
+        let dictionary = blinear_dictionary_for(path, &header)?;
+        if header[8] & BLINEAR_FLAG_DICTIONARY != 0 && dictionary.is_none() {
+            return Err(ParseError::MissingDictionary.into());
+        }
 
-            for chunk in &self.chunks {
-                if chunk.position_to_sector_index() == index {
-                    target_chunk = Some(chunk);
-                    break
-                }
+        if bucketed {
+            return Ok(read_bucketed_chunk(&mut file, body, sector_index, dictionary.as_deref())?);
+        }
+
+        let mut entry = [0u8; 8];
+        file.seek(SeekFrom::Current(sector_index as i64 * 8))?;
+        file.read_exact(&mut entry)?;
+
+        let offset = u32::from_be_bytes(entry[0..4].try_into().unwrap()) as u64;
+        let length = u32::from_be_bytes(entry[4..8].try_into().unwrap()) as usize;
+        if length == 0 {
+            return Ok(None);
+        }
+
+        let mut frame = vec![0u8; length];
+        file.seek(SeekFrom::Start((body + 8 + BLINEAR_SEEK_INDEX_SIZE) as u64 + offset))?;
+        file.read_exact(&mut frame)?;
+        let decompressed = decompress_zstd(&frame, usize::MAX, dictionary.as_deref())?;
+
+        // the frame starts with the empty slots written before this chunk
+        let mut buffer_pointer = 0;
+        loop {
+            let section_len = i32::from_be_bytes(checked_slice(&decompressed, buffer_pointer, 4)?.try_into().unwrap());
+            buffer_pointer += 4;
+
+            if section_len > 0 {
+                let section = checked_slice(&decompressed, buffer_pointer, section_len as usize)?;
+                let chunk = parse_blinear_section(sector_index, section, &ParseLimits::DEFAULT)?;
+                return Ok(Some(chunk.with_sizes(section.len() - 16, length)));
             }
+        }
+    }
 
-            if target_chunk.is_none() {
-                region_data.extend_from_slice(&0i32.to_be_bytes());
-                continue;
+    /// The region with every chunk serialized back to NBT bytes.
+    pub fn to_raw(&self) -> RawRegion {
+        let chunks = self
+            .chunks
+            .iter()
+            .map(|chunk| RawChunk { sector_index: chunk.position_to_sector_index() as usize, timestamp: chunk.timestamp(), data: chunk.to_raw_bytes(), compressed_size: 0 })
+            .collect();
+
+        RawRegion { chunks, timestamp: self.timestamp, features: self.features.clone(), region: None }
+    }
+
+    pub fn from_bytes_blinear(bytes: &[u8], limits: &ParseLimits) -> Result<Self, ParseError> {
+        Self::from_bytes_blinear_with_dictionary(bytes, limits, None)
+    }
+
+    /// Parses blinear, decompressing with `dictionary` if the file was written with a shared one.
+    pub fn from_bytes_blinear_with_dictionary(bytes: &[u8], limits: &ParseLimits, dictionary: Option<&[u8]>) -> Result<Self, ParseError> {
+        RawRegion::from_bytes_blinear(bytes, limits, dictionary, false)?.parse(limits)
+    }
+}
+
+/// The compressed buckets of a linear v2 or blinear v3 file written earlier. Writers copy a
+/// bucket from it instead of compressing it again when none of its slots changed since.
+pub struct ReusedBuckets<'a> {
+    format: RegionFormat,
+    grid_size: u8,
+    frames: Vec<&'a [u8]>,
+    /// Slots whose chunk differs from the earlier file
+    changed: Vec<bool>,
+}
+
+impl<'a> ReusedBuckets<'a> {
+    /// The buckets of `previous`, `None` unless it is a linear v2 or blinear v3 file.
+    pub fn new(previous: &'a [u8], changed: Vec<bool>) -> Option<Self> {
+        let (format, grid_size, frames) = if previous.get(0..8)? == LINEAR_FILE_HEAD.to_be_bytes() {
+            let grid_size = *previous.get(17)?;
+            if previous[8] != LINEAR_V2 || !LINEAR_GRID_SIZES.contains(&grid_size) {
+                return None;
             }
+            let (_, buckets) = linear_v2_buckets(previous, grid_size).ok()?;
+            (RegionFormat::Linear, grid_size, buckets.into_iter().map(|(frame, _)| frame).collect())
+        } else {
+            if i64::from_be_bytes(previous[0..8].try_into().unwrap()) != BLINEAR_FILE_HEAD || previous.get(8)? & BLINEAR_VERSION_MASK != BLINEAR_V3 {
+                return None;
+            }
+            let body = previous.get(blinear_body_offset(previous)?..)?;
+            let (grid_size, frames) = parse_blinear_bucket_index(body).ok()?;
+            let frames = frames.into_iter().map(|(offset, length)| checked_slice(body, offset, length).ok()).collect::<Option<Vec<_>>>()?;
+            (RegionFormat::Blinear, grid_size, frames)
+        };
+
+        Some(Self { format, grid_size, frames, changed })
+    }
+
+    /// The earlier frame of a bucket when the file had the same layout and none of the bucket's
+    /// slots changed.
+    fn frame(&self, format: RegionFormat, grid_size: u8, bucket: usize, slots: &[usize]) -> Option<&'a [u8]> {
+        if self.format != format || self.grid_size != grid_size || slots.iter().any(|slot| self.changed.get(*slot).is_none_or(|changed| *changed)) {
+            return None;
+        }
+
+        self.frames.get(bucket).copied()
+    }
+}
+
+/// A chunk's NBT as stored in a region file, not parsed.
+pub struct RawChunk {
+    pub sector_index: usize,
+    pub timestamp: i64,
+    pub data: Vec<u8>,
+    /// The chunk's share of the compressed bytes it was read from
+    compressed_size: usize,
+}
+
+/// A linear or blinear region with its chunks left as NBT bytes, so conversions between the two
+/// only change the container.
+pub struct RawRegion {
+    chunks: Vec<RawChunk>,
+    timestamp: i64,
+    features: Vec<(String, i32)>,
+    /// The region a linear v2 file names, its chunks are positioned in the world
+    region: Option<(i32, i32)>,
+}
+
+impl RawRegion {
+    fn new(timestamp: i64, features: Vec<(String, i32)>, region: Option<(i32, i32)>) -> Self {
+        Self { chunks: Vec::new(), timestamp, features, region }
+    }
+
+    fn push(&mut self, chunk: RawChunk, limits: &ParseLimits) -> Result<(), ParseError> {
+        if self.chunks.len() == limits.max_chunks {
+            return Err(ParseError::TooManyChunks(limits.max_chunks));
+        }
+
+        self.chunks.push(chunk);
+        Ok(())
+    }
 
-            let mut hasher = XxHash32::with_seed(hash_seed);
+    pub fn chunks(&self) -> &[RawChunk] {
+        &self.chunks
+    }
+
+    pub fn retain_chunks(&mut self, keep: impl FnMut(&RawChunk) -> bool) {
+        self.chunks.retain(keep);
+    }
 
-            let chunk_data = target_chunk.unwrap().to_raw_bytes(); // 3
-            let length_of_chunk_data = (chunk_data.len() as i32).to_be_bytes(); // 0
-            let timestamp_of_chunk = target_chunk.unwrap().timestamp().to_be_bytes(); // 1
+    pub fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
 
-            hasher.write(&chunk_data);
-            let xxhash32_of_chunk_data = (hasher.finish() as i32).to_be_bytes(); // 2
+    /// The region coordinates a linear v2 file records.
+    pub fn region(&self) -> Option<(i32, i32)> {
+        self.region
+    }
 
-            let mut local_temp_buffer = Vec::new();
+    /// Removes and returns the chunk in the given slot.
+    pub fn take_chunk(&mut self, sector_index: usize) -> Option<RawChunk> {
+        let position = self.chunks.iter().position(|chunk| chunk.sector_index == sector_index)?;
+        Some(self.chunks.remove(position))
+    }
 
-            local_temp_buffer.extend_from_slice(&length_of_chunk_data); // len
-            local_temp_buffer.extend_from_slice(&timestamp_of_chunk); // timestamp of chunk
-            local_temp_buffer.extend_from_slice(&xxhash32_of_chunk_data); // xxhash32 of chunk data
-            local_temp_buffer.extend_from_slice(&chunk_data); // chunk data
+    /// Adds a chunk, serialized to NBT bytes.
+    pub fn push_chunk(&mut self, chunk: &Chunk) {
+        self.chunks.push(RawChunk { sector_index: chunk.position_to_sector_index() as usize, timestamp: chunk.timestamp(), data: chunk.to_raw_bytes(), compressed_size: 0 });
+    }
 
-            region_data.extend_from_slice(&(local_temp_buffer.len() as i32).to_be_bytes());
-            region_data.extend_from_slice(local_temp_buffer.as_slice());
+    /// Parses the NBT of one chunk of the region, positioned in the world for linear v2.
+    pub fn parse_chunk(&self, raw: &RawChunk, limits: &ParseLimits) -> Result<Chunk, ParseError> {
+        let mut chunk = Chunk::from_sector(raw.sector_index as i32, raw.timestamp, &raw.data, &limits.nbt)?;
+        if let Some((region_x, region_z)) = self.region {
+            let (x, z) = chunk.global_position(region_x, region_z);
+            chunk.set_position(x, z);
         }
 
-        if let Ok(compressed) = zstd::encode_all(region_data.as_slice(), compression_level as i32) {
-            result.extend_from_slice(&compressed);
+        Ok(chunk.with_sizes(raw.data.len(), raw.compressed_size))
+    }
+
+    /// Parses the NBT of every chunk.
+    pub fn parse(self, limits: &ParseLimits) -> Result<Region, ParseError> {
+        let chunks = self.chunks.iter().map(|raw| self.parse_chunk(raw, limits)).collect::<Result<_, _>>()?;
+
+        Ok(Region { chunks, timestamp: self.timestamp, features: self.features })
+    }
+
+    /// Parses the NBT of every chunk, leaving out the chunks that do not parse. Those are
+    /// returned by slot with their error.
+    pub fn parse_lossy(self, limits: &ParseLimits) -> (Region, Vec<(usize, ParseError)>) {
+        let mut chunks = Vec::with_capacity(self.chunks.len());
+        let mut failed = Vec::new();
+        for raw in &self.chunks {
+            match self.parse_chunk(raw, limits) {
+                Ok(chunk) => chunks.push(chunk),
+                Err(err) => failed.push((raw.sector_index, err)),
+            }
         }
 
-        result
+        (Region { chunks, timestamp: self.timestamp, features: self.features }, failed)
     }
 
-    pub fn from_bytes_blinear(bytes: &[u8]) -> Result<Self, ParseError> {
-        let mut chunk_sections = Vec::with_capacity(1024);
+    /// Reads an Anvil file, decompressing every chunk to its NBT bytes.
+    pub fn from_bytes_mca(bytes: &[u8], limits: &ParseLimits) -> Result<Self, ParseError> {
+        // 4096 bytes of locations followed by 4096 bytes of timestamps
+        if bytes.len() < 8192 {
+            return Err(ParseError::HeaderError);
+        }
+
+        let mut region = RawRegion::new(0, Vec::new(), None);
 
-        // 8 + 1 + 8 + 1
-        let file_head = i64::from_be_bytes(bytes[0..8].try_into().unwrap());
-        let version = &bytes[8..9];
+        for sector_index in 0..1024usize {
+            let location = u32::from_be_bytes(bytes[sector_index * 4..sector_index * 4 + 4].try_into().unwrap());
+            if location == 0 {
+                continue;
+            }
 
-        // incorrect file
-        if file_head != -0x200812250269 {
+            let timestamp_of_chunk = i32::from_be_bytes(bytes[4096 + sector_index * 4..4096 + sector_index * 4 + 4].try_into().unwrap()) as i64;
+            region.timestamp = region.timestamp.max(timestamp_of_chunk);
+
+            let chunk_offset = (location >> 8) as usize * 4096;
+
+            // the length counts the compression type byte as well
+            let length_of_chunk = i32::from_be_bytes(checked_slice(bytes, chunk_offset, 4)?.try_into().unwrap());
+            if length_of_chunk <= 1 {
+                return Err(ParseError::ReadError);
+            }
+
+            let chunk_bytes = checked_slice(bytes, chunk_offset + 4, length_of_chunk as usize)?;
+            let compression_type = chunk_bytes[0];
+            let compressed_data = &chunk_bytes[1..];
+            let data_of_chunk = decompress_mca_chunk(compression_type, compressed_data, limits.max_decompressed_size)?;
+
+            region.push(RawChunk { sector_index, timestamp: timestamp_of_chunk, data: data_of_chunk, compressed_size: compressed_data.len() }, limits)?;
+        }
+
+        Ok(region)
+    }
+
+    /// Writes an Anvil file, see [`Region::to_bytes_mca`].
+    pub fn to_bytes_mca(&self, compression_level: u8) -> Result<Vec<u8>, WriteError> {
+        let compressed_chunks = parallel::map(self.slots().into_iter().flatten().collect(), |chunk: &RawChunk| {
+            let (x, z) = ((chunk.sector_index % 32) as i32, (chunk.sector_index / 32) as i32);
+            McaChunk {
+                position: self.region.map_or((x, z), |(region_x, region_z)| (region_x * 32 + x, region_z * 32 + z)),
+                sector_index: chunk.sector_index,
+                timestamp: chunk.timestamp,
+                compressed: compress_zlib(&chunk.data, compression_level),
+            }
+        });
+
+        mca_bytes(compressed_chunks)
+    }
+
+    /// Reads a linear file of either version, picked by the version byte. With `verify_checksums`
+    /// the bucket hashes of linear v2 are checked, v1 has none per chunk.
+    pub fn from_bytes_linear(bytes: &[u8], limits: &ParseLimits, verify_checksums: bool) -> Result<Self, ParseError> {
+        match bytes.get(8) {
+            Some(version) if LINEAR_V1_VERSIONS.contains(version) => Self::from_bytes_linear_v1(bytes, limits),
+            _ => Self::from_bytes_linear_v2(bytes, limits, verify_checksums),
+        }
+    }
+
+    /// Reads linear v1, see [`Region::from_bytes_linear_v1`].
+    pub fn from_bytes_linear_v1(bytes: &[u8], limits: &ParseLimits) -> Result<Self, ParseError> {
+        if bytes.len() < 32 + 8 {
+            return Err(ParseError::HeaderError);
+        }
+
+        let file_head_got = u64::from_be_bytes(bytes[0..8].try_into().unwrap());
+        if file_head_got != LINEAR_FILE_HEAD {
             return Err(ParseError::HeaderError);
         }
 
-        if version[0] != 0x02 {
+        if !LINEAR_V1_VERSIONS.contains(&bytes[8]) {
             return Err(VersionError);
         }
 
-        let timestamp_of_master_file = i64::from_be_bytes(bytes[9..17].try_into().unwrap());
-        let _compression_level = &bytes[17..18];
+        let timestamp = i64::from_be_bytes(bytes[9..17].try_into().unwrap());
+        let data_count = i32::from_be_bytes(bytes[20..24].try_into().unwrap());
+        if data_count < 0 {
+            return Err(ParseError::HeaderError);
+        }
 
-        let decompressed_region_sections_data = zstd::decode_all(&bytes[18..bytes.len()])
-            .map_err(|_| ParseError::ReadError)?;
+        let compressed = checked_slice(bytes, 32, data_count as usize)?;
+        let decompressed = decompress_zstd(compressed, limits.max_decompressed_size, None)?;
 
-        let mut buffer_pointer = 0;
-        for sector_index in 0..1024 {
-            let sector_len = i32::from_be_bytes(decompressed_region_sections_data[buffer_pointer..buffer_pointer + 4].try_into().unwrap()) as usize;
-            buffer_pointer += 4;
+        let header = checked_slice(&decompressed, 0, 1024 * 8)?;
+        let mut read_pointer = 1024 * 8;
+        let mut region = RawRegion::new(timestamp, Vec::new(), None);
 
-            if sector_len <= 0 {
+        for (sector_index, entry) in header.chunks_exact(8).enumerate() {
+            let chunk_size = i32::from_be_bytes(entry[0..4].try_into().unwrap());
+            let chunk_timestamp = i32::from_be_bytes(entry[4..8].try_into().unwrap());
+
+            if chunk_size <= 0 {
                 continue;
             }
 
-            let section_data_this_section = &decompressed_region_sections_data[buffer_pointer..buffer_pointer + sector_len];
-            buffer_pointer += sector_len;
+            let chunk_data = checked_slice(&decompressed, read_pointer, chunk_size as usize)?;
+            read_pointer += chunk_size as usize;
 
+            let compressed_size = compressed.len() * chunk_data.len() / decompressed.len().max(1);
+            region.push(RawChunk { sector_index, timestamp: chunk_timestamp as i64, data: chunk_data.to_vec(), compressed_size }, limits)?;
+        }
 
-            let _length_of_chunk = i32::from_be_bytes(section_data_this_section[0..4].try_into().unwrap()); // unused
-            let timestamp_of_chunk = i64::from_be_bytes(section_data_this_section[4..12].try_into().unwrap());
-            let _xxhash32_of_chunk = i32::from_be_bytes(section_data_this_section[12..16].try_into().unwrap()); // unused
+        Ok(region)
+    }
 
-            let data_of_chunk = &section_data_this_section[16..section_data_this_section.len()];
+    pub fn from_bytes_linear_v2(bytes: &[u8], limits: &ParseLimits, verify_checksums: bool) -> Result<Self, ParseError> {
+        let file_head = LINEAR_FILE_HEAD;
+        let version = LINEAR_V2;
 
-            if let Ok(chunk) = Chunk::from_sector(sector_index, timestamp_of_chunk, data_of_chunk) {
-                chunk_sections.push(chunk);
-            }
+        if bytes.len() < 9 {
+            return Err(ParseError::HeaderError);
         }
 
-        Ok(Self{
-            chunks: chunk_sections,
-            timestamp: timestamp_of_master_file
-        })
-    }
-}
\ No newline at end of file
+        let file_head_got = u64::from_be_bytes(bytes[0..8].try_into().unwrap());
+        if file_head_got != file_head {
+            return Err(ParseError::HeaderError);
+        }
+
+        let version_got = bytes[8];
+        if version_got != version {
+            return Err(VersionError);
+        }
+
+        if bytes.len() < 26 + 128 {
+            return Err(ParseError::HeaderError);
+        }
+
+        let timestamp = i64::from_be_bytes(bytes[9..17].try_into().unwrap());
+
+        // buckets must tile the 32x32 region exactly
+        let grid_size = bytes[17];
+        if !LINEAR_GRID_SIZES.contains(&grid_size) {
+            return Err(ParseError::InvalidGridSize(grid_size));
+        }
+        let region_x = i32::from_be_bytes(bytes[18..22].try_into().unwrap());
+        let region_z = i32::from_be_bytes(bytes[22..26].try_into().unwrap());
+
+        let (features, buckets) = linear_v2_buckets(bytes, grid_size)?;
+        let mut region = RawRegion::new(timestamp, features, Some((region_x, region_z)));
+
+        for x in 0..(grid_size as i32) {
+            for z in 0..(grid_size as i32) {
+                let index = (x * grid_size as i32 + z) as usize;
+
+                let (bucket_data_compressed, bucket_hash) = buckets[index];
+                if bucket_data_compressed.is_empty() {
+                    continue;
+                }
+                let bucket_data_len = bucket_data_compressed.len();
+
+                if verify_checksums {
+                    let mut hasher = XxHash64::with_seed(0);
+                    hasher.write(bucket_data_compressed);
+                    if hasher.finish() != bucket_hash {
+                        return Err(ParseError::ChecksumMismatch);
+                    }
+                }
+
+                let decompressed = decompress_zstd(bucket_data_compressed, limits.max_decompressed_size, None)?;
+
+                let mut read_pointer_this_loop = 0usize;
+                let bucket_dim = 32 / grid_size as i32;
+
+                for ix in 0..bucket_dim {
+                    for iz in 0..bucket_dim {
+                        let chunk_index = (x * bucket_dim + ix) + (z * bucket_dim + iz) * 32;
+
+                        if read_pointer_this_loop + 12 > decompressed.len() {
+                            break;
+                        }
+
+                        let chunk_size = i32::from_be_bytes(
+                            decompressed[read_pointer_this_loop..read_pointer_this_loop + 4]
+                                .try_into().unwrap()
+                        );
+                        read_pointer_this_loop += 4;
+
+                        let chunk_timestamp = i64::from_be_bytes(
+                            decompressed[read_pointer_this_loop..read_pointer_this_loop + 8]
+                                .try_into().unwrap()
+                        );
+                        read_pointer_this_loop += 8;
+
+                        if chunk_size <= 0 {
+                            continue;
+                        }
+
+                        // the size counts the timestamp as well
+                        if chunk_size < 8 {
+                            return Err(ParseError::ReadError);
+                        }
+                        let chunk_data_size = (chunk_size - 8) as usize;
+
+                        let chunk_data = checked_slice(&decompressed, read_pointer_this_loop, chunk_data_size)?;
+                        read_pointer_this_loop += chunk_data_size;
+
+                        let compressed_size = bucket_data_len * chunk_data_size / decompressed.len().max(1);
+                        region.push(RawChunk { sector_index: chunk_index as usize, timestamp: chunk_timestamp, data: chunk_data.to_vec(), compressed_size }, limits)?;
+                    }
+                }
+            }
+        }
+
+        Ok(region)
+    }
+
+    /// Reads blinear, checking the hash of every chunk against its data with `verify_checksums`.
+    pub fn from_bytes_blinear(bytes: &[u8], limits: &ParseLimits, dictionary: Option<&[u8]>, verify_checksums: bool) -> Result<Self, ParseError> {
+        // 8 + 1 + 8 + 1
+        if bytes.len() < 18 {
+            return Err(ParseError::HeaderError);
+        }
+
+        let file_head = i64::from_be_bytes(bytes[0..8].try_into().unwrap());
+        let version = &bytes[8..9];
+
+        // incorrect file
+        if file_head != BLINEAR_FILE_HEAD {
+            return Err(ParseError::HeaderError);
+        }
+
+        let flags = version[0] & !BLINEAR_VERSION_MASK;
+        let known_flags = match version[0] & BLINEAR_VERSION_MASK {
+            BLINEAR_V2 => BLINEAR_KNOWN_FLAGS,
+            BLINEAR_V3 => BLINEAR_V3_KNOWN_FLAGS,
+            _ => return Err(VersionError),
+        };
+        if flags & !known_flags != 0 {
+            return Err(VersionError);
+        }
+
+        let timestamp_of_master_file = i64::from_be_bytes(bytes[9..17].try_into().unwrap());
+        let _compression_level = &bytes[17..18];
+
+        let dictionary = match dictionary {
+            Some(dictionary) if flags & BLINEAR_FLAG_DICTIONARY != 0 => Some(dictionary),
+            None if flags & BLINEAR_FLAG_DICTIONARY != 0 => return Err(ParseError::MissingDictionary),
+            _ => None,
+        };
+
+        let mut features = Vec::new();
+        if flags & BLINEAR_FLAG_FEATURES != 0 {
+            let frame_header = checked_slice(bytes, BLINEAR_HEADER_SIZE, 8)?;
+            if u32::from_le_bytes(frame_header[0..4].try_into().unwrap()) != BLINEAR_FEATURES_MAGIC {
+                return Err(ParseError::HeaderError);
+            }
+            let frame_size = u32::from_le_bytes(frame_header[4..8].try_into().unwrap()) as usize;
+            features = parse_features(checked_slice(bytes, BLINEAR_HEADER_SIZE + 8, frame_size)?, &mut 0)?;
+        }
+
+        // each decompressed block with the slots it holds, in order
+        let blocks = if version[0] & BLINEAR_VERSION_MASK == BLINEAR_V3 {
+            let body = blinear_body_offset(bytes).ok_or(ParseError::ReadError)?;
+            decompress_blinear_buckets(checked_slice(bytes, body, bytes.len().saturating_sub(body))?, limits, dictionary)?
+        } else {
+            // zstd skips the features frame on its own
+            let decompressed = decompress_zstd(&bytes[18..bytes.len()], limits.max_decompressed_size, dictionary)?;
+            vec![(blinear_slot_order(flags & BLINEAR_FLAG_HILBERT_ORDER != 0), decompressed)]
+        };
+        let decompressed_len: usize = blocks.iter().map(|(_, decompressed)| decompressed.len()).sum();
+
+        let mut sections = Vec::new();
+        for (slots, decompressed_region_sections_data) in &blocks {
+            let mut buffer_pointer = 0;
+            for &sector_index in slots {
+                let sector_len = i32::from_be_bytes(checked_slice(decompressed_region_sections_data, buffer_pointer, 4)?.try_into().unwrap());
+                buffer_pointer += 4;
+
+                if sector_len <= 0 {
+                    continue;
+                }
+
+                let sector_len = sector_len as usize;
+
+                sections.push((sector_index, checked_slice(decompressed_region_sections_data, buffer_pointer, sector_len)?));
+                buffer_pointer += sector_len;
+            }
+        }
+
+        let payloads = if flags & BLINEAR_FLAG_NEIGHBOR_DELTA != 0 { resolve_neighbor_deltas(&sections)? } else { Vec::new() };
+
+        let mut region = RawRegion::new(timestamp_of_master_file, features, None);
+        for (sector_index, section_data_this_section) in sections {
+            let (timestamp, hash, stored) = blinear_section_parts(section_data_this_section)?;
+            let data = payloads.get(sector_index).and_then(Option::as_deref).unwrap_or(stored);
+
+            if verify_checksums && hash != blinear_hash(data) {
+                return Err(ParseError::ChecksumMismatch);
+            }
+
+            let compressed_size = (bytes.len() - 18) * data.len() / decompressed_len.max(1);
+            region.push(RawChunk { sector_index, timestamp, data: data.to_vec(), compressed_size }, limits)?;
+        }
+
+        Ok(region)
+    }
+
+    /// The chunk of every slot, the first one where several claim the same slot.
+    pub fn slots(&self) -> Vec<Option<&RawChunk>> {
+        let mut slots = vec![None; 1024];
+        for chunk in &self.chunks {
+            let slot = &mut slots[chunk.sector_index];
+            if slot.is_none() {
+                *slot = Some(chunk);
+            }
+        }
+
+        slots
+    }
+
+    /// Writes linear v2, copying the buckets `reuse` has unchanged instead of compressing them.
+    pub fn to_bytes_linear_v2(&self, region_x: i32, region_z: i32, timestamp: i64, compression_level: u8, grid_size: u8, reuse: Option<&ReusedBuckets>) -> Result<Vec<u8>, WriteError> {
+        if !LINEAR_GRID_SIZES.contains(&grid_size) {
+            return Err(WriteError::InvalidGridSize(grid_size));
+        }
+
+        let slots = self.slots();
+        let bucket_dim = 32 / grid_size as usize;
+
+        let mut existence = [0u8; 128];
+        for (sector_index, _) in slots.iter().enumerate().filter(|(_, slot)| slot.is_some()) {
+            existence[sector_index / 8] |= 1 << (sector_index % 8);
+        }
+
+        let bucket_coords: Vec<(usize, usize)> = (0..grid_size as usize).flat_map(|x| (0..grid_size as usize).map(move |z| (x, z))).collect();
+        let frames = parallel::map(bucket_coords, |(x, z)| {
+            let bucket_slots: Vec<usize> = (0..bucket_dim).flat_map(|ix| (0..bucket_dim).map(move |iz| (x * bucket_dim + ix) + (z * bucket_dim + iz) * 32)).collect();
+            let reused = reuse.and_then(|reuse| reuse.frame(RegionFormat::Linear, grid_size, x * grid_size as usize + z, &bucket_slots));
+
+            match reused {
+                Some(frame) => frame.to_vec(),
+                None if bucket_slots.iter().all(|slot| slots[*slot].is_none()) => Vec::new(),
+                None => {
+                    let mut bucket_data = Vec::new();
+                    for slot in &bucket_slots {
+                        match slots[*slot] {
+                            Some(chunk) => {
+                                // the size counts the timestamp as well
+                                bucket_data.extend_from_slice(&(chunk.data.len() as i32 + 8).to_be_bytes());
+                                bucket_data.extend_from_slice(&chunk.timestamp.to_be_bytes());
+                                bucket_data.extend_from_slice(&chunk.data);
+                            }
+                            None => bucket_data.extend_from_slice(&[0u8; 12]),
+                        }
+                    }
+                    compress_zstd(&bucket_data, compression_level, None)
+                }
+            }
+        });
+
+        let mut bucket_headers = Vec::with_capacity(grid_size as usize * grid_size as usize * 13);
+        let mut buckets = Vec::new();
+        for compressed in frames {
+            let mut hasher = XxHash64::with_seed(0);
+            hasher.write(&compressed);
+
+            bucket_headers.extend_from_slice(&(compressed.len() as i32).to_be_bytes());
+            bucket_headers.push(compression_level);
+            bucket_headers.extend_from_slice(&hasher.finish().to_be_bytes());
+            buckets.extend_from_slice(&compressed);
+        }
+
+        let mut result = Vec::with_capacity(26 + 128 + 1 + bucket_headers.len() + buckets.len() + 8);
+        result.extend_from_slice(&LINEAR_FILE_HEAD.to_be_bytes());
+        result.push(LINEAR_V2);
+        result.extend_from_slice(&timestamp.to_be_bytes());
+        result.push(grid_size);
+        result.extend_from_slice(&region_x.to_be_bytes());
+        result.extend_from_slice(&region_z.to_be_bytes());
+        result.extend_from_slice(&existence);
+        result.extend_from_slice(&features_to_bytes(&self.features));
+        result.extend_from_slice(&bucket_headers);
+        result.extend_from_slice(&buckets);
+        result.extend_from_slice(&LINEAR_FILE_HEAD.to_be_bytes());
+
+        Ok(result)
+    }
+
+    /// Writes blinear, copying the buckets `reuse` has unchanged when writing v3.
+    pub fn to_bytes_blinear(&self, timestamp: i64, compression_level: u8, options: BlinearOptions, dictionary: Option<&[u8]>, reuse: Option<&ReusedBuckets>) -> Vec<u8> {
+        let v2 = options.grid_size.is_none();
+        let sections = blinear_sections(&self.slots(), v2 && options.neighbor_delta);
+        let order = blinear_slot_order(options.hilbert_order);
+
+        let mut flags = if v2 { options.flags() } else { 0 };
+        if dictionary.is_some() {
+            flags |= BLINEAR_FLAG_DICTIONARY;
+        }
+        if !self.features.is_empty() {
+            flags |= BLINEAR_FLAG_FEATURES;
+        }
+
+        let version = if v2 { BLINEAR_V2 } else { BLINEAR_V3 };
+        let mut result = Vec::from(blinear_header(timestamp, compression_level, version | flags));
+        if !self.features.is_empty() {
+            let features = features_to_bytes(&self.features);
+            result.extend_from_slice(&BLINEAR_FEATURES_MAGIC.to_le_bytes());
+            result.extend_from_slice(&(features.len() as u32).to_le_bytes());
+            result.extend_from_slice(&features);
+        }
+        if let Some(grid_size) = options.grid_size {
+            result.extend_from_slice(&blinear_bucketed_body(&sections, grid_size, compression_level, dictionary, reuse));
+            return result;
+        }
+        if options.seekable {
+            result.extend_from_slice(&blinear_seekable_body(&sections, &order, compression_level, dictionary));
+            return result;
+        }
+
+        let mut region_data = Vec::new();
+        for &sector_index in &order {
+            match &sections[sector_index] {
+                Some(section) => region_data.extend_from_slice(section),
+                None => region_data.extend_from_slice(&0i32.to_be_bytes()),
+            }
+        }
+
+        result.extend_from_slice(&compress_zstd(&region_data, compression_level, dictionary));
+
+        result
+    }
+}
+
+/// Reads the chunk in `sector_index` of a blinear v3 file, decompressing only its bucket.
+#[cfg(not(target_arch = "wasm32"))]
+fn read_bucketed_chunk(file: &mut File, body: usize, sector_index: i32, dictionary: Option<&[u8]>) -> Result<Option<Chunk>, ParseError> {
+    let mut grid_size = [0u8];
+    file.seek(SeekFrom::Start(body as u64)).map_err(|_| ParseError::ReadError)?;
+    file.read_exact(&mut grid_size).map_err(|_| ParseError::ReadError)?;
+    let grid_size = grid_size[0];
+    if !LINEAR_GRID_SIZES.contains(&grid_size) {
+        return Err(ParseError::InvalidGridSize(grid_size));
+    }
+
+    let bucket_dim = 32 / grid_size as usize;
+    let slot = sector_index as usize;
+    let bucket = (slot / 32 / bucket_dim) * grid_size as usize + slot % 32 / bucket_dim;
+
+    let mut entry = [0u8; 8];
+    file.seek(SeekFrom::Current(bucket as i64 * 8)).map_err(|_| ParseError::ReadError)?;
+    file.read_exact(&mut entry).map_err(|_| ParseError::ReadError)?;
+
+    let offset = u32::from_be_bytes(entry[0..4].try_into().unwrap()) as u64;
+    let length = u32::from_be_bytes(entry[4..8].try_into().unwrap()) as usize;
+    if length == 0 {
+        return Ok(None);
+    }
+
+    let mut frame = vec![0u8; length];
+    let frames_start = body + 1 + grid_size as usize * grid_size as usize * 8;
+    file.seek(SeekFrom::Start(frames_start as u64 + offset)).map_err(|_| ParseError::ReadError)?;
+    file.read_exact(&mut frame).map_err(|_| ParseError::ReadError)?;
+    let decompressed = decompress_zstd(&frame, usize::MAX, dictionary)?;
+
+    let mut buffer_pointer = 0;
+    for bucket_slot in blinear_bucket_slots(grid_size, bucket) {
+        let section_len = i32::from_be_bytes(checked_slice(&decompressed, buffer_pointer, 4)?.try_into().unwrap()).max(0) as usize;
+        buffer_pointer += 4;
+
+        if bucket_slot == slot {
+            if section_len == 0 {
+                return Ok(None);
+            }
+            let section = checked_slice(&decompressed, buffer_pointer, section_len)?;
+            let chunk = parse_blinear_section(sector_index, section, &ParseLimits::DEFAULT)?;
+            return Ok(Some(chunk.with_sizes(section.len() - 16, length * section.len() / decompressed.len())));
+        }
+        buffer_pointer += section_len;
+    }
+
+    Ok(None)
+}
+
+/// Offset of the zstd data of a blinear file, behind the features frame if there is one. Only
+/// the first 26 bytes are looked at.
+fn blinear_body_offset(bytes: &[u8]) -> Option<usize> {
+    if bytes.get(8)? & BLINEAR_FLAG_FEATURES == 0 {
+        return Some(BLINEAR_HEADER_SIZE);
+    }
+
+    let frame_size = u32::from_le_bytes(bytes.get(BLINEAR_HEADER_SIZE + 4..BLINEAR_HEADER_SIZE + 8)?.try_into().unwrap());
+    BLINEAR_HEADER_SIZE.checked_add(8 + frame_size as usize)
+}
+
+/// Whether the bytes start a blinear file written with a chunk frame index, only the header,
+/// the features frame and the first 8 bytes after it are looked at.
+pub fn is_seekable_blinear(bytes: &[u8]) -> bool {
+    let Some(body) = blinear_body_offset(bytes) else {
+        return false;
+    };
+
+    bytes.len() >= body + 8
+        && i64::from_be_bytes(bytes[0..8].try_into().unwrap()) == BLINEAR_FILE_HEAD
+        && bytes[8] & BLINEAR_VERSION_MASK == 0x02
+        && u32::from_le_bytes(bytes[body..body + 4].try_into().unwrap()) == BLINEAR_SEEK_INDEX_MAGIC
+        && u32::from_le_bytes(bytes[body + 4..body + 8].try_into().unwrap()) as usize == BLINEAR_SEEK_INDEX_SIZE
+}
+
+/// The 18 byte blinear file header: magic, version and flags, timestamp and compression level.
+fn blinear_header(timestamp: i64, compression_level: u8, version: u8) -> [u8; BLINEAR_HEADER_SIZE] {
+    let mut file_header = [0_u8; BLINEAR_HEADER_SIZE];
+
+    file_header[0..8].copy_from_slice(&BLINEAR_FILE_HEAD.to_be_bytes()); // superblock
+    file_header[8..9].copy_from_slice(&version.to_be_bytes()); // version and flags
+    file_header[9..17].copy_from_slice(&timestamp.to_be_bytes()); // master file timestamp
+    file_header[17..18].copy_from_slice(&compression_level.to_be_bytes()); // compression level
+
+    file_header
+}
+
+/// Slots in the order blinear stores them: row by row, or along a Hilbert curve.
+fn blinear_slot_order(hilbert_order: bool) -> Vec<usize> {
+    if hilbert_order {
+        (0..1024).map(hilbert_slot).collect()
+    } else {
+        (0..1024).collect()
+    }
+}
+
+/// The slot at distance `distance` along a Hilbert curve covering the 32x32 region.
+fn hilbert_slot(distance: usize) -> usize {
+    let (mut x, mut z, mut rest) = (0, 0, distance);
+
+    let mut size = 1;
+    while size < 32 {
+        let quadrant_x = 1 & (rest / 2);
+        let quadrant_z = 1 & (rest ^ quadrant_x);
+        if quadrant_z == 0 {
+            if quadrant_x == 1 {
+                x = size - 1 - x;
+                z = size - 1 - z;
+            }
+            std::mem::swap(&mut x, &mut z);
+        }
+
+        x += size * quadrant_x;
+        z += size * quadrant_z;
+        rest /= 4;
+        size *= 2;
+    }
+
+    x + z * 32
+}
+
+/// The body of a seekable blinear file: the frame index as a skippable frame, then the frames.
+fn blinear_seekable_body(sections: &[Option<Vec<u8>>], order: &[usize], compression_level: u8, dictionary: Option<&[u8]>) -> Vec<u8> {
+    let mut index = vec![0u8; BLINEAR_SEEK_INDEX_SIZE];
+    let mut frames = Vec::new();
+
+    // empty slots are written into the frame of the next chunk, trailing ones into a last frame
+    let mut pending = Vec::new();
+    for &sector_index in order {
+        let Some(section) = &sections[sector_index] else {
+            pending.extend_from_slice(&0i32.to_be_bytes());
+            continue;
+        };
+        pending.extend_from_slice(section);
+
+        let frame = compress_zstd(&pending, compression_level, dictionary);
+        index[sector_index * 8..sector_index * 8 + 4].copy_from_slice(&(frames.len() as u32).to_be_bytes());
+        index[sector_index * 8 + 4..sector_index * 8 + 8].copy_from_slice(&(frame.len() as u32).to_be_bytes());
+        frames.extend_from_slice(&frame);
+        pending.clear();
+    }
+    if !pending.is_empty() {
+        frames.extend_from_slice(&compress_zstd(&pending, compression_level, dictionary));
+    }
+
+    let mut body = Vec::with_capacity(8 + BLINEAR_SEEK_INDEX_SIZE + frames.len());
+    body.extend_from_slice(&BLINEAR_SEEK_INDEX_MAGIC.to_le_bytes());
+    body.extend_from_slice(&(BLINEAR_SEEK_INDEX_SIZE as u32).to_le_bytes());
+    body.extend_from_slice(&index);
+    body.extend_from_slice(&frames);
+
+    body
+}
+
+/// Slots of one v3 bucket, row by row within the bucket.
+fn blinear_bucket_slots(grid_size: u8, bucket: usize) -> Vec<usize> {
+    let bucket_dim = 32 / grid_size as usize;
+    let (bucket_x, bucket_z) = (bucket % grid_size as usize, bucket / grid_size as usize);
+
+    (0..bucket_dim)
+        .flat_map(|z| (0..bucket_dim).map(move |x| (bucket_z * bucket_dim + z) * 32 + bucket_x * bucket_dim + x))
+        .collect()
+}
+
+/// The body of a blinear v3 file: the grid size, the offset and length of every bucket's zstd
+/// frame, then the frames. Empty buckets have no frame.
+fn blinear_bucketed_body(sections: &[Option<Vec<u8>>], grid_size: u8, compression_level: u8, dictionary: Option<&[u8]>, reuse: Option<&ReusedBuckets>) -> Vec<u8> {
+    assert!(LINEAR_GRID_SIZES.contains(&grid_size), "Invalid blinear grid size {grid_size}");
+
+    let bucket_count = grid_size as usize * grid_size as usize;
+    let bucket_frames = parallel::map((0..bucket_count).collect(), |bucket| {
+        let slots = blinear_bucket_slots(grid_size, bucket);
+        if let Some(frame) = reuse.and_then(|reuse| reuse.frame(RegionFormat::Blinear, grid_size, bucket, &slots)) {
+            frame.to_vec()
+        } else if slots.iter().any(|slot| sections[*slot].is_some()) {
+            let mut bucket_data = Vec::new();
+            for slot in slots {
+                match &sections[slot] {
+                    Some(section) => bucket_data.extend_from_slice(section),
+                    None => bucket_data.extend_from_slice(&0i32.to_be_bytes()),
+                }
+            }
+            compress_zstd(&bucket_data, compression_level, dictionary)
+        } else {
+            Vec::new()
+        }
+    });
+
+    let mut index = Vec::with_capacity(bucket_count * 8);
+    let mut frames = Vec::new();
+    for frame in bucket_frames {
+        index.extend_from_slice(&(frames.len() as u32).to_be_bytes());
+        index.extend_from_slice(&(frame.len() as u32).to_be_bytes());
+        frames.extend_from_slice(&frame);
+    }
+
+    let mut body = Vec::with_capacity(1 + index.len() + frames.len());
+    body.push(grid_size);
+    body.extend_from_slice(&index);
+    body.extend_from_slice(&frames);
+
+    body
+}
+
+/// Every bucket's compressed frame with its stored hash, in bucket order, and the features of a
+/// linear v2 file. Empty buckets have empty frames.
+fn linear_v2_buckets(bytes: &[u8], grid_size: u8) -> Result<LinearBuckets<'_>, ParseError> {
+    let mut pointer = 26 + 128;
+    let features = parse_features(bytes, &mut pointer)?;
+
+    let bucket_count = grid_size as usize * grid_size as usize;
+    let headers = checked_slice(bytes, pointer, bucket_count * 13)?;
+    pointer += bucket_count * 13;
+
+    let mut buckets = Vec::with_capacity(bucket_count);
+    for header in headers.chunks_exact(13) {
+        let size = i32::from_be_bytes(header[0..4].try_into().unwrap()).max(0) as usize;
+        // header[4] is the compression level, unused
+        let hash = u64::from_be_bytes(header[5..13].try_into().unwrap());
+
+        buckets.push((checked_slice(bytes, pointer, size)?, hash));
+        pointer += size;
+    }
+
+    Ok((features, buckets))
+}
+
+/// The compression level a linear v2 file records for its first non empty bucket.
+pub fn linear_compression_level(bytes: &[u8]) -> Option<u8> {
+    if bytes.get(0..8)? != LINEAR_FILE_HEAD.to_be_bytes() || *bytes.get(8)? != LINEAR_V2 {
+        return None;
+    }
+
+    let mut pointer = 26 + 128;
+    parse_features(bytes, &mut pointer).ok()?;
+    let bucket_count = *bytes.get(17)? as usize * *bytes.get(17)? as usize;
+    let headers = bytes.get(pointer..pointer + bucket_count * 13)?;
+
+    headers.chunks_exact(13).find(|header| i32::from_be_bytes(header[0..4].try_into().unwrap()) > 0).map(|header| header[4])
+}
+
+type LinearBuckets<'a> = (Vec<(String, i32)>, Vec<(&'a [u8], u64)>);
+
+/// The bucket index of a blinear v3 body: the grid size and each bucket's frame as offset and
+/// length into the body.
+fn parse_blinear_bucket_index(body: &[u8]) -> Result<(u8, Vec<(usize, usize)>), ParseError> {
+    let grid_size = *body.first().ok_or(ParseError::ReadError)?;
+    if !LINEAR_GRID_SIZES.contains(&grid_size) {
+        return Err(ParseError::InvalidGridSize(grid_size));
+    }
+
+    let bucket_count = grid_size as usize * grid_size as usize;
+    let index = checked_slice(body, 1, bucket_count * 8)?;
+    let frames_start = 1 + bucket_count * 8;
+
+    let frames = index
+        .chunks_exact(8)
+        .map(|entry| {
+            let offset = u32::from_be_bytes(entry[0..4].try_into().unwrap()) as usize;
+            (frames_start + offset, u32::from_be_bytes(entry[4..8].try_into().unwrap()) as usize)
+        })
+        .collect();
+
+    Ok((grid_size, frames))
+}
+
+/// Decompressed blinear sections with the slots they are written for, in order.
+type SectionBlock = (Vec<usize>, Vec<u8>);
+
+/// Decompresses every non-empty bucket of a blinear v3 body, in parallel where threads exist.
+fn decompress_blinear_buckets(body: &[u8], limits: &ParseLimits, dictionary: Option<&[u8]>) -> Result<Vec<SectionBlock>, ParseError> {
+    let (grid_size, frames) = parse_blinear_bucket_index(body)?;
+    let frames = frames
+        .into_iter()
+        .enumerate()
+        .filter(|(_, (_, length))| *length > 0)
+        .map(|(bucket, (offset, length))| Ok((bucket, checked_slice(body, offset, length)?)))
+        .collect::<Result<Vec<_>, ParseError>>()?;
+
+    let decompress = |(bucket, frame): (usize, &[u8])| -> Result<SectionBlock, ParseError> {
+        Ok((blinear_bucket_slots(grid_size, bucket), decompress_zstd(frame, limits.max_decompressed_size, dictionary)?))
+    };
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        use rayon::prelude::*;
+        frames.into_par_iter().map(decompress).collect()
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        frames.into_iter().map(decompress).collect()
+    }
+}
+
+/// The length prefixed blinear section of every slot, with chunk data stored as neighbour
+/// deltas where that helps if `neighbor_delta` is set.
+fn blinear_sections(slots: &[Option<&RawChunk>], neighbor_delta: bool) -> Vec<Option<Vec<u8>>> {
+    let payloads: Vec<Option<&[u8]>> = slots.iter().map(|slot| slot.map(|chunk| chunk.data.as_slice())).collect();
+
+    slots
+        .iter()
+        .enumerate()
+        .map(|(sector_index, slot)| {
+            let chunk = slot.as_ref()?;
+            Some(match neighbor_delta {
+                true => blinear_section(chunk.timestamp, &chunk.data, &encode_neighbor_delta(&payloads, sector_index)),
+                false => blinear_section(chunk.timestamp, &chunk.data, &chunk.data),
+            })
+        })
+        .collect()
+}
+
+/// One chunk of a blinear body, prefixed by its length. The hash covers the chunk NBT even when
+/// the data is stored as a delta.
+fn blinear_section(timestamp: i64, chunk_data: &[u8], stored: &[u8]) -> Vec<u8> {
+    let mut section = Vec::with_capacity(stored.len() + 20);
+    section.extend_from_slice(&(stored.len() as i32 + 16).to_be_bytes());
+    section.extend_from_slice(&(stored.len() as i32).to_be_bytes()); // len
+    section.extend_from_slice(&timestamp.to_be_bytes()); // timestamp of chunk
+    section.extend_from_slice(&blinear_hash(chunk_data).to_be_bytes()); // xxhash32 of chunk data
+    section.extend_from_slice(stored); // chunk data
+
+    section
+}
+
+/// Parses a blinear section into its chunk.
+#[cfg(not(target_arch = "wasm32"))]
+fn parse_blinear_section(sector_index: i32, section: &[u8], limits: &ParseLimits) -> Result<Chunk, ParseError> {
+    let (timestamp_of_chunk, _, data) = blinear_section_parts(section)?;
+
+    Ok(Chunk::from_sector(sector_index, timestamp_of_chunk, data, &limits.nbt)?)
+}
+
+/// The timestamp, hash and stored data of a blinear section.
+fn blinear_section_parts(section: &[u8]) -> Result<(i64, i32, &[u8]), ParseError> {
+    if section.len() < 16 {
+        return Err(ParseError::ReadError);
+    }
+
+    let _length_of_chunk = i32::from_be_bytes(section[0..4].try_into().unwrap()); // unused
+    let timestamp_of_chunk = i64::from_be_bytes(section[4..12].try_into().unwrap());
+    let xxhash32_of_chunk = i32::from_be_bytes(section[12..16].try_into().unwrap());
+
+    Ok((timestamp_of_chunk, xxhash32_of_chunk, &section[16..]))
+}
+
+/// The xxhash32 blinear sections store of their chunk NBT.
+fn blinear_hash(chunk_data: &[u8]) -> i32 {
+    let mut hasher = XxHash32::with_seed(0x0721);
+    hasher.write(chunk_data);
+    hasher.finish() as i32
+}
+
+fn west_and_north(sector_index: usize) -> [(u8, Option<usize>); 2] {
+    [
+        (DELTA_WEST, (!sector_index.is_multiple_of(32)).then(|| sector_index - 1)),
+        (DELTA_NORTH, sector_index.checked_sub(32)),
+    ]
+}
+
+fn xor_delta(data: &[u8], reference: &[u8]) -> Vec<u8> {
+    data.iter().enumerate().map(|(index, byte)| byte ^ reference.get(index).copied().unwrap_or(0)).collect()
+}
+
+/// The chunk data prefixed by its `DELTA_*` kind: XORed with the neighbour leaving the fewest
+/// non-zero bytes, or raw unless a delta zeroes most of the data. Shifted data XORs into noise
+/// that compresses worse than the raw bytes.
+fn encode_neighbor_delta(payloads: &[Option<&[u8]>], sector_index: usize) -> Vec<u8> {
+    let payload = payloads[sector_index].unwrap_or_default();
+    let (mut kind, mut stored, mut cost) = (DELTA_RAW, payload.to_vec(), payload.len() / 8);
+
+    for (neighbour_kind, neighbour) in west_and_north(sector_index) {
+        let Some(reference) = neighbour.and_then(|neighbour| payloads[neighbour]) else {
+            continue;
+        };
+
+        let delta = xor_delta(payload, reference);
+        let delta_cost = delta.iter().filter(|byte| **byte != 0).count();
+        if delta_cost < cost {
+            (kind, stored, cost) = (neighbour_kind, delta, delta_cost);
+        }
+    }
+
+    stored.insert(0, kind);
+    stored
+}
+
+/// Decodes the chunk data of neighbour delta sections, in slot order so references are always
+/// decoded first. Indexed by slot.
+fn resolve_neighbor_deltas(sections: &[(usize, &[u8])]) -> Result<Vec<Option<Vec<u8>>>, ParseError> {
+    let mut stored = vec![None; 1024];
+    for (sector_index, section) in sections {
+        stored[*sector_index] = Some(section.get(16..).ok_or(ParseError::ReadError)?);
+    }
+
+    let mut decoded: Vec<Option<Vec<u8>>> = vec![None; 1024];
+    for sector_index in 0..1024 {
+        let Some((&kind, data)) = stored[sector_index].and_then(|stored: &[u8]| stored.split_first()) else {
+            continue;
+        };
+
+        let payload = match kind {
+            DELTA_RAW => data.to_vec(),
+            DELTA_WEST | DELTA_NORTH => {
+                let (_, neighbour) = west_and_north(sector_index).into_iter().find(|(neighbour_kind, _)| *neighbour_kind == kind).unwrap();
+                let reference = neighbour.and_then(|neighbour| decoded[neighbour].as_deref()).ok_or(ParseError::DeltaReference)?;
+                xor_delta(data, reference)
+            }
+            _ => return Err(ParseError::ReadError),
+        };
+        decoded[sector_index] = Some(payload);
+    }
+
+    Ok(decoded)
+}
+
+fn decompress_mca_chunk(compression_type: u8, data: &[u8], max_size: usize) -> Result<Vec<u8>, ParseError> {
+    time(Phase::Decompress, || match compression_type {
+        MCA_GZIP => read_limited(GzDecoder::new(data), max_size),
+        MCA_ZLIB => read_limited(ZlibDecoder::new(data), max_size),
+        MCA_UNCOMPRESSED if data.len() > max_size => Err(ParseError::DecompressedTooLarge(max_size)),
+        MCA_UNCOMPRESSED => Ok(data.to_vec()),
+        other => Err(ParseError::UnsupportedCompression(other))
+    })
+}
+
+/// A zlib compressed chunk on its way into an Anvil file.
+struct McaChunk {
+    /// The chunk's position, for errors
+    position: (i32, i32),
+    sector_index: usize,
+    timestamp: i64,
+    compressed: Vec<u8>,
+}
+
+/// Lays out the chunks as an Anvil file, each in its own run of 4096 byte sectors in slot order.
+fn mca_bytes(mut chunks: Vec<McaChunk>) -> Result<Vec<u8>, WriteError> {
+    let mut result = vec![0u8; 8192];
+    chunks.sort_by_key(|chunk| chunk.sector_index);
+
+    for chunk in chunks {
+        // length includes the compression type byte
+        let length = chunk.compressed.len() + 1;
+        let sector_count = (length + 4).div_ceil(4096);
+        if sector_count > 255 {
+            return Err(WriteError::ChunkTooLarge(chunk.position.0, chunk.position.1));
+        }
+
+        let sector_index = chunk.sector_index;
+        let sector_offset = result.len() / 4096;
+        let location = ((sector_offset as u32) << 8) | sector_count as u32;
+
+        result[sector_index * 4..sector_index * 4 + 4].copy_from_slice(&location.to_be_bytes());
+        result[4096 + sector_index * 4..4096 + sector_index * 4 + 4].copy_from_slice(&(chunk.timestamp as i32).to_be_bytes());
+
+        result.extend_from_slice(&(length as i32).to_be_bytes());
+        result.push(MCA_ZLIB);
+        result.extend_from_slice(&chunk.compressed);
+        result.resize((sector_offset + sector_count) * 4096, 0);
+    }
+
+    Ok(result)
+}
+
+fn compress_zlib(data: &[u8], compression_level: u8) -> Vec<u8> {
+    time(Phase::Compress, || {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::new(compression_level.min(MAX_ZLIB_LEVEL) as u32));
+        encoder.write_all(data).expect("Writing to a Vec can not fail");
+        encoder.finish().expect("Writing to a Vec can not fail")
+    })
+}
+
+fn compress_zstd(data: &[u8], compression_level: u8, dictionary: Option<&[u8]>) -> Vec<u8> {
+    let compressed = time(Phase::Compress, || match dictionary {
+        Some(dictionary) => zstd::bulk::Compressor::with_dictionary(compression_level as i32, dictionary).and_then(|mut compressor| compressor.compress(data)),
+        None => zstd::encode_all(data, compression_level as i32),
+    });
+
+    compressed.expect("Compressing into a Vec can not fail")
+}
+
+fn decompress_zstd(data: &[u8], max_size: usize, dictionary: Option<&[u8]>) -> Result<Vec<u8>, ParseError> {
+    time(Phase::Decompress, || match dictionary {
+        Some(dictionary) => read_limited(zstd::stream::read::Decoder::with_dictionary(data, dictionary).map_err(|_| ParseError::ReadError)?, max_size),
+        None => read_limited(zstd::stream::read::Decoder::new(data).map_err(|_| ParseError::ReadError)?, max_size),
+    })
+}
+
+/// Reads a decompressing stream to the end, failing as soon as it produces more than `max_size` bytes.
+fn read_limited(reader: impl Read, max_size: usize) -> Result<Vec<u8>, ParseError> {
+    let mut decompressed = Vec::new();
+    reader
+        .take((max_size as u64).saturating_add(1))
+        .read_to_end(&mut decompressed)
+        .map_err(|_| ParseError::ReadError)?;
+
+    if decompressed.len() > max_size {
+        return Err(ParseError::DecompressedTooLarge(max_size));
+    }
+
+    Ok(decompressed)
+}
+
+/// Reads NBT feature entries (name length, name, i32 value) up to the terminating zero length.
+fn parse_features(bytes: &[u8], pointer: &mut usize) -> Result<Vec<(String, i32)>, ParseError> {
+    let mut features = Vec::new();
+
+    loop {
+        let name_length = *bytes.get(*pointer).ok_or(ParseError::ReadError)? as usize;
+        *pointer += 1;
+
+        if name_length == 0 {
+            return Ok(features);
+        }
+
+        let name = String::from_utf8(checked_slice(bytes, *pointer, name_length)?.to_vec()).map_err(|_| ParseError::ReadError)?;
+        let value = i32::from_be_bytes(checked_slice(bytes, *pointer + name_length, 4)?.try_into().unwrap());
+        *pointer += name_length + 4;
+
+        features.push((name, value));
+    }
+}
+
+/// The NBT features section, leaving out names that are empty or longer than 255 bytes.
+fn features_to_bytes(features: &[(String, i32)]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for (name, value) in features.iter().filter(|(name, _)| (1..=255).contains(&name.len())) {
+        bytes.push(name.len() as u8);
+        bytes.extend_from_slice(name.as_bytes());
+        bytes.extend_from_slice(&value.to_be_bytes());
+    }
+    bytes.push(0);
+
+    bytes
+}
+
+fn checked_slice(bytes: &[u8], start: usize, length: usize) -> Result<&[u8], ParseError> {
+    start
+        .checked_add(length)
+        .and_then(|end| bytes.get(start..end))
+        .ok_or(ParseError::ReadError)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nbt::tag::Tag;
+
+    fn sample_chunk_nbt(x: i32, z: i32) -> Tag {
+        Tag::Compound {
+            name: None,
+            value: vec![
+                Tag::Int { name: Some(String::from("xPos")), value: x },
+                Tag::Int { name: Some(String::from("zPos")), value: z },
+            ],
+        }
+    }
+
+    fn mca_with_chunk(sector_index: usize, chunk: &Tag) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&chunk.to_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut bytes = vec![0u8; 8192];
+        bytes[sector_index * 4..sector_index * 4 + 4].copy_from_slice(&((2u32 << 8) | 1).to_be_bytes());
+        bytes[4096 + sector_index * 4..4096 + sector_index * 4 + 4].copy_from_slice(&1234i32.to_be_bytes());
+
+        bytes.extend_from_slice(&(compressed.len() as i32 + 1).to_be_bytes());
+        bytes.push(2);
+        bytes.extend_from_slice(&compressed);
+        bytes.resize(bytes.len().div_ceil(4096) * 4096, 0);
+        bytes
+    }
+
+    #[test]
+    fn test_read_mca_sparse() {
+        let mut bytes = mca_with_chunk(33, &sample_chunk_nbt(1, 1));
+        // a free sector behind the chunk, as left by a chunk that moved
+        bytes.extend_from_slice(&[0xAB; 4096]);
+
+        let sparse = read_mca_sparse(&mut std::io::Cursor::new(&bytes)).unwrap();
+        assert_eq!(sparse.len(), bytes.len());
+        assert_eq!(sparse[..12288], bytes[..12288]);
+        assert!(sparse[12288..].iter().all(|byte| *byte == 0));
+
+        let region = Region::from_bytes_mca(&sparse, &ParseLimits::DEFAULT).unwrap();
+        assert_eq!(region.chunks().len(), 1);
+    }
+
+    /// A linear v2 file of region -1, 2 holding one chunk in the last slot of the last bucket,
+    /// built by hand rather than by the writer.
+    fn linear_with_last_chunk(grid_size: u8, chunk: &Tag) -> Vec<u8> {
+        let bucket_dim = 32 / grid_size.max(1) as usize;
+        let mut bucket = vec![0u8; (bucket_dim * bucket_dim - 1) * 12];
+        let data = chunk.to_bytes();
+        bucket.extend_from_slice(&(data.len() as i32 + 8).to_be_bytes());
+        bucket.extend_from_slice(&77i64.to_be_bytes());
+        bucket.extend_from_slice(&data);
+        let compressed = zstd::encode_all(bucket.as_slice(), 3).unwrap();
+
+        let mut bytes = LINEAR_FILE_HEAD.to_be_bytes().to_vec();
+        bytes.push(0x03);
+        bytes.extend_from_slice(&5i64.to_be_bytes());
+        bytes.push(grid_size);
+        bytes.extend_from_slice(&(-1i32).to_be_bytes());
+        bytes.extend_from_slice(&2i32.to_be_bytes());
+        bytes.extend_from_slice(&[0u8; 128]);
+        bytes.push(0);
+        let bucket_count = grid_size as usize * grid_size as usize;
+        for index in 0..bucket_count {
+            let size = if index + 1 == bucket_count { compressed.len() as i32 } else { 0 };
+            bytes.extend_from_slice(&size.to_be_bytes());
+            bytes.extend_from_slice(&[3, 0, 0, 0, 0, 0, 0, 0, 0]);
+        }
+        bytes.extend_from_slice(&compressed);
+        bytes
+    }
+
+    #[test]
+    fn test_linear_v2_grid_sizes() {
+        for grid_size in LINEAR_GRID_SIZES {
+            let region = Region::from_bytes(RegionFormat::Linear, &linear_with_last_chunk(grid_size, &sample_chunk_nbt(-1, 95))).unwrap();
+            assert_eq!(region.chunks().len(), 1, "grid size {grid_size}");
+
+            let chunk = &region.chunks()[0];
+            assert_eq!((chunk.x(), chunk.z(), chunk.timestamp()), (-1, 95, 77));
+            assert_eq!(chunk.get_data(), &sample_chunk_nbt(-1, 95));
+        }
+
+        for grid_size in [0, 3, 5, 64, 255] {
+            let bytes = linear_with_last_chunk(1, &sample_chunk_nbt(-1, 95));
+            let mut bytes = bytes.clone();
+            bytes[17] = grid_size;
+            assert!(matches!(Region::from_bytes(RegionFormat::Linear, &bytes), Err(ParseError::InvalidGridSize(size)) if size == grid_size));
+        }
+    }
+
+    #[test]
+    fn test_linear_version_detection() {
+        let data = sample_chunk_nbt(1, 2).to_bytes();
+        let mut body = vec![0u8; 1024 * 8];
+        body[(2 * 32 + 1) * 8..(2 * 32 + 1) * 8 + 4].copy_from_slice(&(data.len() as i32).to_be_bytes());
+        body[(2 * 32 + 1) * 8 + 4..(2 * 32 + 2) * 8].copy_from_slice(&42i32.to_be_bytes());
+        body.extend_from_slice(&data);
+        let compressed = zstd::encode_all(body.as_slice(), 3).unwrap();
+
+        let mut v1 = LINEAR_FILE_HEAD.to_be_bytes().to_vec();
+        v1.push(0x01);
+        v1.extend_from_slice(&9i64.to_be_bytes());
+        v1.push(3);
+        v1.extend_from_slice(&1i16.to_be_bytes());
+        v1.extend_from_slice(&(compressed.len() as i32).to_be_bytes());
+        v1.extend_from_slice(&0i64.to_be_bytes());
+        v1.extend_from_slice(&compressed);
+        v1.extend_from_slice(&LINEAR_FILE_HEAD.to_be_bytes());
+
+        let v2 = Region::from_bytes_linear_v1(&v1, &ParseLimits::default()).unwrap().to_bytes(RegionFormat::Linear, Some((-1, 1)), 9, 3).unwrap();
+
+        for (version, bytes) in [(0x01, v1.clone()), (0x02, { let mut v1 = v1.clone(); v1[8] = 0x02; v1 }), (0x03, v2)] {
+            let region = Region::from_bytes(RegionFormat::Linear, &bytes).unwrap();
+            assert_eq!(region.timestamp(), 9, "version {version}");
+            assert_eq!(region.chunks().len(), 1);
+
+            let chunk = &region.chunks()[0];
+            assert_eq!((chunk.x() & 31, chunk.z() & 31, chunk.timestamp()), (1, 2, 42));
+            assert_eq!(chunk.get_data(), &sample_chunk_nbt(1, 2));
+        }
+
+        v1[8] = 0x04;
+        assert!(matches!(Region::from_bytes(RegionFormat::Linear, &v1), Err(VersionError)));
+    }
+
+    #[test]
+    fn test_raw_region() {
+        let chunks: Vec<Chunk> = (0..3).map(|x| Chunk::new_from_block_pos(32 + x, -32, 6, sample_chunk_nbt(32 + x, -32))).collect();
+        let linear = Region::new(chunks, 0).to_bytes_linear_v2(1, -1, 8, 3, 1).unwrap();
+
+        // linear to blinear and back without parsing a chunk
+        let raw = RawRegion::from_bytes_linear(&linear, &ParseLimits::default(), true).unwrap();
+        assert_eq!(raw.chunks().len(), 3);
+        let blinear = raw.to_bytes_blinear(8, 3, BlinearOptions::default(), None, None);
+        let raw = RawRegion::from_bytes_blinear(&blinear, &ParseLimits::default(), None, true).unwrap();
+        let relinear = raw.to_bytes_linear_v2(1, -1, 8, 3, 1, None).unwrap();
+        assert_eq!(relinear, linear);
+
+        let region = Region::from_bytes(RegionFormat::Linear, &relinear).unwrap();
+        assert_eq!((region.chunks()[2].x(), region.chunks()[2].z()), (34, -32));
+        assert_eq!(region.chunks()[2].get_data(), &sample_chunk_nbt(34, -32));
+
+        // the hash of the only bucket
+        let mut corrupt = linear.clone();
+        corrupt[26 + 128 + 1 + 5] ^= 1;
+        assert!(RawRegion::from_bytes_linear(&corrupt, &ParseLimits::default(), false).is_ok());
+        assert!(matches!(RawRegion::from_bytes_linear(&corrupt, &ParseLimits::default(), true), Err(ParseError::ChecksumMismatch)));
+
+        let mut region_data = blinear_section(0, b"other data", &sample_chunk_nbt(0, 0).to_bytes());
+        region_data.extend(std::iter::repeat_n(0u8, 1023 * 4));
+        let mut corrupt = blinear_header(0, 3, BLINEAR_V2).to_vec();
+        corrupt.extend_from_slice(&compress_zstd(&region_data, 3, None));
+        assert!(RawRegion::from_bytes_blinear(&corrupt, &ParseLimits::default(), None, false).is_ok());
+        assert!(matches!(RawRegion::from_bytes_blinear(&corrupt, &ParseLimits::default(), None, true), Err(ParseError::ChecksumMismatch)));
+    }
+
+    #[test]
+    fn test_reused_buckets() {
+        let region = |value: i32| {
+            let chunks = (0..2).map(|x| Chunk::new_from_block_pos(x * 16, 0, 1, sample_chunk_nbt(x * 16, value))).collect();
+            Region::new(chunks, 0).to_raw()
+        };
+
+        let linear = region(1).to_bytes_linear_v2(0, 0, 0, 3, 2, None).unwrap();
+        let blinear = region(1).to_bytes_blinear(0, 3, BlinearOptions { grid_size: Some(2), ..BlinearOptions::default() }, None, None);
+
+        // only the second chunk is marked as changed, so the first one's bucket comes from before
+        let mut changed = vec![false; 1024];
+        changed[16] = true;
+        for previous in [linear, blinear] {
+            let reuse = ReusedBuckets::new(&previous, changed.clone()).unwrap();
+            let written = match reuse.format {
+                RegionFormat::Linear => region(2).to_bytes_linear_v2(0, 0, 0, 3, 2, Some(&reuse)).unwrap(),
+                _ => region(2).to_bytes_blinear(0, 3, BlinearOptions { grid_size: Some(2), ..BlinearOptions::default() }, None, Some(&reuse)),
+            };
+
+            let chunks = Region::from_bytes(reuse.format, &written).unwrap().into_chunks();
+            assert_eq!(chunks.iter().map(|chunk| chunk.get_data().clone()).collect::<Vec<_>>(), [sample_chunk_nbt(0, 1), sample_chunk_nbt(16, 2)]);
+
+            // other layouts are not reused
+            assert!(reuse.frame(reuse.format, 4, 0, &[0]).is_none());
+        }
+        assert!(ReusedBuckets::new(&region(1).to_bytes_blinear(0, 3, BlinearOptions::default(), None, None), changed).is_none());
+    }
+
+    #[test]
+    fn test_from_bytes_mca() {
+        let bytes = mca_with_chunk(33, &sample_chunk_nbt(1, 1));
+        let region = Region::from_bytes(RegionFormat::Mca, &bytes).unwrap();
+
+        assert_eq!(region.chunks().len(), 1);
+        assert_eq!(region.timestamp(), 1234);
+
+        let chunk = &region.chunks()[0];
+        assert_eq!((chunk.x(), chunk.z()), (1, 1));
+        assert_eq!(chunk.position_to_sector_index(), 33);
+        assert_eq!(chunk.get_data(), &sample_chunk_nbt(1, 1));
+    }
+
+    #[test]
+    fn test_blinear_skips_negative_sector_lengths() {
+        let sectors: Vec<u8> = (0..1024).flat_map(|_| (-1i32).to_be_bytes()).collect();
+
+        let mut bytes = (-0x200812250269i64).to_be_bytes().to_vec();
+        bytes.push(0x02);
+        bytes.extend_from_slice(&1234i64.to_be_bytes());
+        bytes.push(3);
+        bytes.extend(zstd::encode_all(sectors.as_slice(), 3).unwrap());
+
+        let region = Region::from_bytes(RegionFormat::Blinear, &bytes).unwrap();
+        assert!(region.chunks().is_empty());
+    }
+
+    #[test]
+    fn test_mca_round_trip() {
+        let mut bytes = mca_with_chunk(33, &sample_chunk_nbt(1, 1));
+        let second = mca_with_chunk(1023, &sample_chunk_nbt(31, 31));
+        let second_location = ((bytes.len() / 4096) as u32) << 8 | 1;
+        bytes[1023 * 4..1024 * 4].copy_from_slice(&second_location.to_be_bytes());
+        bytes.extend_from_slice(&second[8192..]);
+
+        let region = Region::from_bytes(RegionFormat::Mca, &bytes).unwrap();
+        let written = Region::from_bytes(RegionFormat::Mca, &region.to_bytes_mca(6).unwrap()).unwrap();
+
+        assert_eq!(written.chunks().len(), 2);
+        assert_eq!(written.timestamp(), 1234);
+        for (original, rewritten) in region.chunks().iter().zip(written.chunks()) {
+            assert_eq!((original.x(), original.z()), (rewritten.x(), rewritten.z()));
+            assert_eq!(original.get_data(), rewritten.get_data());
+        }
+
+        // zstd levels beyond zlib's are refused rather than silently lowered
+        assert!(matches!(region.to_bytes(RegionFormat::Mca, None, 0, 12), Err(WriteError::InvalidCompressionLevel { level: 12, format: RegionFormat::Mca })));
+        assert!(region.to_bytes(RegionFormat::Blinear, None, 0, 12).is_ok());
+    }
+
+    #[test]
+    fn test_strict_limits() {
+        let bytes = mca_with_chunk(0, &sample_chunk_nbt(0, 0));
+        let limits = ParseLimits { max_decompressed_size: 8, ..ParseLimits::STRICT };
+
+        assert!(matches!(
+            Region::from_bytes_with_limits(RegionFormat::Mca, &bytes, &limits),
+            Err(ParseError::DecompressedTooLarge(8))
+        ));
+        assert!(Region::from_bytes_with_limits(RegionFormat::Mca, &bytes, &ParseLimits::STRICT).is_ok());
+    }
+
+    #[test]
+    fn test_truncated_input_is_an_error() {
+        let mca = mca_with_chunk(0, &sample_chunk_nbt(0, 0));
+        let blinear = Region::from_bytes(RegionFormat::Mca, &mca).unwrap().to_bytes_blinear(0, 3);
+
+        assert!(Region::from_bytes(RegionFormat::Mca, &mca[..8200]).is_err());
+        assert!(Region::from_bytes(RegionFormat::Blinear, &blinear[..blinear.len() - 4]).is_err());
+        assert!(Region::from_bytes(RegionFormat::Blinear, &blinear[..10]).is_err());
+    }
+
+    #[test]
+    fn test_linear_v2_round_trip() {
+        let chunks = vec![
+            Chunk::new_from_block_pos(-32, 32, 5, sample_chunk_nbt(-32, 32)),
+            Chunk::new_from_block_pos(-17, 61, 6, sample_chunk_nbt(-17, 61)),
+            Chunk::new_from_block_pos(-1, 63, 7, sample_chunk_nbt(-1, 63)),
+        ];
+        let region = Region::new(chunks, 0);
+
+        for grid_size in LINEAR_GRID_SIZES {
+            let bytes = region.to_bytes_linear_v2(-1, 1, 9, 3, grid_size).unwrap();
+            assert_eq!(bytes[17], grid_size);
+
+            let parsed = Region::from_bytes(RegionFormat::Linear, &bytes).unwrap();
+            assert_eq!(parsed.timestamp(), 9);
+            let mut positions: Vec<_> = parsed.chunks().iter().map(|chunk| (chunk.x(), chunk.z(), chunk.timestamp())).collect();
+            positions.sort();
+            assert_eq!(positions, [(-32, 32, 5), (-17, 61, 6), (-1, 63, 7)]);
+            for chunk in parsed.chunks() {
+                assert_eq!(chunk.get_data(), &sample_chunk_nbt(chunk.x(), chunk.z()));
+            }
+        }
+
+        // the header records the region passed in, never one guessed from the chunk positions
+        let bytes = Region::from_bytes(RegionFormat::Mca, &region.to_bytes_mca(3).unwrap()).unwrap().to_bytes(RegionFormat::Linear, Some((3, -2)), 0, 3).unwrap();
+        assert_eq!(bytes[18..26], [3i32.to_be_bytes(), (-2i32).to_be_bytes()].concat());
+        assert!(matches!(region.to_bytes(RegionFormat::Linear, None, 0, 3), Err(WriteError::MissingRegionCoords)));
+        assert!(matches!(region.to_bytes_linear_v2(0, 0, 0, 3, 3), Err(WriteError::InvalidGridSize(3))));
+    }
+
+    #[test]
+    fn test_features_round_trip() {
+        let mut region = Region::new(vec![Chunk::new_from_block_pos(1, 2, 3, sample_chunk_nbt(1, 2))], 0);
+        let features = vec![(String::from("lighting"), 1), (String::from("compression_dict"), -7)];
+        region.set_features(features.clone());
+
+        let linear = region.to_bytes_linear_v2(0, 0, 0, 3, 4).unwrap();
+        let from_linear = Region::from_bytes(RegionFormat::Linear, &linear).unwrap();
+        assert_eq!(from_linear.features(), features);
+
+        let folder = std::env::temp_dir().join("bufferedlinear_tools_features_test");
+        std::fs::create_dir_all(&folder).unwrap();
+        for seekable in [false, true] {
+            let options = BlinearOptions { seekable, ..BlinearOptions::default() };
+            let blinear = from_linear.to_bytes_blinear_with(0, 3, options);
+            assert_eq!(BlinearOptions::of_file(&blinear), options);
+
+            let from_blinear = Region::from_bytes(RegionFormat::Blinear, &blinear).unwrap();
+            assert_eq!(from_blinear.features(), features);
+            assert_eq!(from_blinear.chunks()[0].get_data(), &sample_chunk_nbt(1, 2));
+
+            let path = folder.join("r.0.0.blinear");
+            std::fs::write(&path, &blinear).unwrap();
+            assert_eq!(Region::read_chunk_at(&path, 1, 2).unwrap().unwrap().timestamp(), 3);
+        }
+        std::fs::remove_dir_all(&folder).unwrap();
+
+        // files without features stay readable by readers that predate the flag
+        assert_eq!(Region::new(Vec::new(), 0).to_bytes_blinear(0, 3)[8], 0x02);
+    }
+
+    #[test]
+    fn test_blinear_v3() {
+        let chunks: Vec<Chunk> = [(0, 0), (5, 3), (31, 31), (16, 8)].iter().map(|&(x, z)| Chunk::new_from_block_pos(x, z, 4, sample_chunk_nbt(x, z))).collect();
+        let mut region = Region::new(chunks, 0);
+        region.set_features(vec![(String::from("lighting"), 1)]);
+
+        let folder = std::env::temp_dir().join("bufferedlinear_tools_v3_test");
+        std::fs::create_dir_all(&folder).unwrap();
+        let path = folder.join("r.0.0.blinear");
+
+        for grid_size in LINEAR_GRID_SIZES {
+            let options = BlinearOptions { grid_size: Some(grid_size), ..BlinearOptions::default() };
+            let bytes = region.to_bytes_blinear_with(0, 3, options);
+            assert_eq!(bytes[8] & BLINEAR_VERSION_MASK, BLINEAR_V3);
+            assert_eq!(BlinearOptions::of_file(&bytes), options);
+
+            let parsed = Region::from_bytes(RegionFormat::Blinear, &bytes).unwrap();
+            assert_eq!(parsed.features(), region.features());
+            assert_eq!(parsed.chunks().len(), 4);
+            for chunk in parsed.chunks() {
+                assert_eq!(chunk.get_data(), &sample_chunk_nbt(chunk.x(), chunk.z()));
+            }
+
+            std::fs::write(&path, &bytes).unwrap();
+            assert_eq!(Region::read_chunk_at(&path, 16, 8).unwrap().unwrap().get_data(), &sample_chunk_nbt(16, 8));
+            assert!(Region::read_chunk_at(&path, 17, 8).unwrap().is_none());
+        }
+        std::fs::remove_dir_all(&folder).unwrap();
+
+        // v2 only flags are refused in v3 files
+        let mut bytes = region.to_bytes_blinear_with(0, 3, BlinearOptions { grid_size: Some(4), ..BlinearOptions::default() });
+        bytes[8] |= BLINEAR_FLAG_HILBERT_ORDER;
+        assert!(matches!(Region::from_bytes(RegionFormat::Blinear, &bytes), Err(ParseError::VersionError)));
+    }
+
+    #[test]
+    fn test_seekable_blinear() {
+        let chunks = vec![
+            Chunk::new_from_block_pos(1, 0, 7, sample_chunk_nbt(1, 0)),
+            Chunk::new_from_block_pos(5, 3, 8, sample_chunk_nbt(5, 3)),
+        ];
+        let bytes = Region::new(chunks, 0).to_bytes_blinear_seekable(0, 3);
+
+        // readers that ignore the index still see every chunk
+        let region = Region::from_bytes(RegionFormat::Blinear, &bytes).unwrap();
+        assert_eq!(region.chunks().len(), 2);
+        assert_eq!(region.chunks()[1].get_data(), &sample_chunk_nbt(5, 3));
+
+        let path = std::env::temp_dir().join("bufferedlinear_tools_seekable_test.blinear");
+        std::fs::write(&path, &bytes).unwrap();
+        let chunk = Region::read_chunk_at(&path, 37, 3).unwrap().unwrap();
+        let missing = Region::read_chunk_at(&path, 2, 0).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!((chunk.x(), chunk.z(), chunk.timestamp()), (5, 3, 8));
+        assert_eq!(chunk.get_data(), &sample_chunk_nbt(5, 3));
+        assert!(missing.is_none());
+    }
+
+    #[test]
+    fn test_hilbert_order() {
+        let order = blinear_slot_order(true);
+        let mut sorted = order.clone();
+        sorted.sort();
+        assert_eq!(sorted, blinear_slot_order(false));
+
+        // every step moves to an adjacent chunk
+        for pair in order.windows(2) {
+            let (a, b) = ((pair[0] % 32) as i32 - (pair[1] % 32) as i32, (pair[0] / 32) as i32 - (pair[1] / 32) as i32);
+            assert_eq!(a.abs() + b.abs(), 1);
+        }
+
+        let chunks = vec![
+            Chunk::new_from_block_pos(1, 0, 7, sample_chunk_nbt(1, 0)),
+            Chunk::new_from_block_pos(31, 30, 8, sample_chunk_nbt(31, 30)),
+        ];
+        let region = Region::new(chunks, 0);
+        for seekable in [false, true] {
+            let options = BlinearOptions { seekable, hilbert_order: true, ..BlinearOptions::default() };
+            let bytes = region.to_bytes_blinear_with(0, 3, options);
+            assert_eq!(BlinearOptions::of_file(&bytes), options);
+
+            let parsed = Region::from_bytes(RegionFormat::Blinear, &bytes).unwrap();
+            let mut positions: Vec<_> = parsed.chunks().iter().map(|chunk| (chunk.x(), chunk.z(), chunk.timestamp())).collect();
+            positions.sort();
+            assert_eq!(positions, [(1, 0, 7), (31, 30, 8)]);
+        }
+
+        // flagged files of another version are refused, as are flags whose data is missing
+        let mut bytes = region.to_bytes_blinear(0, 3);
+        bytes[8] = 0x13;
+        assert!(matches!(Region::from_bytes(RegionFormat::Blinear, &bytes), Err(ParseError::VersionError)));
+        bytes[8] = 0x02 | BLINEAR_FLAG_FEATURES;
+        assert!(matches!(Region::from_bytes(RegionFormat::Blinear, &bytes), Err(ParseError::HeaderError)));
+    }
+
+    #[test]
+    fn test_neighbor_delta() {
+        // a flat world: every chunk differs from its neighbours only in its position
+        let chunks: Vec<Chunk> = (0..4).flat_map(|x| (0..4).map(move |z| Chunk::new_from_block_pos(x, z, 9, sample_chunk_nbt(x, z)))).collect();
+        let region = Region::new(chunks, 0);
+        let raw = region.to_raw();
+        let payloads: Vec<_> = raw.slots().iter().map(|slot| slot.map(|chunk| chunk.data.as_slice())).collect();
+
+        assert_eq!(encode_neighbor_delta(&payloads, 0)[0], DELTA_RAW);
+        assert_ne!(encode_neighbor_delta(&payloads, 1)[0], DELTA_RAW);
+        assert_ne!(encode_neighbor_delta(&payloads, 32)[0], DELTA_RAW);
+
+        for hilbert_order in [false, true] {
+            let options = BlinearOptions { neighbor_delta: true, hilbert_order, ..BlinearOptions::default() };
+            let bytes = region.to_bytes_blinear_with(0, 3, options);
+            assert_eq!(BlinearOptions::of_file(&bytes), options);
+
+            let parsed = Region::from_bytes(RegionFormat::Blinear, &bytes).unwrap();
+            assert_eq!(parsed.chunks().len(), 16);
+            for chunk in parsed.chunks() {
+                assert_eq!(chunk.get_data(), &sample_chunk_nbt(chunk.x(), chunk.z()));
+                assert_eq!(chunk.timestamp(), 9);
+            }
+        }
+
+        // a delta whose reference is missing
+        let section: Vec<u8> = [0u8; 16].into_iter().chain([DELTA_WEST, 0]).collect();
+        assert!(matches!(resolve_neighbor_deltas(&[(1, section.as_slice())]), Err(ParseError::DeltaReference)));
+    }
+
+    #[test]
+    fn test_shared_dictionary() {
+        let region = Region::new(vec![Chunk::new_from_block_pos(2, 3, 4, sample_chunk_nbt(2, 3))], 0);
+        // zstd takes any bytes as a raw content dictionary
+        let dictionary = sample_chunk_nbt(0, 0).to_bytes();
+
+        for seekable in [false, true] {
+            let options = BlinearOptions { seekable, ..BlinearOptions::default() };
+            let bytes = region.to_bytes_blinear_with_dictionary(0, 3, options, Some(&dictionary));
+            assert_eq!(BlinearOptions::of_file(&bytes), options);
+            assert!(matches!(Region::from_bytes(RegionFormat::Blinear, &bytes), Err(ParseError::MissingDictionary)));
+
+            let parsed = Region::from_bytes_blinear_with_dictionary(&bytes, &ParseLimits::DEFAULT, Some(&dictionary)).unwrap();
+            assert_eq!(parsed.chunks()[0].get_data(), &sample_chunk_nbt(2, 3));
+        }
+
+        // readers going through the file system pick up the dictionary next to the file
+        let folder = std::env::temp_dir().join("bufferedlinear_tools_dictionary_test");
+        std::fs::create_dir_all(&folder).unwrap();
+        let path = folder.join("r.0.0.blinear");
+        let options = BlinearOptions { seekable: true, ..BlinearOptions::default() };
+        std::fs::write(&path, region.to_bytes_blinear_with_dictionary(0, 3, options, Some(&dictionary))).unwrap();
+        assert!(read_region_file(&path).is_err());
+
+        std::fs::write(folder.join(BLINEAR_DICTIONARY_FILE), &dictionary).unwrap();
+        assert_eq!(read_region_file(&path).unwrap().1.chunks().len(), 1);
+        assert_eq!(Region::read_chunk_at(&path, 2, 3).unwrap().unwrap().timestamp(), 4);
+
+        std::fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn test_detect_format() {
+        let mca = mca_with_chunk(0, &sample_chunk_nbt(0, 0));
+        let blinear = Region::from_bytes(RegionFormat::Mca, &mca).unwrap().to_bytes_blinear(0, 3);
+
+        assert_eq!(RegionFormat::detect(Path::new("r.0.0.mca"), &mca), Some(RegionFormat::Mca));
+        assert_eq!(RegionFormat::detect(Path::new("r.0.0.mca"), &blinear), Some(RegionFormat::Blinear));
+        assert_eq!(RegionFormat::detect(Path::new("r.0.0.dat"), &mca), None);
+    }
+}
@@ -0,0 +1,134 @@
+//! Sidecar files listing every chunk of a region file with its timestamp and a hash of its NBT,
+//! so worlds can be compared without decompressing their region files.
+//!
+//! Layout, big endian: the magic `BLCI`, a version byte, the region's x and z as i32, the chunk
+//! count as u16, then per chunk in slot order its slot as u16, timestamp as i64 and the
+//! [`XxHash64`] of its uncompressed NBT as u64. The hash does not depend on the region format, so
+//! the same chunk hashes alike in mca, linear and blinear files.
+
+use super::{ParseError, RawRegion};
+use std::hash::Hasher;
+use std::path::{Path, PathBuf};
+use twox_hash::XxHash64;
+
+const CHUNK_INDEX_MAGIC: &[u8; 4] = b"BLCI";
+const CHUNK_INDEX_VERSION: u8 = 1;
+const CHUNK_INDEX_HEADER_SIZE: usize = 15;
+const CHUNK_INDEX_ENTRY_SIZE: usize = 18;
+/// Appended to the region file's name, `r.0.0.linear` is indexed in `r.0.0.linear.idx`.
+pub const CHUNK_INDEX_EXTENSION: &str = "idx";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChunkIndexEntry {
+    /// Position within the region, `x + z * 32`
+    pub slot: u16,
+    pub timestamp: i64,
+    pub hash: u64,
+}
+
+/// The chunks of one region file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChunkIndex {
+    pub region_x: i32,
+    pub region_z: i32,
+    pub entries: Vec<ChunkIndexEntry>,
+}
+
+/// Path of the index file of a region file.
+pub fn chunk_index_path(region_file: &Path) -> PathBuf {
+    let mut name = region_file.as_os_str().to_owned();
+    name.push(".");
+    name.push(CHUNK_INDEX_EXTENSION);
+    PathBuf::from(name)
+}
+
+impl ChunkIndex {
+    pub fn new(region: &RawRegion, region_x: i32, region_z: i32) -> Self {
+        let entries = region
+            .slots()
+            .iter()
+            .enumerate()
+            .filter_map(|(slot, chunk)| {
+                let chunk = chunk.as_ref()?;
+                let mut hasher = XxHash64::with_seed(0);
+                hasher.write(&chunk.data);
+                Some(ChunkIndexEntry { slot: slot as u16, timestamp: chunk.timestamp, hash: hasher.finish() })
+            })
+            .collect();
+
+        Self { region_x, region_z, entries }
+    }
+
+    /// Global chunk coordinates of an entry.
+    pub fn global_position(&self, entry: &ChunkIndexEntry) -> (i32, i32) {
+        (self.region_x * 32 + (entry.slot & 31) as i32, self.region_z * 32 + (entry.slot >> 5) as i32)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(CHUNK_INDEX_HEADER_SIZE + self.entries.len() * CHUNK_INDEX_ENTRY_SIZE);
+        bytes.extend_from_slice(CHUNK_INDEX_MAGIC);
+        bytes.push(CHUNK_INDEX_VERSION);
+        bytes.extend_from_slice(&self.region_x.to_be_bytes());
+        bytes.extend_from_slice(&self.region_z.to_be_bytes());
+        bytes.extend_from_slice(&(self.entries.len() as u16).to_be_bytes());
+        for entry in &self.entries {
+            bytes.extend_from_slice(&entry.slot.to_be_bytes());
+            bytes.extend_from_slice(&entry.timestamp.to_be_bytes());
+            bytes.extend_from_slice(&entry.hash.to_be_bytes());
+        }
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+        if bytes.len() < CHUNK_INDEX_HEADER_SIZE || &bytes[..4] != CHUNK_INDEX_MAGIC {
+            return Err(ParseError::HeaderError);
+        }
+        if bytes[4] != CHUNK_INDEX_VERSION {
+            return Err(ParseError::VersionError);
+        }
+
+        let region_x = i32::from_be_bytes(bytes[5..9].try_into().unwrap());
+        let region_z = i32::from_be_bytes(bytes[9..13].try_into().unwrap());
+        let count = u16::from_be_bytes(bytes[13..15].try_into().unwrap()) as usize;
+        let body = &bytes[CHUNK_INDEX_HEADER_SIZE..];
+        if count > 1024 || body.len() != count * CHUNK_INDEX_ENTRY_SIZE {
+            return Err(ParseError::HeaderError);
+        }
+
+        let entries = body
+            .chunks_exact(CHUNK_INDEX_ENTRY_SIZE)
+            .map(|entry| ChunkIndexEntry {
+                slot: u16::from_be_bytes(entry[0..2].try_into().unwrap()),
+                timestamp: i64::from_be_bytes(entry[2..10].try_into().unwrap()),
+                hash: u64::from_be_bytes(entry[10..18].try_into().unwrap()),
+            })
+            .collect::<Vec<_>>();
+        if entries.iter().any(|entry| entry.slot >= 1024) {
+            return Err(ParseError::HeaderError);
+        }
+
+        Ok(Self { region_x, region_z, entries })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_index_roundtrip() {
+        let index = ChunkIndex {
+            region_x: -2,
+            region_z: 3,
+            entries: vec![ChunkIndexEntry { slot: 0, timestamp: 1_700_000_000, hash: 42 }, ChunkIndexEntry { slot: 1023, timestamp: -1, hash: u64::MAX }],
+        };
+
+        let bytes = index.to_bytes();
+        assert_eq!(bytes.len(), CHUNK_INDEX_HEADER_SIZE + 2 * CHUNK_INDEX_ENTRY_SIZE);
+        assert_eq!(ChunkIndex::from_bytes(&bytes).unwrap(), index);
+        assert_eq!(index.global_position(&index.entries[1]), (-33, 127));
+        assert!(ChunkIndex::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert_eq!(chunk_index_path(Path::new("region/r.0.0.linear")), Path::new("region/r.0.0.linear.idx"));
+    }
+}
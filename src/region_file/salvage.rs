@@ -0,0 +1,471 @@
+//! Tolerant reading of damaged region files: every chunk that can be read intact is kept and
+//! every problem found is reported with the chunks it cost.
+
+use super::{
+    blinear_body_offset, blinear_bucket_slots, blinear_hash, blinear_section_parts, blinear_slot_order, checked_slice, decompress_mca_chunk, parse_blinear_bucket_index,
+    parse_features, resolve_neighbor_deltas, ParseError, ParseLimits, RawChunk, RawRegion, Region, RegionFormat, VersionError, BLINEAR_FEATURES_MAGIC, BLINEAR_FILE_HEAD,
+    BLINEAR_FLAG_DICTIONARY, BLINEAR_FLAG_FEATURES, BLINEAR_FLAG_HILBERT_ORDER, BLINEAR_FLAG_NEIGHBOR_DELTA, BLINEAR_HEADER_SIZE, BLINEAR_KNOWN_FLAGS, BLINEAR_V2,
+    BLINEAR_V3, BLINEAR_V3_KNOWN_FLAGS, BLINEAR_VERSION_MASK, LINEAR_FILE_HEAD, LINEAR_GRID_SIZES, LINEAR_V1_VERSIONS, LINEAR_V2,
+};
+use std::collections::BTreeSet;
+use std::hash::Hasher;
+use std::io::Read;
+use twox_hash::XxHash64;
+
+/// One problem found in a region file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Damage {
+    pub problem: String,
+    /// Slots of the chunks lost to the problem. Empty when none were lost, or when the file does
+    /// not tell which slots held chunks
+    pub lost: Vec<usize>,
+}
+
+impl Damage {
+    fn new(problem: impl Into<String>, lost: Vec<usize>) -> Self {
+        Self { problem: problem.into(), lost }
+    }
+}
+
+/// What could be read of a region file, and what was wrong with it.
+pub struct Salvaged {
+    pub region: Region,
+    pub damage: Vec<Damage>,
+}
+
+/// Reads every chunk of a region file that is provably intact: chunks are only left out when
+/// their data is missing, does not decompress, fails its checksum or does not parse. Fails only
+/// when the header can not be read.
+pub fn salvage(format: RegionFormat, bytes: &[u8], dictionary: Option<&[u8]>, limits: &ParseLimits) -> Result<Salvaged, ParseError> {
+    let mut damage = Vec::new();
+    let raw = match format {
+        RegionFormat::Mca => salvage_mca(bytes, limits, &mut damage)?,
+        RegionFormat::Linear => match bytes.get(8) {
+            Some(version) if LINEAR_V1_VERSIONS.contains(version) => salvage_linear_v1(bytes, limits, &mut damage)?,
+            _ => salvage_linear_v2(bytes, limits, &mut damage)?,
+        },
+        RegionFormat::Blinear => salvage_blinear(bytes, limits, dictionary, &mut damage)?,
+    };
+
+    let (region, unparsable) = raw.parse_lossy(limits);
+    for (slot, err) in unparsable {
+        damage.push(Damage::new(format!("chunk in slot {slot} does not parse: {err}"), vec![slot]));
+    }
+
+    Ok(Salvaged { region, damage })
+}
+
+/// Decompresses as much of the zstd data as possible, with whether all of it decompressed.
+fn decompress_prefix(data: &[u8], max_size: usize, dictionary: Option<&[u8]>) -> (Vec<u8>, bool) {
+    let mut decoder: Box<dyn Read + '_> = match dictionary {
+        Some(dictionary) => match zstd::stream::read::Decoder::with_dictionary(data, dictionary) {
+            Ok(decoder) => Box::new(decoder),
+            Err(_) => return (Vec::new(), false),
+        },
+        None => match zstd::stream::read::Decoder::with_buffer(data) {
+            Ok(decoder) => Box::new(decoder),
+            Err(_) => return (Vec::new(), false),
+        },
+    };
+
+    let mut decompressed = Vec::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        match decoder.read(&mut buffer) {
+            Ok(0) => return (decompressed, true),
+            Ok(read) if decompressed.len() + read > max_size => return (decompressed, false),
+            Ok(read) => decompressed.extend_from_slice(&buffer[..read]),
+            Err(_) => return (decompressed, false),
+        }
+    }
+}
+
+fn salvage_mca(bytes: &[u8], limits: &ParseLimits, damage: &mut Vec<Damage>) -> Result<RawRegion, ParseError> {
+    if bytes.len() < 8192 {
+        return Err(ParseError::HeaderError);
+    }
+
+    let sectors = bytes.len().div_ceil(4096);
+    let mut owners: Vec<Option<usize>> = vec![None; sectors];
+    let mut overlaps = BTreeSet::new();
+    let mut region = RawRegion::new(0, Vec::new(), None);
+
+    for slot in 0..1024usize {
+        let location = u32::from_be_bytes(bytes[slot * 4..slot * 4 + 4].try_into().unwrap());
+        if location == 0 {
+            continue;
+        }
+
+        let timestamp = i32::from_be_bytes(bytes[4096 + slot * 4..4096 + slot * 4 + 4].try_into().unwrap()) as i64;
+        let (offset, count) = ((location >> 8) as usize, (location & 0xFF) as usize);
+
+        if offset < 2 {
+            damage.push(Damage::new(format!("chunk in slot {slot} points into the header at sector {offset}"), vec![slot]));
+            continue;
+        }
+        if count == 0 {
+            damage.push(Damage::new(format!("chunk in slot {slot} takes up no sectors"), vec![slot]));
+            continue;
+        }
+        if offset >= sectors {
+            damage.push(Damage::new(format!("chunk in slot {slot} starts at sector {offset}, past the end of the file at sector {sectors}"), vec![slot]));
+            continue;
+        }
+
+        for owner in &mut owners[offset..(offset + count).min(sectors)] {
+            match owner {
+                Some(other) => {
+                    overlaps.insert((*other, slot));
+                }
+                None => *owner = Some(slot),
+            }
+        }
+
+        // the length counts the compression type byte as well
+        let length = match checked_slice(bytes, offset * 4096, 4) {
+            Ok(length) => i32::from_be_bytes(length.try_into().unwrap()),
+            Err(_) => {
+                damage.push(Damage::new(format!("chunk in slot {slot} runs past the end of the file"), vec![slot]));
+                continue;
+            }
+        };
+        if length <= 1 {
+            damage.push(Damage::new(format!("chunk in slot {slot} has an invalid length of {length} bytes"), vec![slot]));
+            continue;
+        }
+        if length as usize + 4 > count * 4096 {
+            damage.push(Damage::new(format!("chunk in slot {slot} is {length} bytes long, more than its {count} sectors hold"), Vec::new()));
+        }
+
+        let Ok(chunk_bytes) = checked_slice(bytes, offset * 4096 + 4, length as usize) else {
+            damage.push(Damage::new(format!("chunk in slot {slot} runs past the end of the file"), vec![slot]));
+            continue;
+        };
+
+        match decompress_mca_chunk(chunk_bytes[0], &chunk_bytes[1..], limits.max_decompressed_size) {
+            Ok(data) => {
+                region.timestamp = region.timestamp.max(timestamp);
+                region.push(RawChunk { sector_index: slot, timestamp, data, compressed_size: chunk_bytes.len() - 1 }, limits)?;
+            }
+            Err(err) => damage.push(Damage::new(format!("chunk in slot {slot} does not decompress: {err}"), vec![slot])),
+        }
+    }
+
+    for (first, second) in overlaps {
+        damage.push(Damage::new(format!("chunks in slots {first} and {second} share sectors"), Vec::new()));
+    }
+
+    Ok(region)
+}
+
+fn salvage_linear_v1(bytes: &[u8], limits: &ParseLimits, damage: &mut Vec<Damage>) -> Result<RawRegion, ParseError> {
+    if bytes.len() < 32 + 8 || u64::from_be_bytes(bytes[0..8].try_into().unwrap()) != LINEAR_FILE_HEAD {
+        return Err(ParseError::HeaderError);
+    }
+
+    let timestamp = i64::from_be_bytes(bytes[9..17].try_into().unwrap());
+    let data_count = i32::from_be_bytes(bytes[20..24].try_into().unwrap());
+    if data_count < 0 {
+        return Err(ParseError::HeaderError);
+    }
+
+    let available = bytes.len() - 32;
+    if data_count as usize > available {
+        damage.push(Damage::new(format!("file is cut off, {available} of {data_count} compressed bytes are left"), Vec::new()));
+    }
+
+    let compressed = &bytes[32..32 + (data_count as usize).min(available)];
+    let (decompressed, complete) = decompress_prefix(compressed, limits.max_decompressed_size, None);
+    let mut region = RawRegion::new(timestamp, Vec::new(), None);
+
+    let Ok(header) = checked_slice(&decompressed, 0, 1024 * 8) else {
+        damage.push(Damage::new("chunk table does not decompress, every chunk is lost", Vec::new()));
+        return Ok(region);
+    };
+
+    let mut read_pointer = 1024 * 8;
+    let mut lost = Vec::new();
+    for (slot, entry) in header.chunks_exact(8).enumerate() {
+        let chunk_size = i32::from_be_bytes(entry[0..4].try_into().unwrap());
+        let chunk_timestamp = i32::from_be_bytes(entry[4..8].try_into().unwrap());
+        if chunk_size <= 0 {
+            continue;
+        }
+
+        match checked_slice(&decompressed, read_pointer, chunk_size as usize) {
+            Ok(data) => region.push(RawChunk { sector_index: slot, timestamp: chunk_timestamp as i64, data: data.to_vec(), compressed_size: 0 }, limits)?,
+            Err(_) => lost.push(slot),
+        }
+        read_pointer += chunk_size as usize;
+    }
+
+    if !complete || !lost.is_empty() {
+        damage.push(Damage::new(format!("compressed data breaks off after {} decompressed bytes", decompressed.len()), lost));
+    }
+
+    Ok(region)
+}
+
+fn salvage_linear_v2(bytes: &[u8], limits: &ParseLimits, damage: &mut Vec<Damage>) -> Result<RawRegion, ParseError> {
+    if bytes.len() < 26 + 128 || u64::from_be_bytes(bytes[0..8].try_into().unwrap()) != LINEAR_FILE_HEAD {
+        return Err(ParseError::HeaderError);
+    }
+    if bytes[8] != LINEAR_V2 {
+        return Err(VersionError);
+    }
+
+    let timestamp = i64::from_be_bytes(bytes[9..17].try_into().unwrap());
+    let grid_size = bytes[17];
+    if !LINEAR_GRID_SIZES.contains(&grid_size) {
+        return Err(ParseError::InvalidGridSize(grid_size));
+    }
+    let region_x = i32::from_be_bytes(bytes[18..22].try_into().unwrap());
+    let region_z = i32::from_be_bytes(bytes[22..26].try_into().unwrap());
+
+    let existence = &bytes[26..26 + 128];
+    let exists = |slot: usize| existence[slot / 8] & (1 << (slot % 8)) != 0;
+
+    let mut pointer = 26 + 128;
+    let features = parse_features(bytes, &mut pointer)?;
+    let bucket_count = grid_size as usize * grid_size as usize;
+    let headers = checked_slice(bytes, pointer, bucket_count * 13)?;
+    pointer += bucket_count * 13;
+
+    let mut region = RawRegion::new(timestamp, features, Some((region_x, region_z)));
+    let mut accounted = vec![false; 1024];
+    let bucket_dim = 32 / grid_size as usize;
+
+    for x in 0..grid_size as usize {
+        for z in 0..grid_size as usize {
+            let header = &headers[(x * grid_size as usize + z) * 13..][..13];
+            let size = i32::from_be_bytes(header[0..4].try_into().unwrap()).max(0) as usize;
+            let hash = u64::from_be_bytes(header[5..13].try_into().unwrap());
+            if size == 0 {
+                continue;
+            }
+
+            let slots: Vec<usize> = (0..bucket_dim).flat_map(|ix| (0..bucket_dim).map(move |iz| (x * bucket_dim + ix) + (z * bucket_dim + iz) * 32)).collect();
+            let lost_in_bucket = |accounted: &[bool]| slots.iter().copied().filter(|slot| exists(*slot) && !accounted[*slot]).collect::<Vec<_>>();
+
+            let Some(frame) = pointer.checked_add(size).and_then(|end| bytes.get(pointer..end)) else {
+                damage.push(Damage::new(format!("bucket {x}, {z} lies past the end of the file"), lost_in_bucket(&accounted)));
+                slots.iter().for_each(|slot| accounted[*slot] = true);
+                pointer = bytes.len();
+                continue;
+            };
+            pointer += size;
+
+            let mut hasher = XxHash64::with_seed(0);
+            hasher.write(frame);
+            if hasher.finish() != hash {
+                damage.push(Damage::new(format!("bucket {x}, {z} fails its checksum"), Vec::new()));
+            }
+
+            let (decompressed, complete) = decompress_prefix(frame, limits.max_decompressed_size, None);
+            let mut read_pointer = 0;
+            for &slot in &slots {
+                let Ok(entry) = checked_slice(&decompressed, read_pointer, 12) else {
+                    break;
+                };
+                let chunk_size = i32::from_be_bytes(entry[0..4].try_into().unwrap());
+                let chunk_timestamp = i64::from_be_bytes(entry[4..12].try_into().unwrap());
+                read_pointer += 12;
+
+                if chunk_size <= 0 {
+                    continue;
+                }
+                // the size counts the timestamp as well
+                let Some(Ok(data)) = (chunk_size as usize).checked_sub(8).map(|data_size| checked_slice(&decompressed, read_pointer, data_size)) else {
+                    break;
+                };
+                read_pointer += data.len();
+
+                accounted[slot] = true;
+                region.push(RawChunk { sector_index: slot, timestamp: chunk_timestamp, data: data.to_vec(), compressed_size: 0 }, limits)?;
+            }
+
+            let lost = lost_in_bucket(&accounted);
+            if !complete {
+                damage.push(Damage::new(format!("bucket {x}, {z} breaks off after {} decompressed bytes", decompressed.len()), lost));
+            } else if !lost.is_empty() {
+                damage.push(Damage::new(format!("bucket {x}, {z} holds fewer chunks than the header lists"), lost));
+            }
+            slots.iter().for_each(|slot| accounted[*slot] = true);
+        }
+    }
+
+    let missing: Vec<usize> = (0..1024).filter(|slot| exists(*slot) && !accounted[*slot]).collect();
+    if !missing.is_empty() {
+        damage.push(Damage::new("chunks listed by the header are in empty buckets", missing));
+    }
+
+    Ok(region)
+}
+
+fn salvage_blinear(bytes: &[u8], limits: &ParseLimits, dictionary: Option<&[u8]>, damage: &mut Vec<Damage>) -> Result<RawRegion, ParseError> {
+    if bytes.len() < BLINEAR_HEADER_SIZE || i64::from_be_bytes(bytes[0..8].try_into().unwrap()) != BLINEAR_FILE_HEAD {
+        return Err(ParseError::HeaderError);
+    }
+
+    let version = bytes[8] & BLINEAR_VERSION_MASK;
+    let flags = bytes[8] & !BLINEAR_VERSION_MASK;
+    let known_flags = match version {
+        BLINEAR_V2 => BLINEAR_KNOWN_FLAGS,
+        BLINEAR_V3 => BLINEAR_V3_KNOWN_FLAGS,
+        _ => return Err(VersionError),
+    };
+    if flags & !known_flags != 0 {
+        return Err(VersionError);
+    }
+    let dictionary = match dictionary {
+        _ if flags & BLINEAR_FLAG_DICTIONARY == 0 => None,
+        Some(dictionary) => Some(dictionary),
+        None => return Err(ParseError::MissingDictionary),
+    };
+
+    let timestamp = i64::from_be_bytes(bytes[9..17].try_into().unwrap());
+    let mut features = Vec::new();
+    if flags & BLINEAR_FLAG_FEATURES != 0 {
+        let frame_header = checked_slice(bytes, BLINEAR_HEADER_SIZE, 8)?;
+        if u32::from_le_bytes(frame_header[0..4].try_into().unwrap()) != BLINEAR_FEATURES_MAGIC {
+            return Err(ParseError::HeaderError);
+        }
+        let frame_size = u32::from_le_bytes(frame_header[4..8].try_into().unwrap()) as usize;
+        features = parse_features(checked_slice(bytes, BLINEAR_HEADER_SIZE + 8, frame_size)?, &mut 0)?;
+    }
+
+    // each decompressed block with what it is, the slots it holds and whether all of it decompressed
+    let mut blocks = Vec::new();
+    if version == BLINEAR_V3 {
+        let body = blinear_body_offset(bytes).and_then(|body| bytes.get(body..)).ok_or(ParseError::ReadError)?;
+        let (grid_size, frames) = parse_blinear_bucket_index(body)?;
+
+        for (bucket, (offset, length)) in frames.into_iter().enumerate().filter(|(_, (_, length))| *length > 0) {
+            match offset.checked_add(length).and_then(|end| body.get(offset..end)) {
+                Some(frame) => {
+                    let (decompressed, complete) = decompress_prefix(frame, limits.max_decompressed_size, dictionary);
+                    blocks.push((format!("bucket {bucket}"), blinear_bucket_slots(grid_size, bucket), decompressed, complete));
+                }
+                None => damage.push(Damage::new(format!("bucket {bucket} lies past the end of the file, its chunks are lost"), Vec::new())),
+            }
+        }
+    } else {
+        // zstd skips the features frame on its own
+        let (decompressed, complete) = decompress_prefix(&bytes[BLINEAR_HEADER_SIZE..], limits.max_decompressed_size, dictionary);
+        blocks.push((String::from("region"), blinear_slot_order(flags & BLINEAR_FLAG_HILBERT_ORDER != 0), decompressed, complete));
+    }
+
+    let mut sections = Vec::new();
+    for (name, slots, decompressed, complete) in &blocks {
+        let mut pointer = 0;
+        let mut cut_off = Vec::new();
+        for &slot in slots {
+            let Ok(length) = checked_slice(decompressed, pointer, 4) else {
+                break;
+            };
+            let length = i32::from_be_bytes(length.try_into().unwrap());
+            pointer += 4;
+            if length <= 0 {
+                continue;
+            }
+
+            let Ok(section) = checked_slice(decompressed, pointer, length as usize) else {
+                cut_off.push(slot);
+                break;
+            };
+            pointer += section.len();
+            sections.push((slot, section));
+        }
+
+        if !complete || !cut_off.is_empty() {
+            damage.push(Damage::new(format!("{name} breaks off after {} decompressed bytes, the chunks after it are lost", decompressed.len()), cut_off));
+        }
+    }
+
+    let mut region = RawRegion::new(timestamp, features, None);
+    let payloads = match flags & BLINEAR_FLAG_NEIGHBOR_DELTA != 0 {
+        true => match resolve_neighbor_deltas(&sections) {
+            Ok(payloads) => payloads,
+            Err(err) => {
+                damage.push(Damage::new(format!("neighbour deltas can not be resolved: {err}"), sections.iter().map(|(slot, _)| *slot).collect()));
+                return Ok(region);
+            }
+        },
+        false => Vec::new(),
+    };
+
+    for (slot, section) in sections {
+        let Ok((chunk_timestamp, hash, stored)) = blinear_section_parts(section) else {
+            damage.push(Damage::new(format!("section of slot {slot} is too short"), vec![slot]));
+            continue;
+        };
+        let data = payloads.get(slot).and_then(Option::as_deref).unwrap_or(stored);
+
+        if hash != blinear_hash(data) {
+            damage.push(Damage::new(format!("chunk in slot {slot} fails its checksum"), vec![slot]));
+            continue;
+        }
+        region.push(RawChunk { sector_index: slot, timestamp: chunk_timestamp, data: data.to_vec(), compressed_size: 0 }, limits)?;
+    }
+
+    Ok(region)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::nbt::tag::Tag;
+
+    fn region() -> Region {
+        let chunks = (0..4)
+            .map(|x| Chunk::new_from_block_pos(x, 0, 1, Tag::Compound { name: None, value: vec![Tag::Int { name: Some(String::from("value")), value: x }] }))
+            .collect();
+        Region::new(chunks, 1)
+    }
+
+    fn slots(salvaged: &Salvaged) -> Vec<i32> {
+        salvaged.region.chunks().iter().map(Chunk::x).collect()
+    }
+
+    #[test]
+    fn test_intact_files() {
+        for format in RegionFormat::ALL {
+            let bytes = region().to_bytes(format, Some((0, 0)), 1, 3).unwrap();
+            let salvaged = salvage(format, &bytes, None, &ParseLimits::default()).unwrap();
+
+            assert_eq!(salvaged.damage, []);
+            assert_eq!(slots(&salvaged), [0, 1, 2, 3]);
+        }
+    }
+
+    #[test]
+    fn test_damaged_mca() {
+        let mut bytes = region().to_bytes(RegionFormat::Mca, None, 1, 3).unwrap();
+        // slot 1 points at the sectors of slot 0, slot 3 past the end of the file
+        bytes.copy_within(0..4, 4);
+        bytes[12..16].copy_from_slice(&((100u32 << 8) | 1).to_be_bytes());
+
+        let salvaged = salvage(RegionFormat::Mca, &bytes, None, &ParseLimits::default()).unwrap();
+
+        assert_eq!(slots(&salvaged), [0, 1, 2]);
+        assert_eq!(salvaged.damage.iter().map(|damage| damage.lost.clone()).collect::<Vec<_>>(), [vec![3], vec![]]);
+        assert_eq!(salvaged.damage[1].problem, "chunks in slots 0 and 1 share sectors");
+    }
+
+    #[test]
+    fn test_truncated_files() {
+        let bytes = region().to_bytes_blinear_with(1, 3, crate::region_file::BlinearOptions { grid_size: Some(2), ..Default::default() });
+        let mut corrupt = bytes.clone();
+        let last = corrupt.len() - 8;
+        corrupt[last] ^= 0xFF;
+
+        let salvaged = salvage(RegionFormat::Blinear, &corrupt, None, &ParseLimits::default()).unwrap();
+        assert!(!salvaged.damage.is_empty());
+        assert!(slots(&salvaged).len() < 4);
+
+        let linear = region().to_bytes(RegionFormat::Linear, Some((0, 0)), 1, 3).unwrap();
+        let salvaged = salvage(RegionFormat::Linear, &linear[..linear.len() - 20], None, &ParseLimits::default()).unwrap();
+        assert_eq!(salvaged.damage.iter().flat_map(|damage| damage.lost.clone()).collect::<Vec<_>>(), [0, 1, 2, 3]);
+    }
+}
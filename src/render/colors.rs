@@ -0,0 +1,180 @@
+/// Approximate top-down colors of common blocks, keyed by id without the `minecraft:` namespace.
+const BLOCK_COLORS: &[(&str, [u8; 3])] = &[
+    ("grass_block", [109, 153, 48]),
+    ("dirt", [134, 96, 67]),
+    ("coarse_dirt", [119, 85, 59]),
+    ("rooted_dirt", [144, 103, 76]),
+    ("podzol", [91, 63, 24]),
+    ("mycelium", [111, 98, 101]),
+    ("dirt_path", [148, 121, 65]),
+    ("farmland", [81, 44, 15]),
+    ("mud", [60, 57, 60]),
+    ("clay", [160, 166, 179]),
+    ("gravel", [131, 127, 126]),
+    ("sand", [219, 207, 163]),
+    ("red_sand", [190, 102, 33]),
+    ("sandstone", [216, 203, 155]),
+    ("red_sandstone", [186, 99, 29]),
+    ("stone", [125, 125, 125]),
+    ("cobblestone", [127, 127, 127]),
+    ("mossy_cobblestone", [110, 118, 94]),
+    ("granite", [149, 103, 85]),
+    ("diorite", [188, 188, 188]),
+    ("andesite", [136, 136, 136]),
+    ("deepslate", [80, 80, 82]),
+    ("cobbled_deepslate", [77, 77, 80]),
+    ("tuff", [108, 109, 102]),
+    ("calcite", [223, 224, 220]),
+    ("dripstone_block", [134, 107, 92]),
+    ("bedrock", [85, 85, 85]),
+    ("obsidian", [15, 10, 24]),
+    ("water", [63, 118, 228]),
+    ("lava", [207, 92, 20]),
+    ("ice", [145, 183, 253]),
+    ("packed_ice", [141, 180, 250]),
+    ("blue_ice", [116, 167, 253]),
+    ("snow", [249, 254, 254]),
+    ("snow_block", [249, 254, 254]),
+    ("powder_snow", [248, 253, 253]),
+    ("terracotta", [152, 94, 67]),
+    ("white_terracotta", [209, 178, 161]),
+    ("orange_terracotta", [161, 83, 37]),
+    ("yellow_terracotta", [186, 133, 35]),
+    ("brown_terracotta", [77, 51, 35]),
+    ("red_terracotta", [143, 61, 46]),
+    ("light_gray_terracotta", [135, 106, 97]),
+    ("oak_leaves", [60, 120, 30]),
+    ("spruce_leaves", [60, 95, 60]),
+    ("birch_leaves", [100, 135, 60]),
+    ("jungle_leaves", [48, 130, 20]),
+    ("acacia_leaves", [80, 120, 30]),
+    ("dark_oak_leaves", [50, 105, 25]),
+    ("mangrove_leaves", [70, 115, 35]),
+    ("cherry_leaves", [228, 177, 200]),
+    ("azalea_leaves", [90, 117, 45]),
+    ("flowering_azalea_leaves", [100, 112, 60]),
+    ("oak_log", [109, 85, 50]),
+    ("spruce_log", [58, 37, 16]),
+    ("birch_log", [216, 215, 210]),
+    ("jungle_log", [85, 67, 25]),
+    ("acacia_log", [103, 96, 86]),
+    ("dark_oak_log", [60, 46, 26]),
+    ("oak_planks", [162, 130, 78]),
+    ("spruce_planks", [114, 84, 48]),
+    ("birch_planks", [192, 175, 121]),
+    ("jungle_planks", [160, 115, 80]),
+    ("acacia_planks", [168, 90, 50]),
+    ("dark_oak_planks", [66, 43, 20]),
+    ("short_grass", [109, 153, 48]),
+    ("grass", [109, 153, 48]),
+    ("tall_grass", [109, 153, 48]),
+    ("fern", [100, 140, 50]),
+    ("large_fern", [100, 140, 50]),
+    ("seagrass", [40, 100, 180]),
+    ("tall_seagrass", [40, 100, 180]),
+    ("kelp", [45, 105, 170]),
+    ("kelp_plant", [45, 105, 170]),
+    ("lily_pad", [32, 128, 48]),
+    ("cactus", [85, 127, 43]),
+    ("sugar_cane", [148, 192, 101]),
+    ("pumpkin", [198, 118, 24]),
+    ("melon", [111, 145, 30]),
+    ("moss_block", [89, 109, 45]),
+    ("moss_carpet", [89, 109, 45]),
+    ("netherrack", [97, 38, 38]),
+    ("soul_sand", [81, 62, 50]),
+    ("soul_soil", [75, 57, 46]),
+    ("basalt", [80, 81, 86]),
+    ("blackstone", [42, 36, 41]),
+    ("crimson_nylium", [130, 31, 31]),
+    ("warped_nylium", [43, 114, 101]),
+    ("nether_wart_block", [114, 2, 2]),
+    ("warped_wart_block", [22, 119, 121]),
+    ("glowstone", [171, 131, 84]),
+    ("magma_block", [142, 63, 31]),
+    ("end_stone", [219, 222, 158]),
+    ("purpur_block", [169, 125, 169]),
+    ("chorus_plant", [93, 57, 93]),
+    ("chorus_flower", [151, 120, 151]),
+    ("stone_bricks", [122, 121, 122]),
+    ("bricks", [150, 97, 83]),
+    ("glass", [175, 213, 219]),
+    ("white_wool", [233, 236, 236]),
+    ("white_concrete", [207, 213, 214]),
+    ("gray_concrete", [54, 57, 61]),
+    ("black_concrete", [8, 10, 15]),
+    ("quartz_block", [235, 229, 222]),
+    ("smooth_stone", [158, 158, 158]),
+    ("hay_block", [166, 136, 38]),
+    ("bookshelf", [117, 94, 59]),
+    ("chest", [162, 130, 78]),
+    ("torch", [255, 216, 0]),
+    ("rail", [125, 111, 90]),
+    ("iron_block", [220, 220, 220]),
+    ("gold_block", [246, 208, 61]),
+    ("diamond_block", [98, 237, 228]),
+    ("emerald_block", [42, 203, 87]),
+    ("redstone_block", [175, 24, 5]),
+    ("lapis_block", [30, 67, 140]),
+    ("coal_block", [16, 15, 15]),
+    ("prismarine", [99, 156, 151]),
+    ("sea_lantern", [172, 199, 190]),
+    ("sculk", [12, 29, 36]),
+    ("mangrove_roots", [74, 59, 38]),
+    ("bamboo", [93, 144, 19]),
+];
+
+/// Fallback colors for blocks missing from the table, matched by a substring of their id.
+const FAMILY_COLORS: &[(&str, [u8; 3])] = &[
+    ("leaves", [60, 120, 30]),
+    ("log", [100, 80, 50]),
+    ("wood", [100, 80, 50]),
+    ("planks", [160, 128, 80]),
+    ("stairs", [130, 120, 110]),
+    ("slab", [130, 120, 110]),
+    ("wool", [200, 200, 200]),
+    ("carpet", [200, 200, 200]),
+    ("concrete", [130, 130, 130]),
+    ("terracotta", [150, 95, 70]),
+    ("glass", [175, 213, 219]),
+    ("flower", [200, 60, 60]),
+    ("tulip", [200, 60, 60]),
+    ("coral", [200, 80, 140]),
+    ("ore", [125, 125, 125]),
+    ("stone", [125, 125, 125]),
+    ("brick", [140, 100, 90]),
+    ("sand", [219, 207, 163]),
+    ("ice", [145, 183, 253]),
+    ("snow", [249, 254, 254]),
+    ("mushroom", [150, 110, 90]),
+];
+
+pub const UNKNOWN_COLOR: [u8; 3] = [200, 0, 200];
+
+pub fn block_color(name: &str) -> [u8; 3] {
+    let id = name.strip_prefix("minecraft:").unwrap_or(name);
+
+    BLOCK_COLORS
+        .iter()
+        .chain(FAMILY_COLORS)
+        .find(|(key, _)| *key == id)
+        .or_else(|| FAMILY_COLORS.iter().find(|(key, _)| id.contains(key)))
+        .map_or(UNKNOWN_COLOR, |(_, color)| *color)
+}
+
+/// Brightens or darkens a color by `factor`, clamping each channel.
+pub fn shade(color: [u8; 3], factor: f64) -> [u8; 3] {
+    color.map(|channel| (channel as f64 * factor).round().clamp(0.0, 255.0) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_color_lookup() {
+        assert_eq!(block_color("minecraft:water"), [63, 118, 228]);
+        assert_eq!(block_color("minecraft:pale_oak_leaves"), [60, 120, 30]);
+        assert_eq!(block_color("somemod:gizmo"), UNKNOWN_COLOR);
+    }
+}
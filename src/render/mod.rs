@@ -0,0 +1,184 @@
+mod colors;
+
+use crate::chunk::Chunk;
+use crate::chunk_data::blocks::{block_sections, BlockSection, BlockState};
+use crate::chunk_data::heightmap::{decode_heightmap, min_block_y};
+use crate::image::{GrayImage16, RgbImage};
+use crate::region_file::read_region_file;
+use crate::render::colors::{block_color, shade};
+use crate::{folder_name, scan_region_files, RegionType};
+use clap::{Args, ValueEnum};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const REGION_BLOCKS: usize = 512;
+const BACKGROUND: [u8; 3] = [0, 0, 0];
+
+#[derive(Args)]
+pub struct RenderArgs {
+    /// Path to your Minecraft world
+    pub world_path: PathBuf,
+
+    /// Folder receiving one `r.<x>.<z>.png` per region
+    pub output_path: PathBuf,
+
+    /// Export this heightmap as 16 bit grayscale `r.<x>.<z>.<heightmap>.png` images instead of
+    /// rendering block colors. Values count blocks above the world's minimum y, 0 is empty.
+    #[arg(long, value_enum)]
+    pub heightmap: Option<HeightmapKind>,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum HeightmapKind {
+    MotionBlocking,
+    WorldSurface,
+}
+
+impl HeightmapKind {
+    fn nbt_name(self) -> &'static str {
+        match self {
+            HeightmapKind::MotionBlocking => "MOTION_BLOCKING",
+            HeightmapKind::WorldSurface => "WORLD_SURFACE",
+        }
+    }
+
+    fn file_suffix(self) -> &'static str {
+        match self {
+            HeightmapKind::MotionBlocking => "motion_blocking",
+            HeightmapKind::WorldSurface => "world_surface",
+        }
+    }
+}
+
+/// Finds the highest non-air block of a column, starting the search at `start_y`.
+fn top_block(sections: &[BlockSection], start_y: i32, x: usize, z: usize) -> Option<(i32, &BlockState)> {
+    for section in sections.iter().rev() {
+        let base_y = section.y * 16;
+        if base_y > start_y {
+            continue;
+        }
+
+        let highest = (start_y - base_y).min(15);
+        for local_y in (0..=highest).rev() {
+            let block = section.block_at(x, local_y as usize, z);
+            if !block.is_air() {
+                return Some((base_y + local_y, block));
+            }
+        }
+    }
+
+    None
+}
+
+/// Draws one chunk into the region sized color and height buffers.
+fn render_chunk(chunk: &Chunk, colors: &mut [Option<[u8; 3]>], heights: &mut [i32]) {
+    let sections = block_sections(chunk);
+    let Some(highest_section) = sections.last() else {
+        return;
+    };
+
+    let min_y = min_block_y(chunk);
+    let surface = decode_heightmap(chunk, "WORLD_SURFACE");
+    let origin_x = (chunk.x() & 31) as usize * 16;
+    let origin_z = (chunk.z() & 31) as usize * 16;
+
+    for z in 0..16 {
+        for x in 0..16 {
+            let start_y = match &surface {
+                Some(heights) if heights[z * 16 + x] > 0 => min_y + heights[z * 16 + x] as i32 - 1,
+                _ => highest_section.y * 16 + 15,
+            };
+
+            if let Some((y, block)) = top_block(&sections, start_y, x, z) {
+                let pixel = (origin_z + z) * REGION_BLOCKS + origin_x + x;
+                colors[pixel] = Some(block_color(&block.name));
+                heights[pixel] = y;
+            }
+        }
+    }
+}
+
+pub fn render_region(chunks: &[Chunk]) -> RgbImage {
+    let mut colors = vec![None; REGION_BLOCKS * REGION_BLOCKS];
+    let mut heights = vec![0; REGION_BLOCKS * REGION_BLOCKS];
+
+    for chunk in chunks {
+        render_chunk(chunk, &mut colors, &mut heights);
+    }
+
+    let mut image = RgbImage::new(REGION_BLOCKS as u32, REGION_BLOCKS as u32, BACKGROUND);
+    for z in 0..REGION_BLOCKS {
+        for x in 0..REGION_BLOCKS {
+            let pixel = z * REGION_BLOCKS + x;
+            let Some(color) = colors[pixel] else {
+                continue;
+            };
+
+            // light the terrain from the north so slopes stand out
+            let north = pixel.wrapping_sub(REGION_BLOCKS);
+            let factor = if z > 0 && colors[north].is_some() {
+                match heights[pixel].cmp(&heights[north]) {
+                    Ordering::Greater => 1.12,
+                    Ordering::Less => 0.85,
+                    Ordering::Equal => 1.0,
+                }
+            } else {
+                1.0
+            };
+
+            image.put_pixel(x as u32, z as u32, shade(color, factor));
+        }
+    }
+
+    image
+}
+
+pub fn render_heightmap(chunks: &[Chunk], kind: HeightmapKind) -> GrayImage16 {
+    let mut image = GrayImage16::new(REGION_BLOCKS as u32, REGION_BLOCKS as u32);
+
+    for chunk in chunks {
+        let Some(heights) = decode_heightmap(chunk, kind.nbt_name()) else {
+            continue;
+        };
+
+        let origin_x = (chunk.x() & 31) as u32 * 16;
+        let origin_z = (chunk.z() & 31) as u32 * 16;
+        for (column, height) in heights.into_iter().enumerate() {
+            image.put_pixel(origin_x + column as u32 % 16, origin_z + column as u32 / 16, height);
+        }
+    }
+
+    image
+}
+
+fn render_region_file(region_file: &Path, output_folder: &Path, heightmap: Option<HeightmapKind>) -> Result<(), Box<dyn Error>> {
+    let (_, region) = read_region_file(region_file)?;
+    let file_stem = region_file.file_stem().and_then(|stem| stem.to_str()).ok_or("Invalid file name")?;
+
+    match heightmap {
+        Some(kind) => {
+            let output_file = output_folder.join(format!("{file_stem}.{}.png", kind.file_suffix()));
+            render_heightmap(region.chunks(), kind).save_png(&output_file)?;
+        }
+        None => render_region(region.chunks()).save_png(&output_folder.join(format!("{file_stem}.png")))?,
+    }
+
+    Ok(())
+}
+
+pub fn run_render(args: &RenderArgs) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(&args.output_path)?;
+
+    let scanned = scan_region_files(args.world_path.join(folder_name(RegionType::REGION)));
+    scanned.par_iter().for_each(|region_file| {
+        match render_region_file(region_file, &args.output_path, args.heightmap) {
+            Ok(()) => println!("Rendered file {}", region_file.display()),
+            Err(err) => eprintln!("Failed to render file {} !, error : {}", region_file.display(), err),
+        }
+    });
+
+    Ok(())
+}
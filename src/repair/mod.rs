@@ -0,0 +1,144 @@
+use crate::chunk::Chunk;
+use crate::region_file::{read_region_file, region_coords_from_path};
+use clap::Args;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct RepairArgs {
+    /// Region file to repair, it is left untouched
+    pub region_file: PathBuf,
+
+    /// Where to write the repaired copy, in the same format as the input
+    pub output_path: PathBuf,
+
+    /// Rewrite chunk NBT `xPos`/`zPos` (or `Position` for entity chunks) to match the chunk's slot
+    #[arg(long)]
+    pub fix_positions: bool,
+
+    /// With --fix-positions, move mismatched chunks to the slot their NBT position names instead
+    #[arg(long, requires = "fix_positions")]
+    pub relocate: bool,
+
+    /// Compression level when writing the repaired region file
+    #[arg(short, long, default_value = "6", value_parser = crate::validate_compression_level)]
+    pub compression_level: u32,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct RepairReport {
+    fixed: Vec<String>,
+    skipped: Vec<String>,
+}
+
+/// Makes the NBT position of every chunk agree with its slot, either by rewriting the NBT or, when
+/// relocating, by moving the chunk. Chunks that would leave the region or land on an occupied
+/// slot are left as they are.
+fn fix_positions(chunks: &mut [Chunk], region_x: i32, region_z: i32, relocate: bool) -> RepairReport {
+    let mut report = RepairReport::default();
+    let mut occupied: HashSet<(i32, i32)> =
+        chunks.iter().map(|chunk| chunk.global_position(region_x, region_z)).collect();
+
+    for chunk in chunks.iter_mut() {
+        let slot = chunk.global_position(region_x, region_z);
+        let Some(recorded) = chunk.nbt_position() else {
+            continue;
+        };
+        if slot == recorded {
+            continue;
+        }
+
+        let (x, z) = slot;
+        let (nbt_x, nbt_z) = recorded;
+
+        if !relocate {
+            chunk.set_nbt_position(x, z);
+            report.fixed.push(format!("chunk {x}, {z}: NBT position {nbt_x}, {nbt_z} rewritten"));
+        } else if (nbt_x >> 5, nbt_z >> 5) != (region_x, region_z) {
+            report.skipped.push(format!("chunk {x}, {z}: NBT position {nbt_x}, {nbt_z} is in another region"));
+        } else if occupied.contains(&recorded) {
+            report.skipped.push(format!("chunk {x}, {z}: slot {nbt_x}, {nbt_z} is already occupied"));
+        } else {
+            chunk.set_position(nbt_x, nbt_z);
+            occupied.remove(&slot);
+            occupied.insert(recorded);
+            report.fixed.push(format!("chunk {x}, {z}: moved to slot {nbt_x}, {nbt_z}"));
+        }
+    }
+
+    report
+}
+
+pub fn run_repair(args: &RepairArgs) -> Result<(), Box<dyn Error>> {
+    if !args.fix_positions {
+        return Err("Nothing to repair, pass --fix-positions".into());
+    }
+
+    let (format, mut region) = read_region_file(&args.region_file)?;
+    let (region_x, region_z) = region_coords_from_path(&args.region_file).ok_or("File name is not r.<x>.<z>.<ext>")?;
+
+    let report = fix_positions(region.chunks_mut(), region_x, region_z, args.relocate);
+    for line in &report.fixed {
+        println!("{line}");
+    }
+    for line in &report.skipped {
+        println!("skipped {line}");
+    }
+
+    let bytes = region.to_bytes(format, Some((region_x, region_z)), region.timestamp(), args.compression_level as u8)?;
+    fs::write(&args.output_path, bytes)?;
+
+    println!(
+        "{} chunks repaired, {} skipped, written to {}",
+        report.fixed.len(),
+        report.skipped.len(),
+        args.output_path.display()
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nbt::tag::Tag;
+
+    fn chunk(slot_x: i32, slot_z: i32, x_pos: i32, z_pos: i32) -> Chunk {
+        let level = Tag::Compound {
+            name: Some(String::from("Level")),
+            value: vec![
+                Tag::Int { name: Some(String::from("xPos")), value: x_pos },
+                Tag::Int { name: Some(String::from("zPos")), value: z_pos },
+            ],
+        };
+        Chunk::new_from_block_pos(slot_x, slot_z, 0, Tag::Compound { name: None, value: vec![level] })
+    }
+
+    /// Slot and NBT position of every chunk
+    fn positions(chunks: &[Chunk]) -> Vec<[(i32, i32); 2]> {
+        chunks.iter().map(|chunk| [chunk.global_position(1, 0), chunk.nbt_position().unwrap()]).collect()
+    }
+
+    #[test]
+    fn test_rewrite_nbt_positions() {
+        let mut chunks = [chunk(0, 0, 32, 0), chunk(1, 0, 40, 3)];
+
+        let report = fix_positions(&mut chunks, 1, 0, false);
+
+        assert_eq!(report.fixed.len(), 1);
+        assert_eq!(positions(&chunks), [[(32, 0), (32, 0)], [(33, 0), (33, 0)]]);
+    }
+
+    #[test]
+    fn test_relocate_chunks() {
+        let mut chunks = [chunk(0, 0, 32, 0), chunk(1, 0, 40, 3), chunk(2, 0, 32, 0), chunk(3, 0, 0, 0)];
+
+        let report = fix_positions(&mut chunks, 1, 0, true);
+
+        assert_eq!(report.fixed, ["chunk 33, 0: moved to slot 40, 3"]);
+        assert_eq!(report.skipped.len(), 2);
+        assert_eq!(positions(&chunks)[1], [(40, 3), (40, 3)]);
+    }
+}
@@ -0,0 +1,150 @@
+use crate::backup::{convert_region_bytes, in_region_folder, BackupError, Decryption, Manifest, ManifestEntry, MANIFEST_FILE};
+use crate::incremental::hash;
+use crate::region_file::RegionFormat;
+use crate::throttle;
+use crate::{validate_compression_level, OutputFormat};
+use clap::Args;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Args)]
+pub struct RestoreArgs {
+    /// Backup folder written by `backup`, or its manifest.toml. Files of a differential backup are
+    /// read from the backups next to it
+    pub backup: PathBuf,
+
+    /// Empty or missing folder receiving the world
+    pub world_path: PathBuf,
+
+    /// Format of the restored region, poi and entities files, defaults to the format they had in
+    /// the backed up world
+    #[arg(long, value_enum)]
+    pub format: Option<OutputFormat>,
+
+    /// Compression level when converting region files
+    #[arg(short, long, default_value = "6", value_parser = validate_compression_level)]
+    pub compression_level: u32,
+
+    /// age identity file decrypting backups encrypted to a recipient, backups encrypted with a
+    /// passphrase are read with `BLT_BACKUP_PASSPHRASE`
+    #[arg(long, value_name = "FILE")]
+    pub identity: Option<PathBuf>,
+}
+
+/// Writes one file of the backup into the world folder, returns whether it was converted.
+fn restore_file(backups_folder: &Path, backup_folder: &Path, entry: &ManifestEntry, decryption: &Decryption, args: &RestoreArgs) -> Result<bool, Box<dyn Error>> {
+    let folder = match &entry.stored_in {
+        Some(name) => backups_folder.join(name),
+        None => backup_folder.to_path_buf(),
+    };
+    let stored = folder.join(entry.plain_path());
+    let mut bytes = throttle::read(folder.join(&entry.path))?;
+    if entry.is_encrypted() {
+        bytes = decryption.decrypt(&bytes).map_err(|err| format!("Failed to decrypt {} : {}", folder.join(&entry.path).display(), err))?;
+    }
+
+    let source_format = Path::new(&entry.source).extension().and_then(|extension| extension.to_str()).and_then(RegionFormat::from_extension);
+    let format = args.format.map(RegionFormat::from).or(source_format);
+
+    let (output, converted) = match (format, RegionFormat::detect(&stored, &bytes)) {
+        (Some(format), Some(stored_format)) if in_region_folder(&entry.source) && format != stored_format => {
+            let output = Path::new(&entry.source).with_extension(format.extension());
+            (output, Some(convert_region_bytes(&stored, &bytes, stored_format, format, args.compression_level as u8)?))
+        }
+        _ => (PathBuf::from(&entry.source), None),
+    };
+
+    if converted.is_none() && entry.plain_path() == entry.source && hash(&bytes) != entry.hash {
+        return Err(format!("{} does not match the hash in the manifest", stored.display()).into());
+    }
+
+    let output = args.world_path.join(output);
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    throttle::write(&output, converted.as_deref().unwrap_or(&bytes))?;
+
+    Ok(converted.is_some())
+}
+
+/// Reassembles the world of a backup, reading unchanged files of differential backups from the
+/// backups they refer to.
+pub fn run_restore(args: &RestoreArgs) -> Result<(), Box<dyn Error>> {
+    let backup_folder = if args.backup.ends_with(MANIFEST_FILE) { args.backup.parent().unwrap_or(Path::new(".")) } else { args.backup.as_path() };
+    let backups_folder = backup_folder.parent().unwrap_or(Path::new("."));
+    let manifest = Manifest::read(backup_folder)?;
+    let decryption = Decryption::new(args.identity.as_deref())?;
+
+    if fs::read_dir(&args.world_path).is_ok_and(|mut entries| entries.next().is_some()) {
+        return Err(BackupError::NotEmpty(args.world_path.clone()).into());
+    }
+
+    let results: Vec<Result<bool, String>> = manifest
+        .files
+        .par_iter()
+        .map(|entry| restore_file(backups_folder, backup_folder, entry, &decryption, args).map_err(|err| err.to_string()))
+        .collect();
+
+    let (mut converted, mut failed) = (0, 0);
+    for (entry, result) in manifest.files.iter().zip(results) {
+        match result {
+            Ok(was_converted) => converted += was_converted as usize,
+            Err(err) => {
+                eprintln!("Failed to restore file {} !, error : {}", entry.source, err);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(BackupError::FailedRestore(failed).into());
+    }
+
+    println!("{}: {} files restored, {} region files converted", args.world_path.display(), manifest.files.len(), converted);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backup::{run_backup, BackupArgs};
+    use crate::chunk::Chunk;
+    use crate::nbt::tag::Tag;
+    use crate::region_file::Region;
+
+    #[test]
+    fn test_restore_differential() {
+        let folder = std::env::temp_dir().join("bufferedlinear_tools_restore_test");
+        let _ = fs::remove_dir_all(&folder);
+        let world = folder.join("world");
+        fs::create_dir_all(world.join("region")).unwrap();
+
+        let chunks = (0..3).map(|x| Chunk::new_from_block_pos(x, 0, 1, Tag::Compound { name: None, value: Vec::new() })).collect();
+        let region = Region::new(chunks, 1).to_bytes(RegionFormat::Mca, None, 1, 6).unwrap();
+        fs::write(world.join("region/r.0.0.mca"), &region).unwrap();
+        fs::write(world.join("level.dat"), b"level").unwrap();
+
+        let destination = folder.join("backups");
+        let mut backup_args = BackupArgs { world_path: world.clone(), destination: destination.clone(), format: Some(OutputFormat::Blinear), compression_level: 6, encrypt: None, differential: true, keep: None, keep_daily: None, keep_weekly: None };
+        let full = run_backup(&backup_args).unwrap();
+
+        // a differential backup of the same world refers to the full one for every file
+        fs::rename(&full, destination.join("backup-20000101-000000")).unwrap();
+        backup_args.keep = Some(1);
+        let differential = run_backup(&backup_args).unwrap();
+        assert_eq!(Manifest::read(&differential).unwrap().references().count(), 2);
+
+        let args = RestoreArgs { backup: differential.join(MANIFEST_FILE), world_path: folder.join("restored"), format: None, compression_level: 6, identity: None };
+        run_restore(&args).unwrap();
+        assert_eq!(fs::read(folder.join("restored/level.dat")).unwrap(), b"level");
+        let (format, restored) = crate::region_file::read_region_file(&folder.join("restored/region/r.0.0.mca")).unwrap();
+        assert_eq!(format, RegionFormat::Mca);
+        assert_eq!(restored.chunks().len(), 3);
+
+        assert!(run_restore(&args).is_err());
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
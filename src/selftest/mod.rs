@@ -0,0 +1,104 @@
+use crate::diff::diff_chunks;
+use crate::region_file::{read_region_file, region_coords_from_path, Region, RegionFormat, WriteError};
+use clap::Args;
+use std::error::Error;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct SelftestArgs {
+    /// Sample region file in any of the supported formats (mca, linear, blinear)
+    pub region_file: PathBuf,
+
+    /// Compression level used for every written format, anvil files are written at most at zlib's
+    /// highest level 9
+    #[arg(short, long, default_value = "6", value_parser = crate::validate_compression_level)]
+    pub compression_level: u32,
+}
+
+enum LegResult {
+    Passed(Region),
+    Skipped(String),
+    Failed(String),
+}
+
+/// Every chain starting at the source format, visiting the other formats in both orders and
+/// returning to the source, e.g. mca → linear → blinear → mca and mca → blinear → linear → mca.
+fn format_chains(source: RegionFormat) -> Vec<Vec<RegionFormat>> {
+    let others: Vec<RegionFormat> = RegionFormat::ALL.into_iter().filter(|format| *format != source).collect();
+
+    [others.clone(), others.into_iter().rev().collect()]
+        .into_iter()
+        .map(|middle| [vec![source], middle, vec![source]].concat())
+        .collect()
+}
+
+/// Writes `region` in `format`, reads it back and compares every chunk with the source.
+fn run_leg(source: &Region, coords: (i32, i32), region: &Region, format: RegionFormat, compression_level: u8) -> LegResult {
+    let compression_level = compression_level.min(*format.compression_levels().end());
+    let bytes = match region.to_bytes(format, Some(coords), region.timestamp(), compression_level) {
+        Ok(bytes) => bytes,
+        Err(err @ WriteError::UnsupportedFormat(_)) => return LegResult::Skipped(err.to_string()),
+        Err(err) => return LegResult::Failed(err.to_string()),
+    };
+
+    let written = match Region::from_bytes(format, &bytes) {
+        Ok(written) => written,
+        Err(err) => return LegResult::Failed(format!("written file does not parse: {err}")),
+    };
+
+    let (diffs, _) = diff_chunks(source.chunks(), coords, written.chunks(), coords);
+    match diffs.keys().next() {
+        None => LegResult::Passed(written),
+        Some((x, z)) => LegResult::Failed(format!("{} chunks differ, first at chunk {x}, {z}", diffs.len())),
+    }
+}
+
+/// Runs every chain and returns whether all legs that could run passed.
+pub fn run_selftest(args: &SelftestArgs) -> Result<bool, Box<dyn Error>> {
+    let (source_format, source) = read_region_file(&args.region_file)?;
+    let coords = region_coords_from_path(&args.region_file).ok_or("File name is not r.<x>.<z>.<ext>")?;
+    let compression_level = args.compression_level as u8;
+
+    println!("{} ({:?}, {} chunks)", args.region_file.display(), source_format, source.chunks().len());
+
+    let mut passed = true;
+    for chain in format_chains(source_format) {
+        let names: Vec<String> = chain.iter().map(|format| format.extension().to_string()).collect();
+        println!("{}", names.join(" -> "));
+
+        // a skipped leg is bridged by converting from the last format that was written
+        let mut current: Option<(RegionFormat, Region)> = None;
+        for to in &chain[1..] {
+            let (from, region) = current.as_ref().map_or((source_format, &source), |(format, region)| (*format, region));
+
+            let leg = format!("  {} -> {}", from.extension(), to.extension());
+            match run_leg(&source, coords, region, *to, compression_level) {
+                LegResult::Passed(written) => {
+                    println!("{leg}: ok");
+                    current = Some((*to, written));
+                }
+                LegResult::Skipped(reason) => println!("{leg}: skipped, {reason}"),
+                LegResult::Failed(reason) => {
+                    println!("{leg}: FAILED, {reason}");
+                    passed = false;
+                    break;
+                }
+            }
+        }
+    }
+
+    Ok(passed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_chains() {
+        use RegionFormat::*;
+
+        assert_eq!(format_chains(Mca), [vec![Mca, Linear, Blinear, Mca], vec![Mca, Blinear, Linear, Mca]]);
+        assert_eq!(format_chains(Blinear), [vec![Blinear, Mca, Linear, Blinear], vec![Blinear, Linear, Mca, Blinear]]);
+    }
+}
@@ -0,0 +1,96 @@
+use crate::incremental::hash;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// SQLite database remembering which files a conversion already wrote, by the hash of the
+/// source, the output and the settings, so unchanged files are skipped without trusting mtimes.
+pub struct StateDb {
+    connection: Mutex<Connection>,
+}
+
+impl StateDb {
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(
+            "PRAGMA journal_mode = WAL;
+             PRAGMA synchronous = NORMAL;
+             CREATE TABLE IF NOT EXISTS converted_files (
+                 source TEXT NOT NULL,
+                 output TEXT NOT NULL,
+                 source_hash INTEGER NOT NULL,
+                 output_hash INTEGER NOT NULL,
+                 settings TEXT NOT NULL,
+                 converted_at INTEGER NOT NULL,
+                 PRIMARY KEY (source, output)
+             );",
+        )?;
+
+        Ok(Self { connection: Mutex::new(connection) })
+    }
+
+    /// Whether `output` was converted from a source with this hash using the same settings, and
+    /// still holds what was written then.
+    pub fn is_converted(&self, source: &Path, source_hash: u64, output: &Path, settings: &str) -> rusqlite::Result<bool> {
+        let recorded: Option<(i64, i64, String)> = self
+            .connection
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT source_hash, output_hash, settings FROM converted_files WHERE source = ?1 AND output = ?2",
+                params![source.to_string_lossy(), output.to_string_lossy()],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()?;
+
+        let Some((recorded_source, recorded_output, recorded_settings)) = recorded else {
+            return Ok(false);
+        };
+        if recorded_source as u64 != source_hash || recorded_settings != settings {
+            return Ok(false);
+        }
+
+        Ok(fs::read(output).is_ok_and(|bytes| hash(&bytes) == recorded_output as u64))
+    }
+
+    pub fn record(&self, source: &Path, source_hash: u64, output: &Path, output_hash: u64, settings: &str, converted_at: i64) -> rusqlite::Result<()> {
+        self.connection.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO converted_files (source, output, source_hash, output_hash, settings, converted_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![source.to_string_lossy(), output.to_string_lossy(), source_hash as i64, output_hash as i64, settings, converted_at],
+        )?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_converted() {
+        let folder = std::env::temp_dir().join("bufferedlinear_tools_state_db_test");
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+        let (source, output) = (folder.join("r.0.0.mca"), folder.join("r.0.0.linear"));
+        fs::write(&output, b"converted").unwrap();
+
+        let state = StateDb::open(&folder.join("state.db")).unwrap();
+        assert!(!state.is_converted(&source, 1, &output, "linear 6").unwrap());
+
+        state.record(&source, 1, &output, hash(b"converted"), "linear 6", 0).unwrap();
+        assert!(state.is_converted(&source, 1, &output, "linear 6").unwrap());
+        assert!(!state.is_converted(&source, 2, &output, "linear 6").unwrap());
+        assert!(!state.is_converted(&source, 1, &output, "linear 7").unwrap());
+
+        // survives reopening, but not the output changing
+        drop(state);
+        let state = StateDb::open(&folder.join("state.db")).unwrap();
+        assert!(state.is_converted(&source, 1, &output, "linear 6").unwrap());
+        fs::write(&output, b"edited").unwrap();
+        assert!(!state.is_converted(&source, 1, &output, "linear 6").unwrap());
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
@@ -0,0 +1,185 @@
+use crate::chunk::Chunk;
+use crate::nbt::tag::Tag;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::io::Write;
+
+/// What the NBT of a terrain chunk is spent on.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Category {
+    BlockStates,
+    Biomes,
+    Heightmaps,
+    BlockEntities,
+    Light,
+    Misc,
+}
+
+impl Category {
+    pub const ALL: [Category; 6] = [Category::BlockStates, Category::Biomes, Category::Heightmaps, Category::BlockEntities, Category::Light, Category::Misc];
+
+    fn name(self) -> &'static str {
+        match self {
+            Category::BlockStates => "block states",
+            Category::Biomes => "biomes",
+            Category::Heightmaps => "heightmaps",
+            Category::BlockEntities => "block entities",
+            Category::Light => "light data",
+            Category::Misc => "misc",
+        }
+    }
+
+    /// Convert option dropping the category, for those the game recomputes.
+    fn strip_hint(self) -> Option<&'static str> {
+        match self {
+            Category::Heightmaps => Some("--strip-recomputable"),
+            Category::Light => Some("--strip-light"),
+            _ => None,
+        }
+    }
+
+    /// The category of a section field (1.18+ names first, then the older ones).
+    fn of_section_field(name: &str) -> Option<Self> {
+        match name {
+            "block_states" | "Palette" | "BlockStates" => Some(Category::BlockStates),
+            "biomes" => Some(Category::Biomes),
+            "BlockLight" | "SkyLight" => Some(Category::Light),
+            _ => None,
+        }
+    }
+
+    /// The category of a top level chunk field.
+    fn of_chunk_field(name: &str) -> Option<Self> {
+        match name {
+            "Biomes" => Some(Category::Biomes),
+            "Heightmaps" => Some(Category::Heightmaps),
+            "block_entities" | "TileEntities" => Some(Category::BlockEntities),
+            _ => None,
+        }
+    }
+}
+
+/// Bytes spent on one category, summed over chunks.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct CategorySize {
+    /// Serialized NBT size of the category's subtrees
+    pub raw: usize,
+    /// Share of the chunks' compressed size, split by how well each category compresses on its own
+    pub compressed: f64,
+}
+
+pub type CategorySizes = [CategorySize; Category::ALL.len()];
+
+fn index(category: Category) -> usize {
+    Category::ALL.iter().position(|other| *other == category).unwrap()
+}
+
+fn zlib_size(bytes: &[u8]) -> usize {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(bytes).expect("Writing to a Vec never fails");
+    encoder.finish().expect("Writing to a Vec never fails").len()
+}
+
+/// Moves the serialized subtrees of each category out of the chunk fields, what is left is misc.
+fn split_fields(fields: &mut Tag, parts: &mut [Vec<u8>]) {
+    if let Tag::Compound { value, .. } = fields {
+        value.retain(|field| match field.get_name().as_deref().and_then(Category::of_chunk_field) {
+            Some(category) => {
+                parts[index(category)].extend(field.to_bytes());
+                false
+            }
+            None => true,
+        });
+    }
+
+    for sections in ["sections", "Sections"] {
+        if let Some(Tag::List { value: sections, .. }) = fields.find_tag_mut(sections) {
+            for section in sections {
+                if let Tag::Compound { value, .. } = section {
+                    value.retain(|field| match field.get_name().as_deref().and_then(Category::of_section_field) {
+                        Some(category) => {
+                            parts[index(category)].extend(field.to_bytes());
+                            false
+                        }
+                        None => true,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Adds the bytes one chunk spends on each category to `sizes`.
+pub fn add_chunk_categories(chunk: &Chunk, sizes: &mut CategorySizes) {
+    let mut data = chunk.get_data().clone();
+    let mut parts = vec![Vec::new(); Category::ALL.len()];
+
+    let fields = if data.find_tag("Level").is_some() { data.find_tag_mut("Level").unwrap() } else { &mut data };
+    split_fields(fields, &mut parts);
+    parts[index(Category::Misc)] = data.to_bytes();
+
+    let standalone: Vec<usize> = parts.iter().map(|part| if part.is_empty() { 0 } else { zlib_size(part) }).collect();
+    let standalone_total: usize = standalone.iter().sum();
+
+    for (size, (part, compressed)) in sizes.iter_mut().zip(parts.iter().zip(&standalone)) {
+        size.raw += part.len();
+        size.compressed += chunk.compressed_size() as f64 * *compressed as f64 / standalone_total.max(1) as f64;
+    }
+}
+
+pub fn print_category_report(sizes: &CategorySizes) {
+    let raw_total: usize = sizes.iter().map(|size| size.raw).sum();
+    let compressed_total: f64 = sizes.iter().map(|size| size.compressed).sum();
+
+    println!("Size by category:");
+    for (category, size) in Category::ALL.iter().zip(sizes) {
+        let hint = category.strip_hint().map_or(String::new(), |option| format!(", dropped by {option}"));
+        println!(
+            "  {:<16} raw {:>12} bytes ({:>5.2}%), compressed {:>12.0} bytes ({:>5.2}%){}",
+            category.name(),
+            size.raw,
+            size.raw as f64 * 100.0 / raw_total.max(1) as f64,
+            size.compressed,
+            size.compressed * 100.0 / compressed_total.max(1.0),
+            hint
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_categories() {
+        let named = |name: &str| Some(String::from(name));
+        let section = Tag::Compound {
+            name: None,
+            value: vec![
+                Tag::Byte { name: named("Y"), value: 0 },
+                Tag::Compound { name: named("block_states"), value: vec![Tag::LongArray { name: named("data"), value: vec![7; 256] }] },
+                Tag::ByteArray { name: named("SkyLight"), value: vec![15; 2048] },
+            ],
+        };
+        let data = Tag::Compound {
+            name: None,
+            value: vec![
+                Tag::Int { name: named("DataVersion"), value: 3953 },
+                Tag::List { name: named("sections"), value: vec![section], tag_type: 10 },
+                Tag::Compound { name: named("Heightmaps"), value: vec![Tag::LongArray { name: named("WORLD_SURFACE"), value: vec![1; 37] }] },
+            ],
+        };
+        let raw_size = data.to_bytes().len();
+        let chunk = Chunk::new_from_block_pos(0, 0, 1, data);
+
+        let mut sizes = CategorySizes::default();
+        add_chunk_categories(&chunk, &mut sizes);
+
+        assert_eq!(sizes.iter().map(|size| size.raw).sum::<usize>(), raw_size);
+        assert!(sizes[index(Category::Light)].raw > 2048);
+        assert!(sizes[index(Category::BlockStates)].raw > 2048);
+        assert!(sizes[index(Category::Heightmaps)].raw > 37 * 8);
+        assert_eq!(sizes[index(Category::Biomes)].raw, 0);
+        assert_eq!(sizes[index(Category::BlockEntities)], CategorySize::default());
+    }
+}
@@ -0,0 +1,79 @@
+use crate::stats::ChunkRecord;
+use std::borrow::Cow;
+use std::io::{self, Write};
+
+const HEADER: &str = "region_file,x,z,timestamp,raw_size,compressed_size,status,data_version,inhabited_time";
+
+fn escape_field(value: &str) -> Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+fn optional_field<T: ToString>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+pub fn write_chunk_csv<W: Write>(mut writer: W, records: &[ChunkRecord]) -> io::Result<()> {
+    writeln!(writer, "{}", HEADER)?;
+
+    for record in records {
+        writeln!(
+            writer,
+            "{},{},{},{},{},{},{},{},{}",
+            escape_field(&record.region_file),
+            record.x,
+            record.z,
+            record.timestamp,
+            record.raw_size,
+            record.compressed_size,
+            escape_field(record.status.as_deref().unwrap_or_default()),
+            optional_field(record.data_version),
+            optional_field(record.inhabited_time),
+        )?;
+    }
+
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(region_file: &str, status: Option<&str>) -> ChunkRecord {
+        ChunkRecord {
+            region_file: String::from(region_file),
+            x: -33,
+            z: 4,
+            timestamp: 1700000000,
+            raw_size: 2048,
+            compressed_size: 512,
+            status: status.map(String::from),
+            data_version: Some(3953),
+            inhabited_time: None,
+            entities: 0,
+            block_entities: 0,
+            content_hash: None,
+            dominant_subtree: None,
+        }
+    }
+
+    #[test]
+    fn test_write_chunk_csv() {
+        let mut output = Vec::new();
+        write_chunk_csv(&mut output, &[record("r.-2.0.linear", Some("minecraft:full"))]).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!("{HEADER}\nr.-2.0.linear,-33,4,1700000000,2048,512,minecraft:full,3953,\n")
+        );
+    }
+
+    #[test]
+    fn test_escape_field() {
+        assert_eq!(escape_field("r.0.0.mca"), "r.0.0.mca");
+        assert_eq!(escape_field("odd,\"name\""), "\"odd,\"\"name\"\"\"");
+    }
+}
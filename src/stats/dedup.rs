@@ -0,0 +1,102 @@
+use crate::stats::ChunkRecord;
+use std::collections::HashMap;
+
+/// Number of most repeated contents listed in the report
+const GROUPS_LISTED: usize = 5;
+
+#[derive(Debug, PartialEq)]
+struct DedupSummary {
+    chunks: usize,
+    unique: usize,
+    /// Size of every copy after the first of a chunk content
+    duplicate_raw_size: usize,
+    duplicate_compressed_size: usize,
+    /// Copies of the most repeated contents, largest first
+    largest_groups: Vec<usize>,
+}
+
+fn summarize(records: &[ChunkRecord]) -> DedupSummary {
+    let mut groups: HashMap<u64, Vec<&ChunkRecord>> = HashMap::new();
+    for record in records {
+        if let Some(hash) = record.content_hash {
+            groups.entry(hash).or_default().push(record);
+        }
+    }
+
+    let duplicates = || groups.values().flat_map(|group| group.iter().skip(1));
+
+    let mut largest_groups: Vec<usize> = groups.values().map(Vec::len).filter(|copies| *copies > 1).collect();
+    largest_groups.sort_unstable_by(|a, b| b.cmp(a));
+    largest_groups.truncate(GROUPS_LISTED);
+
+    DedupSummary {
+        chunks: groups.values().map(Vec::len).sum(),
+        unique: groups.len(),
+        duplicate_raw_size: duplicates().map(|record| record.raw_size).sum(),
+        duplicate_compressed_size: duplicates().map(|record| record.compressed_size).sum(),
+        largest_groups,
+    }
+}
+
+/// Reports how many chunks have the same NBT as another chunk apart from their position, and how
+/// much keeping each content once would save. The chunk store hashes the NBT with the position,
+/// so copies at other positions are not shared there and the savings are an upper bound.
+pub fn print_dedup_report(records: &[ChunkRecord]) {
+    let summary = summarize(records);
+    let compressed_total: usize = records.iter().map(|record| record.compressed_size).sum();
+
+    println!("Duplicate chunks (content-equivalent, ignoring position):");
+    println!("  Unique contents: {} of {} chunks", summary.unique, summary.chunks);
+    println!("  Duplicate chunks: {}", summary.chunks - summary.unique);
+    println!("  Duplicate raw size: {} bytes", summary.duplicate_raw_size);
+    println!(
+        "  Duplicate compressed size: {} bytes ({:.2}% of the compressed total)",
+        summary.duplicate_compressed_size,
+        summary.duplicate_compressed_size as f64 * 100.0 / compressed_total.max(1) as f64
+    );
+
+    if !summary.largest_groups.is_empty() {
+        let copies: Vec<String> = summary.largest_groups.iter().map(usize::to_string).collect();
+        println!("  Most repeated contents: {} copies", copies.join(", "));
+    }
+    println!("  Not achievable with --chunk-store, which only shares chunks identical including their position");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(hash: u64, compressed_size: usize) -> ChunkRecord {
+        ChunkRecord {
+            region_file: String::from("r.0.0.mca"),
+            x: 0,
+            z: 0,
+            timestamp: 0,
+            raw_size: compressed_size * 4,
+            compressed_size,
+            status: None,
+            data_version: None,
+            inhabited_time: None,
+            entities: 0,
+            block_entities: 0,
+            content_hash: Some(hash),
+            dominant_subtree: None,
+        }
+    }
+
+    #[test]
+    fn test_summarize() {
+        let records = [record(1, 100), record(2, 300), record(1, 100), record(1, 100), record(3, 50), record(3, 50)];
+
+        assert_eq!(
+            summarize(&records),
+            DedupSummary {
+                chunks: 6,
+                unique: 3,
+                duplicate_raw_size: 1000,
+                duplicate_compressed_size: 250,
+                largest_groups: vec![3, 2],
+            }
+        );
+    }
+}
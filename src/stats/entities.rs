@@ -0,0 +1,108 @@
+use crate::stats::ChunkRecord;
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Default, Clone, Copy)]
+struct EntityCounts {
+    entities: usize,
+    block_entities: usize,
+}
+
+struct ChunkEntities {
+    region: String,
+    counts: EntityCounts,
+}
+
+impl EntityCounts {
+    fn total(&self) -> usize {
+        self.entities + self.block_entities
+    }
+}
+
+fn region_name(file_name: &str) -> &str {
+    file_name.rsplit_once('.').map_or(file_name, |(stem, _)| stem)
+}
+
+/// Joins entity counts from both region types by chunk position. Since 1.17 entities live in the
+/// `entities` files while block entities stay in the terrain chunks.
+fn join_counts(region_records: &[ChunkRecord], entity_records: &[ChunkRecord]) -> HashMap<(i32, i32), ChunkEntities> {
+    let mut joined = HashMap::new();
+
+    for record in region_records.iter().chain(entity_records) {
+        let chunk = joined.entry((record.x, record.z)).or_insert_with(|| ChunkEntities {
+            region: String::from(region_name(&record.region_file)),
+            counts: EntityCounts::default(),
+        });
+
+        chunk.counts.entities += record.entities;
+        chunk.counts.block_entities += record.block_entities;
+    }
+
+    joined
+}
+
+pub fn print_entity_report(region_records: &[ChunkRecord], entity_records: &[ChunkRecord], heaviest: usize) {
+    let joined = join_counts(region_records, entity_records);
+
+    let mut per_region: BTreeMap<&str, EntityCounts> = BTreeMap::new();
+    for chunk in joined.values() {
+        let region_counts = per_region.entry(chunk.region.as_str()).or_default();
+        region_counts.entities += chunk.counts.entities;
+        region_counts.block_entities += chunk.counts.block_entities;
+    }
+
+    println!("Entities per region:");
+    for (region, counts) in &per_region {
+        println!("  {:<16} entities {:>8}  block entities {:>8}", region, counts.entities, counts.block_entities);
+    }
+
+    let world_entities: usize = per_region.values().map(|counts| counts.entities).sum();
+    let world_block_entities: usize = per_region.values().map(|counts| counts.block_entities).sum();
+    println!("  {:<16} entities {:>8}  block entities {:>8}", "total", world_entities, world_block_entities);
+
+    let mut chunks: Vec<(&(i32, i32), &ChunkEntities)> = joined.iter().filter(|(_, chunk)| chunk.counts.total() > 0).collect();
+    chunks.sort_by(|a, b| b.1.counts.total().cmp(&a.1.counts.total()).then(a.0.cmp(b.0)));
+
+    println!("Heaviest chunks:");
+    for ((x, z), chunk) in chunks.into_iter().take(heaviest) {
+        println!(
+            "  chunk {:>6}, {:>6} ({})  entities {:>6}  block entities {:>6}",
+            x, z, chunk.region, chunk.counts.entities, chunk.counts.block_entities
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(region_file: &str, x: i32, entities: usize, block_entities: usize) -> ChunkRecord {
+        ChunkRecord {
+            region_file: String::from(region_file),
+            x,
+            z: 0,
+            timestamp: 0,
+            raw_size: 0,
+            compressed_size: 0,
+            status: None,
+            data_version: None,
+            inhabited_time: None,
+            entities,
+            block_entities,
+            content_hash: None,
+            dominant_subtree: None,
+        }
+    }
+
+    #[test]
+    fn test_join_counts_by_position() {
+        let region = [record("r.0.0.mca", 1, 0, 4), record("r.0.0.mca", 2, 0, 0)];
+        let entities = [record("r.0.0.linear", 1, 7, 0), record("r.0.0.linear", 3, 2, 0)];
+
+        let joined = join_counts(&region, &entities);
+        let chunk = &joined[&(1, 0)];
+
+        assert_eq!(joined.len(), 3);
+        assert_eq!(chunk.region, "r.0.0");
+        assert_eq!((chunk.counts.entities, chunk.counts.block_entities), (7, 4));
+    }
+}
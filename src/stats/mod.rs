@@ -0,0 +1,329 @@
+mod categories;
+mod csv;
+mod dedup;
+mod entities;
+#[cfg(feature = "arrow")]
+mod parquet;
+mod top;
+
+use crate::chunk::Chunk;
+use crate::chunk_data::biomes::count_biomes;
+use crate::nbt::tag::Tag;
+use crate::region_file::{read_region_file, region_coords_from_path};
+use crate::stats::categories::{add_chunk_categories, print_category_report, CategorySizes};
+use crate::stats::csv::write_chunk_csv;
+use crate::stats::dedup::print_dedup_report;
+use crate::stats::entities::print_entity_report;
+use crate::stats::top::{dominant_subtree, print_top_report};
+use crate::{folder_name, scan_region_files, RegionType};
+use clap::Args;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::File;
+use std::hash::Hasher;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use twox_hash::XxHash64;
+
+#[derive(Args)]
+pub struct StatsArgs {
+    /// Path to your Minecraft world
+    pub world_path: PathBuf,
+
+    #[arg(short = 't', long, value_enum, default_value = "region")]
+    pub region_type: RegionType,
+
+    /// Write one row of metadata per chunk to this CSV file
+    #[arg(long)]
+    pub csv: Option<PathBuf>,
+
+    /// Write the same per-chunk table to this Parquet file
+    #[cfg(feature = "arrow")]
+    #[arg(long)]
+    pub parquet: Option<PathBuf>,
+
+    /// Report the biome distribution per region and for the whole world
+    #[arg(long)]
+    pub biomes: bool,
+
+    /// Report entity and block entity counts, reading both the region and entities folders
+    #[arg(long)]
+    pub entities: bool,
+
+    /// Number of chunks listed in the heaviest chunks part of the entity report
+    #[arg(long, default_value = "10")]
+    pub heaviest: usize,
+
+    /// Report how much of the raw and compressed size goes to block states, biomes, heightmaps,
+    /// block entities, light data and everything else
+    #[arg(long)]
+    pub categories: bool,
+
+    /// Report chunks whose NBT is identical apart from their position and what storing each
+    /// content once would save. `--chunk-store` only shares chunks whose NBT is identical
+    /// including their position, so it saves less than reported
+    #[arg(long)]
+    pub dedup: bool,
+
+    /// List the N largest chunks with their raw and compressed size and the field taking most of
+    /// their NBT
+    #[arg(long, value_name = "N")]
+    pub top: Option<usize>,
+}
+
+/// Which of the more expensive per-region aggregates to compute while scanning.
+#[derive(Default)]
+pub struct CollectOptions {
+    pub biomes: bool,
+    pub categories: bool,
+    pub content_hashes: bool,
+    pub subtrees: bool,
+}
+
+pub struct RegionStats {
+    pub file_name: String,
+    pub chunks: Vec<ChunkRecord>,
+    /// Number of 4x4x4 biome cells per biome id
+    pub biomes: BTreeMap<String, u64>,
+    /// Bytes spent on each data category
+    pub categories: CategorySizes,
+}
+
+pub struct ChunkRecord {
+    pub region_file: String,
+    pub x: i32,
+    pub z: i32,
+    pub timestamp: i64,
+    pub raw_size: usize,
+    pub compressed_size: usize,
+    pub status: Option<String>,
+    pub data_version: Option<i32>,
+    pub inhabited_time: Option<i64>,
+    pub entities: usize,
+    pub block_entities: usize,
+    /// Hash of the chunk's serialized NBT without its position fields, only computed for the
+    /// dedup report
+    pub content_hash: Option<u64>,
+    /// Largest field of the chunk's NBT and its size, only computed for the top report
+    pub dominant_subtree: Option<(String, usize)>,
+}
+
+impl ChunkRecord {
+    fn from_chunk(region_file: &str, region_x: i32, region_z: i32, chunk: &Chunk, options: &CollectOptions) -> Self {
+        let (x, z) = chunk.global_position(region_x, region_z);
+
+        Self {
+            region_file: String::from(region_file),
+            x,
+            z,
+            timestamp: chunk.timestamp(),
+            raw_size: chunk.raw_size(),
+            compressed_size: chunk.compressed_size(),
+            status: chunk.find_field("Status").and_then(|tag| tag.get_string()).cloned(),
+            data_version: chunk.data_version(),
+            inhabited_time: chunk.find_field("InhabitedTime").and_then(|tag| tag.get_long()).copied(),
+            entities: count_children(chunk.find_field("Entities")),
+            block_entities: count_children(chunk.find_field("block_entities").or_else(|| chunk.find_field("TileEntities"))),
+            content_hash: options.content_hashes.then(|| content_hash(chunk)),
+            dominant_subtree: options.subtrees.then(|| dominant_subtree(chunk)).flatten(),
+        }
+    }
+}
+
+/// Fields telling where or when a chunk was saved rather than what it holds, left out of the
+/// content hash so copies of a chunk at other positions hash alike.
+const POSITION_FIELDS: [&str; 5] = ["xPos", "zPos", "yPos", "Position", "LastUpdate"];
+
+fn content_hash(chunk: &Chunk) -> u64 {
+    let mut data = chunk.get_data().clone();
+    for name in POSITION_FIELDS {
+        data.remove_tag(name);
+        if let Some(level) = data.find_tag_mut("Level") {
+            level.remove_tag(name);
+        }
+    }
+
+    let mut hasher = XxHash64::with_seed(0);
+    hasher.write(&data.to_bytes());
+    hasher.finish()
+}
+
+fn count_children(tag: Option<&Tag>) -> usize {
+    tag.and_then(|tag| tag.children()).map_or(0, |children| children.len())
+}
+
+fn collect_region_stats(region_file: &Path, options: &CollectOptions) -> Result<RegionStats, Box<dyn Error>> {
+    let (_, region) = read_region_file(region_file)?;
+    let (region_x, region_z) = region_coords_from_path(region_file).ok_or("File name is not r.<x>.<z>.<ext>")?;
+    let file_name = region_file.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+
+    let mut biomes = BTreeMap::new();
+    if options.biomes {
+        for chunk in region.chunks() {
+            count_biomes(chunk, &mut biomes);
+        }
+    }
+
+    let mut categories = CategorySizes::default();
+    if options.categories {
+        for chunk in region.chunks() {
+            add_chunk_categories(chunk, &mut categories);
+        }
+    }
+
+    Ok(RegionStats {
+        file_name: String::from(file_name),
+        chunks: region
+            .chunks()
+            .iter()
+            .map(|chunk| ChunkRecord::from_chunk(file_name, region_x, region_z, chunk, options))
+            .collect(),
+        biomes,
+        categories,
+    })
+}
+
+/// Reads every region file of the given type in a world and returns its stats, one entry per
+/// region file. Files that fail to parse are reported and skipped.
+pub fn collect_world_stats(world_path: &Path, region_type: RegionType, options: &CollectOptions) -> Vec<RegionStats> {
+    let mut scanned = scan_region_files(world_path.join(folder_name(region_type)));
+    scanned.sort();
+
+    scanned
+        .par_iter()
+        .filter_map(|region_file| match collect_region_stats(region_file, options) {
+            Ok(stats) => Some(stats),
+            Err(err) => {
+                eprintln!("Failed to read file {} !, error : {}", region_file.display(), err);
+                None
+            }
+        })
+        .collect()
+}
+
+pub fn run_stats(args: &StatsArgs) -> Result<(), Box<dyn Error>> {
+    let options = CollectOptions {
+        biomes: args.biomes,
+        categories: args.categories,
+        content_hashes: args.dedup,
+        subtrees: args.top.is_some(),
+    };
+    let mut per_file = collect_world_stats(&args.world_path, args.region_type, &options);
+
+    let region_count = per_file.len();
+    let records: Vec<ChunkRecord> = per_file.iter_mut().flat_map(|stats| std::mem::take(&mut stats.chunks)).collect();
+
+    let raw_total: usize = records.iter().map(|record| record.raw_size).sum();
+    let compressed_total: usize = records.iter().map(|record| record.compressed_size).sum();
+
+    println!("Region files: {}", region_count);
+    println!("Chunks: {}", records.len());
+    println!("Raw size: {} bytes", raw_total);
+    println!("Compressed size: {} bytes", compressed_total);
+
+    let mut data_versions = BTreeMap::new();
+    for record in &records {
+        let version = record.data_version.map_or(String::from("none"), |version| version.to_string());
+        *data_versions.entry(version).or_default() += 1;
+    }
+    println!("DataVersions:");
+    print_distribution(&data_versions);
+
+    if args.biomes {
+        let mut world_biomes = BTreeMap::new();
+        for stats in &per_file {
+            println!("Biomes in {}:", stats.file_name);
+            print_distribution(&stats.biomes);
+
+            for (biome, cells) in &stats.biomes {
+                *world_biomes.entry(biome.clone()).or_default() += cells;
+            }
+        }
+
+        println!("Biomes in the whole world:");
+        print_distribution(&world_biomes);
+    }
+
+    if args.categories {
+        let mut world_categories = CategorySizes::default();
+        for stats in &per_file {
+            for (total, size) in world_categories.iter_mut().zip(&stats.categories) {
+                total.raw += size.raw;
+                total.compressed += size.compressed;
+            }
+        }
+        print_category_report(&world_categories);
+    }
+
+    if args.entities {
+        let collect_records = |region_type| -> Vec<ChunkRecord> {
+            collect_world_stats(&args.world_path, region_type, &CollectOptions::default())
+                .into_iter()
+                .flat_map(|stats| stats.chunks)
+                .collect()
+        };
+
+        match args.region_type {
+            RegionType::REGION => print_entity_report(&records, &collect_records(RegionType::ENTITIES), args.heaviest),
+            RegionType::ENTITIES => print_entity_report(&collect_records(RegionType::REGION), &records, args.heaviest),
+            RegionType::POI => print_entity_report(&collect_records(RegionType::REGION), &collect_records(RegionType::ENTITIES), args.heaviest),
+        }
+    }
+
+    if args.dedup {
+        print_dedup_report(&records);
+    }
+
+    if let Some(top) = args.top {
+        print_top_report(&records, top);
+    }
+
+    if let Some(csv_path) = &args.csv {
+        write_chunk_csv(BufWriter::new(File::create(csv_path)?), &records)?;
+        println!("Wrote {} chunk rows to {}", records.len(), csv_path.display());
+    }
+
+    #[cfg(feature = "arrow")]
+    if let Some(parquet_path) = &args.parquet {
+        parquet::write_chunk_parquet(File::create(parquet_path)?, &records)?;
+        println!("Wrote {} chunk rows to {}", records.len(), parquet_path.display());
+    }
+
+    Ok(())
+}
+
+fn print_distribution(counts: &BTreeMap<String, u64>) {
+    let total: u64 = counts.values().sum();
+
+    let mut sorted: Vec<(&String, &u64)> = counts.iter().collect();
+    sorted.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+
+    for (name, count) in sorted {
+        println!("  {:<40} {:>10} ({:.2}%)", name, count, *count as f64 * 100.0 / total as f64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_hash_ignores_position() {
+        let chunk = |x: i32, z: i32, status: &str| {
+            let data = Tag::Compound {
+                name: None,
+                value: vec![
+                    Tag::Int { name: Some(String::from("xPos")), value: x },
+                    Tag::Int { name: Some(String::from("zPos")), value: z },
+                    Tag::Long { name: Some(String::from("LastUpdate")), value: (x * 100) as i64 },
+                    Tag::String { name: Some(String::from("Status")), value: String::from(status) },
+                ],
+            };
+            Chunk::new_from_block_pos(x, z, 0, data)
+        };
+
+        assert_eq!(content_hash(&chunk(0, 0, "minecraft:full")), content_hash(&chunk(5, -3, "minecraft:full")));
+        assert_ne!(content_hash(&chunk(0, 0, "minecraft:full")), content_hash(&chunk(0, 0, "minecraft:empty")));
+    }
+}
@@ -0,0 +1,97 @@
+use crate::stats::ChunkRecord;
+use arrow_array::{ArrayRef, Int32Array, Int64Array, RecordBatch, StringArray, UInt64Array};
+use arrow_schema::{DataType, Field, Schema};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+use std::io::Write;
+use std::sync::Arc;
+
+const ROWS_PER_BATCH: usize = 65536;
+
+fn chunk_schema() -> Schema {
+    Schema::new(vec![
+        Field::new("region_file", DataType::Utf8, false),
+        Field::new("x", DataType::Int32, false),
+        Field::new("z", DataType::Int32, false),
+        Field::new("timestamp", DataType::Int64, false),
+        Field::new("raw_size", DataType::UInt64, false),
+        Field::new("compressed_size", DataType::UInt64, false),
+        Field::new("status", DataType::Utf8, true),
+        Field::new("data_version", DataType::Int32, true),
+        Field::new("inhabited_time", DataType::Int64, true),
+    ])
+}
+
+fn to_record_batch(schema: &Arc<Schema>, records: &[ChunkRecord]) -> Result<RecordBatch, ParquetError> {
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(records.iter().map(|record| record.region_file.as_str()))),
+        Arc::new(Int32Array::from_iter_values(records.iter().map(|record| record.x))),
+        Arc::new(Int32Array::from_iter_values(records.iter().map(|record| record.z))),
+        Arc::new(Int64Array::from_iter_values(records.iter().map(|record| record.timestamp))),
+        Arc::new(UInt64Array::from_iter_values(records.iter().map(|record| record.raw_size as u64))),
+        Arc::new(UInt64Array::from_iter_values(records.iter().map(|record| record.compressed_size as u64))),
+        Arc::new(StringArray::from_iter(records.iter().map(|record| record.status.as_deref()))),
+        Arc::new(Int32Array::from_iter(records.iter().map(|record| record.data_version))),
+        Arc::new(Int64Array::from_iter(records.iter().map(|record| record.inhabited_time))),
+    ];
+
+    Ok(RecordBatch::try_new(schema.clone(), columns)?)
+}
+
+pub fn write_chunk_parquet<W: Write + Send>(writer: W, records: &[ChunkRecord]) -> Result<(), ParquetError> {
+    let schema = Arc::new(chunk_schema());
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::ZSTD(ZstdLevel::default()))
+        .build();
+
+    let mut writer = ArrowWriter::try_new(writer, schema.clone(), Some(properties))?;
+    for batch in records.chunks(ROWS_PER_BATCH) {
+        writer.write(&to_record_batch(&schema, batch)?)?;
+    }
+    writer.close()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use std::fs::{remove_file, File};
+
+    #[test]
+    fn test_write_chunk_parquet() {
+        let records = [ChunkRecord {
+            region_file: String::from("r.0.0.blinear"),
+            x: 3,
+            z: 7,
+            timestamp: 1700000000,
+            raw_size: 2048,
+            compressed_size: 512,
+            status: None,
+            data_version: Some(3953),
+            inhabited_time: Some(42),
+            entities: 0,
+            block_entities: 0,
+            content_hash: None,
+            dominant_subtree: None,
+        }];
+
+        let path = std::env::temp_dir().join("bufferedlinear_tools_stats_test.parquet");
+        write_chunk_parquet(File::create(&path).unwrap(), &records).unwrap();
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        let batches: Vec<RecordBatch> = reader.map(|batch| batch.unwrap()).collect();
+        remove_file(&path).unwrap();
+
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].num_rows(), 1);
+        assert_eq!(batches[0].schema().as_ref(), &chunk_schema());
+        assert_eq!(batches[0].column(6).null_count(), 1);
+    }
+}
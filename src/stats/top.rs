@@ -0,0 +1,64 @@
+use crate::chunk::Chunk;
+use crate::stats::ChunkRecord;
+
+/// The field of a chunk taking the most serialized bytes, looking inside the `Level` compound of
+/// pre 1.18 chunks, with its size.
+pub fn dominant_subtree(chunk: &Chunk) -> Option<(String, usize)> {
+    let data = chunk.get_data();
+    let (prefix, fields) = match data.find_tag("Level") {
+        Some(level) => ("Level.", level),
+        None => ("", data),
+    };
+
+    fields
+        .children()?
+        .iter()
+        .filter_map(|field| Some((format!("{}{}", prefix, field.get_name()?), field.to_bytes().len())))
+        .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))
+}
+
+/// The largest chunks by raw size, ties broken by compressed size and position.
+fn largest_chunks(records: &[ChunkRecord], top: usize) -> Vec<&ChunkRecord> {
+    let mut largest: Vec<&ChunkRecord> = records.iter().collect();
+    largest.sort_by(|a, b| b.raw_size.cmp(&a.raw_size).then(b.compressed_size.cmp(&a.compressed_size)).then((a.x, a.z).cmp(&(b.x, b.z))));
+    largest.truncate(top);
+    largest
+}
+
+pub fn print_top_report(records: &[ChunkRecord], top: usize) {
+    println!("Largest chunks:");
+    for record in largest_chunks(records, top) {
+        let subtree = record.dominant_subtree.as_ref().map_or(String::from("-"), |(name, size)| {
+            format!("{} {} bytes ({:.0}%)", name, size, *size as f64 * 100.0 / record.raw_size.max(1) as f64)
+        });
+        println!(
+            "  chunk {:>6}, {:>6} ({})  raw {:>9}  compressed {:>9}  largest field {}",
+            record.x, record.z, record.region_file, record.raw_size, record.compressed_size, subtree
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nbt::tag::Tag;
+
+    #[test]
+    fn test_dominant_subtree() {
+        let named = |name: &str| Some(String::from(name));
+        let hopper = || Tag::Compound { name: None, value: vec![Tag::String { name: named("id"), value: String::from("minecraft:hopper") }] };
+        let level = Tag::Compound {
+            name: named("Level"),
+            value: vec![
+                Tag::Int { name: named("xPos"), value: 0 },
+                Tag::List { name: named("TileEntities"), value: vec![hopper(); 60], tag_type: 10 },
+                Tag::IntArray { name: named("Biomes"), value: vec![1; 256] },
+            ],
+        };
+        let chunk = Chunk::new_from_block_pos(0, 0, 1, Tag::Compound { name: None, value: vec![Tag::Int { name: named("DataVersion"), value: 1343 }, level] });
+
+        let (name, size) = dominant_subtree(&chunk).unwrap();
+        assert_eq!(name, "Level.TileEntities");
+        assert!(size > 60 * "minecraft:hopper".len());
+    }
+}
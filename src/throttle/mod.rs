@@ -0,0 +1,182 @@
+use std::fs::{self, File};
+use std::io;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Bytes read or written between two waits for the token bucket.
+const PIECE_SIZE: usize = 1 << 20;
+
+static READ_LIMIT: OnceLock<TokenBucket> = OnceLock::new();
+static WRITE_LIMIT: OnceLock<TokenBucket> = OnceLock::new();
+static FSYNC: AtomicBool = AtomicBool::new(false);
+
+/// Token bucket shared by every thread, refilled at `rate` bytes per second and holding at most
+/// one second of tokens, so short idle periods do not allow a burst afterwards.
+pub struct TokenBucket {
+    rate: f64,
+    /// Available tokens, negative while taken ahead, and when they were last refilled
+    state: Mutex<(f64, Instant)>,
+}
+
+impl TokenBucket {
+    pub fn new(bytes_per_second: f64) -> Self {
+        TokenBucket { rate: bytes_per_second, state: Mutex::new((bytes_per_second, Instant::now())) }
+    }
+
+    /// Takes tokens for `bytes`, returning how long to wait before using them.
+    fn take(&self, bytes: usize) -> Duration {
+        let mut state = self.state.lock().unwrap();
+        let (tokens, refilled) = &mut *state;
+
+        let now = Instant::now();
+        *tokens = (*tokens + now.duration_since(*refilled).as_secs_f64() * self.rate).min(self.rate);
+        *refilled = now;
+        *tokens -= bytes as f64;
+
+        if *tokens < 0.0 { Duration::from_secs_f64(-*tokens / self.rate) } else { Duration::ZERO }
+    }
+
+    fn wait(&self, bytes: usize) {
+        let wait = self.take(bytes);
+        if !wait.is_zero() {
+            thread::sleep(wait);
+        }
+    }
+}
+
+/// Parses a rate in megabytes per second.
+pub fn parse_mbps(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(mbps) if mbps > 0.0 && mbps.is_finite() => Ok(mbps),
+        _ => Err(format!("Rate must be a positive number of megabytes per second, not {s}")),
+    }
+}
+
+/// Limits the reads and writes of `read` and `write` of every thread, in megabytes per second.
+/// Only the first call has an effect.
+pub fn set_limits(read_mbps: Option<f64>, write_mbps: Option<f64>) {
+    if let Some(mbps) = read_mbps {
+        READ_LIMIT.get_or_init(|| TokenBucket::new(mbps * 1_000_000.0));
+    }
+    if let Some(mbps) = write_mbps {
+        WRITE_LIMIT.get_or_init(|| TokenBucket::new(mbps * 1_000_000.0));
+    }
+}
+
+/// Makes `write` fsync every file it writes and the folder it is renamed into.
+pub fn set_fsync(enabled: bool) {
+    FSYNC.store(enabled, Ordering::Relaxed);
+}
+
+/// Reads a whole file like `fs::read`, within the read limit.
+pub fn read(path: impl AsRef<Path>) -> io::Result<Vec<u8>> {
+    let Some(limit) = READ_LIMIT.get() else {
+        return std::fs::read(path);
+    };
+
+    let mut file = File::open(path)?;
+    let mut bytes = Vec::with_capacity(file.metadata().map_or(0, |metadata| metadata.len() as usize));
+    loop {
+        let read = Read::by_ref(&mut file).take(PIECE_SIZE as u64).read_to_end(&mut bytes)?;
+        limit.wait(read);
+        if read < PIECE_SIZE {
+            return Ok(bytes);
+        }
+    }
+}
+
+/// A file whose reads count against the read limit, for readers that seek.
+pub struct LimitedFile {
+    file: File,
+    limit: Option<&'static TokenBucket>,
+}
+
+impl Read for LimitedFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let piece = buf.len().min(PIECE_SIZE);
+        let read = self.file.read(&mut buf[..piece])?;
+        if let Some(limit) = self.limit {
+            limit.wait(read);
+        }
+        Ok(read)
+    }
+}
+
+impl Seek for LimitedFile {
+    fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        self.file.seek(position)
+    }
+}
+
+/// Opens a file for reading within the read limit.
+pub fn open(path: impl AsRef<Path>) -> io::Result<LimitedFile> {
+    Ok(LimitedFile { file: File::open(path)?, limit: READ_LIMIT.get() })
+}
+
+fn write_file(path: &Path, bytes: &[u8]) -> io::Result<File> {
+    let mut file = File::create(path)?;
+    match WRITE_LIMIT.get() {
+        Some(limit) => {
+            for piece in bytes.chunks(PIECE_SIZE) {
+                limit.wait(piece.len());
+                file.write_all(piece)?;
+            }
+        }
+        None => file.write_all(bytes)?,
+    }
+
+    Ok(file)
+}
+
+/// Writes a whole file like `fs::write`, within the write limit. With fsync enabled the bytes go
+/// to a temporary file first, which is synced and renamed over `path`, so a power loss leaves
+/// either the old or the complete new file.
+pub fn write(path: impl AsRef<Path>, bytes: impl AsRef<[u8]>) -> io::Result<()> {
+    let path = path.as_ref();
+    if !FSYNC.load(Ordering::Relaxed) {
+        return write_file(path, bytes.as_ref()).map(drop);
+    }
+
+    let mut temp = PathBuf::from(path);
+    temp.as_mut_os_string().push(".fsync.tmp");
+    write_file(&temp, bytes.as_ref())?.sync_all()?;
+    fs::rename(&temp, path)?;
+
+    sync_parent(path)
+}
+
+/// Syncs the folder holding `path` when fsync is enabled, making a rename into it durable.
+pub fn sync_parent(path: &Path) -> io::Result<()> {
+    if !FSYNC.load(Ordering::Relaxed) {
+        return Ok(());
+    }
+
+    // folders can only be opened and synced like this on unix
+    #[cfg(unix)]
+    {
+        let parent = path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        File::open(parent)?.sync_all()?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket() {
+        let bucket = TokenBucket::new(1000.0);
+
+        // one second of tokens is available at once, more has to wait for the refill
+        assert_eq!(bucket.take(1000), Duration::ZERO);
+        let wait = bucket.take(500);
+        assert!(wait > Duration::from_millis(400) && wait <= Duration::from_millis(500), "{wait:?}");
+        assert!(bucket.take(500) > Duration::from_millis(900));
+    }
+}
@@ -0,0 +1,84 @@
+use bufferedlinear_tools::timings::PhaseTimings;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Where the conversion of one file spent its time.
+#[derive(Clone, Copy, Default)]
+pub struct FileTimings {
+    pub read: Duration,
+    pub phases: PhaseTimings,
+    pub write: Duration,
+    /// Wall time of the whole file, including what no phase covers
+    pub total: Duration,
+}
+
+impl FileTimings {
+    /// Time not spent in any measured phase, e.g. applying transforms.
+    fn other(&self) -> Duration {
+        self.total.saturating_sub(self.read + self.phases.total() + self.write)
+    }
+}
+
+/// Sums the timings of every converted file, from all worker threads.
+#[derive(Default)]
+pub struct TimingReport {
+    files: Mutex<(usize, FileTimings)>,
+}
+
+impl TimingReport {
+    pub fn add(&self, timings: FileTimings) {
+        let mut files = self.files.lock().unwrap();
+        files.0 += 1;
+        files.1.read += timings.read;
+        files.1.phases += timings.phases;
+        files.1.write += timings.write;
+        files.1.total += timings.total;
+    }
+
+    /// Prints every phase with its share of the summed per-file time.
+    pub fn print(&self) {
+        let (count, sum) = *self.files.lock().unwrap();
+
+        println!("Timings over {} files (summed over all threads):", count);
+        for (name, duration) in phase_rows(&sum) {
+            let share = duration.as_secs_f64() * 100.0 / sum.total.as_secs_f64().max(f64::EPSILON);
+            println!("  {:<12} {:>10.1} ms ({:.1}%)", name, duration.as_secs_f64() * 1000.0, share);
+        }
+        println!("  {:<12} {:>10.1} ms", "total", sum.total.as_secs_f64() * 1000.0);
+    }
+}
+
+fn phase_rows(timings: &FileTimings) -> [(&'static str, Duration); 7] {
+    [
+        ("read", timings.read),
+        ("decompress", timings.phases.decompress),
+        ("parse", timings.phases.parse),
+        ("serialize", timings.phases.serialize),
+        ("compress", timings.phases.compress),
+        ("write", timings.write),
+        ("other", timings.other()),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_sums_files() {
+        let report = TimingReport::default();
+        let file = FileTimings {
+            read: Duration::from_millis(2),
+            phases: PhaseTimings { parse: Duration::from_millis(5), ..PhaseTimings::ZERO },
+            write: Duration::from_millis(1),
+            total: Duration::from_millis(10),
+        };
+        report.add(file);
+        report.add(file);
+
+        let (count, sum) = *report.files.lock().unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(sum.phases.parse, Duration::from_millis(10));
+        assert_eq!(sum.other(), Duration::from_millis(4));
+    }
+}
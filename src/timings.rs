@@ -0,0 +1,142 @@
+//! Time spent in each phase of reading and writing region files. Collection is off until
+//! [`enable`] is called, then every thread sums its own phases until they are [`take`]n, so a
+//! file converted on one thread can be attributed exactly.
+
+use std::cell::Cell;
+use std::ops::AddAssign;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    static CURRENT: Cell<PhaseTimings> = const { Cell::new(PhaseTimings::ZERO) };
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    /// Undoing the zlib, gzip or zstd compression of chunks and regions
+    Decompress,
+    /// Turning binary NBT into tags
+    Parse,
+    /// Turning tags into binary NBT
+    Serialize,
+    /// Compressing chunks and regions
+    Compress,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PhaseTimings {
+    pub decompress: Duration,
+    pub parse: Duration,
+    pub serialize: Duration,
+    pub compress: Duration,
+}
+
+impl PhaseTimings {
+    pub const ZERO: Self = Self {
+        decompress: Duration::ZERO,
+        parse: Duration::ZERO,
+        serialize: Duration::ZERO,
+        compress: Duration::ZERO,
+    };
+
+    pub fn total(&self) -> Duration {
+        self.decompress + self.parse + self.serialize + self.compress
+    }
+
+    fn get_mut(&mut self, phase: Phase) -> &mut Duration {
+        match phase {
+            Phase::Decompress => &mut self.decompress,
+            Phase::Parse => &mut self.parse,
+            Phase::Serialize => &mut self.serialize,
+            Phase::Compress => &mut self.compress,
+        }
+    }
+}
+
+impl AddAssign for PhaseTimings {
+    fn add_assign(&mut self, other: Self) {
+        self.decompress += other.decompress;
+        self.parse += other.parse;
+        self.serialize += other.serialize;
+        self.compress += other.compress;
+    }
+}
+
+/// Starts collecting timings, on every thread.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// The phases this thread went through since the last call, resetting them.
+pub fn take() -> PhaseTimings {
+    CURRENT.with(|current| current.replace(PhaseTimings::ZERO))
+}
+
+/// Adds timings of work done for this thread's file elsewhere.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn add(timings: PhaseTimings) {
+    CURRENT.with(|current| {
+        let mut own = current.get();
+        own += timings;
+        current.set(own);
+    });
+}
+
+/// Runs `f` and returns the timings it collected, leaving this thread's own timings as they were.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn isolated<T>(f: impl FnOnce() -> T) -> (T, PhaseTimings) {
+    let own = take();
+    let result = f();
+    let spent = take();
+    CURRENT.with(|current| current.set(own));
+
+    (result, spent)
+}
+
+/// Runs `f`, adding its duration to `phase` if collection is enabled.
+pub(crate) fn time<T>(phase: Phase, f: impl FnOnce() -> T) -> T {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return f();
+    }
+
+    let started = Instant::now();
+    let result = f();
+    let elapsed = started.elapsed();
+
+    CURRENT.with(|current| {
+        let mut timings = current.get();
+        *timings.get_mut(phase) += elapsed;
+        current.set(timings);
+    });
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::nbt::tag::Tag;
+    use crate::region_file::{Region, RegionFormat};
+
+    #[test]
+    fn test_phases_are_collected() {
+        enable();
+        take();
+
+        let data = Tag::Compound { name: None, value: vec![Tag::LongArray { name: Some(String::from("heights")), value: vec![7; 4096] }] };
+        let region = Region::new(vec![Chunk::new_from_block_pos(0, 0, 0, data)], 0);
+        let bytes = region.to_bytes_blinear(0, 3);
+        let written = take();
+        assert!(written.serialize > Duration::ZERO && written.compress > Duration::ZERO);
+        assert_eq!(written.parse, Duration::ZERO);
+
+        Region::from_bytes(RegionFormat::Blinear, &bytes).unwrap();
+        let read = take();
+        assert!(read.decompress > Duration::ZERO && read.parse > Duration::ZERO);
+        assert_eq!(read.compress, Duration::ZERO);
+        assert_eq!(take(), PhaseTimings::ZERO);
+    }
+}
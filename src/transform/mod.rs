@@ -0,0 +1,337 @@
+use crate::chunk::Chunk;
+use crate::nbt::query::Query;
+use crate::nbt::tag::Tag;
+use crate::region_file::Region;
+use crate::upgrade::{upgrade_chunk, UpgradeOutcome};
+use std::collections::BTreeMap;
+
+/// Overworld section range since 1.18, used when a chunk does not tell its own.
+const DEFAULT_MIN_SECTION: i32 = -4;
+const DEFAULT_SECTION_COUNT: i32 = 24;
+
+/// Chunk edits applied to every chunk during conversion.
+#[derive(Default)]
+pub struct Transforms {
+    pub force_blending: bool,
+    /// Paths deleted from every chunk, relative to the chunk root
+    pub strip_tags: Vec<Query>,
+    /// Entity ids removed from every chunk's `Entities` list
+    pub purge_entities: Vec<String>,
+    pub strip_light: bool,
+    /// Drop heightmaps, light and empty tick lists, which the server rebuilds on load
+    pub strip_recomputable: bool,
+    /// DataVersion whose layout chunks are migrated to
+    pub upgrade_to: Option<i32>,
+}
+
+/// How many chunks each transform changed in one region.
+#[derive(Default, Debug, PartialEq)]
+pub struct TransformReport {
+    pub blended: usize,
+    pub stripped_tags: usize,
+    /// Removed entities by id
+    pub purged_entities: BTreeMap<String, usize>,
+    pub unlit: usize,
+    pub recomputed: usize,
+    pub upgraded: usize,
+    /// Chunks older than every upgrade that would migrate them
+    pub too_old: usize,
+}
+
+impl Transforms {
+    pub fn is_empty(&self) -> bool {
+        !self.force_blending && self.strip_tags.is_empty() && self.purge_entities.is_empty() && !self.strip_light && !self.strip_recomputable && self.upgrade_to.is_none()
+    }
+
+    pub fn apply(&self, region: &mut Region) -> TransformReport {
+        let mut report = TransformReport::default();
+
+        for chunk in region.chunks_mut() {
+            // upgrade first, so the other transforms see the layout they expect
+            if let Some(target) = self.upgrade_to {
+                match upgrade_chunk(chunk, target) {
+                    UpgradeOutcome::Migrated => report.upgraded += 1,
+                    UpgradeOutcome::TooOld => report.too_old += 1,
+                    UpgradeOutcome::Unchanged => {}
+                }
+            }
+            if self.force_blending && force_blending(chunk) {
+                report.blended += 1;
+            }
+            for query in &self.strip_tags {
+                report.stripped_tags += query.remove(&mut chunk.data);
+            }
+            if !self.purge_entities.is_empty() {
+                purge_entities(chunk, &self.purge_entities, &mut report.purged_entities);
+            }
+            if self.strip_light && strip_light(chunk) {
+                report.unlit += 1;
+            }
+            if self.strip_recomputable && strip_recomputable(chunk) {
+                report.recomputed += 1;
+            }
+        }
+
+        report
+    }
+}
+
+impl TransformReport {
+    pub fn summary(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        if self.upgraded > 0 {
+            parts.push(format!("{} chunks upgraded", self.upgraded));
+        }
+        if self.too_old > 0 {
+            parts.push(format!("{} chunks too old to upgrade", self.too_old));
+        }
+        if self.blended > 0 {
+            parts.push(format!("blending forced on {} chunks", self.blended));
+        }
+        if self.stripped_tags > 0 {
+            parts.push(format!("{} tags stripped", self.stripped_tags));
+        }
+        if self.unlit > 0 {
+            parts.push(format!("light stripped from {} chunks", self.unlit));
+        }
+        if self.recomputed > 0 {
+            parts.push(format!("recomputable data stripped from {} chunks", self.recomputed));
+        }
+        for (id, count) in &self.purged_entities {
+            parts.push(format!("{count} {id} purged"));
+        }
+
+        (!parts.is_empty()).then(|| parts.join(", "))
+    }
+}
+
+/// Adds `blending_data` so the game blends this chunk's terrain with newly generated neighbours the
+/// next time the world is opened on a newer version. Pre-1.18 chunks (with a `Level` compound) are
+/// left alone, the game blends them on upgrade anyway.
+pub fn force_blending(chunk: &mut Chunk) -> bool {
+    if chunk.get_data().find_tag("Level").is_some() || chunk.get_data().find_tag("sections").is_none() {
+        return false;
+    }
+
+    let min_section = chunk
+        .find_field("yPos")
+        .and_then(|tag| tag.get_int())
+        .copied()
+        .unwrap_or(DEFAULT_MIN_SECTION);
+
+    // sections without block states only carry light above and below the world
+    let max_section = chunk
+        .find_field("sections")
+        .and_then(|sections| sections.children())
+        .unwrap_or_default()
+        .iter()
+        .filter(|section| section.find_tag("block_states").is_some())
+        .filter_map(|section| match section.find_tag("Y") {
+            Some(Tag::Byte { value, .. }) => Some(*value as i32 + 1),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(min_section + DEFAULT_SECTION_COUNT);
+
+    chunk.data.insert_tag(Tag::Compound {
+        name: Some(String::from("blending_data")),
+        value: vec![
+            Tag::Int { name: Some(String::from("min_section")), value: min_section },
+            Tag::Int { name: Some(String::from("max_section")), value: max_section },
+        ],
+    });
+
+    true
+}
+
+/// Entity ids are matched with their namespace, `item` means `minecraft:item`.
+pub fn entity_id(id: &str) -> String {
+    if id.contains(':') { String::from(id) } else { format!("minecraft:{id}") }
+}
+
+/// Removes the entities with one of the given ids from a chunk, counting them by id.
+pub fn purge_entities(chunk: &mut Chunk, ids: &[String], purged: &mut BTreeMap<String, usize>) {
+    let Some(Tag::List { value, .. }) = chunk.fields_mut().find_tag_mut("Entities") else {
+        return;
+    };
+
+    value.retain(|entity| {
+        let Some(id) = entity.find_tag("id").and_then(|id| id.get_string()) else {
+            return true;
+        };
+        if !ids.contains(id) {
+            return true;
+        }
+
+        *purged.entry(id.clone()).or_default() += 1;
+        false
+    });
+}
+
+/// Drops the light arrays of every section and clears `isLightOn`, so the server relights the
+/// chunk on load. Chunks without sections (entities, poi) are left alone.
+pub fn strip_light(chunk: &mut Chunk) -> bool {
+    let fields = chunk.fields_mut();
+    let sections = match fields.find_tag_mut("sections") {
+        Some(sections) => sections,
+        None => match fields.find_tag_mut("Sections") {
+            Some(sections) => sections,
+            None => return false,
+        },
+    };
+
+    if let Tag::List { value, .. } = sections {
+        for section in value {
+            section.remove_tag("BlockLight");
+            section.remove_tag("SkyLight");
+        }
+    }
+
+    fields.insert_tag(Tag::Byte { name: Some(String::from("isLightOn")), value: 0 });
+
+    true
+}
+
+/// Drops what the server rebuilds when it loads a chunk: the heightmaps, the light (see
+/// [`strip_light`]) and the scheduled tick lists when they are empty. Entities and poi chunks are
+/// left alone.
+pub fn strip_recomputable(chunk: &mut Chunk) -> bool {
+    let mut stripped = strip_light(chunk);
+
+    let fields = chunk.fields_mut();
+    stripped |= fields.remove_tag("Heightmaps").is_some();
+    for ticks in ["block_ticks", "fluid_ticks", "TileTicks", "LiquidTicks"] {
+        if fields.find_tag(ticks).and_then(|list| list.children()).is_some_and(|list| list.is_empty()) {
+            stripped |= fields.remove_tag(ticks).is_some();
+        }
+    }
+
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(y: i8, blocks: bool) -> Tag {
+        let mut value = vec![Tag::Byte { name: Some(String::from("Y")), value: y }];
+        if blocks {
+            value.push(Tag::Compound { name: Some(String::from("block_states")), value: Vec::new() });
+        }
+        Tag::Compound { name: None, value }
+    }
+
+    #[test]
+    fn test_force_blending() {
+        let data = Tag::Compound {
+            name: None,
+            value: vec![
+                Tag::Int { name: Some(String::from("yPos")), value: -4 },
+                Tag::List {
+                    name: Some(String::from("sections")),
+                    value: vec![section(-5, false), section(-4, true), section(19, true), section(20, false)],
+                    tag_type: 10,
+                },
+            ],
+        };
+        let mut chunk = Chunk::new_from_block_pos(0, 0, 0, data);
+
+        assert!(force_blending(&mut chunk));
+
+        let blending_data = chunk.get_data().find_tag("blending_data").unwrap();
+        assert_eq!(blending_data.find_tag("min_section").and_then(|tag| tag.get_int()), Some(&-4));
+        assert_eq!(blending_data.find_tag("max_section").and_then(|tag| tag.get_int()), Some(&20));
+    }
+
+    #[test]
+    fn test_force_blending_skips_legacy_chunks() {
+        let level = Tag::Compound { name: Some(String::from("Level")), value: Vec::new() };
+        let mut chunk = Chunk::new_from_block_pos(0, 0, 0, Tag::Compound { name: None, value: vec![level] });
+
+        assert!(!force_blending(&mut chunk));
+    }
+
+    #[test]
+    fn test_strip_tags() {
+        let data = Tag::Compound {
+            name: None,
+            value: vec![
+                Tag::Compound { name: Some(String::from("Heightmaps")), value: Vec::new() },
+                Tag::List { name: Some(String::from("sections")), value: vec![section(0, true), section(1, true)], tag_type: 10 },
+            ],
+        };
+        let mut region = Region::new(vec![Chunk::new_from_block_pos(0, 0, 0, data)], 0);
+        let transforms = Transforms {
+            strip_tags: vec![Query::parse("Heightmaps").unwrap(), Query::parse("sections[*].block_states").unwrap()],
+            ..Transforms::default()
+        };
+
+        assert_eq!(transforms.apply(&mut region), TransformReport { stripped_tags: 3, ..TransformReport::default() });
+        assert!(region.chunks()[0].find_field("Heightmaps").is_none());
+    }
+
+    #[test]
+    fn test_strip_light() {
+        let mut lit = section(0, true);
+        lit.insert_tag(Tag::ByteArray { name: Some(String::from("BlockLight")), value: vec![0; 2048] });
+        lit.insert_tag(Tag::ByteArray { name: Some(String::from("SkyLight")), value: vec![15; 2048] });
+        let data = Tag::Compound {
+            name: None,
+            value: vec![
+                Tag::Byte { name: Some(String::from("isLightOn")), value: 1 },
+                Tag::List { name: Some(String::from("sections")), value: vec![lit], tag_type: 10 },
+            ],
+        };
+        let mut chunk = Chunk::new_from_block_pos(0, 0, 0, data);
+
+        assert!(strip_light(&mut chunk));
+
+        let section = &chunk.find_field("sections").and_then(|sections| sections.children()).unwrap()[0];
+        assert!(section.find_tag("BlockLight").is_none() && section.find_tag("SkyLight").is_none());
+        assert!(section.find_tag("block_states").is_some());
+        assert!(matches!(chunk.find_field("isLightOn"), Some(Tag::Byte { value: 0, .. })));
+    }
+
+    #[test]
+    fn test_strip_recomputable() {
+        let ticks = |name: &str, count: usize| Tag::List { name: Some(String::from(name)), value: vec![Tag::Compound { name: None, value: Vec::new() }; count], tag_type: 10 };
+        let data = Tag::Compound {
+            name: None,
+            value: vec![
+                Tag::Compound { name: Some(String::from("Heightmaps")), value: Vec::new() },
+                Tag::List { name: Some(String::from("sections")), value: vec![section(0, true)], tag_type: 10 },
+                ticks("block_ticks", 0),
+                ticks("fluid_ticks", 2),
+            ],
+        };
+        let mut chunk = Chunk::new_from_block_pos(0, 0, 0, data);
+
+        assert!(strip_recomputable(&mut chunk));
+        assert!(chunk.find_field("Heightmaps").is_none() && chunk.find_field("block_ticks").is_none());
+        assert_eq!(chunk.find_field("fluid_ticks").and_then(|list| list.children()).map(|list| list.len()), Some(2));
+        assert!(matches!(chunk.find_field("isLightOn"), Some(Tag::Byte { value: 0, .. })));
+
+        let mut entities = Chunk::new_from_block_pos(0, 0, 0, Tag::Compound { name: None, value: vec![ticks("Entities", 1)] });
+        assert!(!strip_recomputable(&mut entities));
+    }
+
+    #[test]
+    fn test_purge_entities() {
+        let entity = |id: &str| Tag::Compound {
+            name: None,
+            value: vec![Tag::String { name: Some(String::from("id")), value: String::from(id) }],
+        };
+        let entities = vec![entity("minecraft:item"), entity("minecraft:villager"), entity("minecraft:item")];
+        let data = Tag::Compound {
+            name: None,
+            value: vec![Tag::List { name: Some(String::from("Entities")), value: entities, tag_type: 10 }],
+        };
+        let mut chunk = Chunk::new_from_block_pos(0, 0, 0, data);
+        let mut purged = BTreeMap::new();
+
+        purge_entities(&mut chunk, &[entity_id("item"), entity_id("mod:golem")], &mut purged);
+
+        assert_eq!(purged, BTreeMap::from([(String::from("minecraft:item"), 2)]));
+        assert_eq!(chunk.find_field("Entities").and_then(|list| list.children()).map(|list| list.len()), Some(1));
+    }
+}
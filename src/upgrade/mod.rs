@@ -0,0 +1,155 @@
+use crate::chunk::Chunk;
+use crate::nbt::tag::Tag;
+
+/// One migration of chunk NBT to the layout of a newer DataVersion. Steps only move and rename
+/// fields, whatever else changed between the versions is left to the game's own upgrade.
+pub struct Upgrade {
+    pub name: &'static str,
+    /// Oldest DataVersion whose layout the step understands
+    pub from: i32,
+    /// DataVersion of the chunks the step migrated
+    pub to: i32,
+    /// Migrates the chunk's NBT, false if it does not have the old layout
+    pub apply: fn(&mut Tag) -> bool,
+}
+
+/// Every known upgrade, oldest first. A step's `from` is at most the `to` of the step before it.
+pub const UPGRADES: &[Upgrade] = &[Upgrade { name: "flatten Level (21w43a)", from: 2529, to: 2844, apply: flatten_level }];
+
+/// Fields of the `Level` compound renamed when 21w43a moved them to the chunk root.
+const LEVEL_RENAMES: [(&str, &str); 7] = [
+    ("Sections", "sections"),
+    ("TileEntities", "block_entities"),
+    ("TileTicks", "block_ticks"),
+    ("LiquidTicks", "fluid_ticks"),
+    ("Entities", "entities"),
+    ("Structures", "structures"),
+    ("CarvingMasks", "carving_masks"),
+];
+
+/// The upgrades a run to `target` can apply, oldest first.
+pub fn upgrades_to(target: i32) -> impl Iterator<Item = &'static Upgrade> {
+    UPGRADES.iter().filter(move |step| step.to <= target)
+}
+
+#[derive(Debug, PartialEq)]
+pub enum UpgradeOutcome {
+    /// Already at the target or without the old layout
+    Unchanged,
+    Migrated,
+    /// Older than every step that would migrate it
+    TooOld,
+}
+
+/// Runs the upgrades between the chunk's DataVersion and `target` in order, updating
+/// `DataVersion` after each step that applied.
+pub fn upgrade_chunk(chunk: &mut Chunk, target: i32) -> UpgradeOutcome {
+    let mut version = chunk.data_version().unwrap_or(0);
+    let mut outcome = UpgradeOutcome::Unchanged;
+
+    for step in upgrades_to(target) {
+        if step.to <= version {
+            continue;
+        }
+        if version < step.from {
+            if outcome == UpgradeOutcome::Unchanged {
+                outcome = UpgradeOutcome::TooOld;
+            }
+            break;
+        }
+        if !(step.apply)(&mut chunk.data) {
+            continue;
+        }
+
+        version = step.to;
+        chunk.data.insert_tag(Tag::Int { name: Some(String::from("DataVersion")), value: version });
+        outcome = UpgradeOutcome::Migrated;
+    }
+
+    outcome
+}
+
+/// Moves the fields of `Level` to the chunk root with their 1.18 names, and the `Palette` and
+/// `BlockStates` of each section into a `block_states` compound.
+fn flatten_level(data: &mut Tag) -> bool {
+    if !matches!(data.find_tag("Level"), Some(Tag::Compound { .. })) {
+        return false;
+    }
+    let Some(Tag::Compound { value: fields, .. }) = data.remove_tag("Level") else {
+        return false;
+    };
+
+    for mut field in fields {
+        let name = field.get_name().unwrap_or_default();
+        if let Some((_, renamed)) = LEVEL_RENAMES.iter().find(|(old, _)| *old == name) {
+            field.set_name(Some(String::from(*renamed)));
+        }
+        if name == "Structures"
+            && let Some(starts) = field.find_tag_mut("Starts")
+        {
+            starts.set_name(Some(String::from("starts")));
+        }
+        if name == "Sections"
+            && let Tag::List { value: sections, .. } = &mut field
+        {
+            sections.iter_mut().for_each(nest_block_states);
+        }
+
+        data.insert_tag(field);
+    }
+
+    true
+}
+
+fn nest_block_states(section: &mut Tag) {
+    let mut block_states = Vec::new();
+    for (old, new) in [("Palette", "palette"), ("BlockStates", "data")] {
+        if let Some(mut tag) = section.remove_tag(old) {
+            tag.set_name(Some(String::from(new)));
+            block_states.push(tag);
+        }
+    }
+
+    if !block_states.is_empty() {
+        section.insert_tag(Tag::Compound { name: Some(String::from("block_states")), value: block_states });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flatten_level() {
+        let named = |name: &str| Some(String::from(name));
+        let section = Tag::Compound {
+            name: None,
+            value: vec![
+                Tag::Byte { name: named("Y"), value: 0 },
+                Tag::List { name: named("Palette"), value: Vec::new(), tag_type: 10 },
+                Tag::LongArray { name: named("BlockStates"), value: vec![0; 256] },
+            ],
+        };
+        let level = Tag::Compound {
+            name: named("Level"),
+            value: vec![
+                Tag::Int { name: named("xPos"), value: 0 },
+                Tag::List { name: named("Sections"), value: vec![section], tag_type: 10 },
+                Tag::List { name: named("TileEntities"), value: Vec::new(), tag_type: 10 },
+            ],
+        };
+        let data = Tag::Compound { name: None, value: vec![Tag::Int { name: named("DataVersion"), value: 2730 }, level] };
+        let mut chunk = Chunk::new_from_block_pos(0, 0, 0, data);
+
+        assert_eq!(upgrade_chunk(&mut chunk, 2843), UpgradeOutcome::Unchanged);
+        assert_eq!(upgrade_chunk(&mut chunk, 3953), UpgradeOutcome::Migrated);
+        assert_eq!(chunk.data_version(), Some(2844));
+        assert!(chunk.get_data().find_tag("Level").is_none() && chunk.get_data().find_tag("block_entities").is_some());
+        let section = &chunk.get_data().find_tag("sections").and_then(|sections| sections.children()).unwrap()[0];
+        assert!(matches!(section.find_tag("block_states").and_then(|states| states.find_tag("data")), Some(Tag::LongArray { .. })));
+        assert_eq!(upgrade_chunk(&mut chunk, 3953), UpgradeOutcome::Unchanged);
+
+        let mut old = Chunk::new_from_block_pos(0, 0, 0, Tag::Compound { name: None, value: vec![Tag::Int { name: named("DataVersion"), value: 1343 }] });
+        assert_eq!(upgrade_chunk(&mut old, 3953), UpgradeOutcome::TooOld);
+    }
+}
@@ -0,0 +1,244 @@
+mod schema;
+
+use crate::chunk::Chunk;
+use crate::region_file::{read_region_file_with_limits, region_coords_from_path, ParseLimits};
+use crate::verify::schema::{schema_violations, SchemaViolation};
+use crate::{folder_name, scan_region_files, RegionType};
+use clap::Args;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+#[derive(Args)]
+pub struct VerifyArgs {
+    /// Path to your Minecraft world, or a single region file
+    pub path: PathBuf,
+
+    /// Region type to check when verifying a world
+    #[arg(short = 't', long, value_enum, default_value = "region")]
+    pub region_type: RegionType,
+
+    /// Parse with resource limits, as for untrusted worlds
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Report chunks with a lower DataVersion
+    #[arg(long)]
+    pub min_data_version: Option<i32>,
+
+    /// Report chunks with a higher DataVersion
+    #[arg(long)]
+    pub max_data_version: Option<i32>,
+}
+
+/// Range of accepted chunk `DataVersion`s. Chunks without one predate 1.9 and count as version 0.
+#[derive(Clone, Copy, Default)]
+pub struct DataVersionRange {
+    pub min_data_version: Option<i32>,
+    pub max_data_version: Option<i32>,
+}
+
+impl DataVersionRange {
+    pub fn is_set(&self) -> bool {
+        self.min_data_version.is_some() || self.max_data_version.is_some()
+    }
+
+    pub fn contains(&self, data_version: Option<i32>) -> bool {
+        let version = data_version.unwrap_or(0);
+
+        self.min_data_version.is_none_or(|min| version >= min) && self.max_data_version.is_none_or(|max| version <= max)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct PositionMismatch {
+    /// Position implied by the chunk's slot in the region file
+    pub slot: (i32, i32),
+    /// Position recorded in the chunk NBT
+    pub recorded: (i32, i32),
+}
+
+#[derive(Default)]
+struct FileReport {
+    chunks: usize,
+    mismatches: Vec<PositionMismatch>,
+    data_versions: BTreeMap<Option<i32>, usize>,
+    /// Chunks whose DataVersion is outside the accepted range
+    unexpected_versions: Vec<((i32, i32), Option<i32>)>,
+    /// Fields missing or of the wrong type for the chunk's DataVersion
+    schema_violations: Vec<((i32, i32), SchemaViolation)>,
+}
+
+/// Compares the NBT position of every chunk with its slot. Chunks without a position are skipped.
+pub fn position_mismatches(chunks: &[Chunk], region_x: i32, region_z: i32) -> Vec<PositionMismatch> {
+    chunks
+        .iter()
+        .filter_map(|chunk| {
+            let slot = chunk.global_position(region_x, region_z);
+            let recorded = chunk.nbt_position()?;
+            (slot != recorded).then_some(PositionMismatch { slot, recorded })
+        })
+        .collect()
+}
+
+fn verify_file(region_file: &Path, region_type: RegionType, limits: &ParseLimits, range: &DataVersionRange) -> Result<FileReport, Box<dyn Error>> {
+    let (_, region) = read_region_file_with_limits(region_file, limits)?;
+    let (region_x, region_z) = region_coords_from_path(region_file).ok_or("File name is not r.<x>.<z>.<ext>")?;
+
+    let mut report = FileReport {
+        chunks: region.chunks().len(),
+        mismatches: position_mismatches(region.chunks(), region_x, region_z),
+        ..FileReport::default()
+    };
+
+    for chunk in region.chunks() {
+        let data_version = chunk.data_version();
+        *report.data_versions.entry(data_version).or_default() += 1;
+
+        if !range.contains(data_version) {
+            report.unexpected_versions.push((chunk.global_position(region_x, region_z), data_version));
+        }
+
+        let position = chunk.global_position(region_x, region_z);
+        report.schema_violations.extend(schema_violations(chunk, region_type).into_iter().map(|violation| (position, violation)));
+    }
+
+    Ok(report)
+}
+
+/// Checks every region file and returns whether no problems were found.
+pub fn run_verify(args: &VerifyArgs) -> Result<bool, Box<dyn Error>> {
+    let limits = if args.strict { ParseLimits::STRICT } else { ParseLimits::DEFAULT };
+    let data_versions = DataVersionRange {
+        min_data_version: args.min_data_version,
+        max_data_version: args.max_data_version,
+    };
+
+    let mut region_files = if args.path.is_file() {
+        vec![args.path.clone()]
+    } else {
+        scan_region_files(args.path.join(folder_name(args.region_type)))
+    };
+    region_files.sort();
+
+    let reports: Vec<(&PathBuf, Result<FileReport, String>)> = region_files
+        .par_iter()
+        .map(|region_file| (region_file, verify_file(region_file, args.region_type, &limits, &data_versions).map_err(|err| err.to_string())))
+        .collect();
+
+    let mut chunks = 0;
+    let mut mismatches = 0;
+    let mut unexpected_versions = 0;
+    let mut invalid_fields = 0;
+    let mut unreadable = 0;
+    let mut version_counts: BTreeMap<Option<i32>, usize> = BTreeMap::new();
+
+    for (region_file, report) in reports {
+        match report {
+            Ok(report) => {
+                chunks += report.chunks;
+                mismatches += report.mismatches.len();
+
+                for mismatch in report.mismatches {
+                    println!(
+                        "{}: chunk in slot {}, {} records position {}, {}",
+                        region_file.display(),
+                        mismatch.slot.0,
+                        mismatch.slot.1,
+                        mismatch.recorded.0,
+                        mismatch.recorded.1
+                    );
+                }
+
+                unexpected_versions += report.unexpected_versions.len();
+                for ((x, z), data_version) in report.unexpected_versions {
+                    let version = data_version.map_or(String::from("none"), |version| version.to_string());
+                    println!("{}: chunk {x}, {z} has unexpected DataVersion {version}", region_file.display());
+                }
+
+                invalid_fields += report.schema_violations.len();
+                for ((x, z), violation) in report.schema_violations {
+                    println!("{}: chunk {x}, {z} {violation}", region_file.display());
+                }
+
+                for (data_version, count) in report.data_versions {
+                    *version_counts.entry(data_version).or_default() += count;
+                }
+            }
+            Err(err) => {
+                unreadable += 1;
+                println!("{}: unreadable, {}", region_file.display(), err);
+            }
+        }
+    }
+
+    println!("DataVersions:");
+    for (data_version, count) in &version_counts {
+        let version = data_version.map_or(String::from("none"), |version| version.to_string());
+        println!("  {version:<10} {count:>10} chunks");
+    }
+
+    println!(
+        "{} files, {} chunks checked, {} position mismatches, {} unexpected DataVersions, {} invalid fields, {} unreadable files",
+        region_files.len(),
+        chunks,
+        mismatches,
+        unexpected_versions,
+        invalid_fields,
+        unreadable
+    );
+
+    Ok(mismatches == 0 && unexpected_versions == 0 && invalid_fields == 0 && unreadable == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nbt::tag::Tag;
+
+    fn chunk(slot_x: i32, slot_z: i32, x_pos: i32, z_pos: i32) -> Chunk {
+        let data = Tag::Compound {
+            name: None,
+            value: vec![
+                Tag::Int { name: Some(String::from("xPos")), value: x_pos },
+                Tag::Int { name: Some(String::from("zPos")), value: z_pos },
+            ],
+        };
+        Chunk::new_from_block_pos(slot_x, slot_z, 0, data)
+    }
+
+    #[test]
+    fn test_data_version_range() {
+        let range = DataVersionRange { min_data_version: Some(3700), max_data_version: Some(3953) };
+
+        assert!(range.contains(Some(3700)));
+        assert!(range.contains(Some(3953)));
+        assert!(!range.contains(Some(3954)));
+        assert!(!range.contains(None));
+        assert!(DataVersionRange::default().contains(None));
+        assert!(DataVersionRange { max_data_version: Some(100), ..Default::default() }.contains(None));
+    }
+
+    #[test]
+    fn test_position_mismatches() {
+        let entities = Chunk::new_from_block_pos(
+            3,
+            0,
+            0,
+            Tag::Compound {
+                name: None,
+                value: vec![Tag::IntArray { name: Some(String::from("Position")), value: vec![-29, 64] }],
+            },
+        );
+        let chunks = [chunk(0, 0, -32, 32), chunk(1, 2, -31, 2), entities];
+
+        assert_eq!(
+            position_mismatches(&chunks, -1, 1),
+            [
+                PositionMismatch { slot: (-31, 34), recorded: (-31, 2) },
+                PositionMismatch { slot: (-29, 32), recorded: (-29, 64) },
+            ]
+        );
+    }
+}
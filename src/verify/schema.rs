@@ -0,0 +1,193 @@
+use crate::chunk::Chunk;
+use crate::nbt::tag::{tag_type_name, Tag};
+use crate::RegionType;
+use std::ops::RangeInclusive;
+
+const BYTE: u8 = 1;
+const INT: u8 = 3;
+const LONG: u8 = 4;
+const STRING: u8 = 8;
+const LIST: u8 = 9;
+const COMPOUND: u8 = 10;
+const INT_ARRAY: u8 = 11;
+
+/// 21w43a moved the chunk fields out of `Level` and renamed `Sections` and `TileEntities`.
+const FLAT_CHUNKS: i32 = 2844;
+/// 18w06a replaced `TerrainPopulated` and friends with `Status`.
+const CHUNK_STATUS: i32 = 1466;
+/// 20w45a moved entities into their own region files.
+const ENTITY_FILES: i32 = 2681;
+
+/// A field every chunk of a schema has, or may have, with the type the game reads it as. Paths
+/// are dot separated, `[]` after a list's name checks the field in each of its elements.
+struct Field {
+    path: &'static str,
+    tag_type: u8,
+    required: bool,
+}
+
+const fn required(path: &'static str, tag_type: u8) -> Field {
+    Field { path, tag_type, required: true }
+}
+
+const fn optional(path: &'static str, tag_type: u8) -> Field {
+    Field { path, tag_type, required: false }
+}
+
+/// The fields of chunks of one region type within a DataVersion range.
+struct Schema {
+    region_type: RegionType,
+    data_versions: RangeInclusive<i32>,
+    fields: &'static [Field],
+}
+
+const SCHEMAS: &[Schema] = &[
+    Schema {
+        region_type: RegionType::REGION,
+        data_versions: FLAT_CHUNKS..=i32::MAX,
+        fields: &[
+            required("DataVersion", INT),
+            required("xPos", INT),
+            required("zPos", INT),
+            optional("yPos", INT),
+            required("Status", STRING),
+            required("sections", LIST),
+            required("sections[].Y", BYTE),
+            optional("sections[].block_states", COMPOUND),
+            optional("sections[].biomes", COMPOUND),
+            required("block_entities", LIST),
+            optional("Heightmaps", COMPOUND),
+            optional("LastUpdate", LONG),
+            optional("InhabitedTime", LONG),
+            optional("structures", COMPOUND),
+        ],
+    },
+    Schema {
+        region_type: RegionType::REGION,
+        data_versions: CHUNK_STATUS..=FLAT_CHUNKS - 1,
+        fields: &[required("Level.Status", STRING)],
+    },
+    Schema {
+        region_type: RegionType::REGION,
+        data_versions: 0..=FLAT_CHUNKS - 1,
+        fields: &[
+            required("Level", COMPOUND),
+            required("Level.xPos", INT),
+            required("Level.zPos", INT),
+            optional("Level.Sections", LIST),
+            optional("Level.Sections[].Y", BYTE),
+            optional("Level.TileEntities", LIST),
+            optional("Level.Heightmaps", COMPOUND),
+            optional("Level.LastUpdate", LONG),
+            optional("Level.InhabitedTime", LONG),
+        ],
+    },
+    Schema {
+        region_type: RegionType::ENTITIES,
+        data_versions: ENTITY_FILES..=i32::MAX,
+        fields: &[required("DataVersion", INT), required("Position", INT_ARRAY), required("Entities", LIST)],
+    },
+    Schema {
+        region_type: RegionType::POI,
+        data_versions: 0..=i32::MAX,
+        fields: &[optional("DataVersion", INT), required("Sections", COMPOUND)],
+    },
+];
+
+/// A field of a chunk that does not match its schema.
+#[derive(Debug, PartialEq)]
+pub enum SchemaViolation {
+    Missing { path: String },
+    WrongType { path: String, expected: u8, found: u8 },
+}
+
+impl std::fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchemaViolation::Missing { path } => write!(f, "{} is missing", path),
+            SchemaViolation::WrongType { path, expected, found } => {
+                write!(f, "{} is {}, expected {}", path, tag_type_name(*found), tag_type_name(*expected))
+            }
+        }
+    }
+}
+
+/// Checks one field below `tag`, `shown` is the path walked so far as printed in reports.
+fn check_field(tag: &Tag, path: &str, shown: String, field: &Field, violations: &mut Vec<SchemaViolation>) {
+    let (segment, rest) = path.split_once('.').map_or((path, None), |(segment, rest)| (segment, Some(rest)));
+    let (name, each) = segment.strip_suffix("[]").map_or((segment, false), |name| (name, true));
+    let shown = if shown.is_empty() { name.to_string() } else { format!("{}.{}", shown, name) };
+
+    let Some(child) = tag.find_tag(name) else {
+        // a missing list or compound on the way is reported by its own field
+        if field.required && rest.is_none() {
+            violations.push(SchemaViolation::Missing { path: shown });
+        }
+        return;
+    };
+
+    match rest {
+        // the type of lists and compounds on the way is checked by their own fields
+        Some(rest) if each => {
+            for (index, element) in child.children().unwrap_or_default().iter().enumerate() {
+                check_field(element, rest, format!("{}[{}]", shown, index), field, violations);
+            }
+        }
+        Some(rest) => check_field(child, rest, shown, field, violations),
+        None if child.get_tag_type() != field.tag_type => {
+            violations.push(SchemaViolation::WrongType { path: shown, expected: field.tag_type, found: child.get_tag_type() });
+        }
+        None => {}
+    }
+}
+
+/// Fields of the chunk that are missing or have the wrong type for its DataVersion. Chunks
+/// without a DataVersion predate 1.9 and are checked as version 0.
+pub fn schema_violations(chunk: &Chunk, region_type: RegionType) -> Vec<SchemaViolation> {
+    let data_version = chunk.data_version().unwrap_or(0);
+
+    let mut violations = Vec::new();
+    for schema in SCHEMAS.iter().filter(|schema| schema.region_type == region_type && schema.data_versions.contains(&data_version)) {
+        for field in schema.fields {
+            check_field(chunk.get_data(), field.path, String::new(), field, &mut violations);
+        }
+    }
+
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_violations() {
+        let named = |name: &str| Some(String::from(name));
+        let section = |y: Tag| Tag::Compound { name: None, value: vec![y] };
+        let data = Tag::Compound {
+            name: None,
+            value: vec![
+                Tag::Int { name: named("DataVersion"), value: 3953 },
+                Tag::Int { name: named("xPos"), value: 0 },
+                Tag::Int { name: named("zPos"), value: 0 },
+                Tag::Int { name: named("Status"), value: 1 },
+                Tag::List {
+                    name: named("sections"),
+                    value: vec![section(Tag::Byte { name: named("Y"), value: -4 }), section(Tag::Int { name: named("Y"), value: -3 })],
+                    tag_type: 10,
+                },
+            ],
+        };
+        let chunk = Chunk::new_from_block_pos(0, 0, 0, data);
+
+        assert_eq!(
+            schema_violations(&chunk, RegionType::REGION),
+            [
+                SchemaViolation::WrongType { path: String::from("Status"), expected: STRING, found: INT },
+                SchemaViolation::WrongType { path: String::from("sections[1].Y"), expected: BYTE, found: INT },
+                SchemaViolation::Missing { path: String::from("block_entities") },
+            ]
+        );
+        assert_eq!(schema_violations(&chunk, RegionType::ENTITIES).len(), 2);
+    }
+}
@@ -0,0 +1,109 @@
+//! wasm-bindgen interface, built with the `wasm` feature, e.g. through
+//! `wasm-pack build --target web -- --features wasm`. Everything works on bytes, the page reads
+//! and saves the files itself.
+
+use crate::chunk::Chunk;
+use crate::nbt::snbt::to_snbt;
+use crate::region_file::{region_coords_from_path, Region, RegionFormat};
+use std::path::Path;
+use wasm_bindgen::prelude::*;
+
+fn parse_format(format: &str) -> Result<RegionFormat, JsError> {
+    RegionFormat::from_extension(format).ok_or_else(|| JsError::new(&format!("Unknown region format {format}, expected mca, linear or blinear")))
+}
+
+/// A parsed region file.
+#[wasm_bindgen]
+pub struct RegionFile {
+    format: RegionFormat,
+    region: Region,
+    /// Region coordinates of the file name, written into linear files
+    region_coords: Option<(i32, i32)>,
+}
+
+#[wasm_bindgen]
+impl RegionFile {
+    /// Parses the bytes of a region file, the format is detected from the content and the file name.
+    #[wasm_bindgen(constructor)]
+    pub fn new(file_name: &str, bytes: &[u8]) -> Result<RegionFile, JsError> {
+        let format = RegionFormat::detect(Path::new(file_name), bytes).ok_or_else(|| JsError::new("Unknown region file format"))?;
+        let region = Region::from_bytes(format, bytes)?;
+
+        Ok(Self { format, region, region_coords: region_coords_from_path(Path::new(file_name)) })
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn format(&self) -> String {
+        self.format.extension().to_string()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn timestamp(&self) -> i64 {
+        self.region.timestamp()
+    }
+
+    #[wasm_bindgen(getter, js_name = chunkCount)]
+    pub fn chunk_count(&self) -> usize {
+        self.region.chunks().len()
+    }
+
+    /// Slots holding a chunk, `x + z * 32` in region local chunk coordinates.
+    #[wasm_bindgen(js_name = chunkSlots)]
+    pub fn chunk_slots(&self) -> Vec<u32> {
+        self.region.chunks().iter().map(|chunk| chunk.position_to_sector_index() as u32).collect()
+    }
+
+    /// The chunk NBT as SNBT text, undefined if the slot is empty.
+    #[wasm_bindgen(js_name = chunkSnbt)]
+    pub fn chunk_snbt(&self, x: i32, z: i32) -> Option<String> {
+        self.chunk(x, z).map(|chunk| to_snbt(chunk.get_data()))
+    }
+
+    /// The uncompressed binary NBT of the chunk, undefined if the slot is empty.
+    #[wasm_bindgen(js_name = chunkNbt)]
+    pub fn chunk_nbt(&self, x: i32, z: i32) -> Option<Vec<u8>> {
+        self.chunk(x, z).map(Chunk::to_raw_bytes)
+    }
+
+    /// Serializes the region in the given format (`mca`, `linear` or `blinear`).
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&self, format: &str, compression_level: u8) -> Result<Vec<u8>, JsError> {
+        Ok(self.region.to_bytes(parse_format(format)?, self.region_coords, self.region.timestamp(), compression_level)?)
+    }
+
+    fn chunk(&self, x: i32, z: i32) -> Option<&Chunk> {
+        let sector_index = (x & 31) + ((z & 31) << 5);
+        self.region.chunks().iter().find(|chunk| chunk.position_to_sector_index() == sector_index)
+    }
+}
+
+/// Converts the bytes of a region file into the given format.
+#[wasm_bindgen]
+pub fn convert(file_name: &str, bytes: &[u8], format: &str, compression_level: u8) -> Result<Vec<u8>, JsError> {
+    RegionFile::new(file_name, bytes)?.to_bytes(format, compression_level)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nbt::tag::Tag;
+
+    #[test]
+    fn test_inspect_and_convert() {
+        let data = Tag::Compound { name: None, value: vec![Tag::Int { name: Some(String::from("DataVersion")), value: 3953 }] };
+        let bytes = Region::new(vec![Chunk::new_from_block_pos(3, 4, 0, data.clone())], 0).to_bytes_blinear(0, 3);
+
+        let region = RegionFile::new("r.0.0.blinear", &bytes).unwrap();
+        assert_eq!(region.format(), "blinear");
+        assert_eq!(region.chunk_slots(), [3 + 4 * 32]);
+        assert_eq!(region.chunk_nbt(3, 4).unwrap(), data.to_bytes());
+        assert!(region.chunk_snbt(3, 4).unwrap().contains("DataVersion: 3953"));
+        assert!(region.chunk_snbt(0, 0).is_none());
+
+        let converted = convert("r.0.0.blinear", &bytes, "mca", 6).unwrap();
+        assert_eq!(RegionFile::new("r.0.0.mca", &converted).unwrap().chunk_count(), 1);
+
+        let linear = convert("r.3.-2.blinear", &bytes, "linear", 6).unwrap();
+        assert_eq!(linear[18..26], [3i32.to_be_bytes(), (-2i32).to_be_bytes()].concat());
+    }
+}